@@ -1,4 +1,4 @@
-use crate::cli::{BLogger, Cli};
+use crate::cli::{BLogger, Cli, LogFile, LogFormat};
 use crate::commands::BCommand;
 use crate::configs::WsConfigFileHandler;
 use crate::error::BError;
@@ -6,6 +6,7 @@ use crate::workspace::{Workspace, WsBuildConfigHandler, WsSettingsHandler};
 
 use clap::Command;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use super::BSystem;
 
@@ -20,9 +21,10 @@ impl Bakery {
             the about, author and version can be read out from the
             Cargo.toml
         */
-        let cli: Cli = Cli::new(
-            Box::new(BLogger::new()),
-            Box::new(BSystem::new()),
+        let log_file: Arc<LogFile> = Arc::new(LogFile::new(LogFormat::Text));
+        let mut cli: Cli = Cli::new(
+            Box::new(BLogger::with_log_file(log_file.clone())),
+            Box::new(BSystem::with_log_file(log_file.clone())),
             Command::new("bakery")
                 .version(env!("CARGO_PKG_VERSION"))
                 .subcommand_required(true)
@@ -31,6 +33,12 @@ impl Bakery {
                 .author("bakery by Mikrodidakt(mikro.io)"),
             None,
         );
+        /*
+         * The log format is only known once the args has been parsed
+         * by the cli
+         */
+        log_file.set_format(cli.log_format());
+        cli.set_log_file(log_file);
 
         Bakery { cli: cli }
     }
@@ -62,14 +70,21 @@ impl Bakery {
                     Some(config),
                 ));
                 let res: Result<(), BError> = command.execute(&self.cli, &mut workspace);
-                res.unwrap_or_else(|err| {
+                if let Err(err) = &res {
                     self.cli.error(format!(
                         "Failed to execute '{}', with error '{}'",
                         cmd_name,
                         err.to_string()
                     ));
+                }
+                /*
+                 * Close the log file once the error has been logged so that
+                 * it is part of the log file of a failed build
+                 */
+                self.cli.close_log_file();
+                if res.is_err() {
                     std::process::exit(1);
-                });
+                }
             }
             Err(err) => {
                 self.cli.error(format!("{}", err.to_string()));
@@ -1,8 +1,10 @@
 use clap::ArgMatches;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::ThreadId;
 
-use crate::cli::{CallParams, Logger, System};
+use crate::cli::{CallParams, LogFile, LogFormat, LogLevel, Logger, System};
 use crate::commands::{BCommand, CmdHandler};
 use crate::error::BError;
 
@@ -13,6 +15,14 @@ pub struct Cli {
     logger: Box<dyn Logger>,
     system: Box<dyn System>,
     verbose: bool,
+    log_level: LogLevel,
+    log_format: LogFormat,
+    // The log file shared with the logger and the system, it is only
+    // written to once it has been opened for example by a build
+    log_file: Arc<LogFile>,
+    // Command lines passed to check_call recorded per thread so that
+    // the calls can be tracked per task even when running tasks in parallel
+    calls: Mutex<HashMap<ThreadId, Vec<Vec<String>>>>,
 }
 
 impl Cli {
@@ -51,6 +61,15 @@ impl Cli {
             }
         }
 
+        let log_level: LogLevel = args
+            .get_one::<String>("log_level")
+            .and_then(|l| LogLevel::from_str(l).ok())
+            .unwrap_or(LogLevel::Info);
+        let log_format: LogFormat = args
+            .get_one::<String>("log_format")
+            .and_then(|f| LogFormat::from_str(f).ok())
+            .unwrap_or(LogFormat::Text);
+
         Cli {
             cmd_line: c,
             args,
@@ -58,6 +77,10 @@ impl Cli {
             logger,
             system,
             verbose,
+            log_level,
+            log_format,
+            log_file: Arc::new(LogFile::new(log_format)),
+            calls: Mutex::new(HashMap::new()),
         }
     }
 
@@ -67,12 +90,7 @@ impl Cli {
         env: &HashMap<String, String>,
         shell: bool,
     ) -> Result<(), BError> {
-        let mut cmd: String = String::new();
-        cmd_line.iter().for_each(|c| {
-            cmd.push_str(c);
-            cmd.push(' ');
-        });
-        self.debug(format!("{}", cmd.as_str().trim_end()));
+        self.record_call(cmd_line);
         self.system.check_call(&CallParams {
             cmd_line: cmd_line.to_owned(),
             env: env.to_owned(),
@@ -82,6 +100,132 @@ impl Cli {
         Ok(())
     }
 
+    /*
+     * Log and record a command line for the current thread. Used by check_call
+     * and by the docker executer when a docker command line is run using the
+     * docker engine API instead of the docker CLI.
+     */
+    pub fn record_call(&self, cmd_line: &Vec<String>) {
+        let mut cmd: String = String::new();
+        cmd_line.iter().for_each(|c| {
+            cmd.push_str(c);
+            cmd.push(' ');
+        });
+        self.debug(format!("{}", cmd.as_str().trim_end()));
+        if let Ok(mut calls) = self.calls.lock() {
+            calls
+                .entry(std::thread::current().id())
+                .or_default()
+                .push(cmd_line.to_owned());
+        }
+    }
+
+    /*
+     * Write a line of output from a command that is not run by the system,
+     * like a container run using the docker engine API, to the terminal, the
+     * log file and the task log of the current thread
+     */
+    pub fn output(&self, line: &str, stderr: bool) {
+        self.log_file
+            .output(self.log_file.task().as_ref(), line, stderr);
+    }
+
+    /*
+     * Returns all the command lines passed to check_call from the current
+     * thread since the last time the calls were taken
+     */
+    pub fn take_calls(&self) -> Vec<Vec<String>> {
+        match self.calls.lock() {
+            Ok(mut calls) => calls
+                .remove(&std::thread::current().id())
+                .unwrap_or_default(),
+            Err(_e) => vec![],
+        }
+    }
+
+    /*
+     * Register the value of a secret so that it is redacted from
+     * all the output. The secrets are kept by the log file which is
+     * redacting both the messages to the terminal and the log files.
+     */
+    pub fn add_secret(&self, value: &str) {
+        self.log_file.add_secret(value);
+    }
+
+    pub fn is_secret(&self, value: &str) -> bool {
+        self.log_file.is_secret(value)
+    }
+
+    pub fn contains_secret(&self, content: &str) -> bool {
+        self.log_file.contains_secret(content)
+    }
+
+    fn redact(&self, message: String) -> String {
+        self.log_file.redact(&message)
+    }
+
+    pub fn log_level(&self) -> LogLevel {
+        if self.verbose {
+            return LogLevel::Debug;
+        }
+        self.log_level
+    }
+
+    pub fn log_format(&self) -> LogFormat {
+        self.log_format
+    }
+
+    /*
+     * Share the log file with the logger and the system so that
+     * opening it will start logging to it
+     */
+    pub fn set_log_file(&mut self, log_file: Arc<LogFile>) {
+        self.log_file = log_file;
+    }
+
+    /*
+     * Start logging the messages and the output of the child processes
+     * to a file, the file is truncated if it already exists
+     */
+    pub fn open_log_file(&self, path: &Path) -> Result<(), BError> {
+        self.log_file.open(path)?;
+        self.debug(format!("Logging to {}", path.display()));
+        Ok(())
+    }
+
+    /*
+     * Stop logging to the log file when the command has been executed
+     */
+    pub fn close_log_file(&self) {
+        self.log_file.close();
+    }
+
+    /*
+     * Prefix the output of the tasks with the task name, used when
+     * tasks are running concurrently
+     */
+    pub fn set_task_prefix(&self, prefix: bool) {
+        self.log_file.set_prefix(prefix);
+    }
+
+    /*
+     * Log the output of the commands executed by the current thread to a task
+     * log file. Only done when the build is logged to a log file.
+     */
+    pub fn start_task_log(&self, name: &str, path: &Path) -> Result<(), BError> {
+        if self.log_file.start_task(name, path)? {
+            self.debug(format!("Logging task '{}' to {}", name, path.display()));
+        }
+        Ok(())
+    }
+
+    /*
+     * Stop logging to the task log file and return the last lines of output
+     */
+    pub fn finish_task_log(&self) -> Vec<String> {
+        self.log_file.finish_task()
+    }
+
     pub fn rmdir_all(&self, path: &PathBuf) -> Result<(), BError> {
         self.system.rmdir_all(path)?;
         Ok(())
@@ -146,24 +290,28 @@ impl Cli {
     }
 
     pub fn info(&self, message: String) {
-        (*self.logger).info(message);
+        if self.log_level() >= LogLevel::Info {
+            (*self.logger).info(self.redact(message));
+        }
     }
 
     pub fn debug(&self, message: String) {
-        if self.verbose {
-            (*self.logger).debug(message);
+        if self.log_level() >= LogLevel::Debug {
+            (*self.logger).debug(self.redact(message));
         }
     }
 
-    pub fn _warn(&self, message: String) {
-        (*self.logger).warn(message);
+    pub fn warn(&self, message: String) {
+        if self.log_level() >= LogLevel::Warn {
+            (*self.logger).warn(self.redact(message));
+        }
     }
 
     pub fn error(&self, message: String) {
-        (*self.logger).error(message);
+        (*self.logger).error(self.redact(message));
     }
 
     pub fn stdout(&self, message: String) {
-        (*self.logger).stdout(message);
+        (*self.logger).stdout(self.redact(message));
     }
 }
@@ -1,7 +1,331 @@
+use chrono::Local;
 use mockall::*;
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::ThreadId;
+
+use crate::error::BError;
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub fn from_str(level: &str) -> Result<Self, BError> {
+        match level {
+            "error" => Ok(LogLevel::Error),
+            "warn" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            _ => Err(BError::ValueError(format!("Invalid log level '{}'", level))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    pub fn from_str(format: &str) -> Result<Self, BError> {
+        match format {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(BError::ValueError(format!(
+                "Invalid log format '{}'",
+                format
+            ))),
+        }
+    }
+
+    /*
+     * Format a message, the level is None for the output of the commands
+     * and the child processes which should be printed as is in text format
+     */
+    pub fn line(&self, level: Option<LogLevel>, message: &str, timestamp: bool) -> String {
+        let time: String = Local::now().format("%Y-%m-%dT%H:%M:%S%.3f").to_string();
+        match self {
+            LogFormat::Json => json!({
+                "time": time,
+                "level": level.map(|l| l.as_str()).unwrap_or("output"),
+                "message": message,
+            })
+            .to_string(),
+            LogFormat::Text => {
+                let mut line: String = String::new();
+                if timestamp {
+                    line.push_str(&time);
+                    line.push(' ');
+                }
+                if let Some(l) = level {
+                    line.push_str(&format!("{}: ", l.as_str().to_ascii_uppercase()));
+                }
+                line.push_str(message);
+                line
+            }
+        }
+    }
+}
+
+/* The number of lines of output kept per task and reported if the task fails */
+pub const TASK_LOG_TAIL: usize = 20;
+
+/*
+ * The output of the commands executed by a task is written to a log file
+ * per task and the last lines are kept so that they can be reported if the
+ * task fails.
+ */
+pub struct TaskLog {
+    name: String,
+    file: File,
+    tail: VecDeque<String>,
+}
+
+impl TaskLog {
+    fn write(&mut self, line: &str) {
+        let _ = writeln!(self.file, "{}", line);
+        if self.tail.len() == TASK_LOG_TAIL {
+            self.tail.pop_front();
+        }
+        self.tail.push_back(line.to_string());
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/*
+ * The log file is shared by the logger and the system so that both the
+ * messages from bakery and the output from the child processes ends up
+ * in the same file. Nothing is written until a file has been opened. The
+ * format is also used by the logger for the messages to the terminal.
+ */
+pub struct LogFile {
+    format: Mutex<LogFormat>,
+    file: Mutex<Option<(PathBuf, File)>>,
+    secrets: Mutex<Vec<String>>,
+    // The task logs are kept per thread since tasks can be built in parallel
+    tasks: Mutex<HashMap<ThreadId, Arc<Mutex<TaskLog>>>>,
+    prefix: AtomicBool,
+}
+
+impl LogFile {
+    pub fn new(format: LogFormat) -> Self {
+        LogFile {
+            format: Mutex::new(format),
+            file: Mutex::new(None),
+            secrets: Mutex::new(Vec::new()),
+            tasks: Mutex::new(HashMap::new()),
+            prefix: AtomicBool::new(false),
+        }
+    }
+
+    pub fn format(&self) -> LogFormat {
+        match self.format.lock() {
+            Ok(f) => *f,
+            Err(_e) => LogFormat::Text,
+        }
+    }
+
+    pub fn set_format(&self, format: LogFormat) {
+        if let Ok(mut f) = self.format.lock() {
+            *f = format;
+        }
+    }
+
+    pub fn open(&self, path: &Path) -> Result<(), BError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file: File = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)?;
+        if let Ok(mut f) = self.file.lock() {
+            *f = Some((path.to_path_buf(), file));
+        }
+        Ok(())
+    }
+
+    pub fn path(&self) -> Option<PathBuf> {
+        match self.file.lock() {
+            Ok(f) => f.as_ref().map(|(p, _file)| p.clone()),
+            Err(_e) => None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.path().is_some()
+    }
+
+    pub fn close(&self) {
+        if let Ok(mut f) = self.file.lock() {
+            *f = None;
+        }
+    }
+
+    pub fn add_secret(&self, value: &str) {
+        if value.is_empty() {
+            return;
+        }
+        if let Ok(mut secrets) = self.secrets.lock() {
+            if !secrets.iter().any(|s| s == value) {
+                secrets.push(value.to_string());
+                // Replace the longest values first in case one secret contains another
+                secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+            }
+        }
+    }
+
+    pub fn is_secret(&self, value: &str) -> bool {
+        match self.secrets.lock() {
+            Ok(secrets) => secrets.iter().any(|s| s == value),
+            Err(_e) => false,
+        }
+    }
+
+    pub fn contains_secret(&self, content: &str) -> bool {
+        match self.secrets.lock() {
+            Ok(secrets) => secrets.iter().any(|s| content.contains(s.as_str())),
+            Err(_e) => false,
+        }
+    }
+
+    pub fn redact(&self, message: &str) -> String {
+        match self.secrets.lock() {
+            Ok(secrets) => secrets.iter().fold(message.to_string(), |msg, secret| {
+                msg.replace(secret.as_str(), "****")
+            }),
+            Err(_e) => message.to_string(),
+        }
+    }
+
+    /*
+     * When tasks are running concurrently the output lines are prefixed
+     * with the name of the task
+     */
+    pub fn set_prefix(&self, prefix: bool) {
+        self.prefix.store(prefix, Ordering::Relaxed);
+    }
+
+    /*
+     * Start logging the output of the commands executed from the current
+     * thread to a task log file. The task logs are only written when the
+     * log file is open. Returns true if the task log was started.
+     */
+    pub fn start_task(&self, name: &str, path: &Path) -> Result<bool, BError> {
+        if !self.is_open() {
+            return Ok(false);
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file: File = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)?;
+        if let Ok(mut tasks) = self.tasks.lock() {
+            tasks.insert(
+                std::thread::current().id(),
+                Arc::new(Mutex::new(TaskLog {
+                    name: name.to_string(),
+                    file,
+                    tail: VecDeque::new(),
+                })),
+            );
+        }
+        Ok(true)
+    }
+
+    /*
+     * Stop logging to the task log of the current thread and return
+     * the last lines of output from the task
+     */
+    pub fn finish_task(&self) -> Vec<String> {
+        let task: Option<Arc<Mutex<TaskLog>>> = match self.tasks.lock() {
+            Ok(mut tasks) => tasks.remove(&std::thread::current().id()),
+            Err(_e) => None,
+        };
+        match task {
+            Some(t) => match t.lock() {
+                Ok(log) => log.tail.iter().cloned().collect(),
+                Err(_e) => vec![],
+            },
+            None => vec![],
+        }
+    }
+
+    /*
+     * The task log of the current thread if any
+     */
+    pub fn task(&self) -> Option<Arc<Mutex<TaskLog>>> {
+        match self.tasks.lock() {
+            Ok(tasks) => tasks.get(&std::thread::current().id()).cloned(),
+            Err(_e) => None,
+        }
+    }
+
+    /*
+     * Write a line of output from a child process to the terminal, the
+     * log file and the task log
+     */
+    pub fn output(&self, task: Option<&Arc<Mutex<TaskLog>>>, line: &str, stderr: bool) {
+        let line: String = self.redact(line);
+        let mut out: String = line.clone();
+        if let Some(t) = task {
+            if let Ok(mut log) = t.lock() {
+                if self.prefix.load(Ordering::Relaxed) {
+                    out = format!("[{}] {}", log.name(), line);
+                }
+                log.write(&line);
+            }
+        }
+        if stderr {
+            eprintln!("{}", out);
+        } else {
+            println!("{}", out);
+        }
+        self.write(None, &out);
+    }
+
+    /*
+     * The lines in the log file are always timestamped
+     */
+    pub fn write(&self, level: Option<LogLevel>, message: &str) {
+        if let Ok(mut f) = self.file.lock() {
+            if let Some((_path, file)) = f.as_mut() {
+                let line: String = self.format().line(level, &self.redact(message), true);
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+}
 
 #[automock]
-pub trait Logger {
+pub trait Logger: Send + Sync {
     fn info(&self, message: String);
 
     fn warn(&self, message: String);
@@ -13,32 +337,161 @@ pub trait Logger {
     fn debug(&self, message: String);
 }
 
-pub struct BLogger {}
+pub struct BLogger {
+    log_file: Arc<LogFile>,
+}
 
 impl BLogger {
     pub fn new() -> Self {
-        BLogger {}
+        Self::with_log_file(Arc::new(LogFile::new(LogFormat::Text)))
+    }
+
+    pub fn with_log_file(log_file: Arc<LogFile>) -> Self {
+        BLogger { log_file }
+    }
+
+    fn log(&self, level: Option<LogLevel>, message: String) {
+        let line: String = self.log_file.format().line(level, &message, false);
+        if level == Some(LogLevel::Error) {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+        self.log_file.write(level, &message);
     }
 }
 
 impl Logger for BLogger {
     fn info(&self, message: String) {
-        self.stdout(format!("INFO: {}", message));
+        self.log(Some(LogLevel::Info), message);
     }
 
     fn warn(&self, message: String) {
-        println!("WARN: {}", message);
+        self.log(Some(LogLevel::Warn), message);
     }
 
     fn error(&self, message: String) {
-        eprintln!("ERROR: {}", message);
+        self.log(Some(LogLevel::Error), message);
     }
 
     fn debug(&self, message: String) {
-        println!("DEBUG: {}", message);
+        self.log(Some(LogLevel::Debug), message);
     }
 
     fn stdout(&self, message: String) {
-        println!("{}", message);
+        self.log(None, message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use tempdir::TempDir;
+
+    use crate::cli::logger::{LogFile, LogFormat, LogLevel, TASK_LOG_TAIL};
+    use crate::cli::{BLogger, Logger};
+
+    #[test]
+    fn test_logger_level() {
+        assert!(LogLevel::Debug > LogLevel::Info);
+        assert!(LogLevel::Warn < LogLevel::Info);
+        assert_eq!(LogLevel::from_str("warn").unwrap(), LogLevel::Warn);
+        match LogLevel::from_str("trace") {
+            Ok(_level) => {
+                panic!("We should have recived an error because the level is invalid");
+            }
+            Err(e) => {
+                assert_eq!(e.to_string(), "Invalid log level 'trace'");
+            }
+        }
+    }
+
+    #[test]
+    fn test_logger_format() {
+        assert_eq!(
+            LogFormat::Text.line(Some(LogLevel::Info), "test message", false),
+            "INFO: test message"
+        );
+        assert_eq!(
+            LogFormat::Text.line(None, "test message", false),
+            "test message"
+        );
+        let line: String = LogFormat::Json.line(Some(LogLevel::Warn), "test message", false);
+        let value: Value = serde_json::from_str(&line).expect("Failed to parse json line");
+        assert_eq!(value["level"], "warn");
+        assert_eq!(value["message"], "test message");
+        assert!(value["time"].is_string());
+    }
+
+    #[test]
+    fn test_logger_log_file() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let path: PathBuf = temp_dir.path().join("logs/bakery-default.log");
+        let log_file: Arc<LogFile> = Arc::new(LogFile::new(LogFormat::Text));
+        let logger: BLogger = BLogger::with_log_file(log_file.clone());
+        logger.info(String::from("not logged to file"));
+        log_file.open(&path).expect("Failed to open log file");
+        assert_eq!(log_file.path(), Some(path.clone()));
+        log_file.add_secret("secret-token");
+        logger.info(String::from("test info"));
+        logger.error(String::from("test error secret-token"));
+        log_file.write(None, "child output");
+        log_file.close();
+        logger.info(String::from("not logged to file"));
+        let content: String = std::fs::read_to_string(&path).expect("Failed to read log file");
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with(" INFO: test info"));
+        assert!(lines[1].ends_with(" ERROR: test error ****"));
+        assert!(lines[2].ends_with(" child output"));
+    }
+
+    #[test]
+    fn test_logger_secrets() {
+        let log_file: LogFile = LogFile::new(LogFormat::Text);
+        log_file.add_secret("");
+        log_file.add_secret("token");
+        log_file.add_secret("secret-token");
+        assert!(log_file.is_secret("token"));
+        assert!(!log_file.is_secret("secret"));
+        assert!(log_file.contains_secret("TOKEN=secret-token"));
+        assert!(!log_file.contains_secret("no secrets"));
+        assert_eq!(log_file.redact("secret-token and token"), "**** and ****");
+        assert_eq!(log_file.redact(""), "");
+    }
+
+    #[test]
+    fn test_logger_task_log() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let path: PathBuf = temp_dir.path().join("logs/bakery-default.log");
+        let task_path: PathBuf = temp_dir.path().join("logs/image.log");
+        let log_file: LogFile = LogFile::new(LogFormat::Text);
+        /* No task log is written unless the build is logged */
+        assert!(!log_file.start_task("image", &task_path).unwrap());
+        assert!(log_file.task().is_none());
+        log_file.open(&path).expect("Failed to open log file");
+        log_file.set_prefix(true);
+        assert!(log_file.start_task("image", &task_path).unwrap());
+        let task = log_file.task();
+        assert!(task.is_some());
+        for i in 0..TASK_LOG_TAIL + 5 {
+            log_file.output(task.as_ref(), &format!("line {}", i), false);
+        }
+        let tail: Vec<String> = log_file.finish_task();
+        assert!(log_file.task().is_none());
+        assert_eq!(tail.len(), TASK_LOG_TAIL);
+        assert_eq!(tail.first(), Some(&String::from("line 5")));
+        assert_eq!(tail.last(), Some(&format!("line {}", TASK_LOG_TAIL + 4)));
+        let task_content: String =
+            std::fs::read_to_string(&task_path).expect("Failed to read task log");
+        assert_eq!(task_content.lines().count(), TASK_LOG_TAIL + 5);
+        assert_eq!(task_content.lines().next(), Some("line 0"));
+        /* The lines in the build log are prefixed with the task name */
+        let content: String = std::fs::read_to_string(&path).expect("Failed to read log file");
+        assert!(content.lines().next().unwrap().ends_with(" [image] line 0"));
     }
 }
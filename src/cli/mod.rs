@@ -7,6 +7,6 @@ pub use bakery::Bakery;
 pub use cli::Cli;
 #[cfg(test)]
 pub use logger::MockLogger;
-pub use logger::{BLogger, Logger};
+pub use logger::{BLogger, LogFile, LogFormat, LogLevel, Logger, TaskLog};
 pub use system::MockSystem;
 pub use system::{BSystem, CallParams, System};
@@ -1,10 +1,14 @@
+use crate::cli::{LogFile, TaskLog};
 use crate::error::BError;
 
 use mockall::*;
 use std::collections::HashMap;
 use std::fmt;
+use std::io::{BufRead, BufReader, Read};
 use std::path::PathBuf;
 use std::str;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 
 /*
 Tried using "withf" and closure when mocking the check_call for testing
@@ -35,7 +39,7 @@ impl fmt::Display for CallParams {
 }
 
 #[automock]
-pub trait System {
+pub trait System: Send + Sync {
     fn check_call(&self, params: &CallParams) -> Result<(), BError>;
     fn init_env_file(
         &self,
@@ -46,11 +50,32 @@ pub trait System {
     fn env(&self) -> HashMap<String, String>;
 }
 
-pub struct BSystem {}
+pub struct BSystem {
+    log_file: Arc<LogFile>,
+}
 
 impl BSystem {
-    pub fn new() -> Self {
-        BSystem {}
+    pub fn with_log_file(log_file: Arc<LogFile>) -> Self {
+        BSystem { log_file }
+    }
+
+    /*
+     * Read the output of the child line by line and write it to the terminal,
+     * the log file and the log of the task executing the command. The task
+     * log has to be looked up by the caller since it is kept per thread.
+     */
+    fn tee<R: Read + Send + 'static>(
+        reader: R,
+        log_file: Arc<LogFile>,
+        task: Option<Arc<Mutex<TaskLog>>>,
+        stderr: bool,
+    ) -> JoinHandle<()> {
+        std::thread::spawn(move || {
+            let reader: BufReader<R> = BufReader::new(reader);
+            for line in reader.lines().map_while(Result::ok) {
+                log_file.output(task.as_ref(), &line, stderr);
+            }
+        })
     }
 }
 
@@ -66,31 +91,47 @@ impl System for BSystem {
 
         // TODO: we should consider how to handle different shells for now we
         // will stick to bash since that is what OE/Yocto requires
-        let mut child: std::process::Child = std::process::Command::new("/bin/bash")
+        let mut command: std::process::Command = std::process::Command::new("/bin/bash");
+        command
             .arg("-c")
             .arg(cmd.as_str().trim_end())
-            .stdout(os_pipe::dup_stdout()?)
-            .stderr(os_pipe::dup_stderr()?)
             .env_clear()
-            .envs(&params.env)
-            .spawn()?;
+            .envs(&params.env);
 
         /*
-        Lets keep this for now we might need this later
-        let stdout_reader = std::io::BufReader::new(child.stdout.take().unwrap());
-        let stderr_reader = std::io::BufReader::new(child.stderr.take().unwrap());
-        let stdout_lines = stdout_reader.lines();
-        let stderr_lines = stderr_reader.lines();
-        let lines = stdout_lines.chain(stderr_lines);
-
-        for line in lines {
-            if let Ok(line) = line {
-                println!("{}", line);
-            }
-        }*/
-
-        // Wait for the command to finish
-        let status: std::process::ExitStatus = child.wait()?;
+         * If there is an open log file the output of the child is captured
+         * and written to the log file as well as to the terminal otherwise
+         * the child is writing directly to the terminal
+         */
+        let status: std::process::ExitStatus = if self.log_file.is_open() {
+            let (stdout_reader, stdout_writer) = os_pipe::pipe()?;
+            let (stderr_reader, stderr_writer) = os_pipe::pipe()?;
+            let mut child: std::process::Child = command
+                .stdout(stdout_writer)
+                .stderr(stderr_writer)
+                .spawn()?;
+            /*
+             * The command holds on to the write ends of the pipes, drop it
+             * so that the readers are closed when the child exits
+             */
+            drop(command);
+            let task: Option<Arc<Mutex<TaskLog>>> = self.log_file.task();
+            let stdout_handle: JoinHandle<()> =
+                Self::tee(stdout_reader, self.log_file.clone(), task.clone(), false);
+            let stderr_handle: JoinHandle<()> =
+                Self::tee(stderr_reader, self.log_file.clone(), task, true);
+            let status: std::process::ExitStatus = child.wait()?;
+            let _ = stdout_handle.join();
+            let _ = stderr_handle.join();
+            status
+        } else {
+            let mut child: std::process::Child = command
+                .stdout(os_pipe::dup_stdout()?)
+                .stderr(os_pipe::dup_stderr()?)
+                .spawn()?;
+            // Wait for the command to finish
+            child.wait()?
+        };
         if !status.success() {
             return Err(BError::CliError(format!("{}", status)));
         }
@@ -170,10 +211,11 @@ impl System for BSystem {
 mod tests {
     use std::collections::HashMap;
     use std::path::PathBuf;
+    use std::sync::Arc;
     use std::{fs::File, io::Write};
     use tempdir::TempDir;
 
-    use crate::cli::{BSystem, System};
+    use crate::cli::{BSystem, LogFile, LogFormat, System};
     use crate::error::BError;
 
     use super::CallParams;
@@ -194,7 +236,7 @@ mod tests {
         test_file
             .write_all(env.as_bytes())
             .expect("Failed to write init env test file");
-        let system: BSystem = BSystem::new();
+        let system: BSystem = BSystem::with_log_file(Arc::new(LogFile::new(LogFormat::Text)));
         let envs: HashMap<String, String> = system
             .init_env_file(&test_file_path, &build_dir)
             .expect("Failed to process init env test file");
@@ -230,7 +272,7 @@ mod tests {
         let env: &str = r#"#!/bin/sh
         garbage sdlkjsdklsjdlskj"#;
         test_file.write_all(env.as_bytes()).expect("Failed to write init env test file");
-        let system: BSystem = BSystem::new();
+        let system: BSystem = BSystem::with_log_file(Arc::new(LogFile::new(LogFormat::Text)));
         let result: Result<HashMap<String, String>, BError> = system.init_env_file(&test_file_path, &build_dir);
         match result {
             Ok(_env) => {
@@ -250,7 +292,7 @@ mod tests {
         let work_dir: PathBuf = PathBuf::from(temp_dir.path());
         let build_dir: PathBuf = work_dir.clone().join("build");
         let test_file_path: PathBuf = work_dir.clone().join("init_env");
-        let system: BSystem = BSystem::new();
+        let system: BSystem = BSystem::with_log_file(Arc::new(LogFile::new(LogFormat::Text)));
         let result: Result<HashMap<String, String>, BError> =
             system.init_env_file(&test_file_path, &build_dir);
         match result {
@@ -271,7 +313,7 @@ mod tests {
 
     #[test]
     fn test_system_check_call_error() {
-        let system: BSystem = BSystem::new();
+        let system: BSystem = BSystem::with_log_file(Arc::new(LogFile::new(LogFormat::Text)));
         let params: CallParams = CallParams {
             cmd_line: vec!["exit 1".to_string()],
             env: HashMap::new(),
@@ -293,7 +335,7 @@ mod tests {
         let temp_dir: TempDir =
             TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
         let work_dir: PathBuf = PathBuf::from(temp_dir.path());
-        let system: BSystem = BSystem::new();
+        let system: BSystem = BSystem::with_log_file(Arc::new(LogFile::new(LogFormat::Text)));
         let mut env: HashMap<String, String> = HashMap::new();
         env.insert(
             "TEST_FILE1".to_string(),
@@ -320,4 +362,28 @@ mod tests {
         assert!(work_dir.clone().join("test2").exists());
         assert!(work_dir.clone().join("test3").exists());
     }
+
+    #[test]
+    fn test_system_check_call_log_file() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let log_path: PathBuf = temp_dir.path().join("logs/bakery-default.log");
+        let log_file: Arc<LogFile> = Arc::new(LogFile::new(LogFormat::Text));
+        log_file.open(&log_path).expect("Failed to open log file");
+        log_file.add_secret("secret-token");
+        let system: BSystem = BSystem::with_log_file(log_file.clone());
+        let params: CallParams = CallParams {
+            cmd_line: vec![String::from(
+                "echo stdout line; echo stderr line >&2; echo secret-token",
+            )],
+            env: HashMap::new(),
+            shell: true,
+        };
+        system.check_call(&params).expect("Failed to run command");
+        let content: String = std::fs::read_to_string(&log_path).expect("Failed to read log file");
+        assert!(content.lines().any(|l| l.ends_with(" stdout line")));
+        assert!(content.lines().any(|l| l.ends_with(" stderr line")));
+        assert!(content.lines().any(|l| l.ends_with(" ****")));
+        assert!(!content.contains("secret-token"));
+    }
 }
@@ -32,7 +32,20 @@ impl<'a> Collector for ArchiveCollector<'a> {
         }
 
         let files: Vec<PathBuf> = collected.iter().map(|f| f.dest.clone()).collect();
-        let archiver: Archiver = Archiver::new(&archive_path)?;
+        let mut archiver: Archiver = Archiver::new(&archive_path)?;
+        if let Some(level) = self.compression_level()? {
+            archiver.set_compression_level(level)?;
+        }
+        if let Some(epoch) = self.reproducible()? {
+            self.info(
+                self.cli,
+                format!(
+                    "Creating reproducible archive '{}' using source date epoch {}",
+                    archive_name, epoch
+                ),
+            );
+            archiver.set_reproducible(epoch);
+        }
         archiver.add_files(&files, &archive_tmp_dir)?;
         self.info(
             self.cli,
@@ -42,10 +55,7 @@ impl<'a> Collector for ArchiveCollector<'a> {
             ),
         );
 
-        Ok(vec![Collected {
-            src: PathBuf::from(""),
-            dest: archive_path,
-        }])
+        Ok(vec![Collected::new(&PathBuf::from(""), &archive_path)?])
     }
 
     fn verify_attributes(&self) -> Result<(), BError> {
@@ -54,6 +64,8 @@ impl<'a> Collector for ArchiveCollector<'a> {
                 "Archive node requires name and list of artifacts!",
             )));
         }
+        self.compression_level()?;
+        self.reproducible()?;
         Ok(())
     }
 }
@@ -62,15 +74,78 @@ impl<'a> ArchiveCollector<'a> {
     pub fn new(artifact: &'a WsArtifactsHandler, cli: Option<&'a Cli>) -> Self {
         ArchiveCollector { artifact, cli }
     }
+
+    fn compression_level(&self) -> Result<Option<u32>, BError> {
+        let level: &str = self.artifact.data().compression_level();
+        if level.is_empty() {
+            return Ok(None);
+        }
+
+        match level.parse::<u32>() {
+            Ok(l) => Ok(Some(l)),
+            Err(_e) => Err(BError::ValueError(format!(
+                "Invalid compression level '{}' for archive '{}'!",
+                level,
+                self.artifact.data().name()
+            ))),
+        }
+    }
+
+    /*
+     * The source date epoch is taken from the context variable
+     * BKRY_SOURCE_DATE_EPOCH and if it is not set from the environment
+     * variable SOURCE_DATE_EPOCH.
+     */
+    fn source_date_epoch(&self) -> Result<Option<u64>, BError> {
+        let mut epoch: String = self.artifact.data().source_date_epoch().to_string();
+        if epoch.is_empty() {
+            epoch = std::env::var("SOURCE_DATE_EPOCH").unwrap_or_default();
+        }
+
+        if epoch.is_empty() {
+            return Ok(None);
+        }
+
+        match epoch.trim().parse::<u64>() {
+            Ok(e) => Ok(Some(e)),
+            Err(_e) => Err(BError::ValueError(format!(
+                "Invalid source date epoch '{}'!",
+                epoch
+            ))),
+        }
+    }
+
+    /*
+     * Returns the source date epoch to use if the archive should be reproducible.
+     * By default the archive is reproducible if there is a source date epoch.
+     */
+    fn reproducible(&self) -> Result<Option<u64>, BError> {
+        let epoch: Option<u64> = self.source_date_epoch()?;
+        match self.artifact.data().reproducible() {
+            "" => Ok(epoch),
+            "true" => Ok(Some(epoch.unwrap_or(0))),
+            "false" => Ok(None),
+            value => Err(BError::ValueError(format!(
+                "Invalid reproducible value '{}' for archive '{}'!",
+                value,
+                self.artifact.data().name()
+            ))),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::collector::{ArchiveCollector, Collected, Collector};
+    use crate::configs::Context;
     use crate::data::WsBuildData;
+    use crate::error::BError;
+    use crate::fs::Archiver;
     use crate::helper::Helper;
     use crate::workspace::WsArtifactsHandler;
 
+    use indexmap::{indexmap, IndexMap};
+    use std::fs::File;
     use std::path::PathBuf;
     use tempdir::TempDir;
 
@@ -115,10 +190,11 @@ mod tests {
             .expect("Failed to collect artifacts");
         assert_eq!(
             collected,
-            vec![Collected {
-                src: PathBuf::from(""),
-                dest: artifacts_dir.clone().join(archive_name)
-            }]
+            vec![Collected::new(
+                &PathBuf::from(""),
+                &artifacts_dir.clone().join(archive_name)
+            )
+            .unwrap()]
         );
         for c in collected.iter() {
             assert!(c.dest.exists());
@@ -180,13 +256,173 @@ mod tests {
             .expect("Failed to collect artifacts");
         assert_eq!(
             collected,
-            vec![Collected {
-                src: PathBuf::from(""),
-                dest: artifacts_dir.clone().join(archive_name)
-            }]
+            vec![Collected::new(
+                &PathBuf::from(""),
+                &artifacts_dir.clone().join(archive_name)
+            )
+            .unwrap()]
         );
         for c in collected.iter() {
             assert!(c.dest.exists());
         }
     }
+
+    #[test]
+    fn test_archive_collector_compression_level() {
+        let archive_name: &str = "archive.tar.zst";
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from(temp_dir.path());
+        let task_build_dir: PathBuf = work_dir.clone().join("task/dir");
+        let files: Vec<PathBuf> = vec![
+            task_build_dir.clone().join("file1.txt"),
+            task_build_dir.clone().join("file2.txt"),
+        ];
+        let json_artifacts_config: &str = r#"
+        {
+            "type": "archive",
+            "name": "archive.tar.zst",
+            "compression_level": "19",
+            "artifacts": [
+                {
+                    "source": "file1.txt"
+                },
+                {
+                    "source": "file2.txt"
+                }
+            ]
+        }"#;
+        let build_data: WsBuildData = Helper::setup_build_data(&work_dir, None, None);
+        let artifacts: WsArtifactsHandler = Helper::setup_collector_test_ws(
+            &work_dir,
+            &task_build_dir,
+            &files,
+            &build_data,
+            json_artifacts_config,
+        );
+        let collector: ArchiveCollector = ArchiveCollector::new(&artifacts, None);
+        assert!(collector.verify_attributes().is_ok());
+        let artifacts_dir: PathBuf = build_data.settings().artifacts_dir();
+        let collected: Vec<Collected> = collector
+            .collect(&task_build_dir, &artifacts_dir)
+            .expect("Failed to collect artifacts");
+        assert_eq!(
+            collected,
+            vec![Collected::new(&PathBuf::from(""), &artifacts_dir.join(archive_name)).unwrap()]
+        );
+        let archiver: Archiver =
+            Archiver::new(&artifacts_dir.join(archive_name)).expect("Failed to setup archiver!");
+        let archived_files: Vec<PathBuf> =
+            Helper::list_files_in_archive(&archiver, &work_dir.join("unpack"))
+                .expect("Failed to list files in archive");
+        Helper::verify_archived_files(&files, &archived_files, &task_build_dir);
+    }
+
+    #[test]
+    fn test_archive_collector_invalid_compression_level() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from(temp_dir.path());
+        let task_build_dir: PathBuf = work_dir.clone().join("task/dir");
+        let files: Vec<PathBuf> = vec![task_build_dir.clone().join("file1.txt")];
+        let json_artifacts_config: &str = r#"
+        {
+            "type": "archive",
+            "name": "archive.tar.gz",
+            "compression_level": "best",
+            "artifacts": [
+                {
+                    "source": "file1.txt"
+                }
+            ]
+        }"#;
+        let build_data: WsBuildData = Helper::setup_build_data(&work_dir, None, None);
+        let artifacts: WsArtifactsHandler = Helper::setup_collector_test_ws(
+            &work_dir,
+            &task_build_dir,
+            &files,
+            &build_data,
+            json_artifacts_config,
+        );
+        let collector: ArchiveCollector = ArchiveCollector::new(&artifacts, None);
+        let result: Result<(), BError> = collector.verify_attributes();
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because the compression level is invalid!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Invalid compression level 'best' for archive 'archive.tar.gz'!"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_archive_collector_reproducible() {
+        let archive_name: &str = "archive.zip";
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from(temp_dir.path());
+        let task_build_dir: PathBuf = work_dir.clone().join("task/dir");
+        let files: Vec<PathBuf> = vec![
+            task_build_dir.clone().join("file1.txt"),
+            task_build_dir.clone().join("file2.txt"),
+        ];
+        let json_artifacts_config: &str = r#"
+        {
+            "type": "archive",
+            "name": "archive.zip",
+            "artifacts": [
+                {
+                    "source": "file2.txt"
+                },
+                {
+                    "source": "file1.txt"
+                }
+            ]
+        }"#;
+        let build_data: WsBuildData = Helper::setup_build_data(&work_dir, None, None);
+        let mut artifacts: WsArtifactsHandler = Helper::setup_collector_test_ws(
+            &work_dir,
+            &task_build_dir,
+            &files,
+            &build_data,
+            json_artifacts_config,
+        );
+        let variables: IndexMap<String, String> = indexmap! {
+            "BKRY_SOURCE_DATE_EPOCH".to_string() => "1700000000".to_string(),
+        };
+        artifacts.expand_ctx(&Context::new(&variables)).unwrap();
+        let collector: ArchiveCollector = ArchiveCollector::new(&artifacts, None);
+        assert!(collector.verify_attributes().is_ok());
+        let collected1: Vec<Collected> = collector
+            .collect(&task_build_dir, &work_dir.join("artifacts1"))
+            .expect("Failed to collect artifacts");
+        let collected2: Vec<Collected> = collector
+            .collect(&task_build_dir, &work_dir.join("artifacts2"))
+            .expect("Failed to collect artifacts");
+        assert_eq!(collected1[0].sha256, collected2[0].sha256);
+        let file: File = File::open(work_dir.join("artifacts1").join(archive_name))
+            .expect("Failed to open archive");
+        let mut zip: zip::ZipArchive<File> =
+            zip::ZipArchive::new(file).expect("Failed to setup zip archive");
+        let entry: zip::read::ZipFile<'_> = zip.by_index(0).expect("Failed to read archive");
+        // The entries are sorted and the mtime is clamped to the source date epoch
+        assert_eq!(entry.name(), "file1.txt");
+        assert_eq!(entry.unix_mode(), Some(0o100644));
+        let mtime: zip::DateTime = entry.last_modified();
+        assert_eq!(
+            (
+                mtime.year(),
+                mtime.month(),
+                mtime.day(),
+                mtime.hour(),
+                mtime.minute(),
+                mtime.second()
+            ),
+            (2023, 11, 14, 22, 13, 20)
+        );
+    }
 }
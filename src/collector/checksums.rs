@@ -0,0 +1,147 @@
+use crate::cli::Cli;
+use crate::collector::{Collected, Collector};
+use crate::error::BError;
+use crate::fs::Checksums;
+use crate::workspace::WsArtifactsHandler;
+
+use std::path::PathBuf;
+
+pub struct ChecksumsCollector<'a> {
+    artifact: &'a WsArtifactsHandler,
+    cli: Option<&'a Cli>,
+}
+
+impl<'a> Collector for ChecksumsCollector<'a> {
+    /*
+     * The checksums file is created once everything else has been
+     * collected by the task so nothing is done when collecting.
+     */
+    fn post_collect(
+        &self,
+        dest: &PathBuf,
+        collected: &Vec<Collected>,
+    ) -> Result<Vec<Collected>, BError> {
+        let checksums_file: &str = self.artifact.data().name();
+        let checksums_path: PathBuf = dest.join(PathBuf::from(checksums_file));
+        let checksums: Checksums = Checksums::new(&checksums_path);
+        let files: Vec<(PathBuf, String)> = collected
+            .iter()
+            .filter(|c| !c.sha256.is_empty() && c.dest != checksums_path)
+            .map(|c| (c.dest.clone(), c.sha256.clone()))
+            .collect();
+
+        self.info(
+            self.cli,
+            format!("Creating checksums file '{}'", checksums_file),
+        );
+        checksums.write(&files)?;
+        self.info(
+            self.cli,
+            format!(
+                "Checksums file '{}' available at {}",
+                checksums_file,
+                checksums_path.display()
+            ),
+        );
+
+        Ok(vec![Collected::new(&PathBuf::from(""), &checksums_path)?])
+    }
+
+    fn verify_attributes(&self) -> Result<(), BError> {
+        if self.artifact.data().name().is_empty() {
+            return Err(BError::ValueError(String::from(
+                "Checksums node requires name attribute!",
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ChecksumsCollector<'a> {
+    pub fn new(artifact: &'a WsArtifactsHandler, cli: Option<&'a Cli>) -> Self {
+        ChecksumsCollector { artifact, cli }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collector::{ChecksumsCollector, Collected, Collector, FileCollector};
+    use crate::data::WsBuildData;
+    use crate::fs::Checksums;
+    use crate::helper::Helper;
+    use crate::workspace::WsArtifactsHandler;
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_checksums_collector() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from(temp_dir.path());
+        let task_build_dir: PathBuf = work_dir.clone().join("task/dir");
+        let files: Vec<PathBuf> = vec![
+            task_build_dir.clone().join("file1.txt"),
+            task_build_dir.clone().join("file2.txt"),
+        ];
+        let build_data: WsBuildData = Helper::setup_build_data(&work_dir, None, None);
+        let file_artifacts: WsArtifactsHandler = Helper::setup_collector_test_ws(
+            &work_dir,
+            &task_build_dir,
+            &files,
+            &build_data,
+            r#"
+            {
+                "source": "*.txt",
+                "dest": "images/"
+            }"#,
+        );
+        let checksums_artifacts: WsArtifactsHandler = Helper::setup_collector_test_ws(
+            &work_dir,
+            &task_build_dir,
+            &vec![],
+            &build_data,
+            r#"
+            {
+                "type": "checksums"
+            }"#,
+        );
+        let artifacts_dir: PathBuf = build_data.settings().artifacts_dir();
+        let collected: Vec<Collected> = FileCollector::new(&file_artifacts, None)
+            .collect(&task_build_dir, &artifacts_dir)
+            .expect("Failed to collect artifacts");
+        let file1: PathBuf = artifacts_dir.join("images/file1.txt");
+        let file2: PathBuf = artifacts_dir.join("images/file2.txt");
+        assert_eq!(collected.len(), 2);
+        assert_eq!(collected[0].dest, file1);
+        assert_eq!(collected[0].size, std::fs::metadata(&file1).unwrap().len());
+        assert_eq!(collected[0].sha256, Checksums::sha256(&file1).unwrap());
+        let collector: ChecksumsCollector = ChecksumsCollector::new(&checksums_artifacts, None);
+        assert!(collector.verify_attributes().is_ok());
+        assert!(collector
+            .collect(&task_build_dir, &artifacts_dir)
+            .expect("Failed to collect artifacts")
+            .is_empty());
+        let sums: Vec<Collected> = collector
+            .post_collect(&artifacts_dir, &collected)
+            .expect("Failed to collect artifacts");
+        let sums_file: PathBuf = artifacts_dir.join("SHA256SUMS");
+        assert_eq!(
+            sums,
+            vec![Collected::new(&PathBuf::from(""), &sums_file).unwrap()]
+        );
+        assert_eq!(
+            std::fs::read_to_string(&sums_file).expect("Failed to read checksums file"),
+            format!(
+                "{}  images/file1.txt\n{}  images/file2.txt\n",
+                Checksums::sha256(&file1).unwrap(),
+                Checksums::sha256(&file2).unwrap()
+            )
+        );
+        assert_eq!(
+            Checksums::new(&sums_file)
+                .verify()
+                .expect("Failed to verify checksums"),
+            2
+        );
+    }
+}
@@ -90,10 +90,11 @@ mod tests {
             .expect("Failed to collect artifacts");
         assert_eq!(
             &collected,
-            &vec![Collected {
-                src: task_build_dir.clone().join("file1.txt"),
-                dest: artifacts_dir.clone().join("file1.txt")
-            },]
+            &vec![Collected::new(
+                &task_build_dir.clone().join("file1.txt"),
+                &artifacts_dir.clone().join("file1.txt")
+            )
+            .unwrap(),]
         );
         for c in collected.iter() {
             assert!(c.dest.exists());
@@ -200,18 +201,21 @@ mod tests {
         assert_eq!(
             &collected,
             &vec![
-                Collected {
-                    src: task_build_dir.clone().join("file1.txt"),
-                    dest: artifacts_dir.clone().join("dir1/file1.txt")
-                },
-                Collected {
-                    src: task_build_dir.clone().join("file2.txt"),
-                    dest: artifacts_dir.clone().join("dir1/dest/dest-file.txt")
-                },
-                Collected {
-                    src: task_build_dir.clone().join("src-file.txt"),
-                    dest: artifacts_dir.clone().join("dir1/dir2/src-file.txt")
-                },
+                Collected::new(
+                    &task_build_dir.clone().join("file1.txt"),
+                    &artifacts_dir.clone().join("dir1/file1.txt")
+                )
+                .unwrap(),
+                Collected::new(
+                    &task_build_dir.clone().join("file2.txt"),
+                    &artifacts_dir.clone().join("dir1/dest/dest-file.txt")
+                )
+                .unwrap(),
+                Collected::new(
+                    &task_build_dir.clone().join("src-file.txt"),
+                    &artifacts_dir.clone().join("dir1/dir2/src-file.txt")
+                )
+                .unwrap(),
             ]
         );
         for c in collected.iter() {
@@ -1,5 +1,5 @@
 use crate::cli::Cli;
-use crate::collector::{Collected, Collector, CollectorFactory};
+use crate::collector::{Collected, Collector, CollectorFactory, FileCollector};
 use crate::error::BError;
 use crate::workspace::WsArtifactsHandler;
 
@@ -19,6 +19,15 @@ impl<'a> Collector for DirectoryCollector<'a> {
             self.cli,
             format!("Collecting directory '{}'", directory_name),
         );
+        /*
+         * The files matching the source of the directory are collected using the
+         * same exclude, match and select filters as a file artifact
+         */
+        if !self.artifact.data().source().is_empty() {
+            let mut c: Vec<Collected> =
+                FileCollector::new(self.artifact, None).collect(src, &directory_path)?;
+            collected.append(&mut c);
+        }
         for child in self.artifact.children().iter() {
             let collector: Box<dyn Collector> = CollectorFactory::create(child, None)?;
             let mut c: Vec<Collected> = collector.collect(src, &directory_path)?;
@@ -32,9 +41,11 @@ impl<'a> Collector for DirectoryCollector<'a> {
     }
 
     fn verify_attributes(&self) -> Result<(), BError> {
-        if self.artifact.data().name().is_empty() || self.artifact.children().is_empty() {
+        if self.artifact.data().name().is_empty()
+            || (self.artifact.children().is_empty() && self.artifact.data().source().is_empty())
+        {
             return Err(BError::ValueError(String::from(
-                "Directory node requires name and list of artifacts!",
+                "Directory node requires name and source or list of artifacts!",
             )));
         }
         Ok(())
@@ -52,11 +63,14 @@ mod tests {
     use crate::collector::{Collected, Collector, DirectoryCollector};
     use crate::configs::Context;
     use crate::data::WsBuildData;
+    use crate::error::BError;
     use crate::helper::Helper;
     use crate::workspace::WsArtifactsHandler;
 
     use indexmap::{indexmap, IndexMap};
+    use std::fs::File;
     use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
     use tempdir::TempDir;
 
     #[test]
@@ -100,14 +114,16 @@ mod tests {
         assert_eq!(
             &collected,
             &vec![
-                Collected {
-                    src: task_build_dir.clone().join("file1.txt"),
-                    dest: artifacts_dir.clone().join("dirname/file1.txt")
-                },
-                Collected {
-                    src: task_build_dir.clone().join("file2.txt"),
-                    dest: artifacts_dir.clone().join("dirname/dest/dest-file.txt")
-                },
+                Collected::new(
+                    &task_build_dir.clone().join("file1.txt"),
+                    &artifacts_dir.clone().join("dirname/file1.txt")
+                )
+                .unwrap(),
+                Collected::new(
+                    &task_build_dir.clone().join("file2.txt"),
+                    &artifacts_dir.clone().join("dirname/dest/dest-file.txt")
+                )
+                .unwrap(),
             ]
         );
         for c in collected.iter() {
@@ -166,18 +182,21 @@ mod tests {
         assert_eq!(
             &collected,
             &vec![
-                Collected {
-                    src: task_build_dir.clone().join("file1.txt"),
-                    dest: artifacts_dir.clone().join("dirname1/file1.txt")
-                },
-                Collected {
-                    src: task_build_dir.clone().join("file2.txt"),
-                    dest: artifacts_dir.clone().join("dirname1/dest/dest-file.txt")
-                },
-                Collected {
-                    src: task_build_dir.clone().join("file3.txt"),
-                    dest: artifacts_dir.clone().join("dirname1/dirname2/file3.txt")
-                },
+                Collected::new(
+                    &task_build_dir.clone().join("file1.txt"),
+                    &artifacts_dir.clone().join("dirname1/file1.txt")
+                )
+                .unwrap(),
+                Collected::new(
+                    &task_build_dir.clone().join("file2.txt"),
+                    &artifacts_dir.clone().join("dirname1/dest/dest-file.txt")
+                )
+                .unwrap(),
+                Collected::new(
+                    &task_build_dir.clone().join("file3.txt"),
+                    &artifacts_dir.clone().join("dirname1/dirname2/file3.txt")
+                )
+                .unwrap(),
             ]
         );
         for c in collected.iter() {
@@ -244,22 +263,221 @@ mod tests {
         assert_eq!(
             &collected,
             &vec![
-                Collected {
-                    src: task_build_dir.clone().join("file1.txt"),
-                    dest: artifacts_dir.clone().join("dir1/file1.txt")
-                },
-                Collected {
-                    src: task_build_dir.clone().join("file2.txt"),
-                    dest: artifacts_dir.clone().join("dir1/dest/dest-file.txt")
-                },
-                Collected {
-                    src: task_build_dir.clone().join("src-file.txt"),
-                    dest: artifacts_dir.clone().join("dir1/dir2/src-file.txt")
-                },
+                Collected::new(
+                    &task_build_dir.clone().join("file1.txt"),
+                    &artifacts_dir.clone().join("dir1/file1.txt")
+                )
+                .unwrap(),
+                Collected::new(
+                    &task_build_dir.clone().join("file2.txt"),
+                    &artifacts_dir.clone().join("dir1/dest/dest-file.txt")
+                )
+                .unwrap(),
+                Collected::new(
+                    &task_build_dir.clone().join("src-file.txt"),
+                    &artifacts_dir.clone().join("dir1/dir2/src-file.txt")
+                )
+                .unwrap(),
             ]
         );
         for c in collected.iter() {
             assert!(c.dest.exists());
         }
     }
+
+    #[test]
+    fn test_directory_collector_source_exclude() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from(temp_dir.path());
+        let task_build_dir: PathBuf = work_dir.clone().join("task/dir");
+        let files: Vec<PathBuf> = vec![
+            task_build_dir.clone().join("images/image.wic.bz2"),
+            task_build_dir.clone().join("images/image-dbg.wic.bz2"),
+            task_build_dir.clone().join("images/old/image.wic.bz2"),
+            task_build_dir.clone().join("file1.txt"),
+        ];
+        let json_artifacts_config: &str = r#"
+        {
+            "type": "directory",
+            "name": "dirname",
+            "source": "images/*",
+            "exclude": [
+                "*-dbg*",
+                "old/*"
+            ],
+            "artifacts": [
+                {
+                    "source": "file1.txt"
+                }
+            ]
+        }"#;
+        let build_data: WsBuildData = Helper::setup_build_data(&work_dir, None, None);
+        let artifacts: WsArtifactsHandler = Helper::setup_collector_test_ws(
+            &work_dir,
+            &task_build_dir,
+            &files,
+            &build_data,
+            json_artifacts_config,
+        );
+        let collector: DirectoryCollector = DirectoryCollector::new(&artifacts, None);
+        assert!(collector.verify_attributes().is_ok());
+        let artifacts_dir: PathBuf = build_data.settings().artifacts_dir();
+        let collected: Vec<Collected> = collector
+            .collect(&task_build_dir, &artifacts_dir)
+            .expect("Failed to collect artifacts");
+        assert_eq!(
+            &collected,
+            &vec![
+                Collected::new(
+                    &task_build_dir.join("images/image.wic.bz2"),
+                    &artifacts_dir.join("dirname/image.wic.bz2")
+                )
+                .unwrap(),
+                Collected::new(
+                    &task_build_dir.join("file1.txt"),
+                    &artifacts_dir.join("dirname/file1.txt")
+                )
+                .unwrap(),
+            ]
+        );
+        assert!(!artifacts_dir.join("dirname/image-dbg.wic.bz2").exists());
+        assert!(!artifacts_dir.join("dirname/old/image.wic.bz2").exists());
+    }
+
+    #[test]
+    fn test_directory_collector_source_regex_newest() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from(temp_dir.path());
+        let task_build_dir: PathBuf = work_dir.clone().join("task/dir");
+        let files: Vec<PathBuf> = vec![
+            task_build_dir.clone().join("images/image-20240101.wic.bz2"),
+            task_build_dir.clone().join("images/image-20240102.wic.bz2"),
+            task_build_dir.clone().join("images/image.wic.bz2"),
+        ];
+        let json_artifacts_config: &str = r#"
+        {
+            "type": "directory",
+            "name": "dirname",
+            "source": "images/image-[0-9]+\\.wic\\.bz2",
+            "match": "regex",
+            "select": "newest"
+        }"#;
+        let build_data: WsBuildData = Helper::setup_build_data(&work_dir, None, None);
+        let artifacts: WsArtifactsHandler = Helper::setup_collector_test_ws(
+            &work_dir,
+            &task_build_dir,
+            &files,
+            &build_data,
+            json_artifacts_config,
+        );
+        /* Make the first image the newest one */
+        let now: SystemTime = SystemTime::now();
+        for (i, f) in files.iter().enumerate() {
+            let mtime: SystemTime = match i {
+                0 => now,
+                _ => now - Duration::from_secs(3600),
+            };
+            File::options()
+                .write(true)
+                .open(f)
+                .expect("Failed to open file")
+                .set_modified(mtime)
+                .expect("Failed to set mtime");
+        }
+        let collector: DirectoryCollector = DirectoryCollector::new(&artifacts, None);
+        assert!(collector.verify_attributes().is_ok());
+        let artifacts_dir: PathBuf = build_data.settings().artifacts_dir();
+        let collected: Vec<Collected> = collector
+            .collect(&task_build_dir, &artifacts_dir)
+            .expect("Failed to collect artifacts");
+        assert_eq!(
+            &collected,
+            &vec![Collected::new(
+                &task_build_dir.join("images/image-20240101.wic.bz2"),
+                &artifacts_dir.join("dirname/image-20240101.wic.bz2")
+            )
+            .unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_directory_collector_source_exactly_one() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from(temp_dir.path());
+        let task_build_dir: PathBuf = work_dir.clone().join("task/dir");
+        let files: Vec<PathBuf> = vec![
+            task_build_dir.clone().join("images/image-1.wic.bz2"),
+            task_build_dir.clone().join("images/image-2.wic.bz2"),
+        ];
+        let json_artifacts_config: &str = r#"
+        {
+            "type": "directory",
+            "name": "dirname",
+            "source": "images/*.wic.bz2",
+            "select": "exactly-one"
+        }"#;
+        let build_data: WsBuildData = Helper::setup_build_data(&work_dir, None, None);
+        let artifacts: WsArtifactsHandler = Helper::setup_collector_test_ws(
+            &work_dir,
+            &task_build_dir,
+            &files,
+            &build_data,
+            json_artifacts_config,
+        );
+        let collector: DirectoryCollector = DirectoryCollector::new(&artifacts, None);
+        assert!(collector.verify_attributes().is_ok());
+        let result: Result<Vec<Collected>, BError> =
+            collector.collect(&task_build_dir, &build_data.settings().artifacts_dir());
+        match result {
+            Ok(_status) => {
+                panic!("We should have recived an error because there are two matching files!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!(
+                        "Expected exactly one file matching '{}' but found 2 '{}', '{}'",
+                        task_build_dir.join("images/*.wic.bz2").display(),
+                        files[0].display(),
+                        files[1].display()
+                    )
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_directory_collector_no_source() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from(temp_dir.path());
+        let task_build_dir: PathBuf = work_dir.clone().join("task/dir");
+        let build_data: WsBuildData = Helper::setup_build_data(&work_dir, None, None);
+        let artifacts: WsArtifactsHandler = Helper::setup_collector_test_ws(
+            &work_dir,
+            &task_build_dir,
+            &vec![],
+            &build_data,
+            r#"
+            {
+                "type": "directory",
+                "name": "dirname"
+            }"#,
+        );
+        let collector: DirectoryCollector = DirectoryCollector::new(&artifacts, None);
+        match collector.verify_attributes() {
+            Ok(()) => {
+                panic!("We should have recived an error because there is no source or artifacts!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Directory node requires name and source or list of artifacts!"
+                );
+            }
+        }
+    }
 }
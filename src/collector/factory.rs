@@ -1,7 +1,7 @@
 use crate::cli::Cli;
 use crate::collector::{
-    ArchiveCollector, Collector, ConditionalCollector, DirectoryCollector, FileCollector,
-    LinkCollector, ManifestCollector,
+    ArchiveCollector, ChecksumsCollector, Collector, ConditionalCollector, DirectoryCollector,
+    FileCollector, LinkCollector, ManifestCollector,
 };
 use crate::data::AType;
 use crate::error::BError;
@@ -34,6 +34,9 @@ impl CollectorFactory {
             AType::Conditional => {
                 collector = Box::new(ConditionalCollector::new(artifact, cli));
             }
+            AType::Checksums => {
+                collector = Box::new(ChecksumsCollector::new(artifact, cli));
+            }
         }
         collector.verify_attributes()?;
         Ok(collector)
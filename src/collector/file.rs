@@ -1,9 +1,12 @@
 use crate::cli::Cli;
 use crate::collector::{Collected, Collector};
+use crate::data::{AMatch, ASelect};
 use crate::error::BError;
 use crate::workspace::WsArtifactsHandler;
 
+use regex::Regex;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 pub struct FileCollector<'a> {
     artifact: &'a WsArtifactsHandler,
@@ -15,8 +18,13 @@ impl<'a> Collector for FileCollector<'a> {
         let dest_str: &str = self.artifact.data().dest();
         let src_path: PathBuf = src.join(PathBuf::from(self.artifact.data().source()));
         let dest_path: PathBuf = dest.join(PathBuf::from(dest_str));
-        let files: Vec<PathBuf> = self.list_files(&src_path)?;
         let base_dir: &Path = src_path.parent().unwrap();
+        let mut files: Vec<PathBuf> = match self.artifact.data().amatch() {
+            AMatch::Glob => self.list_files(&src_path)?,
+            AMatch::Regex => self.list_regex_files(&src_path)?,
+        };
+        files = self.exclude_files(files, base_dir)?;
+        files = self.select_files(files, &src_path)?;
         let mut collected: Vec<Collected> = vec![];
 
         if files.is_empty() && !src_path.exists() {
@@ -47,10 +55,7 @@ impl<'a> Collector for FileCollector<'a> {
             );
             std::fs::create_dir_all(dest_file.parent().unwrap())?;
             std::fs::copy(f, &dest_file)?;
-            collected.push(Collected {
-                src: f.clone(),
-                dest: dest_file.clone(),
-            });
+            collected.push(Collected::new(f, &dest_file)?);
         }
 
         Ok(collected)
@@ -117,6 +122,121 @@ impl<'a> FileCollector<'a> {
         Ok(files)
     }
 
+    /*
+     * When the source is a regex only the file name is a regular expression
+     * the directory part of the source is used as it is. The regex has to
+     * match the complete file name.
+     */
+    fn list_regex_files(&self, regex_path: &PathBuf) -> Result<Vec<PathBuf>, BError> {
+        let mut files: Vec<PathBuf> = vec![];
+        let dir: &Path = regex_path.parent().unwrap();
+        let pattern: String = regex_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let regex: Regex = Regex::new(&format!("^(?:{})$", pattern))
+            .map_err(|e| BError::CollectorError(format!("Invalid regex '{}', {}", pattern, e)))?;
+
+        if !dir.is_dir() {
+            return Ok(files);
+        }
+
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect();
+        entries.sort();
+
+        for path in entries.iter() {
+            let file_name: String = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            if !regex.is_match(&file_name) {
+                continue;
+            }
+
+            if path.is_dir() {
+                let mut f: Vec<PathBuf> = self.list_files(&path.join("*"))?;
+                files.append(&mut f);
+            } else {
+                files.push(path.clone());
+            }
+        }
+
+        Ok(files)
+    }
+
+    /*
+     * A file is excluded if any of the exclude glob patterns is matching
+     * either the file name or the path relative to the source dir.
+     */
+    fn exclude_files(&self, files: Vec<PathBuf>, base_dir: &Path) -> Result<Vec<PathBuf>, BError> {
+        let mut patterns: Vec<glob::Pattern> = vec![];
+        for exclude in self.artifact.data().exclude().iter() {
+            patterns.push(glob::Pattern::new(exclude)?);
+        }
+
+        if patterns.is_empty() {
+            return Ok(files);
+        }
+
+        Ok(files
+            .into_iter()
+            .filter(|f| {
+                let file_name: String = f
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                let relative: &Path = f.strip_prefix(base_dir).unwrap_or(f);
+                !patterns
+                    .iter()
+                    .any(|p| p.matches(&file_name) || p.matches_path(relative))
+            })
+            .collect())
+    }
+
+    fn select_files(
+        &self,
+        files: Vec<PathBuf>,
+        src_path: &PathBuf,
+    ) -> Result<Vec<PathBuf>, BError> {
+        match self.artifact.data().select() {
+            ASelect::All => Ok(files),
+            ASelect::Newest => {
+                let newest: Option<PathBuf> = files.into_iter().max_by_key(|f| {
+                    std::fs::metadata(f)
+                        .and_then(|m| m.modified())
+                        .unwrap_or(SystemTime::UNIX_EPOCH)
+                });
+                Ok(newest.into_iter().collect())
+            }
+            ASelect::ExactlyOne => {
+                if files.len() == 1 {
+                    return Ok(files);
+                }
+
+                if files.is_empty() {
+                    return Err(BError::CollectorError(format!(
+                        "Expected exactly one file matching '{}' but found none",
+                        src_path.display()
+                    )));
+                }
+
+                let found: Vec<String> = files.iter().map(|f| f.display().to_string()).collect();
+                Err(BError::CollectorError(format!(
+                    "Expected exactly one file matching '{}' but found {} '{}'",
+                    src_path.display(),
+                    files.len(),
+                    found.join("', '")
+                )))
+            }
+        }
+    }
+
     pub fn new(artifact: &'a WsArtifactsHandler, cli: Option<&'a Cli>) -> Self {
         FileCollector { artifact, cli }
     }
@@ -132,7 +252,9 @@ mod tests {
     use crate::workspace::WsArtifactsHandler;
 
     use indexmap::{indexmap, IndexMap};
+    use std::fs::File;
     use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
     use tempdir::TempDir;
 
     #[test]
@@ -167,10 +289,7 @@ mod tests {
             .join(src_file_name);
         assert_eq!(
             collected,
-            vec![Collected {
-                src: task_build_dir.join(src_file_name),
-                dest: dest.clone()
-            }]
+            vec![Collected::new(&task_build_dir.join(src_file_name), &dest).unwrap()]
         );
         assert!(dest.exists());
     }
@@ -209,10 +328,7 @@ mod tests {
             .join(dest_file_name);
         assert_eq!(
             collected,
-            vec![Collected {
-                src: task_build_dir.join(src_file_name),
-                dest: dest.clone()
-            }]
+            vec![Collected::new(&task_build_dir.join(src_file_name), &dest).unwrap()]
         );
         assert!(dest.exists());
     }
@@ -251,10 +367,7 @@ mod tests {
             .join(dest_file_name);
         assert_eq!(
             collected,
-            vec![Collected {
-                src: task_build_dir.join(src_file_name),
-                dest: dest.clone()
-            },]
+            vec![Collected::new(&task_build_dir.join(src_file_name), &dest).unwrap(),]
         );
         assert!(dest.exists());
     }
@@ -293,10 +406,7 @@ mod tests {
             .join(dest_file_name);
         assert_eq!(
             collected,
-            vec![Collected {
-                src: task_build_dir.join(src_file_name),
-                dest: dest.clone()
-            },]
+            vec![Collected::new(&task_build_dir.join(src_file_name), &dest).unwrap(),]
         );
         assert!(dest.exists());
     }
@@ -336,26 +446,31 @@ mod tests {
         assert_eq!(
             &collected,
             &vec![
-                Collected {
-                    src: task_build_dir.clone().join("src/sub/dir1/file2.txt"),
-                    dest: artifacts_dir.clone().join("dest/dir1/file2.txt")
-                },
-                Collected {
-                    src: task_build_dir.clone().join("src/sub/dir2/file3.txt"),
-                    dest: artifacts_dir.clone().join("dest/dir2/file3.txt")
-                },
-                Collected {
-                    src: task_build_dir.clone().join("src/sub/dir3/file4.txt"),
-                    dest: artifacts_dir.clone().join("dest/dir3/file4.txt")
-                },
-                Collected {
-                    src: task_build_dir.clone().join("src/sub/dir4/dir5/file5.txt"),
-                    dest: artifacts_dir.clone().join("dest/dir4/dir5/file5.txt")
-                },
-                Collected {
-                    src: task_build_dir.clone().join("src/sub/file1.txt"),
-                    dest: artifacts_dir.clone().join("dest/file1.txt")
-                },
+                Collected::new(
+                    &task_build_dir.clone().join("src/sub/dir1/file2.txt"),
+                    &artifacts_dir.clone().join("dest/dir1/file2.txt")
+                )
+                .unwrap(),
+                Collected::new(
+                    &task_build_dir.clone().join("src/sub/dir2/file3.txt"),
+                    &artifacts_dir.clone().join("dest/dir2/file3.txt")
+                )
+                .unwrap(),
+                Collected::new(
+                    &task_build_dir.clone().join("src/sub/dir3/file4.txt"),
+                    &artifacts_dir.clone().join("dest/dir3/file4.txt")
+                )
+                .unwrap(),
+                Collected::new(
+                    &task_build_dir.clone().join("src/sub/dir4/dir5/file5.txt"),
+                    &artifacts_dir.clone().join("dest/dir4/dir5/file5.txt")
+                )
+                .unwrap(),
+                Collected::new(
+                    &task_build_dir.clone().join("src/sub/file1.txt"),
+                    &artifacts_dir.clone().join("dest/file1.txt")
+                )
+                .unwrap(),
             ]
         );
         for c in collected.iter() {
@@ -406,10 +521,7 @@ mod tests {
             .join(dest_file_name);
         assert_eq!(
             collected,
-            vec![Collected {
-                src: task_build_dir.join(src_file_name),
-                dest: dest.clone()
-            },]
+            vec![Collected::new(&task_build_dir.join(src_file_name), &dest).unwrap(),]
         );
         assert!(dest.exists());
     }
@@ -448,4 +560,171 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_file_collector_exclude() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from(temp_dir.path());
+        let task_build_dir: PathBuf = work_dir.clone().join("task/dir");
+        let files: Vec<PathBuf> = vec![
+            task_build_dir.clone().join("images/image.wic.bz2"),
+            task_build_dir.clone().join("images/image-dbg.wic.bz2"),
+            task_build_dir.clone().join("images/old/image.wic.bz2"),
+        ];
+        let json_artifacts_config: &str = r#"
+        {
+            "source": "images/*",
+            "dest": "images/",
+            "exclude": [
+                "*-dbg*",
+                "old/*"
+            ]
+        }"#;
+        let build_data: WsBuildData = Helper::setup_build_data(&work_dir, None, None);
+        let artifacts: WsArtifactsHandler = Helper::setup_collector_test_ws(
+            &work_dir,
+            &task_build_dir,
+            &files,
+            &build_data,
+            json_artifacts_config,
+        );
+        let collector: FileCollector = FileCollector::new(&artifacts, None);
+        assert!(collector.verify_attributes().is_ok());
+        let artifacts_dir: PathBuf = build_data.settings().artifacts_dir();
+        let collected: Vec<Collected> = collector
+            .collect(&task_build_dir, &artifacts_dir)
+            .expect("Failed to collect artifacts");
+        assert_eq!(
+            collected,
+            vec![Collected::new(
+                &task_build_dir.join("images/image.wic.bz2"),
+                &artifacts_dir.join("images/image.wic.bz2")
+            )
+            .unwrap()]
+        );
+        assert!(!artifacts_dir.join("images/image-dbg.wic.bz2").exists());
+        assert!(!artifacts_dir.join("images/old/image.wic.bz2").exists());
+    }
+
+    #[test]
+    fn test_file_collector_regex_newest() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from(temp_dir.path());
+        let task_build_dir: PathBuf = work_dir.clone().join("task/dir");
+        let files: Vec<PathBuf> = vec![
+            task_build_dir.clone().join("images/image-20240101.wic.bz2"),
+            task_build_dir.clone().join("images/image-20240102.wic.bz2"),
+            task_build_dir.clone().join("images/image-20240103.wic.bz2"),
+            task_build_dir.clone().join("images/image.wic.bz2"),
+        ];
+        let json_artifacts_config: &str = r#"
+        {
+            "source": "images/image-[0-9]+\\.wic\\.bz2",
+            "dest": "image.wic.bz2",
+            "match": "regex",
+            "select": "newest"
+        }"#;
+        let build_data: WsBuildData = Helper::setup_build_data(&work_dir, None, None);
+        let artifacts: WsArtifactsHandler = Helper::setup_collector_test_ws(
+            &work_dir,
+            &task_build_dir,
+            &files,
+            &build_data,
+            json_artifacts_config,
+        );
+        /* Make the second image the newest one */
+        let now: SystemTime = SystemTime::now();
+        for (i, f) in files.iter().enumerate() {
+            let mtime: SystemTime = match i {
+                1 => now,
+                _ => now - Duration::from_secs(3600),
+            };
+            File::options()
+                .write(true)
+                .open(f)
+                .expect("Failed to open file")
+                .set_modified(mtime)
+                .expect("Failed to set mtime");
+        }
+        let collector: FileCollector = FileCollector::new(&artifacts, None);
+        assert!(collector.verify_attributes().is_ok());
+        let artifacts_dir: PathBuf = build_data.settings().artifacts_dir();
+        let collected: Vec<Collected> = collector
+            .collect(&task_build_dir, &artifacts_dir)
+            .expect("Failed to collect artifacts");
+        assert_eq!(
+            collected,
+            vec![Collected::new(
+                &task_build_dir.join("images/image-20240102.wic.bz2"),
+                &artifacts_dir.join("image.wic.bz2")
+            )
+            .unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_file_collector_exactly_one() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from(temp_dir.path());
+        let task_build_dir: PathBuf = work_dir.clone().join("task/dir");
+        let files: Vec<PathBuf> = vec![
+            task_build_dir.clone().join("images/image-1.wic.bz2"),
+            task_build_dir.clone().join("images/image-2.wic.bz2"),
+        ];
+        let json_artifacts_config: &str = r#"
+        {
+            "source": "images/*.wic.bz2",
+            "select": "exactly-one"
+        }"#;
+        let build_data: WsBuildData = Helper::setup_build_data(&work_dir, None, None);
+        let artifacts: WsArtifactsHandler = Helper::setup_collector_test_ws(
+            &work_dir,
+            &task_build_dir,
+            &files,
+            &build_data,
+            json_artifacts_config,
+        );
+        let collector: FileCollector = FileCollector::new(&artifacts, None);
+        assert!(collector.verify_attributes().is_ok());
+        let result: Result<Vec<Collected>, BError> =
+            collector.collect(&task_build_dir, &build_data.settings().artifacts_dir());
+        match result {
+            Ok(_status) => {
+                panic!("We should have recived an error because there are two matching files!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!(
+                        "Expected exactly one file matching '{}' but found 2 '{}', '{}'",
+                        task_build_dir.join("images/*.wic.bz2").display(),
+                        files[0].display(),
+                        files[1].display()
+                    )
+                );
+            }
+        }
+
+        std::fs::remove_file(&files[0]).expect("Failed to remove file");
+        std::fs::remove_file(&files[1]).expect("Failed to remove file");
+        let result: Result<Vec<Collected>, BError> =
+            collector.collect(&task_build_dir, &build_data.settings().artifacts_dir());
+        match result {
+            Ok(_status) => {
+                panic!("We should have recived an error because there are no matching files!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!(
+                        "Expected exactly one file matching '{}' but found none",
+                        task_build_dir.join("images/*.wic.bz2").display()
+                    )
+                );
+            }
+        }
+    }
 }
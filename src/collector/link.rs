@@ -38,10 +38,7 @@ impl<'a> Collector for LinkCollector<'a> {
             std::fs::remove_file(link_path.clone())?;
         }
         fs::symlink(&src_path, &link_path)?;
-        collected.push(Collected {
-            src: src_path.clone(),
-            dest: link_path.clone(),
-        });
+        collected.push(Collected::new(&src_path, &link_path)?);
 
         Ok(collected)
     }
@@ -115,10 +112,7 @@ mod tests {
             .join("link.txt");
         assert_eq!(
             collected,
-            vec![Collected {
-                src: task_build_dir.join(src_file_name),
-                dest: dest.clone()
-            }]
+            vec![Collected::new(&task_build_dir.join(src_file_name), &dest).unwrap()]
         );
         assert!(dest.exists());
     }
@@ -169,10 +163,7 @@ mod tests {
             .join(link_file_name);
         assert_eq!(
             collected,
-            vec![Collected {
-                src: task_build_dir.join(src_file_name),
-                dest: dest.clone()
-            },]
+            vec![Collected::new(&task_build_dir.join(src_file_name), &dest).unwrap(),]
         );
         assert!(dest.exists());
     }
@@ -31,10 +31,7 @@ impl<'a> Collector for ManifestCollector<'a> {
             ),
         );
 
-        Ok(vec![Collected {
-            src: PathBuf::from(""),
-            dest: manifest_path,
-        }])
+        Ok(vec![Collected::new(&PathBuf::from(""), &manifest_path)?])
     }
 
     fn verify_attributes(&self) -> Result<(), BError> {
@@ -109,10 +106,7 @@ mod tests {
             .join("manifest.json");
         assert_eq!(
             collected,
-            vec![Collected {
-                src: PathBuf::from(""),
-                dest: manifest_file.clone()
-            }]
+            vec![Collected::new(&PathBuf::from(""), &manifest_file.clone()).unwrap()]
         );
         assert!(manifest_file.exists());
         let mut file: File = File::open(&manifest_file).expect("Failed to open manifest file!");
@@ -172,10 +166,7 @@ mod tests {
             .join("ctxmanifest.json");
         assert_eq!(
             collected,
-            vec![Collected {
-                src: PathBuf::from(""),
-                dest: manifest_file.clone()
-            }]
+            vec![Collected::new(&PathBuf::from(""), &manifest_file.clone()).unwrap()]
         );
         assert!(manifest_file.exists());
         let mut file: File = File::open(&manifest_file).expect("Failed to open manifest file!");
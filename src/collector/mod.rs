@@ -1,4 +1,5 @@
 pub mod archive;
+pub mod checksums;
 pub mod conditional;
 pub mod directory;
 pub mod factory;
@@ -7,6 +8,7 @@ pub mod link;
 pub mod manifest;
 
 pub use archive::ArchiveCollector;
+pub use checksums::ChecksumsCollector;
 pub use conditional::ConditionalCollector;
 pub use directory::DirectoryCollector;
 pub use factory::CollectorFactory;
@@ -16,6 +18,7 @@ pub use manifest::ManifestCollector;
 
 use crate::cli::Cli;
 use crate::error::BError;
+use crate::fs::Checksums;
 
 use std::path::PathBuf;
 
@@ -23,6 +26,32 @@ use std::path::PathBuf;
 pub struct Collected {
     pub src: PathBuf,
     pub dest: PathBuf,
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl Collected {
+    /*
+     * Size and SHA-256 are calculated from the collected file in the
+     * destination. A link is resolved and if it is not pointing to a
+     * regular file the size is 0 and the SHA-256 is empty.
+     */
+    pub fn new(src: &PathBuf, dest: &PathBuf) -> Result<Self, BError> {
+        let mut size: u64 = 0;
+        let mut sha256: String = String::new();
+
+        if dest.is_file() {
+            size = std::fs::metadata(dest)?.len();
+            sha256 = Checksums::sha256(dest)?;
+        }
+
+        Ok(Collected {
+            src: src.clone(),
+            dest: dest.clone(),
+            size,
+            sha256,
+        })
+    }
 }
 
 pub trait Collector {
@@ -30,6 +59,19 @@ pub trait Collector {
         Ok(vec![])
     }
 
+    /*
+     * Called once all the artifacts of a task have been collected with
+     * everything that has been collected by the task. Used by collectors
+     * that depend on the output of the other collectors.
+     */
+    fn post_collect(
+        &self,
+        _dest: &PathBuf,
+        _collected: &Vec<Collected>,
+    ) -> Result<Vec<Collected>, BError> {
+        Ok(vec![])
+    }
+
     fn verify_attributes(&self) -> Result<(), BError> {
         Ok(())
     }
@@ -1,6 +1,7 @@
 use clap::builder::Str;
 use indexmap::{indexmap, IndexMap};
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 use crate::cli::Cli;
 use crate::commands::{BBaseCommand, BCommand};
@@ -10,13 +11,41 @@ use crate::data::context::{
 };
 use crate::data::WsContextData;
 use crate::error::BError;
-use crate::executers::Docker;
-use crate::workspace::{Workspace, WsTaskHandler};
+use crate::executers::{Docker, DockerApi};
+use crate::fs::{BakeryLock, LocalConf};
+use crate::report::BuildReport;
+use crate::workspace::{TaskGraph, TaskOptions, Workspace, WsTaskHandler};
 
 static BCOMMAND: &str = "build";
 static BCOMMAND_ABOUT: &str =
     "Execute a build, either a full build or a task of one of the builds.";
 
+/*
+ * The options from the command line controlling how the tasks are built
+ */
+struct BuildOptions {
+    tasks: Vec<String>,
+    jobs: usize,
+    dry_run: bool,
+    interactive: bool,
+    force: bool,
+}
+
+impl BuildOptions {
+    /*
+     * Tasks specified on the command line are built even if they are disabled.
+     * Unless the build is forced a task is skipped if the inputs are unchanged.
+     */
+    fn task(&self, force: bool) -> TaskOptions {
+        TaskOptions {
+            dry_run: self.dry_run,
+            interactive: self.interactive,
+            force,
+            incremental: !self.force,
+        }
+    }
+}
+
 pub struct BuildCommand {
     cmd: BBaseCommand,
     // Your struct fields and methods here
@@ -64,7 +93,9 @@ impl BCommand for BuildCommand {
         let tasks: Vec<String> = self.get_arg_many(cli, "tasks", BCOMMAND)?;
         let variant: String = self.get_arg_str(cli, "variant", BCOMMAND)?;
         let verbose: bool = self.get_arg_flag(cli, "verbose", BCOMMAND)?;
-        let mut bb_variables: Vec<String> = Vec::new();
+        let jobs: usize = self.get_jobs(cli)?;
+        let force: bool = self.get_arg_flag(cli, "force", BCOMMAND)?;
+        let mut bb_conf: LocalConf = LocalConf::new();
         let mut interactive: bool = false;
 
         if interactive_str == "true" {
@@ -98,14 +129,26 @@ impl BCommand for BuildCommand {
         */
 
         if tar_balls {
-            bb_variables.push("BB_GENERATE_MIRROR_TARBALLS = \"1\"".to_string());
+            bb_conf.add_line("BB_GENERATE_MIRROR_TARBALLS = \"1\"", "--tar-balls");
         }
 
         if build_history {
-            bb_variables.push("INHERIT += \"buildhistory\"".to_string());
-            bb_variables.push("BUILDHISTORY_COMMIT = \"1\"".to_string());
+            bb_conf.add_line("INHERIT += \"buildhistory\"", "--build-history");
+            bb_conf.add_line("BUILDHISTORY_COMMIT = \"1\"", "--build-history");
         }
 
+        /*
+         * The messages and the output of all the tasks are logged to a file per
+         * build config in the builds dir so a failed build can be inspected
+         */
+        cli.open_log_file(
+            &workspace
+                .settings()
+                .builds_dir()
+                .join("logs")
+                .join(format!("bakery-{}.log", config)),
+        )?;
+
         let env_variables: HashMap<String, String> = self.setup_env(env);
         let mut args_context: IndexMap<String, String> = self.setup_context(ctx);
 
@@ -119,14 +162,14 @@ impl BCommand for BuildCommand {
         };
 
         if archiver {
-            bb_variables.push("INHERIT += \"archiver\"".to_string());
-            bb_variables.push("ARCHIVER_MODE[src] = \"original\"".to_string());
+            bb_conf.add_line("INHERIT += \"archiver\"", "--archiver");
+            bb_conf.add_line("ARCHIVER_MODE[src] = \"original\"", "--archiver");
             args_context.insert("BKRY_ARCHIVER".to_string(), "1".to_string());
         }
 
         if debug_symbols {
-            bb_variables.push("IMAGE_GEN_DEBUGFS = \"1\"".to_string());
-            bb_variables.push("IMAGE_FSTYPES_DEBUGFS = \"tar.bz2\"".to_string());
+            bb_conf.add_line("IMAGE_GEN_DEBUGFS = \"1\"", "--debug-symbols");
+            bb_conf.add_line("IMAGE_FSTYPES_DEBUGFS = \"tar.bz2\"", "--debug-symbols");
             args_context.insert("BKRY_DEBUG_SYMBOLS".to_string(), "1".to_string());
         }
 
@@ -147,12 +190,13 @@ impl BCommand for BuildCommand {
         // they can also be injected as context variables
         for (key, value) in extra_ctx.clone() {
             let bb_key: &str = key.strip_prefix("BKRY_").unwrap();
-            bb_variables.push(format!("{} ?= \"{}\"", bb_key, value));
+            bb_conf.add_line(&format!("{} ?= \"{}\"", bb_key, value), "build context");
         }
 
         // Update the config context with the context from the args
         let mut context: WsContextData = WsContextData::new(&args_context)?;
         context.update(&extra_ctx);
+        self.set_strict_ctx(cli, workspace)?;
         workspace.update_ctx(&context)?;
 
         if verbose {
@@ -161,57 +205,179 @@ impl BCommand for BuildCommand {
             variables.iter().for_each(|(key, value)| {
                 cli.stdout(format!("{}={}", key.to_ascii_uppercase(), value));
             });
+            /*
+             * Show where every line in the local.conf is coming from so it is
+             * possible to find out which source is setting a variable
+             */
+            let mut local_conf: LocalConf =
+                workspace.config().build_data().bitbake().local_conf_vars();
+            local_conf.extend(&bb_conf);
+            cli.debug(format!("Local.conf for build config '{}':", config));
+            cli.stdout(local_conf.to_string_with_provenance());
+        }
+
+        let bb_variables: Vec<String> = bb_conf.lines();
+
+        /*
+         * The build report is written to the artifacts dir even if the build fails so that
+         * the result of each task can be inspected
+         */
+        let report: BuildReport = BuildReport::new(&config);
+        report.set_context(&workspace.context()?);
+        let options: BuildOptions = BuildOptions {
+            tasks,
+            jobs,
+            dry_run,
+            interactive,
+            force,
+        };
+        let result: Result<(), BError> = self.build_tasks(
+            cli,
+            workspace,
+            &bb_variables,
+            &env_variables,
+            &options,
+            &report,
+        );
+
+        /*
+         * Nothing is built on a dry run so there is no build report or lock file to write
+         */
+        if dry_run {
+            return result;
+        }
+
+        /*
+         * Failing to write the report should not hide the result of the build
+         */
+        let artifacts_dir: PathBuf = workspace.settings().artifacts_dir();
+        match report.write(&artifacts_dir, &result) {
+            Ok(report_path) => {
+                cli.info(format!(
+                    "Build report available at {}",
+                    report_path.display()
+                ));
+            }
+            Err(err) => {
+                cli.error(format!("Failed to write build report: {}", err));
+            }
+        }
+
+        /*
+         * The lock file is only written after a successful build so that the
+         * layer revisions and the docker image of a release can be restored.
+         * Like the report failing to write it should not fail the build.
+         */
+        if result.is_ok() {
+            match self.write_lock(workspace, &config) {
+                Ok(lock_path) => {
+                    cli.info(format!(
+                        "Lock file available at {}",
+                        lock_path.display()
+                    ));
+                }
+                Err(err) => {
+                    cli.warn(format!("Failed to write lock file: {}", err));
+                }
+            }
         }
+        result
+    }
+}
 
+impl BuildCommand {
+    fn build_tasks(
+        &self,
+        cli: &Cli,
+        workspace: &Workspace,
+        bb_variables: &Vec<String>,
+        env_variables: &HashMap<String, String>,
+        options: &BuildOptions,
+        report: &BuildReport,
+    ) -> Result<(), BError> {
+        let tasks: &Vec<String> = &options.tasks;
         if tasks.len() > 1 {
             // More then one task was specified on the command line
             for t_name in tasks {
-                let task: &WsTaskHandler = workspace.config().task(&t_name)?;
+                let task: &WsTaskHandler = workspace.config().task(t_name)?;
                 task.build(
                     cli,
-                    &workspace.config().build_data(),
-                    &bb_variables,
-                    &env_variables,
-                    dry_run,
-                    interactive,
-                    true,
+                    workspace.config().build_data(),
+                    bb_variables,
+                    env_variables,
+                    &options.task(true),
+                    report,
                 )?;
             }
         } else {
             // One task was specified on the command line or default was used
             let task: &String = tasks.get(0).unwrap();
             if task == "all" {
-                // The alias "all" was specified on the command line or it none was specified and "all" was used
-                for (_t_name, task) in workspace.config().tasks() {
+                // The alias "all" was specified on the command line or it none was specified and "all" was used.
+                // The tasks are built in dependency order and independent tasks can be built in parallel
+                let graph: TaskGraph = workspace.config().task_graph()?;
+                // When tasks are built in parallel the output is prefixed with the task name
+                cli.set_task_prefix(options.jobs > 1);
+                graph.run(options.jobs, |t_name| {
+                    let task: &WsTaskHandler = workspace.config().task(t_name)?;
                     task.build(
                         cli,
-                        &workspace.config().build_data(),
-                        &bb_variables,
-                        &env_variables,
-                        dry_run,
-                        interactive,
-                        false,
-                    )?;
-                }
+                        workspace.config().build_data(),
+                        bb_variables,
+                        env_variables,
+                        &options.task(false),
+                        report,
+                    )
+                })?;
             } else {
                 // One task was specified on the command line
-                let task: &WsTaskHandler = workspace.config().task(tasks.get(0).unwrap())?;
+                let task: &WsTaskHandler = workspace.config().task(task)?;
                 task.build(
                     cli,
-                    &workspace.config().build_data(),
-                    &bb_variables,
-                    &env_variables,
-                    dry_run,
-                    interactive,
-                    true,
+                    workspace.config().build_data(),
+                    bb_variables,
+                    env_variables,
+                    &options.task(true),
+                    report,
                 )?;
             }
         }
         Ok(())
     }
-}
 
-impl BuildCommand {
+    fn write_lock(&self, workspace: &Workspace, config: &str) -> Result<PathBuf, BError> {
+        let mut lock: BakeryLock = workspace
+            .config()
+            .build_data()
+            .layers()
+            .lock_layers_dir(config, &workspace.settings().layers_dir())?;
+        if !workspace.settings().docker_disabled() {
+            let image: String = workspace.settings().docker_image().to_string();
+            let api: DockerApi = DockerApi::new(&workspace.settings().docker_runtime().socket());
+            let mut digest: String = String::new();
+            if api.available() {
+                digest = api
+                    .image_digest(&workspace.settings().docker_image())?
+                    .unwrap_or_default();
+            }
+            lock.set_docker(&image, &digest);
+        }
+        let lock_path: PathBuf = workspace.settings().lock_file();
+        lock.write(&lock_path)?;
+        Ok(lock_path)
+    }
+
+    fn get_jobs(&self, cli: &Cli) -> Result<usize, BError> {
+        let jobs_str: String = self.get_arg_str(cli, "jobs", BCOMMAND)?;
+        match jobs_str.parse::<usize>() {
+            Ok(jobs) if jobs > 0 => Ok(jobs),
+            _ => Err(BError::CliError(format!(
+                "Invalid number of jobs '{}'",
+                jobs_str
+            ))),
+        }
+    }
+
     fn setup_env(&self, env: Vec<String>) -> HashMap<String, String> {
         let variables: HashMap<String, String> = env
             .iter()
@@ -241,6 +407,7 @@ impl BuildCommand {
                     .long("verbose")
                     .help("Set verbose level."),
             )
+            .arg(BBaseCommand::strict_ctx_arg())
             .arg(
                 clap::Arg::new("tasks")
                     .short('t')
@@ -250,6 +417,14 @@ impl BuildCommand {
                     .value_delimiter(',')
                     .help("The task(s) to execute."),
             )
+            .arg(
+                clap::Arg::new("jobs")
+                    .short('j')
+                    .long("jobs")
+                    .value_name("nbr")
+                    .default_value("1")
+                    .help("The max number of tasks to execute in parallel when building all tasks. Bitbake tasks sharing the same build dir are never executed in parallel."),
+            )
             .arg(
                 clap::Arg::new("env")
                     .action(clap::ArgAction::Append)
@@ -284,6 +459,12 @@ impl BuildCommand {
                     .long("debug-symbols")
                     .help("Setting context variable BKRY_DEBUG_SYMBOLS to 1 which will result in adding IMAGE_GEN_DEBUGFS=1 to the local.conf. For more information see https://www.yoctoproject.org/docs/latest/mega-manual/mega-manual.html#platdev-gdb-remotedebug."),
             )
+            .arg(
+                clap::Arg::new("force")
+                    .action(clap::ArgAction::SetTrue)
+                    .long("force")
+                    .help("Execute the task(s) even if nothing has changed since the last successful build."),
+            )
             .arg(
                 clap::Arg::new("dry_run")
                     .action(clap::ArgAction::SetTrue)
@@ -372,6 +553,7 @@ mod tests {
     use crate::commands::{BCommand, BuildCommand};
     use crate::error::BError;
     use crate::executers::DockerImage;
+    use crate::fs::BakeryLock;
     use crate::helper::Helper;
     use crate::workspace::{Workspace, WsBuildConfigHandler, WsSettingsHandler};
 
@@ -564,6 +746,8 @@ mod tests {
         );
         let cmd: BuildCommand = BuildCommand::new();
         let _result: Result<(), BError> = cmd.execute(&cli, &mut workspace);
+        // Nothing is built on a dry run so no build report should be written
+        assert!(!work_dir.join("artifacts/build-report.json").exists());
         let mut bblayers_conf_content: String = String::from("");
         bblayers_conf_content.push_str("LCONF_VERSION=\"7\"\n");
         bblayers_conf_content.push_str("BBPATH=\"${TOPDIR}\"\n");
@@ -1261,6 +1445,135 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cmd_build_depends() {
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6",
+            "builds": {
+                "supported": [
+                    "default"
+                ]
+            },
+            "docker": {
+                "disabled": "true"
+            }
+        }"#;
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "bb": {},
+            "tasks": {
+                "sign": {
+                    "index": "1",
+                    "name": "sign",
+                    "type": "non-bitbake",
+                    "depends": [ "image" ],
+                    "builddir": "test-dir",
+                    "build": "sign.sh"
+                },
+                "image": {
+                    "index": "2",
+                    "name": "image",
+                    "type": "non-bitbake",
+                    "builddir": "test-dir",
+                    "build": "image.sh"
+                }
+            }
+        }
+        "#;
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let build_dir: PathBuf = work_dir.join("test-dir");
+        let mut seq: mockall::Sequence = mockall::Sequence::new();
+        let mut mocked_system: MockSystem = MockSystem::new();
+        for script in ["image.sh", "sign.sh"] {
+            mocked_system
+                .expect_check_call()
+                .with(mockall::predicate::eq(CallParams {
+                    cmd_line: vec!["cd", &build_dir.to_string_lossy().to_string(), "&&", script]
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect(),
+                    env: HashMap::new(),
+                    shell: true,
+                }))
+                .once()
+                .in_sequence(&mut seq)
+                .returning(|_x| Ok(()));
+        }
+        mocked_system.expect_env().returning(|| HashMap::new());
+        let result: Result<(), BError> = helper_test_build_subcommand(
+            json_ws_settings,
+            json_build_config,
+            &work_dir,
+            Box::new(BLogger::new()),
+            Box::new(mocked_system),
+            vec!["bakery", "build", "--config", "default", "--jobs", "2"],
+        );
+        assert!(result.is_ok());
+        let report: String = std::fs::read_to_string(work_dir.join("artifacts/build-report.json"))
+            .expect("Failed to read build report");
+        let value: serde_json::Value =
+            serde_json::from_str(&report).expect("Failed to parse build report");
+        assert_eq!(value["config"], "default");
+        assert_eq!(value["status"], "success");
+        assert_eq!(value["context"]["BKRY_PLATFORM_VERSION"], "0.0.0");
+        assert_eq!(value["tasks"][0]["name"], "image");
+        assert_eq!(value["tasks"][0]["status"], "success");
+        assert_eq!(value["tasks"][0]["commands"][0][3], "image.sh");
+        assert_eq!(value["tasks"][1]["name"], "sign");
+        assert_eq!(value["tasks"][1]["commands"][0][3], "sign.sh");
+    }
+
+    #[test]
+    fn test_cmd_build_invalid_jobs() {
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6",
+            "builds": {
+                "supported": [
+                    "default"
+                ]
+            },
+            "docker": {
+                "disabled": "true"
+            }
+        }"#;
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "bb": {}
+        }
+        "#;
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let result: Result<(), BError> = helper_test_build_subcommand(
+            json_ws_settings,
+            json_build_config,
+            &work_dir,
+            Box::new(BLogger::new()),
+            Box::new(MockSystem::new()),
+            vec!["bakery", "build", "--config", "default", "--jobs", "0"],
+        );
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because of the invalid number of jobs!");
+            }
+            Err(e) => {
+                assert_eq!(e.to_string(), "Invalid number of jobs '0'");
+            }
+        }
+    }
+
     #[test]
     fn test_cmd_build_arg_build_history() {
         let mut local_conf_lines: String = String::from("");
@@ -1409,6 +1722,142 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cmd_build_lock() {
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6",
+            "builds": {
+                "supported": [
+                    "default"
+                ]
+            },
+            "docker": {
+                "disabled": "true"
+            }
+        }"#;
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "tasks": {
+                "task-name": {
+                    "index": "1",
+                    "name": "task-name",
+                    "type": "non-bitbake",
+                    "builddir": "build",
+                    "build": "test.sh"
+                }
+            }
+        }"#;
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let layer_dir: PathBuf = work_dir.join("layers/meta-test");
+        std::fs::create_dir_all(&layer_dir).expect("Failed to create layer dir");
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git")
+                .arg("-C")
+                .arg(&layer_dir)
+                .args(["-c", "user.name=bakery", "-c", "user.email=bakery@localhost"])
+                .args(args)
+                .output()
+                .expect("Failed to execute git");
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+        git(&["init", "--quiet", "-b", "main"]);
+        git(&["commit", "--quiet", "--allow-empty", "-m", "layer"]);
+        let commit: String = git(&["rev-parse", "HEAD"]);
+        let mut mocked_system: MockSystem = MockSystem::new();
+        mocked_system
+            .expect_check_call()
+            .returning(|_x| Ok(()));
+        mocked_system
+            .expect_init_env_file()
+            .returning(|_x, _y| Ok(HashMap::new()));
+        helper_test_build_subcommand(
+            json_ws_settings,
+            json_build_config,
+            &work_dir,
+            Box::new(BLogger::new()),
+            Box::new(mocked_system),
+            vec!["bakery", "build", "--config", "default"],
+        )
+        .expect("Failed to build");
+        let lock: BakeryLock = BakeryLock::read(&work_dir.join("artifacts/bakery.lock"))
+            .expect("Failed to read lock file");
+        lock.verify_config("default")
+            .expect("The lock file is for another build config");
+        assert_eq!(lock.commit("meta-test"), Some(commit.as_str()));
+        /* Docker is disabled so no image is used by the build */
+        assert!(lock.docker().is_none());
+    }
+
+    #[test]
+    fn test_cmd_build_lock_failed() {
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6",
+            "builds": {
+                "supported": [
+                    "default"
+                ]
+            },
+            "docker": {
+                "disabled": "true"
+            }
+        }"#;
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "tasks": {
+                "task-name": {
+                    "index": "1",
+                    "name": "task-name",
+                    "type": "non-bitbake",
+                    "builddir": "build",
+                    "build": "test.sh"
+                }
+            }
+        }"#;
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        /* A dir in place of the lock file makes it impossible to write the lock file */
+        let lock_path: PathBuf = work_dir.join("artifacts/bakery.lock");
+        std::fs::create_dir_all(&lock_path).expect("Failed to create lock dir");
+        let mut mocked_system: MockSystem = MockSystem::new();
+        mocked_system
+            .expect_check_call()
+            .returning(|_x| Ok(()));
+        mocked_system
+            .expect_init_env_file()
+            .returning(|_x, _y| Ok(HashMap::new()));
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger.expect_info().returning(|_x| ());
+        mocked_logger
+            .expect_warn()
+            .withf(|m| m.starts_with("Failed to write lock file: "))
+            .once()
+            .returning(|_x| ());
+        /* The build was successful so failing to write the lock file should not fail it */
+        helper_test_build_subcommand(
+            json_ws_settings,
+            json_build_config,
+            &work_dir,
+            Box::new(mocked_logger),
+            Box::new(mocked_system),
+            vec!["bakery", "build", "--config", "default"],
+        )
+        .expect("Failed to build");
+        assert!(lock_path.is_dir());
+    }
+
     /*
     #[test]
     fn test_cmd_build_env() {
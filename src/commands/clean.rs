@@ -77,6 +77,7 @@ impl BCommand for CleanCommand {
          * We will update the context with the variables from the cli
          * and then expand the context variables in the config
          */
+        self.set_strict_ctx(cli, workspace)?;
         workspace.update_ctx(&context)?;
 
         /*
@@ -121,6 +122,7 @@ impl CleanCommand {
                 .value_name("name")
                 .required(true),
           )
+        .arg(BBaseCommand::strict_ctx_arg())
           .arg(
             clap::Arg::new("tasks")
                 .short('t')
@@ -0,0 +1,440 @@
+use std::collections::HashMap;
+
+use crate::cli::Cli;
+use crate::commands::{BBaseCommand, BCommand, BError};
+use crate::executers::{ContainerStatus, Docker, WsContainer};
+use crate::workspace::Workspace;
+
+static BCOMMAND: &str = "container";
+static BCOMMAND_ABOUT: &str =
+    "Manage a long-lived workspace container that bakery will exec into instead of starting a new container per command.";
+pub struct ContainerCommand {
+    cmd: BBaseCommand,
+    // Your struct fields and methods here
+}
+
+impl BCommand for ContainerCommand {
+    fn get_config_name(&self, _cli: &Cli) -> String {
+        /* The workspace container is shared by all build configs */
+        String::from("NA")
+    }
+
+    fn cmd_str(&self) -> &str {
+        &self.cmd.cmd_str
+    }
+
+    fn subcommand(&self) -> &clap::Command {
+        &self.cmd.sub_cmd
+    }
+
+    fn is_docker_required(&self) -> bool {
+        self.cmd.require_docker
+    }
+
+    fn execute(&self, cli: &Cli, workspace: &mut Workspace) -> Result<(), BError> {
+        let action: String = self.get_arg_str(cli, "action", BCOMMAND)?;
+        let volumes: Vec<String> = self.get_arg_many(cli, "volume", BCOMMAND)?;
+        let cmd_line: Vec<String> = self.get_arg_many(cli, "cmd", BCOMMAND)?;
+        let interactive_str: String = self.get_arg_str(cli, "interactive", BCOMMAND)?;
+        let interactive: bool = interactive_str == "true";
+
+        if workspace.settings().docker_disabled() {
+            return Err(BError::CliError(String::from(
+                "Docker is disabled in the workspace settings, the workspace container requires docker",
+            )));
+        }
+
+        let mut docker: Docker = Docker::new(workspace.settings().docker_image(), interactive);
+        docker.set_runtime(workspace.settings().docker_runtime());
+        let container: WsContainer =
+            WsContainer::new(&docker, docker.api(), &workspace.settings().work_dir());
+        let status: ContainerStatus = container.status()?;
+
+        match action.as_str() {
+            "start" => {
+                if status == ContainerStatus::Running {
+                    cli.stdout(format!(
+                        "Workspace container '{}' is already running",
+                        container.name()
+                    ));
+                    return Ok(());
+                }
+                self.start(cli, workspace, &docker, &container, &volumes)
+            }
+            "stop" => {
+                if status == ContainerStatus::Missing {
+                    cli.stdout(format!(
+                        "Workspace container '{}' is not running",
+                        container.name()
+                    ));
+                    return Ok(());
+                }
+                container.stop()?;
+                cli.stdout(format!(
+                    "Stopped workspace container '{}'",
+                    container.name()
+                ));
+                Ok(())
+            }
+            "status" => {
+                match status {
+                    ContainerStatus::Running => cli.stdout(format!(
+                        "Workspace container '{}' is running image '{}'",
+                        container.name(),
+                        docker.image()
+                    )),
+                    ContainerStatus::Outdated(image) => cli.stdout(format!(
+                        "Workspace container '{}' is running image '{}' but the workspace is using '{}', it will be recreated by the next command",
+                        container.name(),
+                        image,
+                        docker.image()
+                    )),
+                    ContainerStatus::Stopped => cli.stdout(format!(
+                        "Workspace container '{}' is stopped",
+                        container.name()
+                    )),
+                    ContainerStatus::Missing => cli.stdout(format!(
+                        "Workspace container '{}' is not running",
+                        container.name()
+                    )),
+                }
+                Ok(())
+            }
+            _ => {
+                match status {
+                    ContainerStatus::Running => {}
+                    ContainerStatus::Outdated(_image) => {
+                        self.start(cli, workspace, &docker, &container, &volumes)?;
+                    }
+                    _ => {
+                        return Err(BError::CliError(format!(
+                            "Workspace container '{}' is not running, start it using 'bakery container start'",
+                            container.name()
+                        )));
+                    }
+                }
+                let cmd_line: Vec<String> = if cmd_line.is_empty() {
+                    vec![String::from("/bin/bash"), String::from("-i")]
+                } else {
+                    cmd_line
+                };
+                let env: HashMap<String, String> = cli.env();
+                container.exec(&cmd_line, cli, &env, &[])
+            }
+        }
+    }
+}
+
+impl ContainerCommand {
+    fn start(
+        &self,
+        cli: &Cli,
+        workspace: &Workspace,
+        docker: &Docker,
+        container: &WsContainer,
+        volumes: &Vec<String>,
+    ) -> Result<(), BError> {
+        if docker.runtime().locate().is_none() {
+            return Err(BError::DockerError(docker.runtime().binary().to_string()));
+        }
+        docker.pull_if_missing(cli)?;
+        container.start(
+            cli,
+            &workspace.settings().docker_top_dir(),
+            workspace.settings().docker_args(),
+            volumes,
+        )?;
+        cli.stdout(format!(
+            "Started workspace container '{}' using image '{}'",
+            container.name(),
+            docker.image()
+        ));
+        Ok(())
+    }
+
+    pub fn new() -> Self {
+        let subcmd: clap::Command = clap::Command::new(BCOMMAND)
+            .about(BCOMMAND_ABOUT)
+            .arg(
+                clap::Arg::new("action")
+                    .value_name("action")
+                    .required(true)
+                    .value_parser(["start", "stop", "status", "exec"])
+                    .help("Start, stop or show the status of the workspace container or exec a command inside it"),
+            )
+            .arg(
+                clap::Arg::new("cmd")
+                    .value_name("cmd")
+                    .num_args(0..)
+                    .trailing_var_arg(true)
+                    .allow_hyphen_values(true)
+                    .help("The command to exec inside the workspace container, if not set an interactive shell is started"),
+            )
+            .arg(
+                clap::Arg::new("volume")
+                    .action(clap::ArgAction::Append)
+                    .short('v')
+                    .long("docker-volume")
+                    .value_name("path:path")
+                    .help("Docker volume to mount bind when starting the workspace container."),
+            )
+            .arg(
+                clap::Arg::new("interactive")
+                    .short('i')
+                    .long("interactive")
+                    .value_name("interactive")
+                    .default_value("true")
+                    .value_parser(["true", "false"])
+                    .help("Determines if the command executed inside the workspace container should be interactive or not"),
+            )
+            .arg(
+                clap::Arg::new("verbose")
+                    .action(clap::ArgAction::SetTrue)
+                    .long("verbose")
+                    .help("Set verbose level."),
+            );
+        // Initialize and return a new ContainerCommand instance
+        ContainerCommand {
+            // Initialize fields if any
+            cmd: BBaseCommand {
+                cmd_str: String::from(BCOMMAND),
+                sub_cmd: subcmd,
+                interactive: false,
+                require_docker: false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    use crate::cli::*;
+    use crate::commands::{BCommand, ContainerCommand};
+    use crate::error::BError;
+    use crate::executers::{Docker, DockerApi, WsContainer};
+    use crate::helper::Helper;
+    use crate::workspace::{Workspace, WsSettingsHandler};
+
+    fn helper_test_container_subcommand(
+        work_dir: &PathBuf,
+        json_ws_settings: &str,
+        mlogger: MockLogger,
+        cmd_line: Vec<&str>,
+    ) -> Result<(), BError> {
+        let settings: WsSettingsHandler = WsSettingsHandler::from_str(work_dir, json_ws_settings)?;
+        std::fs::create_dir_all(settings.configs_dir())?;
+        let mut workspace: Workspace =
+            Workspace::new(Some(work_dir.to_owned()), Some(settings), None)?;
+        let cmd: ContainerCommand = ContainerCommand::new();
+        let cli: Cli = Cli::new(
+            Box::new(mlogger),
+            Box::new(MockSystem::new()),
+            clap::Command::new("bakery"),
+            Some(cmd_line),
+        );
+        cmd.execute(&cli, &mut workspace)
+    }
+
+    fn container_name(work_dir: &PathBuf, json_ws_settings: &str) -> String {
+        let settings: WsSettingsHandler = WsSettingsHandler::from_str(work_dir, json_ws_settings)
+            .expect("Failed to parse settings");
+        let docker: Docker = Docker::new(settings.docker_image(), false);
+        let container: WsContainer = WsContainer::new(
+            &docker,
+            DockerApi::new(&work_dir.join("none.sock")),
+            work_dir,
+        );
+        container.name()
+    }
+
+    #[test]
+    fn test_cmd_container_status() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6"
+        }"#;
+        let name: String = container_name(&work_dir, json_ws_settings);
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_stdout()
+            .with(mockall::predicate::eq(format!(
+                "Workspace container '{}' is not running",
+                name
+            )))
+            .once()
+            .returning(|_x| ());
+        let result: Result<(), BError> = helper_test_container_subcommand(
+            &work_dir,
+            json_ws_settings,
+            mocked_logger,
+            vec!["bakery", "container", "status"],
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_cmd_container_exec_not_running() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6"
+        }"#;
+        let name: String = container_name(&work_dir, json_ws_settings);
+        let result: Result<(), BError> = helper_test_container_subcommand(
+            &work_dir,
+            json_ws_settings,
+            MockLogger::new(),
+            vec!["bakery", "container", "exec", "bitbake", "-e"],
+        );
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because the workspace container is not running");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!("Workspace container '{}' is not running, start it using 'bakery container start'", name)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cmd_container_docker_disabled() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6",
+            "docker": {
+                "disabled": "true"
+            }
+        }"#;
+        let result: Result<(), BError> = helper_test_container_subcommand(
+            &work_dir,
+            json_ws_settings,
+            MockLogger::new(),
+            vec!["bakery", "container", "start"],
+        );
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because docker is disabled");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Docker is disabled in the workspace settings, the workspace container requires docker"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cmd_container_bootstrap_running_secrets() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.path().to_path_buf();
+        Helper::setup_test_ws_default_dirs(&work_dir);
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "test-name",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "bb": {},
+            "secrets": {
+                "UPLOAD_TOKEN": {
+                    "source": "env",
+                    "from": "CI_UPLOAD_TOKEN"
+                }
+            }
+        }"#;
+        let workspace: Workspace = Helper::setup_ws(
+            &work_dir.to_string_lossy(),
+            r#"{"version": "6"}"#,
+            json_build_config,
+        );
+        let docker: Docker = Docker::new(workspace.settings().docker_image(), false);
+        let socket: PathBuf = work_dir.join("docker.sock");
+        let name: String = WsContainer::new(&docker, DockerApi::new(&socket), &work_dir).name();
+        let requests = Helper::docker_api_stub(
+            &socket,
+            vec![
+                (
+                    format!("GET /containers/{}/json", name).as_str(),
+                    Helper::http_response(
+                        200,
+                        format!(
+                            r#"{{"State": {{"Running": true}}, "Config": {{"Labels": {{"bakery.image": "{}", "bakery.args": "e3b0c44298fc", "bakery.volumes": ""}}}}}}"#,
+                            docker.image()
+                        )
+                        .into_bytes(),
+                        false,
+                    ),
+                ),
+                ("GET /_ping", Helper::http_response(200, b"OK".to_vec(), false)),
+                (
+                    format!("POST /containers/{}/exec", name).as_str(),
+                    Helper::http_response(201, br#"{"Id": "exec123"}"#.to_vec(), false),
+                ),
+                (
+                    "POST /exec/exec123/start",
+                    Helper::http_upgrade_response(vec![]),
+                ),
+                (
+                    "GET /exec/exec123/json",
+                    Helper::http_response(200, br#"{"ExitCode": 0}"#.to_vec(), false),
+                ),
+            ],
+        );
+        let cli: Cli = Cli::new(
+            Box::new(MockLogger::new()),
+            Box::new(MockSystem::new()),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery"]),
+        );
+        let cmd_line: Vec<String> = vec![String::from("bakery"), String::from("build")];
+        ContainerCommand::new()
+            .bootstrap_container(
+                &cmd_line,
+                &cli,
+                &workspace,
+                &docker,
+                &DockerApi::new(&socket),
+                &vec![],
+                &HashMap::from([(String::from("CI_UPLOAD_TOKEN"), String::from("token"))]),
+            )
+            .expect("Failed to exec in the workspace container");
+        /* The secret should be passed on to the running workspace container */
+        assert_eq!(
+            cli.take_calls(),
+            vec![docker.exec_cmd_line(
+                &name,
+                &cmd_line,
+                &work_dir,
+                &[String::from("CI_UPLOAD_TOKEN")]
+            )]
+        );
+        assert_eq!(
+            requests.lock().unwrap().clone(),
+            vec![
+                format!("GET /containers/{}/json", name),
+                format!("GET /containers/{}/json", name),
+                String::from("GET /_ping"),
+                format!("POST /containers/{}/exec", name),
+                String::from("POST /exec/exec123/start"),
+                String::from("GET /exec/exec123/json"),
+            ]
+        );
+    }
+}
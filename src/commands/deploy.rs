@@ -69,10 +69,19 @@ impl BCommand for DeployCommand {
          * We will update the context with the variables from the cli
          * and then expand the context variables in the config
          */
+        self.set_strict_ctx(cli, workspace)?;
         workspace.update_ctx(&context)?;
 
         let deploy: &WsCustomSubCmdHandler = workspace.config().deploy();
-        deploy.run(cli, &cli.env(), false, self.cmd.interactive)
+        let mut env: HashMap<String, String> = cli.env();
+        env.extend(
+            workspace
+                .config()
+                .build_data()
+                .secrets()
+                .env(cli, deploy.data().secrets())?,
+        );
+        deploy.run(cli, &env, false, self.cmd.interactive)
     }
 }
 
@@ -94,6 +103,7 @@ impl DeployCommand {
                 .long("verbose")
                 .help("Set verbose level."),
         )
+        .arg(BBaseCommand::strict_ctx_arg())
         .arg(
           clap::Arg::new("ctx")
               .action(clap::ArgAction::Append)
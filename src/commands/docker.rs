@@ -0,0 +1,160 @@
+use std::path::PathBuf;
+
+use crate::cli::Cli;
+use crate::commands::{BBaseCommand, BCommand, BError};
+use crate::executers::{Docker, DockerApi};
+use crate::workspace::Workspace;
+
+static BCOMMAND: &str = "docker";
+static BCOMMAND_ABOUT: &str =
+    "Build the workspace docker image from the Dockerfile in the workspace docker dir.";
+pub struct DockerCommand {
+    cmd: BBaseCommand,
+    // Your struct fields and methods here
+}
+
+impl BCommand for DockerCommand {
+    fn get_config_name(&self, _cli: &Cli) -> String {
+        /* The workspace docker image is shared by all build configs */
+        String::from("NA")
+    }
+
+    fn cmd_str(&self) -> &str {
+        &self.cmd.cmd_str
+    }
+
+    fn subcommand(&self) -> &clap::Command {
+        &self.cmd.sub_cmd
+    }
+
+    fn is_docker_required(&self) -> bool {
+        self.cmd.require_docker
+    }
+
+    fn execute(&self, cli: &Cli, workspace: &mut Workspace) -> Result<(), BError> {
+        let action: String = self.get_arg_str(cli, "action", BCOMMAND)?;
+        match action.as_str() {
+            "build" => self.build(cli, workspace),
+            _ => Err(BError::CliError(format!(
+                "Unsupported docker action '{}'",
+                action
+            ))),
+        }
+    }
+}
+
+impl DockerCommand {
+    fn build(&self, cli: &Cli, workspace: &Workspace) -> Result<(), BError> {
+        let force: bool = self.get_arg_flag(cli, "force", BCOMMAND)?;
+        let docker_dir: PathBuf = workspace.settings().docker_dir();
+
+        if !docker_dir.join("Dockerfile").exists() {
+            return Err(BError::ValueError(format!(
+                "No Dockerfile found in '{}'",
+                docker_dir.display()
+            )));
+        }
+
+        let mut docker: Docker = Docker::new(workspace.settings().docker_image(), false);
+        docker.set_runtime(workspace.settings().docker_runtime());
+
+        if docker.runtime().locate().is_none() {
+            return Err(BError::DockerError(docker.runtime().binary().to_string()));
+        }
+
+        let api: DockerApi = docker.api();
+        if docker.build(cli, &api, &docker_dir, force)? {
+            cli.stdout(format!("Built image '{}'", docker.image()));
+        } else {
+            cli.stdout(format!(
+                "Image '{}' is already built from the current docker dir, use --force to rebuild it",
+                docker.image()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn new() -> Self {
+        let subcmd: clap::Command = clap::Command::new(BCOMMAND)
+            .about(BCOMMAND_ABOUT)
+            .arg(
+                clap::Arg::new("action")
+                    .value_name("action")
+                    .required(true)
+                    .value_parser(["build"])
+                    .help("Build the image using the registry, image and tag from the workspace settings"),
+            )
+            .arg(
+                clap::Arg::new("force")
+                    .action(clap::ArgAction::SetTrue)
+                    .long("force")
+                    .help("Build the image even if it has already been built from the current docker dir"),
+            )
+            .arg(
+                clap::Arg::new("verbose")
+                    .action(clap::ArgAction::SetTrue)
+                    .long("verbose")
+                    .help("Set verbose level."),
+            );
+        // Initialize and return a new DockerCommand instance
+        DockerCommand {
+            // Initialize fields if any
+            cmd: BBaseCommand {
+                cmd_str: String::from(BCOMMAND),
+                sub_cmd: subcmd,
+                interactive: false,
+                require_docker: false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    use crate::cli::*;
+    use crate::commands::{BCommand, DockerCommand};
+    use crate::error::BError;
+    use crate::workspace::{Workspace, WsSettingsHandler};
+
+    #[test]
+    fn test_cmd_docker_build_no_dockerfile() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6"
+        }"#;
+        let settings: WsSettingsHandler = WsSettingsHandler::from_str(&work_dir, json_ws_settings)
+            .expect("Failed to parse settings");
+        std::fs::create_dir_all(settings.configs_dir()).expect("Failed to create configs dir");
+        let mut workspace: Workspace =
+            Workspace::new(Some(work_dir.to_owned()), Some(settings), None)
+                .expect("Failed to setup workspace");
+        let cli: Cli = Cli::new(
+            Box::new(MockLogger::new()),
+            Box::new(MockSystem::new()),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery", "docker", "build"]),
+        );
+        let cmd: DockerCommand = DockerCommand::new();
+        let result: Result<(), BError> = cmd.execute(&cli, &mut workspace);
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because there is no Dockerfile");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!(
+                        "No Dockerfile found in '{}'",
+                        work_dir.join("docker").display()
+                    )
+                );
+            }
+        }
+    }
+}
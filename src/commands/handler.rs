@@ -23,6 +23,29 @@ impl CmdHandler {
     }
 
     pub fn build_cli(&self, mut cli: clap::Command) -> clap::Command {
+        /*
+         * The logging args are global so they can be passed to any of the
+         * sub-commands
+         */
+        cli = cli
+            .arg(
+                clap::Arg::new("log_level")
+                    .long("log-level")
+                    .value_name("level")
+                    .global(true)
+                    .default_value("info")
+                    .value_parser(["error", "warn", "info", "debug"])
+                    .help("Only log messages up to this level. Setting 'debug' is the same as --verbose."),
+            )
+            .arg(
+                clap::Arg::new("log_format")
+                    .long("log-format")
+                    .value_name("format")
+                    .global(true)
+                    .default_value("text")
+                    .value_parser(["text", "json"])
+                    .help("Log messages as plain text or as JSON lines including a timestamp."),
+            );
         for (_, value) in self.cmds.iter() {
             /*
                 We clone the clap::Command owned by the bakery Command.
@@ -160,6 +183,81 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_get_validate_command() {
+        let cmd_handler: CmdHandler = CmdHandler::new();
+        let cmd: Result<&Box<dyn BCommand>, BError> = cmd_handler.get_cmd("validate");
+
+        match cmd {
+            Ok(command) => {
+                assert_eq!(command.cmd_str(), "validate");
+            }
+            Err(err) => {
+                assert!(false, "Expected OK result, but got an error '{}'", err);
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_migrate_command() {
+        let cmd_handler: CmdHandler = CmdHandler::new();
+        let cmd: Result<&Box<dyn BCommand>, BError> = cmd_handler.get_cmd("migrate");
+
+        match cmd {
+            Ok(command) => {
+                assert_eq!(command.cmd_str(), "migrate");
+            }
+            Err(err) => {
+                assert!(false, "Expected OK result, but got an error '{}'", err);
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_container_command() {
+        let cmd_handler: CmdHandler = CmdHandler::new();
+        let cmd: Result<&Box<dyn BCommand>, BError> = cmd_handler.get_cmd("container");
+
+        match cmd {
+            Ok(command) => {
+                assert_eq!(command.cmd_str(), "container");
+            }
+            Err(err) => {
+                assert!(false, "Expected OK result, but got an error '{}'", err);
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_docker_command() {
+        let cmd_handler: CmdHandler = CmdHandler::new();
+        let cmd: Result<&Box<dyn BCommand>, BError> = cmd_handler.get_cmd("docker");
+
+        match cmd {
+            Ok(command) => {
+                assert_eq!(command.cmd_str(), "docker");
+            }
+            Err(err) => {
+                assert!(false, "Expected OK result, but got an error '{}'", err);
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_layers_command() {
+        let cmd_handler: CmdHandler = CmdHandler::new();
+        let cmd: Result<&Box<dyn BCommand>, BError> = cmd_handler.get_cmd("layers");
+
+        match cmd {
+            Ok(command) => {
+                assert_eq!(command.cmd_str(), "layers");
+            }
+            Err(err) => {
+                assert!(false, "Expected OK result, but got an error '{}'", err);
+            }
+        }
+    }
+
     #[test]
     fn test_get_invalid_command() {
         let cmd_handler: CmdHandler = CmdHandler::new();
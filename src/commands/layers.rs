@@ -0,0 +1,399 @@
+use indexmap::IndexMap;
+use std::path::PathBuf;
+
+use crate::cli::Cli;
+use crate::commands::{BBaseCommand, BCommand, BError};
+use crate::data::WsContextData;
+use crate::executers::Git;
+use crate::fs::{BakeryLock, RepoManifest};
+use crate::workspace::{Workspace, WsLayersHandler};
+
+static BCOMMAND: &str = "layers";
+static BCOMMAND_ABOUT: &str =
+    "Fetch, update, show the status of or lock the meta layers defined in the build config.";
+pub struct LayersCommand {
+    cmd: BBaseCommand,
+    // Your struct fields and methods here
+}
+
+impl BCommand for LayersCommand {
+    fn get_config_name(&self, cli: &Cli) -> String {
+        if let Some(sub_matches) = cli.get_args().subcommand_matches(BCOMMAND) {
+            if sub_matches.contains_id("config") {
+                if let Some(value) = sub_matches.get_one::<String>("config") {
+                    return value.clone();
+                }
+            }
+        }
+
+        String::from("default")
+    }
+
+    fn cmd_str(&self) -> &str {
+        &self.cmd.cmd_str
+    }
+
+    fn subcommand(&self) -> &clap::Command {
+        &self.cmd.sub_cmd
+    }
+
+    fn is_docker_required(&self) -> bool {
+        self.cmd.require_docker
+    }
+
+    fn execute(&self, cli: &Cli, workspace: &mut Workspace) -> Result<(), BError> {
+        let action: String = self.get_arg_str(cli, "action", BCOMMAND)?;
+        let config: String = self.get_arg_str(cli, "config", BCOMMAND)?;
+        let ctx: Vec<String> = self.get_arg_many(cli, "ctx", BCOMMAND)?;
+        let locked: bool = self.get_arg_flag(cli, "locked", BCOMMAND)?;
+        let args_context: IndexMap<String, String> = self.setup_context(ctx);
+        let context: WsContextData = WsContextData::new(&args_context)?;
+
+        if !workspace.valid_config(config.as_str()) {
+            return Err(BError::CliError(format!(
+                "Unsupported build config '{}'",
+                config
+            )));
+        }
+
+        self.set_strict_ctx(cli, workspace)?;
+        workspace.update_ctx(&context)?;
+
+        let lock_path: PathBuf = workspace.settings().lock_file();
+        let lock: Option<BakeryLock> = if locked {
+            let lock: BakeryLock = BakeryLock::read(&lock_path)?;
+            lock.verify_config(&config)?;
+            Some(lock)
+        } else {
+            None
+        };
+        let layers: &WsLayersHandler = workspace.config().build_data().layers();
+        let layers_dir: PathBuf = workspace.settings().layers_dir();
+        if action == "import" || action == "export" {
+            let manifest_file: String = self.get_arg_str(cli, "repo_manifest", BCOMMAND)?;
+            if manifest_file == "NA" {
+                return Err(BError::CliError(format!(
+                    "The action '{}' requires a repo manifest, use --repo-manifest",
+                    action
+                )));
+            }
+            let manifest_path: PathBuf = PathBuf::from(manifest_file);
+            if action == "export" {
+                layers
+                    .export_repo_manifest(&layers_dir)?
+                    .write(&manifest_path)?;
+                cli.info(format!(
+                    "Exported repo manifest to '{}'",
+                    manifest_path.display()
+                ));
+                return Ok(());
+            }
+            /*
+             * A relative fetch url in the manifest is relative to the url of
+             * the git repository containing the manifest
+             */
+            let manifest: RepoManifest = RepoManifest::read(&manifest_path)?;
+            let manifest_url: Option<String> = manifest_path
+                .parent()
+                .and_then(|dir| Git::new(dir).remote_url().ok());
+            return layers.import_repo_manifest(
+                cli,
+                &manifest,
+                manifest_url.as_deref(),
+                &layers_dir,
+            );
+        }
+
+        if layers.layers().is_empty() {
+            cli.info(format!("No layers defined in build config '{}'", config));
+            return Ok(());
+        }
+
+        match action.as_str() {
+            "fetch" => layers.fetch(cli, lock.as_ref()),
+            "update" => layers.update(cli, lock.as_ref()),
+            "status" => layers.status(cli),
+            "lock" => {
+                layers.lock(&config)?.write(&lock_path)?;
+                cli.info(format!("Locked layers to '{}'", lock_path.display()));
+                Ok(())
+            }
+            _ => Err(BError::CliError(format!("Invalid action '{}'", action))),
+        }
+    }
+}
+
+impl LayersCommand {
+    pub fn new() -> Self {
+        let subcmd: clap::Command = clap::Command::new(BCOMMAND)
+            .about(BCOMMAND_ABOUT)
+            .arg(
+                clap::Arg::new("action")
+                    .value_name("action")
+                    .required(true)
+                    .value_parser(["fetch", "update", "status", "lock", "import", "export"])
+                    .help("Fetch the missing layers, update all layers, show the status of the layers, lock the layers to the current commits, checkout the projects of a repo manifest or export the layers dir to a repo manifest"),
+            )
+            .arg(
+                clap::Arg::new("config")
+                    .short('c')
+                    .long("config")
+                    .help("The build config defining the layers")
+                    .value_name("name")
+                    .required(true),
+            )
+            .arg(
+                clap::Arg::new("locked")
+                    .action(clap::ArgAction::SetTrue)
+                    .long("locked")
+                    .help("Checkout the layers on the commits in the bakery.lock file in the artifacts dir."),
+            )
+            .arg(
+                clap::Arg::new("repo_manifest")
+                    .long("repo-manifest")
+                    .value_name("path")
+                    .default_value("NA")
+                    .help("The Android repo manifest to import or export."),
+            )
+            .arg(
+                clap::Arg::new("verbose")
+                    .action(clap::ArgAction::SetTrue)
+                    .long("verbose")
+                    .help("Set verbose level."),
+            )
+            .arg(BBaseCommand::strict_ctx_arg())
+            .arg(
+                clap::Arg::new("ctx")
+                    .action(clap::ArgAction::Append)
+                    .short('x')
+                    .long("context")
+                    .value_name("KEY=VALUE")
+                    .help("Adding variable to the context. Any KEY that already exists in the context will be overwriten."),
+            );
+        // Initialize and return a new LayersCommand instance
+        LayersCommand {
+            // Initialize fields if any
+            cmd: BBaseCommand {
+                cmd_str: String::from(BCOMMAND),
+                sub_cmd: subcmd,
+                interactive: false,
+                require_docker: false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use tempdir::TempDir;
+
+    use crate::cli::*;
+    use crate::commands::{BCommand, LayersCommand};
+    use crate::error::BError;
+    use crate::fs::BakeryLock;
+    use crate::workspace::{Workspace, WsBuildConfigHandler, WsSettingsHandler};
+
+    fn helper_git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args([
+                "-c",
+                "user.name=bakery",
+                "-c",
+                "user.email=bakery@localhost",
+            ])
+            .args(args)
+            .output()
+            .expect("Failed to execute git");
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    fn helper_test_layers_subcommand(
+        work_dir: &PathBuf,
+        json_build_config: &str,
+        mlogger: MockLogger,
+        cmd_line: Vec<&str>,
+    ) -> Result<(), BError> {
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6",
+            "builds": {
+                "supported": [
+                    "default"
+                ]
+            }
+        }"#;
+        let settings: WsSettingsHandler = WsSettingsHandler::from_str(work_dir, json_ws_settings)?;
+        let config: WsBuildConfigHandler =
+            WsBuildConfigHandler::from_str(json_build_config, &settings)?;
+        let mut workspace: Workspace =
+            Workspace::new(Some(work_dir.to_owned()), Some(settings), Some(config))?;
+        let cmd: LayersCommand = LayersCommand::new();
+        let cli: Cli = Cli::new(
+            Box::new(mlogger),
+            Box::new(MockSystem::new()),
+            clap::Command::new("bakery"),
+            Some(cmd_line),
+        );
+        cmd.execute(&cli, &mut workspace)
+    }
+
+    #[test]
+    fn test_cmd_layers_fetch_lock() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.path().join("workspace");
+        let repo: PathBuf = temp_dir.path().join("src/meta-test");
+        let bare: PathBuf = temp_dir.path().join("remotes/meta-test.git");
+        std::fs::create_dir_all(repo.join("conf")).expect("Failed to create repo dir");
+        helper_git(&repo, &["init", "--quiet", "-b", "main"]);
+        std::fs::write(repo.join("conf/layer.conf"), "").expect("Failed to write layer.conf");
+        helper_git(&repo, &["add", "."]);
+        helper_git(&repo, &["commit", "--quiet", "-m", "layer"]);
+        let commit: String = helper_git(&repo, &["rev-parse", "HEAD"]);
+        helper_git(
+            temp_dir.path(),
+            &[
+                "clone",
+                "--quiet",
+                "--bare",
+                &repo.to_string_lossy(),
+                &bare.to_string_lossy(),
+            ],
+        );
+        std::fs::create_dir_all(work_dir.join("configs")).expect("Failed to create configs dir");
+        let json_build_config: String = format!(
+            r#"
+        {{
+            "version": "6",
+            "name": "default",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "layers": {{
+                "meta-test": {{
+                    "url": "{}",
+                    "branch": "main"
+                }}
+            }}
+        }}"#,
+            bare.display()
+        );
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Fetching layer 'meta-test' from '{}' to '{}'",
+                bare.display(),
+                work_dir.join("layers/meta-test").display()
+            )))
+            .once()
+            .returning(|_x| ());
+        helper_test_layers_subcommand(
+            &work_dir,
+            &json_build_config,
+            mocked_logger,
+            vec!["bakery", "layers", "fetch", "-c", "default"],
+        )
+        .expect("Failed to fetch layers");
+        assert!(work_dir.join("layers/meta-test/conf/layer.conf").exists());
+
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Locked layers to '{}'",
+                work_dir.join("artifacts/bakery.lock").display()
+            )))
+            .once()
+            .returning(|_x| ());
+        helper_test_layers_subcommand(
+            &work_dir,
+            &json_build_config,
+            mocked_logger,
+            vec!["bakery", "layers", "lock", "-c", "default"],
+        )
+        .expect("Failed to lock layers");
+        let lock: BakeryLock =
+            BakeryLock::read(&work_dir.join("artifacts/bakery.lock"))
+                .expect("Failed to read lock file");
+        lock.verify_config("default")
+            .expect("The lock file is for another build config");
+        assert_eq!(lock.commit("meta-test"), Some(commit.as_str()));
+    }
+
+    #[test]
+    fn test_cmd_layers_locked_missing() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        std::fs::create_dir_all(work_dir.join("configs")).expect("Failed to create configs dir");
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "layers": {
+                "meta-test": {
+                    "url": "https://example.com/meta-test.git"
+                }
+            }
+        }"#;
+        let result: Result<(), BError> = helper_test_layers_subcommand(
+            &work_dir,
+            json_build_config,
+            MockLogger::new(),
+            vec!["bakery", "layers", "fetch", "-c", "default", "--locked"],
+        );
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because there is no lock file");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!(
+                        "No lock file '{}', create it using 'bakery layers lock'",
+                        work_dir.join("artifacts/bakery.lock").display()
+                    )
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cmd_layers_export_no_manifest() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        std::fs::create_dir_all(work_dir.join("configs")).expect("Failed to create configs dir");
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "description": "Test Description",
+            "arch": "test-arch"
+        }"#;
+        let result: Result<(), BError> = helper_test_layers_subcommand(
+            &work_dir,
+            json_build_config,
+            MockLogger::new(),
+            vec!["bakery", "layers", "export", "-c", "default"],
+        );
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because there is no repo manifest");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "The action 'export' requires a repo manifest, use --repo-manifest"
+                );
+            }
+        }
+    }
+}
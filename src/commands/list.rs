@@ -59,6 +59,7 @@ impl BCommand for ListCommand {
         } else {
             // List all tasks for a build config
             if workspace.valid_config(config.as_str()) {
+                self.set_strict_ctx(cli, workspace)?;
                 workspace.expand_ctx()?;
                 cli.stdout(format!(
                     "name: {}\narch: {}\nmachine: {}\ndescription: {}\n",
@@ -121,6 +122,7 @@ impl ListCommand {
                     .long("verbose")
                     .help("Set verbose level."),
             )
+            .arg(BBaseCommand::strict_ctx_arg())
             .arg(
                 clap::Arg::new("ctx")
                     .action(clap::ArgAction::SetTrue)
@@ -383,6 +385,7 @@ mod tests {
             "BKRY_BRANCH".to_string() => "NA".to_string(),
             "BKRY_RESET".to_string() => "false".to_string(),
             "BKRY_EYECANDY".to_string() => "false".to_string(),
+            "BKRY_SOURCE_DATE_EPOCH".to_string() => "".to_string(),
         };
         ctx_variables.iter().for_each(|(key, value)| {
             mocked_logger
@@ -0,0 +1,350 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::cli::Cli;
+use crate::commands::{BBaseCommand, BCommand, BError};
+use crate::configs::{WsConfigMigration, WsConfigMigrator};
+use crate::fs::ConfigFileReader;
+use crate::workspace::{Workspace, WsSettingsHandler};
+
+static BCOMMAND: &str = "migrate";
+static BCOMMAND_ABOUT: &str =
+    "Migrate a build config, the included build configs and the workspace settings to the current format.";
+pub struct MigrateCommand {
+    cmd: BBaseCommand,
+    // Your struct fields and methods here
+}
+
+impl BCommand for MigrateCommand {
+    fn get_config_name(&self, _cli: &Cli) -> String {
+        /*
+         * The build config to migrate is most likely not compatible with the
+         * current version so the migrate sub-command is reading it by it self
+         */
+        String::from("NA")
+    }
+
+    fn cmd_str(&self) -> &str {
+        &self.cmd.cmd_str
+    }
+
+    fn subcommand(&self) -> &clap::Command {
+        &self.cmd.sub_cmd
+    }
+
+    fn is_docker_required(&self) -> bool {
+        self.cmd.require_docker
+    }
+
+    fn execute(&self, cli: &Cli, workspace: &mut Workspace) -> Result<(), BError> {
+        let config: String = self.get_arg_str(cli, "config", BCOMMAND)?;
+        let write: bool = self.get_arg_flag(cli, "write", BCOMMAND)?;
+        let mut changed: bool = false;
+
+        if let Some(path) = self.settings_path(cli, workspace.settings()) {
+            let settings_str: String = std::fs::read_to_string(&path)?;
+            let migration: WsConfigMigration = WsConfigMigrator::migrate_settings(&settings_str)?;
+            self.migrate_file(cli, workspace.settings(), &path, &migration, write)?;
+            changed |= migration.is_changed();
+        }
+
+        if config != "NA" {
+            let file: String = format!("{}.json", config);
+            /*
+             * Look for the build config in the same order as bakery, first in the
+             * workspace/work dir and then in the configs dir
+             */
+            let path: PathBuf = match [
+                workspace.settings().work_dir().join(&file),
+                workspace.settings().configs_dir().join(&file),
+            ]
+            .into_iter()
+            .find(|p| p.exists())
+            {
+                Some(p) => p,
+                None => {
+                    return Err(BError::ValueError(format!(
+                        "Build config '{}' is missing",
+                        file
+                    )));
+                }
+            };
+
+            let config_str: String = ConfigFileReader::new(&path).read_json()?;
+            let migration: WsConfigMigration = WsConfigMigrator::migrate_build_config(&config_str)?;
+            self.migrate_file(cli, workspace.settings(), &path, &migration, write)?;
+            changed |= migration.is_changed();
+
+            for include in Self::includes(migration.migrated())?.iter() {
+                let include_path: PathBuf = workspace
+                    .settings()
+                    .include_dir()
+                    .join(format!("{}.json", include));
+                let include_str: String = ConfigFileReader::new(&include_path).read_json()?;
+                let migration: WsConfigMigration =
+                    WsConfigMigrator::migrate_build_config(&include_str)?;
+                self.migrate_file(cli, workspace.settings(), &include_path, &migration, write)?;
+                changed |= migration.is_changed();
+            }
+        }
+
+        if !write && changed {
+            cli.stdout(String::from(
+                "Run 'bakery migrate' with --write to update the file(s)",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl MigrateCommand {
+    fn display_path(settings: &WsSettingsHandler, path: &Path) -> String {
+        match path.strip_prefix(settings.work_dir()) {
+            Ok(p) => p.display().to_string(),
+            Err(_e) => path.display().to_string(),
+        }
+    }
+
+    /*
+     * Use the same workspace settings as bakery would use, if available the
+     * workspace.json under ${HOME}/.bakery is overriding the one in the work dir
+     */
+    fn settings_path(&self, cli: &Cli, settings: &WsSettingsHandler) -> Option<PathBuf> {
+        [
+            cli.get_home_dir().join(".bakery/workspace.json"),
+            settings.work_dir().join("workspace.json"),
+        ]
+        .into_iter()
+        .find(|p| p.exists())
+    }
+
+    fn includes(config_str: &str) -> Result<Vec<String>, BError> {
+        let data: Value = ConfigFileReader::parse(config_str)?;
+        match data.get("include").and_then(|v| v.as_array()) {
+            Some(includes) => Ok(includes
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.to_string())
+                .collect()),
+            None => Ok(vec![]),
+        }
+    }
+
+    fn migrate_file(
+        &self,
+        cli: &Cli,
+        settings: &WsSettingsHandler,
+        path: &Path,
+        migration: &WsConfigMigration,
+        write: bool,
+    ) -> Result<(), BError> {
+        let file: String = Self::display_path(settings, path);
+        if !migration.is_changed() {
+            cli.stdout(format!(
+                "'{}' is already at version '{}'",
+                file,
+                migration.new_version()
+            ));
+            return Ok(());
+        }
+
+        cli.stdout(migration.diff(&file));
+        if write {
+            std::fs::write(path, migration.migrated())?;
+            cli.stdout(format!(
+                "Migrated '{}' from version '{}' to '{}'",
+                file,
+                migration.old_version(),
+                migration.new_version()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn new() -> Self {
+        let subcmd: clap::Command = clap::Command::new(BCOMMAND)
+            .about(BCOMMAND_ABOUT)
+            .arg(
+                clap::Arg::new("config")
+                    .short('c')
+                    .long("config")
+                    .help("The build config to migrate, if not set only the workspace settings are migrated")
+                    .value_name("name")
+                    .default_value("NA"),
+            )
+            .arg(
+                clap::Arg::new("write")
+                    .action(clap::ArgAction::SetTrue)
+                    .long("write")
+                    .help("Write the migrated build configs and workspace settings instead of only showing the diff"),
+            )
+            .arg(
+                clap::Arg::new("verbose")
+                    .action(clap::ArgAction::SetTrue)
+                    .long("verbose")
+                    .help("Set verbose level."),
+            );
+        // Initialize and return a new MigrateCommand instance
+        MigrateCommand {
+            // Initialize fields if any
+            cmd: BBaseCommand {
+                cmd_str: String::from(BCOMMAND),
+                sub_cmd: subcmd,
+                interactive: false,
+                require_docker: false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::{indexmap, IndexMap};
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    use crate::cli::*;
+    use crate::commands::{BCommand, MigrateCommand};
+    use crate::error::BError;
+    use crate::helper::Helper;
+    use crate::workspace::{Workspace, WsSettingsHandler};
+
+    fn helper_test_migrate_subcommand(
+        work_dir: &PathBuf,
+        json_ws_settings: &str,
+        configs: &IndexMap<PathBuf, String>,
+        mlogger: MockLogger,
+        cmd_line: Vec<&str>,
+    ) -> Result<(), BError> {
+        let settings: WsSettingsHandler = WsSettingsHandler::from_str(work_dir, json_ws_settings)?;
+        std::fs::create_dir_all(settings.include_dir())?;
+        Helper::setup_test_build_configs_files(configs);
+        let mut workspace: Workspace =
+            Workspace::new(Some(work_dir.to_owned()), Some(settings), None)?;
+        let cli: Cli = Cli::new(
+            Box::new(mlogger),
+            Box::new(MockSystem::new()),
+            clap::Command::new("bakery"),
+            Some(cmd_line),
+        );
+        let cmd: MigrateCommand = MigrateCommand::new();
+        cmd.execute(&cli, &mut workspace)
+    }
+
+    #[test]
+    fn test_cmd_migrate() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6"
+        }"#;
+        let configs: IndexMap<PathBuf, String> = indexmap! {
+            work_dir.join("configs/default.json") => r#"{
+    "version": "5",
+    "name": "default",
+    "include": [
+        "tasks"
+    ]
+}"#.to_string(),
+            work_dir.join("configs/include/tasks.json") => r#"{
+    "version": "6",
+    "tasks": {}
+}"#.to_string(),
+        };
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_stdout()
+            .with(mockall::predicate::eq(
+                r#"--- a/configs/default.json
++++ b/configs/default.json
+@@ -6,1 +6,1 @@
+-  "version": "5"
++  "version": "6""#
+                    .to_string(),
+            ))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_stdout()
+            .with(mockall::predicate::eq(
+                "'configs/include/tasks.json' is already at version '6'".to_string(),
+            ))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_stdout()
+            .with(mockall::predicate::eq(
+                "Run 'bakery migrate' with --write to update the file(s)".to_string(),
+            ))
+            .once()
+            .returning(|_x| ());
+        let result: Result<(), BError> = helper_test_migrate_subcommand(
+            &work_dir,
+            json_ws_settings,
+            &configs,
+            mocked_logger,
+            vec!["bakery", "migrate", "-c", "default"],
+        );
+        assert!(result.is_ok());
+        let config_str: String = std::fs::read_to_string(work_dir.join("configs/default.json"))
+            .expect("Failed to read build config");
+        assert!(config_str.contains(r#""version": "5""#));
+    }
+
+    #[test]
+    fn test_cmd_migrate_write() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6"
+        }"#;
+        let configs: IndexMap<PathBuf, String> = indexmap! {
+            work_dir.join("configs/default.json") => r#"{
+    "version": "5",
+    "name": "default"
+}"#.to_string(),
+        };
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_stdout()
+            .with(mockall::predicate::eq(
+                r#"--- a/configs/default.json
++++ b/configs/default.json
+@@ -3,1 +3,1 @@
+-  "version": "5"
++  "version": "6""#
+                .to_string(),
+            ))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_stdout()
+            .with(mockall::predicate::eq(
+                "Migrated 'configs/default.json' from version '5' to '6'".to_string(),
+            ))
+            .once()
+            .returning(|_x| ());
+        let result: Result<(), BError> = helper_test_migrate_subcommand(
+            &work_dir,
+            json_ws_settings,
+            &configs,
+            mocked_logger,
+            vec!["bakery", "migrate", "-c", "default", "--write"],
+        );
+        assert!(result.is_ok());
+        let config_str: String = std::fs::read_to_string(work_dir.join("configs/default.json"))
+            .expect("Failed to read build config");
+        assert_eq!(
+            config_str,
+            r#"{
+  "name": "default",
+  "version": "6"
+}
+"#
+        );
+    }
+}
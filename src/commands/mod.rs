@@ -1,12 +1,17 @@
 pub mod build;
 pub mod clean;
+pub mod container;
 pub mod deploy;
+pub mod docker;
 pub mod handler;
+pub mod layers;
 pub mod list;
+pub mod migrate;
 pub mod setup;
 pub mod shell;
 pub mod sync;
 pub mod upload;
+pub mod validate;
 
 use indexmap::IndexMap;
 use std::collections::HashMap;
@@ -15,11 +20,11 @@ use std::path::PathBuf;
 use crate::cli::Cli;
 use crate::error::BError;
 use crate::executers::docker::Docker;
-use crate::executers::DockerImage;
+use crate::executers::{ContainerStatus, DockerApi, WsContainer};
 use crate::workspace::Workspace;
 
 // Bakery SubCommand
-pub trait BCommand {
+pub trait BCommand: Send + Sync {
     fn setup_context(&self, ctx: Vec<String>) -> IndexMap<String, String> {
         let context: IndexMap<String, String> = ctx
             .iter()
@@ -41,7 +46,8 @@ pub trait BCommand {
     }
 
     fn docker_pull(&self, cli: &Cli, workspace: &Workspace) -> Result<(), BError> {
-        let docker: Docker = Docker::new(workspace.settings().docker_image(), false);
+        let mut docker: Docker = Docker::new(workspace.settings().docker_image(), false);
+        docker.set_runtime(workspace.settings().docker_runtime());
         return docker.pull(cli);
     }
 
@@ -53,7 +59,15 @@ pub trait BCommand {
         volumes: &Vec<String>,
         interactive: bool,
     ) -> Result<(), BError> {
-        let docker: Docker = Docker::new(workspace.settings().docker_image(), interactive);
+        let mut docker: Docker = Docker::new(workspace.settings().docker_image(), interactive);
+        docker.set_runtime(workspace.settings().docker_runtime());
+
+        /*
+         * The shared cache dirs from the workspace settings are always mounted
+         * read-only so that bitbake can use them as mirrors inside docker
+         */
+        let mut volumes: Vec<String> = volumes.clone();
+        volumes.append(&mut workspace.settings().cache_volumes());
 
         /*
          * When we bootstrap bakery into docker we should make sure that we pull
@@ -63,30 +77,124 @@ pub trait BCommand {
 
         cli.info(format!("Bootstrap bakery into '{}'", docker.image()));
 
-        if !PathBuf::from("/usr/bin/docker").exists() {
-            return Err(BError::DockerError());
+        if docker.runtime().locate().is_none() {
+            return Err(BError::DockerError(docker.runtime().binary().to_string()));
         }
 
         /*
          * The docker pull expects that there is a registry available and it will
          * check if there is a newer image in the registry and fail if it cannot
-         * find the registry even if there is an image locally available.
-         * Ideally it should only pull the image if it cannot find a local image.
-         * I get the logic but in this case the image could only be available
-         * as a local image and we don't want to fail because of that. It might
-         * be that this is a bit to much of logic and we should migrate our current
-         * docker implemmentation to rust docker API.
+         * find the registry even if there is an image locally available. Instead
+         * we are using the docker engine API to only pull the image if it cannot
+         * find a local image. If the workspace has a Dockerfile in the docker dir
+         * the missing image is built instead of pulled.
+         */
+        let api: DockerApi = docker.api();
+        let docker_dir: PathBuf = workspace.settings().docker_dir();
+        if docker_dir.join("Dockerfile").exists()
+            && api.available()
+            && api.inspect_image(docker.image())?.is_none()
+        {
+            cli.info(format!(
+                "Image '{}' is missing locally, building it from '{}'",
+                docker.image(),
+                docker_dir.display()
+            ));
+            docker.build(cli, &api, &docker_dir, true)?;
+        } else {
+            docker.pull_if_missing(cli)?;
+        }
+
+        self.bootstrap_container(cmd_line, cli, workspace, &docker, &api, &volumes, &env)
+    }
+
+    /*
+     * If a workspace container has been started using 'bakery container start'
+     * we exec into the running container instead of starting a new container.
+     * If the image of the workspace has changed since the container was started
+     * the container is recreated using the current image. The container is also
+     * recreated if it is missing any of the volumes or if the docker args have
+     * changed since it was started.
+     */
+    fn bootstrap_container(
+        &self,
+        cmd_line: &Vec<String>,
+        cli: &Cli,
+        workspace: &Workspace,
+        docker: &Docker,
+        api: &DockerApi,
+        volumes: &Vec<String>,
+        env: &HashMap<String, String>,
+    ) -> Result<(), BError> {
+        /*
+         * Secrets read from env variables needs to be passed on to bakery
+         * running inside docker. Only the name is passed on the command line
+         * and docker will pick up the value from the env
          */
-        // docker.pull(cli)?;
+        let secrets: Vec<String> = workspace
+            .config()
+            .build_data()
+            .secrets()
+            .env_sources()
+            .into_iter()
+            .filter(|name| env.contains_key(name.as_str()))
+            .collect();
+
+        let container: WsContainer = WsContainer::new(
+            docker,
+            DockerApi::new(api.socket()),
+            &workspace.settings().work_dir(),
+        );
+        match container.status()? {
+            ContainerStatus::Running => {
+                if let Some(volumes) =
+                    container.recreate_volumes(workspace.settings().docker_args(), volumes)?
+                {
+                    cli.info(format!(
+                        "Workspace container '{}' is missing volumes or using other docker args, recreating it",
+                        container.name()
+                    ));
+                    container.start(
+                        cli,
+                        &workspace.settings().docker_top_dir(),
+                        workspace.settings().docker_args(),
+                        &volumes,
+                    )?;
+                }
+                return container.exec(cmd_line, cli, env, &secrets);
+            }
+            ContainerStatus::Outdated(image) => {
+                cli.info(format!(
+                    "Workspace container '{}' is using image '{}', recreating it using '{}'",
+                    container.name(),
+                    image,
+                    docker.image()
+                ));
+                container.start(
+                    cli,
+                    &workspace.settings().docker_top_dir(),
+                    workspace.settings().docker_args(),
+                    volumes,
+                )?;
+                return container.exec(cmd_line, cli, env, &secrets);
+            }
+            _ => {}
+        }
+
+        let mut docker_args: Vec<String> = workspace.settings().docker_args().clone();
+        secrets.iter().for_each(|name| {
+            docker_args.append(&mut vec![String::from("-e"), name.clone()]);
+        });
 
         return docker.bootstrap_bakery(
             cmd_line,
             cli,
+            api,
             &workspace.settings().docker_top_dir(),
             &workspace.settings().work_dir(),
-            workspace.settings().docker_args(),
+            &docker_args,
             volumes,
-            &env,
+            env,
         );
     }
 
@@ -137,6 +245,17 @@ pub trait BCommand {
         return Err(BError::CliError(format!("Failed to read arg {}", id)));
     }
 
+    /*
+     * The --strict-ctx flag is enabling strict expansion of the context for
+     * a single command overriding the workspace settings
+     */
+    fn set_strict_ctx(&self, cli: &Cli, workspace: &mut Workspace) -> Result<(), BError> {
+        if self.get_arg_flag(cli, "strict_ctx", self.cmd_str())? {
+            workspace.set_strict_ctx(true);
+        }
+        Ok(())
+    }
+
     // Return a clap sub-command containing the args
     // for the bakery command
     fn subcommand(&self) -> &clap::Command;
@@ -152,6 +271,18 @@ pub struct BBaseCommand {
     //_env: Vars,
 }
 
+impl BBaseCommand {
+    /*
+     * The arg used by all the sub-commands expanding the context of a build config
+     */
+    pub fn strict_ctx_arg() -> clap::Arg {
+        clap::Arg::new("strict_ctx")
+            .action(clap::ArgAction::SetTrue)
+            .long("strict-ctx")
+            .help("Fail on any context variable used in the build config that is not defined.")
+    }
+}
+
 pub fn get_supported_cmds() -> HashMap<&'static str, Box<dyn BCommand>> {
     let mut supported_cmds: HashMap<&'static str, Box<dyn BCommand>> = HashMap::new();
 
@@ -164,6 +295,11 @@ pub fn get_supported_cmds() -> HashMap<&'static str, Box<dyn BCommand>> {
     supported_cmds.insert("upload", Box::new(UploadCommand::new()));
     supported_cmds.insert("setup", Box::new(SetupCommand::new()));
     supported_cmds.insert("sync", Box::new(SyncCommand::new()));
+    supported_cmds.insert("validate", Box::new(ValidateCommand::new()));
+    supported_cmds.insert("migrate", Box::new(MigrateCommand::new()));
+    supported_cmds.insert("container", Box::new(ContainerCommand::new()));
+    supported_cmds.insert("docker", Box::new(DockerCommand::new()));
+    supported_cmds.insert("layers", Box::new(LayersCommand::new()));
 
     // Add more commands as needed
 
@@ -172,10 +308,15 @@ pub fn get_supported_cmds() -> HashMap<&'static str, Box<dyn BCommand>> {
 
 pub use build::BuildCommand;
 pub use clean::CleanCommand;
+pub use container::ContainerCommand;
 pub use deploy::DeployCommand;
+pub use docker::DockerCommand;
 pub use handler::CmdHandler;
+pub use layers::LayersCommand;
 pub use list::ListCommand;
+pub use migrate::MigrateCommand;
 pub use setup::SetupCommand;
 pub use shell::ShellCommand;
 pub use sync::SyncCommand;
 pub use upload::UploadCommand;
+pub use validate::ValidateCommand;
@@ -1,4 +1,5 @@
 use indexmap::{indexmap, IndexMap};
+use std::collections::HashMap;
 
 use crate::cli::Cli;
 use crate::commands::{BBaseCommand, BCommand, BError};
@@ -58,10 +59,19 @@ impl BCommand for SetupCommand {
             )));
         }
 
+        self.set_strict_ctx(cli, workspace)?;
         workspace.update_ctx(&context)?;
 
         let setup: &WsCustomSubCmdHandler = workspace.config().setup();
-        setup.run(cli, &cli.env(), false, self.cmd.interactive)
+        let mut env: HashMap<String, String> = cli.env();
+        env.extend(
+            workspace
+                .config()
+                .build_data()
+                .secrets()
+                .env(cli, setup.data().secrets())?,
+        );
+        setup.run(cli, &env, false, self.cmd.interactive)
     }
 }
 
@@ -92,6 +102,7 @@ impl SetupCommand {
             .long("verbose")
             .help("Set verbose level."),
       )
+      .arg(BBaseCommand::strict_ctx_arg())
       .arg(
         clap::Arg::new("ctx")
             .action(clap::ArgAction::Append)
@@ -123,6 +123,7 @@ impl BCommand for ShellCommand {
             )));
         }
 
+        self.set_strict_ctx(cli, workspace)?;
         workspace.update_ctx(&context)?;
         workspace.expand_ctx()?;
 
@@ -159,6 +160,7 @@ impl ShellCommand {
                 .long("verbose")
                 .help("Set verbose level."),
         )
+        .arg(BBaseCommand::strict_ctx_arg())
         .arg(
             clap::Arg::new("volume")
                 .action(clap::ArgAction::Append)
@@ -327,7 +329,8 @@ impl ShellCommand {
         cli.info(String::from("Start shell setting up bitbake build env"));
         if !docker.is_empty() {
             let image: DockerImage = DockerImage::new(&docker)?;
-            let executer: Docker = Docker::new(image, true);
+            let mut executer: Docker = Docker::new(image, true);
+            executer.set_runtime(workspace.settings().docker_runtime());
             return executer.run_cmd(&cmd_line, &env, &workspace.settings().work_dir(), cli);
         }
 
@@ -357,7 +360,8 @@ impl ShellCommand {
         cli.info(format!("Running command '{}'", cmd));
         if !docker.is_empty() {
             let image: DockerImage = DockerImage::new(&docker)?;
-            let executer: Docker = Docker::new(image, interactive);
+            let mut executer: Docker = Docker::new(image, interactive);
+            executer.set_runtime(workspace.settings().docker_runtime());
             return executer.run_cmd(&cmd_line, &env, &workspace.settings().work_dir(), cli);
         }
 
@@ -376,7 +380,8 @@ impl ShellCommand {
         cli.info(String::from("Starting shell"));
         if !docker.is_empty() {
             let image: DockerImage = DockerImage::new(&docker)?;
-            let executer: Docker = Docker::new(image, interactive);
+            let mut executer: Docker = Docker::new(image, interactive);
+            executer.set_runtime(workspace.settings().docker_runtime());
             return executer.run_cmd(
                 &cmd_line,
                 &HashMap::new(),
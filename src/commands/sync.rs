@@ -1,8 +1,12 @@
 use indexmap::{indexmap, IndexMap};
+use std::collections::HashMap;
+use std::path::PathBuf;
 
 use crate::cli::Cli;
 use crate::commands::{BBaseCommand, BCommand, BError};
 use crate::data::{WsContextData, CTX_KEY_BRANCH, CTX_KEY_RESET};
+use crate::executers::DockerApi;
+use crate::fs::{BakeryLock, LockedImage};
 use crate::workspace::Workspace;
 use crate::workspace::WsCustomSubCmdHandler;
 
@@ -63,14 +67,83 @@ impl BCommand for SyncCommand {
             )));
         }
 
+        self.set_strict_ctx(cli, workspace)?;
         workspace.update_ctx(&context)?;
 
+        /*
+         * When locked the layers are restored to the revisions in the lock file
+         * instead of running the sync command which would move them
+         */
+        if self.get_arg_flag(cli, "locked", BCOMMAND)? {
+            let lock_file: String = self.get_arg_str(cli, "lock_file", BCOMMAND)?;
+            let lock_path: PathBuf = if lock_file == "NA" {
+                workspace.settings().lock_file()
+            } else {
+                PathBuf::from(lock_file)
+            };
+            cli.info(format!("Restore workspace from '{}'", lock_path.display()));
+            let lock: BakeryLock = BakeryLock::read(&lock_path)?;
+            lock.verify_config(&config)?;
+            workspace.config().build_data().layers().restore(
+                cli,
+                &lock,
+                &workspace.settings().layers_dir(),
+            )?;
+            return self.check_docker(cli, workspace, &lock);
+        }
+
         let sync: &WsCustomSubCmdHandler = workspace.config().sync();
-        sync.run(cli, &cli.env(), false, self.cmd.interactive)
+        let mut env: HashMap<String, String> = cli.env();
+        env.extend(
+            workspace
+                .config()
+                .build_data()
+                .secrets()
+                .env(cli, sync.data().secrets())?,
+        );
+        sync.run(cli, &env, false, self.cmd.interactive)
     }
 }
 
 impl SyncCommand {
+    /*
+     * The docker image cannot be restored since the workspace settings decides
+     * what image to use so we only let the user know if it differs
+     */
+    fn check_docker(
+        &self,
+        cli: &Cli,
+        workspace: &Workspace,
+        lock: &BakeryLock,
+    ) -> Result<(), BError> {
+        let locked: &LockedImage = match lock.docker() {
+            Some(docker) if !workspace.settings().docker_disabled() => docker,
+            _ => return Ok(()),
+        };
+        let image: String = workspace.settings().docker_image().to_string();
+        if locked.image != image {
+            cli.info(format!(
+                "The lock file is using docker image '{}' but the workspace is using '{}'",
+                locked.image, image
+            ));
+            return Ok(());
+        }
+        let api: DockerApi = DockerApi::new(&workspace.settings().docker_runtime().socket());
+        if locked.digest.is_empty() || !api.available() {
+            return Ok(());
+        }
+        let digest: String = api
+            .image_digest(&workspace.settings().docker_image())?
+            .unwrap_or_default();
+        if digest != locked.digest {
+            cli.info(format!(
+                "The lock file is using docker image '{}' with digest '{}' but the local image has digest '{}'",
+                image, locked.digest, digest
+            ));
+        }
+        Ok(())
+    }
+
     pub fn new() -> Self {
         let subcmd: clap::Command = clap::Command::new(BCOMMAND)
       .about(BCOMMAND_ABOUT)
@@ -97,12 +170,26 @@ impl SyncCommand {
             .long("verbose")
             .help("Set verbose level."),
       )
+      .arg(BBaseCommand::strict_ctx_arg())
       .arg(
         clap::Arg::new("reset")
             .action(clap::ArgAction::SetTrue)
             .long("reset")
             .help("Reset workspace, all changes will be lost"),
       )
+      .arg(
+        clap::Arg::new("locked")
+            .action(clap::ArgAction::SetTrue)
+            .long("locked")
+            .help("Restore the layers to the revisions in the lock file written by the last successful build instead of running the sync command"),
+      )
+      .arg(
+        clap::Arg::new("lock_file")
+            .long("lock-file")
+            .value_name("path")
+            .default_value("NA")
+            .help("The lock file to use with --locked, defaults to the bakery.lock in the artifacts dir"),
+      )
       .arg(
         clap::Arg::new("ctx")
             .action(clap::ArgAction::Append)
@@ -127,14 +214,73 @@ impl SyncCommand {
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
     use tempdir::TempDir;
 
     use crate::cli::*;
     use crate::commands::{BCommand, SyncCommand};
     use crate::error::BError;
+    use crate::executers::Git;
+    use crate::fs::BakeryLock;
     use crate::workspace::{Workspace, WsBuildConfigHandler, WsSettingsHandler};
 
+    fn helper_git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=bakery", "-c", "user.email=bakery@localhost"])
+            .args(args)
+            .output()
+            .expect("Failed to execute git");
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    fn helper_test_sync_locked(
+        work_dir: &PathBuf,
+        mlogger: MockLogger,
+        cmd_line: Vec<&str>,
+    ) -> Result<(), BError> {
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6",
+            "builds": {
+                "supported": [
+                    "default"
+                ]
+            }
+        }"#;
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "sync": {
+                "cmd": "$#[BKRY_SCRIPTS_DIR]/sync.sh"
+            }
+        }
+        "#;
+        let settings: WsSettingsHandler = WsSettingsHandler::from_str(work_dir, json_ws_settings)
+            .expect("Failed to parse settings");
+        let config: WsBuildConfigHandler =
+            WsBuildConfigHandler::from_str(json_build_config, &settings)
+                .expect("Failed to parse build config");
+        let mut workspace: Workspace =
+            Workspace::new(Some(work_dir.to_owned()), Some(settings), Some(config))
+                .expect("Failed to setup workspace");
+        /* The sync command should never be called when locked */
+        let cli: Cli = Cli::new(
+            Box::new(mlogger),
+            Box::new(MockSystem::new()),
+            clap::Command::new("bakery"),
+            Some(cmd_line),
+        );
+        let cmd: SyncCommand = SyncCommand::new();
+        cmd.execute(&cli, &mut workspace)
+    }
+
     #[test]
     fn test_cmd_sync() {
         let temp_dir: TempDir =
@@ -346,4 +492,128 @@ mod tests {
         let cmd: SyncCommand = SyncCommand::new();
         let _result: Result<(), BError> = cmd.execute(&cli, &mut workspace);
     }
+
+    #[test]
+    fn test_cmd_sync_locked() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let layer_dir: PathBuf = work_dir.join("layers/meta-test");
+        std::fs::create_dir_all(layer_dir.join("conf")).expect("Failed to create layer dir");
+        helper_git(&layer_dir, &["init", "--quiet", "-b", "main"]);
+        std::fs::write(layer_dir.join("conf/layer.conf"), "").expect("Failed to write file");
+        helper_git(&layer_dir, &["add", "."]);
+        helper_git(&layer_dir, &["commit", "--quiet", "-m", "first"]);
+        let first: String = helper_git(&layer_dir, &["rev-parse", "HEAD"]);
+        helper_git(&layer_dir, &["commit", "--quiet", "--allow-empty", "-m", "second"]);
+        let lock_path: PathBuf = work_dir.join("artifacts/bakery.lock");
+        let mut lock: BakeryLock = BakeryLock::new("default");
+        lock.add_layer("meta-test", "meta-test", "", &first);
+        lock.write(&lock_path).expect("Failed to write lock file");
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Restore workspace from '{}'",
+                lock_path.display()
+            )))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Checkout layer 'meta-test' in '{}' at '{}'",
+                layer_dir.display(),
+                first
+            )))
+            .once()
+            .returning(|_x| ());
+        helper_test_sync_locked(
+            &work_dir,
+            mocked_logger,
+            vec!["bakery", "sync", "-c", "default", "--locked"],
+        )
+        .expect("Failed to restore workspace");
+        assert_eq!(Git::new(&layer_dir).head().unwrap(), first);
+    }
+
+    #[test]
+    fn test_cmd_sync_locked_missing() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let lock_path: PathBuf = work_dir.join("release/bakery.lock");
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Restore workspace from '{}'",
+                lock_path.display()
+            )))
+            .once()
+            .returning(|_x| ());
+        let result: Result<(), BError> = helper_test_sync_locked(
+            &work_dir,
+            mocked_logger,
+            vec![
+                "bakery",
+                "sync",
+                "-c",
+                "default",
+                "--locked",
+                "--lock-file",
+                &lock_path.to_string_lossy(),
+            ],
+        );
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because there is no lock file");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!(
+                        "No lock file '{}', create it using 'bakery layers lock'",
+                        lock_path.display()
+                    )
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cmd_sync_locked_other_config() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let lock_path: PathBuf = work_dir.join("artifacts/bakery.lock");
+        BakeryLock::new("other")
+            .write(&lock_path)
+            .expect("Failed to write lock file");
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Restore workspace from '{}'",
+                lock_path.display()
+            )))
+            .once()
+            .returning(|_x| ());
+        let result: Result<(), BError> = helper_test_sync_locked(
+            &work_dir,
+            mocked_logger,
+            vec!["bakery", "sync", "-c", "default", "--locked"],
+        );
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because the lock file is for another build config");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    String::from("The lock file is for build config 'other' not 'default'")
+                );
+            }
+        }
+    }
 }
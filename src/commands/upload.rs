@@ -5,6 +5,7 @@ use crate::cli::Cli;
 use crate::commands::{BBaseCommand, BCommand, BError};
 use crate::data::WsContextData;
 use crate::executers::Docker;
+use crate::fs::Checksums;
 use crate::workspace::Workspace;
 use crate::workspace::WsCustomSubCmdHandler;
 
@@ -46,6 +47,7 @@ impl BCommand for UploadCommand {
         let args_context: IndexMap<String, String> = self.setup_context(ctx);
         let context: WsContextData = WsContextData::new(&args_context)?;
         let volumes: Vec<String> = self.get_arg_many(cli, "volume", BCOMMAND)?;
+        let verify: Vec<String> = self.get_arg_many(cli, "verify", BCOMMAND)?;
 
         if !workspace.valid_config(config.as_str()) {
             return Err(BError::CliError(format!(
@@ -73,10 +75,44 @@ impl BCommand for UploadCommand {
             );
         }
 
+        self.set_strict_ctx(cli, workspace)?;
         workspace.update_ctx(&context)?;
 
+        /*
+         * Verify the integrity of the artifacts before uploading anything. The
+         * checksums files are relative to the artifacts dir. If a signing tool
+         * has been set in the workspace settings the signature of the checksums
+         * file is verified first.
+         */
+        for file in verify.iter() {
+            let checksums: Checksums =
+                Checksums::new(&workspace.settings().artifacts_dir().join(file));
+            let tool: &str = workspace.settings().signing_tool();
+            if !tool.is_empty() {
+                checksums.verify_signature(cli, tool, &workspace.settings().signing_pubkey())?;
+                cli.info(format!(
+                    "Verified signature '{}'",
+                    checksums.signature_path().display()
+                ));
+            }
+            let verified: usize = checksums.verify()?;
+            cli.info(format!(
+                "Verified {} files in '{}'",
+                verified,
+                checksums.path().display()
+            ));
+        }
+
         let upload: &WsCustomSubCmdHandler = workspace.config().upload();
-        upload.run(cli, &cli.env(), false, self.cmd.interactive)
+        let mut env: HashMap<String, String> = cli.env();
+        env.extend(
+            workspace
+                .config()
+                .build_data()
+                .secrets()
+                .env(cli, upload.data().secrets())?,
+        );
+        upload.run(cli, &env, false, self.cmd.interactive)
     }
 }
 
@@ -100,12 +136,20 @@ impl UploadCommand {
             .value_name("path:path")
             .help("Docker volume to mount bind when boot strapping into docker."),
       )
+      .arg(
+        clap::Arg::new("verify")
+            .action(clap::ArgAction::Append)
+            .long("verify")
+            .value_name("file")
+            .help("Verify the artifacts listed in a checksums file relative to the artifacts dir before uploading."),
+      )
       .arg(
         clap::Arg::new("verbose")
             .action(clap::ArgAction::SetTrue)
             .long("verbose")
             .help("Set verbose level."),
       )
+      .arg(BBaseCommand::strict_ctx_arg())
       .arg(
         clap::Arg::new("ctx")
             .action(clap::ArgAction::Append)
@@ -280,4 +324,180 @@ mod tests {
         let cmd: UploadCommand = UploadCommand::new();
         let _result: Result<(), BError> = cmd.execute(&cli, &mut workspace);
     }
+
+    #[test]
+    fn test_cmd_upload_verify() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: &PathBuf = &temp_dir.into_path();
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6",
+            "builds": {
+                "supported": [
+                    "default"
+                ]
+            }
+        }"#;
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "bb": {},
+            "upload": {
+                "cmd": "$#[BKRY_SCRIPTS_DIR]/script.sh"
+            }
+        }
+        "#;
+        let artifacts_dir: PathBuf = work_dir.join("artifacts");
+        std::fs::create_dir_all(&artifacts_dir).expect("Failed to create artifacts dir");
+        std::fs::write(artifacts_dir.join("image.bin"), "image").expect("Failed to write image");
+        std::fs::write(
+            artifacts_dir.join("SHA256SUMS"),
+            format!("{}  image.bin\n", "0".repeat(64)),
+        )
+        .expect("Failed to write checksums");
+        /* No upload is expected since the verification is failing */
+        let mut mocked_system: MockSystem = MockSystem::new();
+        mocked_system.expect_env().returning(|| HashMap::new());
+        let settings: WsSettingsHandler = WsSettingsHandler::from_str(work_dir, json_ws_settings)
+            .expect("Failed to parse settings");
+        let config: WsBuildConfigHandler =
+            WsBuildConfigHandler::from_str(json_build_config, &settings)
+                .expect("Failed to parse build config");
+        let mut workspace: Workspace =
+            Workspace::new(Some(work_dir.to_owned()), Some(settings), Some(config))
+                .expect("Failed to setup workspace");
+        let cli: Cli = Cli::new(
+            Box::new(BLogger::new()),
+            Box::new(mocked_system),
+            clap::Command::new("bakery"),
+            Some(vec![
+                "bakery",
+                "upload",
+                "-c",
+                "default",
+                "--verify",
+                "SHA256SUMS",
+            ]),
+        );
+        let cmd: UploadCommand = UploadCommand::new();
+        let result: Result<(), BError> = cmd.execute(&cli, &mut workspace);
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because the checksum is invalid!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!(
+                        "Checksum verification of '{}' failed for 'image.bin'",
+                        artifacts_dir.join("SHA256SUMS").display()
+                    )
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cmd_upload_verify_signature() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: &PathBuf = &temp_dir.into_path();
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6",
+            "builds": {
+                "supported": [
+                    "default"
+                ]
+            },
+            "signing": {
+                "tool": "openssl",
+                "key": "keys/private.pem",
+                "pubkey": "keys/public.pem"
+            }
+        }"#;
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "bb": {},
+            "upload": {
+                "cmd": "$#[BKRY_SCRIPTS_DIR]/script.sh"
+            }
+        }
+        "#;
+        let artifacts_dir: PathBuf = work_dir.join("artifacts");
+        let checksums_path: PathBuf = artifacts_dir.join("SHA256SUMS");
+        let signature_path: PathBuf = artifacts_dir.join("SHA256SUMS.sig");
+        std::fs::create_dir_all(&artifacts_dir).expect("Failed to create artifacts dir");
+        std::fs::write(&checksums_path, "").expect("Failed to write checksums");
+        std::fs::write(&signature_path, "signature").expect("Failed to write signature");
+        /* No upload is expected since the signature verification is failing */
+        let mut mocked_system: MockSystem = MockSystem::new();
+        mocked_system.expect_env().returning(HashMap::new);
+        mocked_system
+            .expect_check_call()
+            .with(mockall::predicate::eq(CallParams {
+                cmd_line: [
+                    "openssl",
+                    "dgst",
+                    "-sha256",
+                    "-verify",
+                    &work_dir.join("keys/public.pem").to_string_lossy(),
+                    "-signature",
+                    &signature_path.to_string_lossy(),
+                    &checksums_path.to_string_lossy(),
+                ]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+                env: HashMap::new(),
+                shell: false,
+            }))
+            .once()
+            .returning(|_x| Err(BError::CliError(String::from("Verification failure"))));
+        let settings: WsSettingsHandler = WsSettingsHandler::from_str(work_dir, json_ws_settings)
+            .expect("Failed to parse settings");
+        let config: WsBuildConfigHandler =
+            WsBuildConfigHandler::from_str(json_build_config, &settings)
+                .expect("Failed to parse build config");
+        let mut workspace: Workspace =
+            Workspace::new(Some(work_dir.to_owned()), Some(settings), Some(config))
+                .expect("Failed to setup workspace");
+        let cli: Cli = Cli::new(
+            Box::new(BLogger::new()),
+            Box::new(mocked_system),
+            clap::Command::new("bakery"),
+            Some(vec![
+                "bakery",
+                "upload",
+                "-c",
+                "default",
+                "--verify",
+                "SHA256SUMS",
+            ]),
+        );
+        let cmd: UploadCommand = UploadCommand::new();
+        let result: Result<(), BError> = cmd.execute(&cli, &mut workspace);
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because the signature is invalid!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!(
+                        "Signature verification of '{}' failed, Verification failure",
+                        checksums_path.display()
+                    )
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,473 @@
+use indexmap::IndexMap;
+use std::path::{Path, PathBuf};
+
+use crate::cli::Cli;
+use crate::commands::{BBaseCommand, BCommand, BError};
+use crate::configs::WsConfigValidator;
+use crate::fs::ConfigFileReader;
+use crate::workspace::{Workspace, WsSettingsHandler};
+
+static BCOMMAND: &str = "validate";
+static BCOMMAND_ABOUT: &str =
+    "Validate build configs, included build configs and the workspace settings reporting all problems found.";
+pub struct ValidateCommand {
+    cmd: BBaseCommand,
+    // Your struct fields and methods here
+}
+
+impl BCommand for ValidateCommand {
+    fn get_config_name(&self, _cli: &Cli) -> String {
+        /*
+         * The validate sub-command is reading the build configs by it self so
+         * that it can report all problems instead of failing on the first one
+         * when setting up the workspace.
+         */
+        String::from("NA")
+    }
+
+    fn cmd_str(&self) -> &str {
+        &self.cmd.cmd_str
+    }
+
+    fn subcommand(&self) -> &clap::Command {
+        &self.cmd.sub_cmd
+    }
+
+    fn is_docker_required(&self) -> bool {
+        self.cmd.require_docker
+    }
+
+    fn execute(&self, cli: &Cli, workspace: &mut Workspace) -> Result<(), BError> {
+        let config: String = self.get_arg_str(cli, "config", BCOMMAND)?;
+        let all: bool = self.get_arg_flag(cli, "all", BCOMMAND)?;
+        let ctx: Vec<String> = self.get_arg_many(cli, "ctx", BCOMMAND)?;
+        let args_context: IndexMap<String, String> = self.setup_context(ctx);
+
+        let configs: Vec<String> = if all {
+            self.all_configs(workspace)
+        } else if config != "NA" {
+            vec![config]
+        } else {
+            return Err(BError::CliError(String::from(
+                "Either a build config or --all is required",
+            )));
+        };
+
+        let mut errors: usize = 0;
+        for (i, config) in configs.iter().enumerate() {
+            let mut validator: WsConfigValidator = WsConfigValidator::new(&args_context)?;
+            /* The workspace settings are shared by all build configs so only report them once */
+            if i == 0 {
+                self.validate_settings(cli, workspace.settings(), &mut validator)?;
+            }
+            self.validate_config(config, workspace.settings(), &mut validator)?;
+            validator.finish();
+
+            validator.warnings().iter().for_each(|warning| {
+                cli.stdout(format!("warning: {}", warning));
+            });
+            validator.errors().iter().for_each(|error| {
+                cli.stdout(format!("error: {}", error));
+            });
+
+            if validator.errors().is_empty() {
+                cli.stdout(format!("Build config '{}' is valid", config));
+            }
+            errors += validator.errors().len();
+        }
+
+        if errors != 0 {
+            return Err(BError::ValidateError(format!(
+                "Found {} problem(s) when validating the build config(s)",
+                errors
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl ValidateCommand {
+    fn display_path(settings: &WsSettingsHandler, path: &Path) -> String {
+        match path.strip_prefix(settings.work_dir()) {
+            Ok(p) => p.display().to_string(),
+            Err(_e) => path.display().to_string(),
+        }
+    }
+
+    /*
+     * Validate the same build configs as the workspace is listing. When no
+     * supported builds are set this is every build config found in the configs
+     * dir, which is skipping the workspace.json even if the configs dir is the
+     * work dir.
+     */
+    fn all_configs(&self, workspace: &Workspace) -> Vec<String> {
+        workspace
+            .build_configs()
+            .keys()
+            .filter_map(|path| path.file_stem())
+            .map(|stem| stem.to_string_lossy().to_string())
+            .collect()
+    }
+
+    fn validate_settings(
+        &self,
+        cli: &Cli,
+        settings: &WsSettingsHandler,
+        validator: &mut WsConfigValidator,
+    ) -> Result<(), BError> {
+        /*
+         * Use the same workspace settings as bakery would use, if available the
+         * workspace.json under ${HOME}/.bakery is overriding the one in the work dir
+         */
+        let paths: Vec<PathBuf> = vec![
+            cli.get_home_dir().join(".bakery/workspace.json"),
+            settings.work_dir().join("workspace.json"),
+        ];
+        if let Some(path) = paths.iter().find(|p| p.exists()) {
+            let settings_str: String = std::fs::read_to_string(path)?;
+            validator.validate_settings(&Self::display_path(settings, path), &settings_str);
+        }
+        Ok(())
+    }
+
+    fn validate_config(
+        &self,
+        config: &str,
+        settings: &WsSettingsHandler,
+        validator: &mut WsConfigValidator,
+    ) -> Result<(), BError> {
+        let file: String = format!("{}.json", config);
+        /*
+         * Look for the build config in the same order as bakery, first in the
+         * workspace/work dir and then in the configs dir
+         */
+        let path: PathBuf = match [
+            settings.work_dir().join(&file),
+            settings.configs_dir().join(&file),
+        ]
+        .into_iter()
+        .find(|p| p.exists())
+        {
+            Some(p) => p,
+            None => {
+                validator.missing_file(&file, "", String::from("Build config is missing"));
+                return Ok(());
+            }
+        };
+
+        let config_str: String = ConfigFileReader::new(&path).read_json()?;
+        let includes: Vec<String> =
+            validator.validate_build_config(&Self::display_path(settings, &path), &config_str);
+
+        for (i, include) in includes.iter().enumerate() {
+            let include_path: PathBuf = settings.include_dir().join(format!("{}.json", include));
+            if !include_path.exists() {
+                validator.missing_file(
+                    &Self::display_path(settings, &path),
+                    &format!("include[{}]", i),
+                    format!(
+                        "Included build config '{}' is missing",
+                        Self::display_path(settings, &include_path)
+                    ),
+                );
+                continue;
+            }
+            let include_str: String = ConfigFileReader::new(&include_path).read_json()?;
+            validator.validate_include_config(
+                &Self::display_path(settings, &include_path),
+                &include_str,
+            );
+        }
+        Ok(())
+    }
+
+    pub fn new() -> Self {
+        let subcmd: clap::Command = clap::Command::new(BCOMMAND)
+            .about(BCOMMAND_ABOUT)
+            .arg(
+                clap::Arg::new("config")
+                    .short('c')
+                    .long("config")
+                    .help("The build config to validate")
+                    .value_name("name")
+                    .default_value("NA"),
+            )
+            .arg(
+                clap::Arg::new("all")
+                    .action(clap::ArgAction::SetTrue)
+                    .long("all")
+                    .help("Validate all build configs in the workspace"),
+            )
+            .arg(
+                clap::Arg::new("verbose")
+                    .action(clap::ArgAction::SetTrue)
+                    .long("verbose")
+                    .help("Set verbose level."),
+            )
+            .arg(
+                clap::Arg::new("ctx")
+                    .action(clap::ArgAction::Append)
+                    .short('x')
+                    .long("context")
+                    .value_name("KEY=VALUE")
+                    .help("Adding variable to the context. Any variable added will be treated as defined when validating."),
+            );
+        // Initialize and return a new ValidateCommand instance
+        ValidateCommand {
+            // Initialize fields if any
+            cmd: BBaseCommand {
+                cmd_str: String::from(BCOMMAND),
+                sub_cmd: subcmd,
+                interactive: false,
+                require_docker: false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::{indexmap, IndexMap};
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    use crate::cli::*;
+    use crate::commands::{BCommand, ValidateCommand};
+    use crate::error::BError;
+    use crate::helper::Helper;
+    use crate::workspace::{Workspace, WsSettingsHandler};
+
+    fn helper_test_validate_subcommand(
+        work_dir: &PathBuf,
+        json_ws_settings: &str,
+        configs: &IndexMap<PathBuf, String>,
+        mlogger: MockLogger,
+        cmd_line: Vec<&str>,
+    ) -> Result<(), BError> {
+        let settings: WsSettingsHandler = WsSettingsHandler::from_str(work_dir, json_ws_settings)?;
+        std::fs::create_dir_all(settings.include_dir())?;
+        Helper::setup_test_build_configs_files(configs);
+        let mut workspace: Workspace =
+            Workspace::new(Some(work_dir.to_owned()), Some(settings), None)?;
+        let cli: Cli = Cli::new(
+            Box::new(mlogger),
+            Box::new(MockSystem::new()),
+            clap::Command::new("bakery"),
+            Some(cmd_line),
+        );
+        let cmd: ValidateCommand = ValidateCommand::new();
+        cmd.execute(&cli, &mut workspace)
+    }
+
+    #[test]
+    fn test_cmd_validate() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6",
+            "builds": {
+                "supported": [
+                    "default"
+                ]
+            }
+        }"#;
+        let configs: IndexMap<PathBuf, String> = indexmap! {
+            work_dir.join("configs/default.json") => r#"
+            {
+                "version": "6",
+                "name": "default",
+                "description": "Test Description",
+                "arch": "test-arch",
+                "include": [
+                    "tasks"
+                ]
+            }"#.to_string(),
+            work_dir.join("configs/include/tasks.json") => r#"
+            {
+                "version": "6",
+                "tasks": {
+                    "task1": {
+                        "index": "1",
+                        "name": "task1",
+                        "type": "non-bitbake",
+                        "build": "$#[BKRY_SCRIPTS_DIR]/build.sh"
+                    }
+                }
+            }"#.to_string(),
+        };
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_stdout()
+            .with(mockall::predicate::eq(
+                "Build config 'default' is valid".to_string(),
+            ))
+            .once()
+            .returning(|_x| ());
+        let result: Result<(), BError> = helper_test_validate_subcommand(
+            &work_dir,
+            json_ws_settings,
+            &configs,
+            mocked_logger,
+            vec!["bakery", "validate", "-c", "default"],
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_cmd_validate_all_problems() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6",
+            "builds": {
+                "supported": [
+                    "default",
+                    "product"
+                ]
+            }
+        }"#;
+        let configs: IndexMap<PathBuf, String> = indexmap! {
+            work_dir.join("configs/default.json") => r#"
+            {
+                "version": "6",
+                "name": "default",
+                "description": "Test Description",
+                "arch": "test-arch",
+                "context": [
+                    "UNUSED=unused"
+                ],
+                "include": [
+                    "tasks",
+                    "missing"
+                ]
+            }"#.to_string(),
+            work_dir.join("configs/product.json") => r#"
+            {
+                "version": "6",
+                "name": "product",
+                "description": "Test Description",
+                "arch": "test-arch"
+            }"#.to_string(),
+            work_dir.join("configs/include/tasks.json") => r#"
+            {
+                "version": "6",
+                "tasks": {
+                    "sign-image": {
+                        "index": "1",
+                        "name": "sign-image",
+                        "type": "non-bitbake",
+                        "build": "$#[SIGN_SCRIPT]",
+                        "artifacts": [
+                            {
+                                "type": "fil",
+                                "source": "image.bin"
+                            }
+                        ]
+                    }
+                }
+            }"#.to_string(),
+        };
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_stdout()
+            .with(mockall::predicate::eq(
+                "warning: configs/default.json: context[0]: Context variable 'UNUSED' is never used"
+                    .to_string(),
+            ))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_stdout()
+            .with(mockall::predicate::eq(
+                "error: configs/default.json: include[1]: Included build config 'configs/include/missing.json' is missing"
+                    .to_string(),
+            ))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_stdout()
+            .with(mockall::predicate::eq(
+                "error: configs/include/tasks.json: tasks.sign-image.artifacts[0].type: Invalid value 'fil', expected one of 'file', 'directory', 'archive', 'manifest', 'link', 'conditional', 'checksums'"
+                    .to_string(),
+            ))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_stdout()
+            .with(mockall::predicate::eq(
+                "error: configs/include/tasks.json: tasks.sign-image.build: Context variable 'SIGN_SCRIPT' is not defined and will never be expanded"
+                    .to_string(),
+            ))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_stdout()
+            .with(mockall::predicate::eq(
+                "Build config 'product' is valid".to_string(),
+            ))
+            .once()
+            .returning(|_x| ());
+        let result: Result<(), BError> = helper_test_validate_subcommand(
+            &work_dir,
+            json_ws_settings,
+            &configs,
+            mocked_logger,
+            vec!["bakery", "validate", "--all"],
+        );
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because the build config is invalid");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    String::from("Found 3 problem(s) when validating the build config(s)")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cmd_validate_all_configs_in_work_dir() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6",
+            "workspace": {
+                "configsdir": ""
+            }
+        }"#;
+        let configs: IndexMap<PathBuf, String> = indexmap! {
+            work_dir.join("workspace.json") => json_ws_settings.to_string(),
+            work_dir.join("beaglebone.json") => r#"
+            {
+                "version": "6",
+                "name": "beaglebone",
+                "description": "Test Description",
+                "arch": "test-arch"
+            }"#.to_string(),
+        };
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_stdout()
+            .with(mockall::predicate::eq(
+                "Build config 'beaglebone' is valid".to_string(),
+            ))
+            .once()
+            .returning(|_x| ());
+        let result: Result<(), BError> = helper_test_validate_subcommand(
+            &work_dir,
+            json_ws_settings,
+            &configs,
+            mocked_logger,
+            vec!["bakery", "validate", "--all"],
+        );
+        assert!(result.is_ok());
+    }
+}
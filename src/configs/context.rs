@@ -4,9 +4,20 @@ use std::path::PathBuf;
 
 use crate::error::BError;
 
+/*
+ * A context variable is referenced using $#[VAR] and can optionally
+ * define a fallback value $#[VAR:-fallback] that is used if the variable
+ * is not defined or is empty. The fallback cannot contain a ']'.
+ */
+pub const CTX_VAR_REGEX: &str = r"\$\#\[(\w+|\{([^}]+)\})(:-([^\]]*))?\]";
+
+/* Separating a context error from the key of the value that was expanded */
+const CTX_KEY_IN: &str = " in '";
+
 pub struct Context {
     regexp: Regex,
     variables: IndexMap<String, String>,
+    strict: bool,
 }
 
 impl Context {
@@ -27,29 +38,53 @@ impl Context {
          * assertion to try and skip \$#{VARIABLE} let us see if we can manage without
          * or we will have figure something out.
          */
-        let regexp = Regex::new(r"\$\#\[(\w+|\{([^}]+)\})\]").unwrap();
+        let regexp = Regex::new(CTX_VAR_REGEX).unwrap();
         Context {
             regexp,
             variables: v,
+            strict: false,
         }
     }
 
-    fn __expand_str(&self, s: &str) -> String {
+    fn __expand_str(&self, s: &str) -> Result<String, BError> {
+        let mut undefined: Option<String> = None;
         let replaced = self.regexp.replace_all(s, |caps: &regex::Captures| {
             let var_name = &caps[1].to_lowercase(); // Extract the variable name
-            match self.variables.get(var_name) {
-                Some(value) => value.to_string(), // Replace with the value from the IndexMap
-                None => caps[0].to_string(),      // No replacement found, keep the original text
+            let fallback: Option<&str> = caps.get(4).map(|f| f.as_str());
+            match (self.variables.get(var_name), fallback) {
+                (Some(value), Some(fallback)) if value.is_empty() => fallback.to_string(), // Empty value use the fallback
+                (Some(value), _) => value.to_string(), // Replace with the value from the IndexMap
+                (None, Some(fallback)) => fallback.to_string(), // Not defined use the fallback
+                (None, None) => {
+                    // No replacement found, keep the original text
+                    if undefined.is_none() {
+                        undefined = Some(caps[1].to_string());
+                    }
+                    caps[0].to_string()
+                }
             }
         });
-        replaced.to_string()
+
+        /*
+         * In strict mode we fail directly on the first variable that is not
+         * defined instead of leaving the $#[VAR] in the expanded string
+         */
+        if self.strict {
+            if let Some(variable) = undefined {
+                return Err(BError::CtxKeyError(format!(
+                    "Context variable '{}' is not defined when expanding '{}'",
+                    variable, s
+                )));
+            }
+        }
+        Ok(replaced.to_string())
     }
 
     pub fn expand_str(&self, s: &str) -> Result<String, BError> {
         let mut counter = 0;
         let mut expanded_string: String = s.to_string();
         while self.regexp.is_match(expanded_string.as_str()) {
-            expanded_string = self.__expand_str(expanded_string.as_str());
+            expanded_string = self.__expand_str(expanded_string.as_str())?;
             if counter > 10 {
                 // TODO not sure we should panic. If we don't find a context should we panic then?
                 return Err(BError::CtxKeyError(format!(
@@ -62,11 +97,48 @@ impl Context {
         Ok(expanded_string)
     }
 
-    pub fn expand_path(&self, p: &PathBuf) -> Result<PathBuf, BError> {
-        let p_str: String = self.expand_str(p.to_str().unwrap())?;
+    /*
+     * Expand the value of a key in a build config, a context error is including
+     * the key so that it is possible to locate the value that failed
+     */
+    pub fn expand_key(&self, key: &str, s: &str) -> Result<String, BError> {
+        self.expand_str(s).map_err(|err| match err {
+            BError::CtxKeyError(msg) => {
+                BError::CtxKeyError(format!("{}{}{}'", msg, CTX_KEY_IN, key))
+            }
+            _ => err,
+        })
+    }
+
+    /*
+     * Add the key of the parent node to a context error returned by expand_key
+     * so that the key is the full path to the value in the build config
+     */
+    pub fn key_error(err: BError, key: &str) -> BError {
+        match err {
+            BError::CtxKeyError(msg) => match msg.rfind(CTX_KEY_IN) {
+                Some(i) => BError::CtxKeyError(format!(
+                    "{}{}{}.{}",
+                    &msg[..i],
+                    CTX_KEY_IN,
+                    key,
+                    &msg[i + CTX_KEY_IN.len()..]
+                )),
+                None => BError::CtxKeyError(msg),
+            },
+            _ => err,
+        }
+    }
+
+    pub fn expand_path(&self, key: &str, p: &PathBuf) -> Result<PathBuf, BError> {
+        let p_str: String = self.expand_key(key, p.to_str().unwrap())?;
         Ok(PathBuf::from(p_str))
     }
 
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
     pub fn value(&self, key: &str) -> String {
         match self.variables.get(&key.to_lowercase()) {
             Some(value) => value.clone(),
@@ -79,10 +151,6 @@ impl Context {
         }
     }
 
-    pub fn merge(&mut self, context: &Context) {
-        self.update(&context.variables);
-    }
-
     pub fn update(&mut self, variables: &IndexMap<String, String>) {
         self.variables.extend(
             variables
@@ -228,7 +296,7 @@ mod tests {
         let ctx: Context = Context::new(&variables);
         let path: PathBuf = PathBuf::from("/dir1/$#[VAR1]/$#[VAR2]/$#[VAR3]/file1.txt");
         assert_eq!(
-            ctx.expand_path(&path).unwrap(),
+            ctx.expand_path("builddir", &path).unwrap(),
             PathBuf::from("/dir1/var1/var2/var3/file1.txt")
         );
     }
@@ -240,7 +308,7 @@ mod tests {
         };
         let ctx: Context = Context::new(&variables);
         let path: PathBuf = PathBuf::from("/dir1/$#[VAR1]/$#[VAR2]/file1.txt");
-        let result: Result<PathBuf, BError> = ctx.expand_path(&path);
+        let result: Result<PathBuf, BError> = ctx.expand_path("builddir", &path);
 
         match result {
             Ok(path) => {
@@ -253,11 +321,121 @@ mod tests {
             Err(err_msg) => {
                 assert_eq!(
                     String::from(
-                        "Failed to expand context in string '/dir1/var1/$#[VAR2]/file1.txt'"
+                        "Failed to expand context in string '/dir1/var1/$#[VAR2]/file1.txt' in 'builddir'"
                     ),
                     err_msg.to_string()
                 );
             }
         }
     }
+
+    #[test]
+    fn test_task_context_expand_fallback() {
+        let variables: IndexMap<String, String> = indexmap! {
+            "VAR1".to_string() => "var1".to_string(),
+            "VAR2".to_string() => "".to_string(),
+            "VAR3".to_string() => "$#[VAR4:-var4]".to_string()
+        };
+        let ctx: Context = Context::new(&variables);
+        assert_eq!(
+            ctx.expand_str("Testing $#[VAR1:-fallback] expansion")
+                .unwrap(),
+            "Testing var1 expansion"
+        );
+        assert_eq!(
+            ctx.expand_str("Testing $#[VAR2:-fallback] expansion")
+                .unwrap(),
+            "Testing fallback expansion"
+        );
+        assert_eq!(
+            ctx.expand_str("Testing $#[VAR5:-fallback value] expansion")
+                .unwrap(),
+            "Testing fallback value expansion"
+        );
+        assert_eq!(
+            ctx.expand_str("Testing $#[VAR5:-] expansion").unwrap(),
+            "Testing  expansion"
+        );
+        assert_eq!(
+            ctx.expand_str("Testing $#[VAR3] expansion").unwrap(),
+            "Testing var4 expansion"
+        );
+    }
+
+    #[test]
+    fn test_task_context_expand_strict() {
+        let variables: IndexMap<String, String> = indexmap! {
+            "VAR1".to_string() => "var1".to_string(),
+            "VAR2".to_string() => "".to_string(),
+            "VAR3".to_string() => "$#[VAR4]".to_string()
+        };
+        let mut ctx: Context = Context::new(&variables);
+        ctx.set_strict(true);
+        assert_eq!(
+            ctx.expand_str("Testing $#[VAR1] $#[VAR2] $#[VAR5:-var5] expansion")
+                .unwrap(),
+            "Testing var1  var5 expansion"
+        );
+        let result: Result<String, BError> = ctx.expand_str("Testing $#[VAR1] $#[VAR5] expansion");
+        match result {
+            Ok(s) => {
+                panic!(
+                    "We should have recived an error because VAR5 is not defined but got '{}'",
+                    s
+                );
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Context variable 'VAR5' is not defined when expanding 'Testing $#[VAR1] $#[VAR5] expansion'"
+                );
+            }
+        }
+        let result: Result<String, BError> = ctx.expand_str("Testing $#[VAR3] expansion");
+        match result {
+            Ok(s) => {
+                panic!(
+                    "We should have recived an error because VAR4 is not defined but got '{}'",
+                    s
+                );
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Context variable 'VAR4' is not defined when expanding 'Testing $#[VAR4] expansion'"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_task_context_expand_strict_key() {
+        let variables: IndexMap<String, String> = indexmap! {
+            "VAR1".to_string() => "var1".to_string()
+        };
+        let mut ctx: Context = Context::new(&variables);
+        ctx.set_strict(true);
+        assert_eq!(
+            ctx.expand_key("source", "$#[VAR1].bin").unwrap(),
+            "var1.bin"
+        );
+        let result: Result<String, BError> = ctx
+            .expand_key("source", "Testing in '$#[VAR2]'")
+            .map_err(|err| Context::key_error(err, "artifacts[0]"))
+            .map_err(|err| Context::key_error(err, "tasks.task1"));
+        match result {
+            Ok(s) => {
+                panic!(
+                    "We should have recived an error because VAR2 is not defined but got '{}'",
+                    s
+                );
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Context variable 'VAR2' is not defined when expanding 'Testing in '$#[VAR2]'' in 'tasks.task1.artifacts[0].source'"
+                );
+            }
+        }
+    }
 }
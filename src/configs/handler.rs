@@ -55,6 +55,14 @@ impl WsConfigFileHandler {
         return WsSettingsHandler::from_str(&self.work_dir, default_settings);
     }
 
+    /* The build config file relative to the work dir used when reporting errors */
+    fn config_file(&self, path: &PathBuf) -> String {
+        match path.strip_prefix(&self.work_dir) {
+            Ok(p) => p.display().to_string(),
+            Err(_e) => path.display().to_string(),
+        }
+    }
+
     fn config_header(&self, config: &WsBuildConfigHandler) -> String {
         let cfg_bitbake_json: String = config.build_data().bitbake().to_string();
         let cfg_product_json: String = config.build_data().product().to_string();
@@ -70,6 +78,7 @@ impl WsConfigFileHandler {
         let build_config_json: String = ConfigFileReader::new(&path).read_json()?;
         let mut main_config: WsBuildConfigHandler =
             WsBuildConfigHandler::from_str(&build_config_json, settings)?;
+        main_config.set_file(&self.config_file(path));
         let cfg_header_json: String = self.config_header(&main_config);
 
         /*
@@ -95,6 +104,7 @@ impl WsConfigFileHandler {
             );
             let mut cfg: WsBuildConfigHandler =
                 WsBuildConfigHandler::from_str(&cfg_json, settings)?;
+            cfg.set_file(&self.config_file(config));
             main_config.merge(&mut cfg);
         }
 
@@ -508,4 +518,57 @@ mod tests {
         let upload: &WsCustomSubCmdHandler = config.subcmds().get("upload").unwrap();
         assert_eq!(upload.data().cmd(), "config2");
     }
+
+    #[test]
+    fn test_cfg_handler_ws_include_configs_strict_ctx() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from(temp_dir.path()).join("workspace");
+        let home_dir: PathBuf = PathBuf::from(temp_dir.path()).join("home");
+        let cfg_handler: WsConfigFileHandler = WsConfigFileHandler::new(&work_dir, &home_dir);
+        let settings: WsSettingsHandler = cfg_handler
+            .ws_settings()
+            .expect("Failed parse workspace settings");
+        Helper::setup_test_ws_default_dirs(&work_dir);
+        let main_build_config = r#"
+        {
+            "version": "6",
+            "name": "test-product",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "include": [
+                "config1"
+            ]
+        }"#;
+        write_json_conf(&settings.work_dir().join("main.json"), main_build_config);
+        let build_config1 = r#"
+        {
+            "version": "6",
+            "tasks": {
+                "task1": {
+                    "index": "1",
+                    "name": "task1",
+                    "type": "non-bitbake",
+                    "build": "$#[BUILD_SCRIPT]"
+                }
+            }
+        }"#;
+        write_json_conf(&settings.include_dir().join("config1.json"), build_config1);
+        let mut config: WsBuildConfigHandler = cfg_handler
+            .build_config("main", &settings)
+            .expect("Failed parse build config");
+        config.set_strict_ctx(true);
+        let result: Result<(), BError> = config.expand_ctx();
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because BUILD_SCRIPT is not defined!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Context variable 'BUILD_SCRIPT' is not defined when expanding '$#[BUILD_SCRIPT]' in 'tasks.task1.build' of build config 'test-product' read from 'configs/include/config1.json'"
+                );
+            }
+        }
+    }
 }
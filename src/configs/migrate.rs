@@ -0,0 +1,349 @@
+use serde_json::{Map, Value};
+
+use crate::error::BError;
+
+type JsonObject = Map<String, Value>;
+type MigrateFn = fn(&mut JsonObject) -> Result<(), BError>;
+
+/*
+ * A migration step is upgrading a build config and the workspace settings
+ * from one version to the next. When the format is changed in a way that is
+ * breaking the current version a new step should be added to the end of the
+ * list and the migration will chain all the steps needed to reach the latest
+ * version.
+ */
+struct MigrationStep {
+    from: &'static str,
+    to: &'static str,
+    build_config: MigrateFn,
+    settings: MigrateFn,
+}
+
+const MIGRATION_STEPS: &[MigrationStep] = &[MigrationStep {
+    from: "5",
+    to: "6",
+    build_config: values_to_str,
+    settings: values_to_str,
+}];
+
+/*
+ * Version 6 is reading every value using Config::get_str_value which only
+ * accepts strings, a boolean or a number is silently replaced by the default
+ * value. This is the only difference from version 5 that can be verified
+ * against the current format so the rest of the file is kept as it is. Run
+ * 'bakery validate' after the migration to find anything else that is not
+ * supported by the current format.
+ */
+fn values_to_str(object: &mut JsonObject) -> Result<(), BError> {
+    for value in object.values_mut() {
+        match value {
+            Value::Bool(b) => *value = Value::String(b.to_string()),
+            Value::Number(n) => *value = Value::String(n.to_string()),
+            Value::Object(child) => values_to_str(child)?,
+            Value::Array(array) => {
+                for node in array.iter_mut() {
+                    if let Value::Object(child) = node {
+                        values_to_str(child)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/*
+ * The result of migrating a build config or workspace settings. Both the
+ * original and the migrated content are pretty printed the same way so that
+ * the diff is only showing what the migration changed.
+ */
+pub struct WsConfigMigration {
+    from: String,
+    to: String,
+    original: String,
+    migrated: String,
+}
+
+impl WsConfigMigration {
+    pub fn old_version(&self) -> &str {
+        &self.from
+    }
+
+    pub fn new_version(&self) -> &str {
+        &self.to
+    }
+
+    pub fn migrated(&self) -> &str {
+        &self.migrated
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.original != self.migrated
+    }
+
+    /*
+     * A migration step is only changing values in place so a line by line
+     * comparison of the pretty printed content is enough to show the changes
+     */
+    pub fn diff(&self, file: &str) -> String {
+        let old: Vec<&str> = self.original.lines().collect();
+        let new: Vec<&str> = self.migrated.lines().collect();
+        let len: usize = old.len().max(new.len());
+        let mut lines: Vec<String> = vec![format!("--- a/{}", file), format!("+++ b/{}", file)];
+        let mut i: usize = 0;
+
+        while i < len {
+            if old.get(i) == new.get(i) {
+                i += 1;
+                continue;
+            }
+            let start: usize = i;
+            while i < len && old.get(i) != new.get(i) {
+                i += 1;
+            }
+            let removed: &[&str] = &old[start.min(old.len())..i.min(old.len())];
+            let added: &[&str] = &new[start.min(new.len())..i.min(new.len())];
+            lines.push(format!(
+                "@@ -{},{} +{},{} @@",
+                start + 1,
+                removed.len(),
+                start + 1,
+                added.len()
+            ));
+            removed.iter().for_each(|l| lines.push(format!("-{}", l)));
+            added.iter().for_each(|l| lines.push(format!("+{}", l)));
+        }
+        lines.join("\n")
+    }
+}
+
+pub struct WsConfigMigrator {}
+
+impl WsConfigMigrator {
+    /* The version that any build config and workspace settings is migrated to */
+    pub fn latest_version() -> &'static str {
+        MIGRATION_STEPS.last().map(|step| step.to).unwrap_or("6")
+    }
+
+    pub fn migrate_build_config(json_string: &str) -> Result<WsConfigMigration, BError> {
+        Self::migrate(json_string, |step| step.build_config)
+    }
+
+    pub fn migrate_settings(json_string: &str) -> Result<WsConfigMigration, BError> {
+        Self::migrate(json_string, |step| step.settings)
+    }
+
+    fn pretty(object: &JsonObject) -> Result<String, BError> {
+        let mut pretty: String = serde_json::to_string_pretty(object)
+            .map_err(|err| BError::JsonParseError(err.to_string()))?;
+        pretty.push('\n');
+        Ok(pretty)
+    }
+
+    fn migrate(
+        json_string: &str,
+        select: fn(&MigrationStep) -> MigrateFn,
+    ) -> Result<WsConfigMigration, BError> {
+        let mut object: JsonObject = match serde_json::from_str(json_string) {
+            Ok(Value::Object(object)) => object,
+            Ok(_) => {
+                return Err(BError::ParseError(String::from(
+                    "The config is not a JSON object",
+                )));
+            }
+            Err(err) => {
+                return Err(BError::JsonParseError(err.to_string()));
+            }
+        };
+
+        let from: String = match object.get("version") {
+            Some(Value::String(version)) => version.clone(),
+            Some(Value::Number(version)) => version.to_string(),
+            _ => {
+                return Err(BError::ValueError(String::from(
+                    "Failed to read string value 'version'",
+                )));
+            }
+        };
+
+        if from == Self::latest_version() {
+            return Ok(WsConfigMigration {
+                from: from.clone(),
+                to: from,
+                original: json_string.to_string(),
+                migrated: json_string.to_string(),
+            });
+        }
+
+        let original: String = Self::pretty(&object)?;
+        let mut version: String = from.clone();
+        while version != Self::latest_version() {
+            let step: &MigrationStep = match MIGRATION_STEPS.iter().find(|s| s.from == version) {
+                Some(step) => step,
+                None => {
+                    let supported: Vec<String> = MIGRATION_STEPS
+                        .iter()
+                        .map(|s| format!("'{}'", s.from))
+                        .collect();
+                    return Err(BError::ValueError(format!(
+                        "Unable to migrate from version '{}', supported versions are {}",
+                        version,
+                        supported.join(", ")
+                    )));
+                }
+            };
+            select(step)(&mut object)?;
+            version = step.to.to_string();
+        }
+        object.insert(String::from("version"), Value::String(version.clone()));
+
+        Ok(WsConfigMigration {
+            from,
+            to: version,
+            original,
+            migrated: Self::pretty(&object)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configs::{WsConfigMigration, WsConfigMigrator};
+    use crate::error::BError;
+
+    #[test]
+    fn test_migrate_build_config_v5() {
+        let json_build_config: &str = r#"
+        {
+            "version": "5",
+            "name": "test-name",
+            "context": [
+                "DEVICE=qemu"
+            ],
+            "tasks": {
+                "task1": {
+                    "index": 1,
+                    "name": "task1",
+                    "type": "non-bitbake",
+                    "disabled": true,
+                    "build": "build.sh"
+                }
+            }
+        }"#;
+        let migration: WsConfigMigration =
+            WsConfigMigrator::migrate_build_config(json_build_config)
+                .expect("Failed to migrate build config");
+        assert!(migration.is_changed());
+        assert_eq!(migration.old_version(), "5");
+        assert_eq!(migration.new_version(), "6");
+        assert_eq!(
+            migration.migrated(),
+            r#"{
+  "context": [
+    "DEVICE=qemu"
+  ],
+  "name": "test-name",
+  "tasks": {
+    "task1": {
+      "build": "build.sh",
+      "disabled": "true",
+      "index": "1",
+      "name": "task1",
+      "type": "non-bitbake"
+    }
+  },
+  "version": "6"
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_migrate_build_config_numeric_version() {
+        let json_build_config: &str = r#"
+        {
+            "version": 5,
+            "name": "test-name"
+        }"#;
+        let migration: WsConfigMigration =
+            WsConfigMigrator::migrate_build_config(json_build_config)
+                .expect("Failed to migrate build config");
+        assert_eq!(migration.old_version(), "5");
+        assert_eq!(
+            migration.diff("default.json"),
+            r#"--- a/default.json
++++ b/default.json
+@@ -3,1 +3,1 @@
+-  "version": 5
++  "version": "6""#
+        );
+    }
+
+    #[test]
+    fn test_migrate_build_config_latest() {
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "test-name"
+        }"#;
+        let migration: WsConfigMigration =
+            WsConfigMigrator::migrate_build_config(json_build_config)
+                .expect("Failed to migrate build config");
+        assert!(!migration.is_changed());
+        assert_eq!(migration.migrated(), json_build_config);
+    }
+
+    #[test]
+    fn test_migrate_build_config_unsupported() {
+        let json_build_config: &str = r#"
+        {
+            "version": "4",
+            "name": "test-name"
+        }"#;
+        let result: Result<WsConfigMigration, BError> =
+            WsConfigMigrator::migrate_build_config(json_build_config);
+        match result {
+            Ok(_m) => {
+                panic!("We should have recived an error because the version is not supported");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    String::from("Unable to migrate from version '4', supported versions are '5'")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_migrate_settings_diff() {
+        let json_ws_settings: &str = r#"
+        {
+            "version": "5",
+            "workspace": {
+                "configsdir": "configs",
+                "buildsdir": "builds",
+                "artifactsdir": "artifacts",
+                "layersdir": "layers",
+                "scriptsdir": "scripts"
+            },
+            "docker": {
+                "disabled": false
+            }
+        }"#;
+        let migration: WsConfigMigration = WsConfigMigrator::migrate_settings(json_ws_settings)
+            .expect("Failed to migrate settings");
+        assert_eq!(
+            migration.diff("workspace.json"),
+            r#"--- a/workspace.json
++++ b/workspace.json
+@@ -3,1 +3,1 @@
+-    "disabled": false
++    "disabled": "false"
+@@ -5,1 +5,1 @@
+-  "version": "5",
++  "version": "6","#
+        );
+    }
+}
@@ -1,10 +1,14 @@
 pub mod context;
 pub mod handler;
+pub mod migrate;
 pub mod settings;
+pub mod validator;
 
 pub use context::Context;
 pub use handler::WsConfigFileHandler;
+pub use migrate::{WsConfigMigration, WsConfigMigrator};
 pub use settings::WsSettings;
+pub use validator::WsConfigValidator;
 
 use crate::error::BError;
 use indexmap::IndexMap;
@@ -1,5 +1,6 @@
 use crate::configs::Config;
 use crate::error::BError;
+use crate::executers::ContainerRuntime;
 use serde_json::Value;
 
 // Not the ideal solution we should see if it is possible to
@@ -28,6 +29,16 @@ pub struct WsSettings {
     pub docker_args: Vec<String>,
     pub docker_disabled: String,
     pub docker_top_dir: String,
+    pub docker_runtime: String,
+    pub strict_ctx: String,
+    pub sstate_mirrors: Vec<String>,
+    pub premirrors: Vec<String>,
+    pub hashserve: String,
+    pub shared_sstate_dir: String,
+    pub shared_dl_dir: String,
+    pub signing_tool: String,
+    pub signing_key: String,
+    pub signing_pubkey: String,
 }
 
 impl Config for WsSettings {}
@@ -35,7 +46,14 @@ impl Config for WsSettings {}
 impl WsSettings {
     pub fn from_str(json_string: &str) -> Result<Self, BError> {
         let data: Value = Self::parse(json_string)?;
-        let version: String = Self::get_str_value("version", &data, None)?;
+        /*
+         * Older workspace settings could have the version as a number, it is
+         * read as a string so that it can be migrated using 'bakery migrate'
+         */
+        let version: String = match data.get("version") {
+            Some(Value::Number(version)) => version.to_string(),
+            _ => Self::get_str_value("version", &data, None)?,
+        };
         let mut configs_dir: String = String::from("configs");
         let mut include_dir: String = String::from("configs/include");
         let mut builds_dir: String = String::from("builds");
@@ -51,6 +69,16 @@ impl WsSettings {
         let mut docker_args: Vec<String> = vec![];
         let mut docker_disabled: String = String::from("false");
         let mut docker_top_dir: String = String::from("");
+        let mut docker_runtime: String = String::from("docker");
+        let mut strict_ctx: String = String::from("false");
+        let mut sstate_mirrors: Vec<String> = vec![];
+        let mut premirrors: Vec<String> = vec![];
+        let mut hashserve: String = String::from("");
+        let mut shared_sstate_dir: String = String::from("");
+        let mut shared_dl_dir: String = String::from("");
+        let mut signing_tool: String = String::from("");
+        let mut signing_key: String = String::from("");
+        let mut signing_pubkey: String = String::from("");
 
         match Self::get_value("workspace", &data) {
             Ok(ws_data) => {
@@ -104,6 +132,39 @@ impl WsSettings {
                 docker_args = Self::get_array_value("args", docker_data, Some(vec![]))?;
                 docker_top_dir =
                     Self::get_str_value("topdir", docker_data, Some(String::from("")))?;
+                docker_runtime =
+                    Self::get_str_value("runtime", docker_data, Some(String::from("docker")))?;
+                ContainerRuntime::new(&docker_runtime)?;
+            }
+            Err(_err) => {}
+        }
+
+        match Self::get_value("context", &data) {
+            Ok(ctx_data) => {
+                strict_ctx = Self::get_str_value("strict", ctx_data, Some(String::from("false")))?;
+            }
+            Err(_err) => {}
+        }
+
+        match Self::get_value("cache", &data) {
+            Ok(cache_data) => {
+                sstate_mirrors = Self::get_array_value("sstatemirrors", cache_data, Some(vec![]))?;
+                premirrors = Self::get_array_value("premirrors", cache_data, Some(vec![]))?;
+                hashserve = Self::get_str_value("hashserve", cache_data, Some(String::from("")))?;
+                shared_sstate_dir =
+                    Self::get_str_value("sharedsstatedir", cache_data, Some(String::from("")))?;
+                shared_dl_dir =
+                    Self::get_str_value("shareddldir", cache_data, Some(String::from("")))?;
+            }
+            Err(_err) => {}
+        }
+
+        match Self::get_value("signing", &data) {
+            Ok(signing_data) => {
+                signing_tool = Self::get_str_value("tool", signing_data, Some(String::from("")))?;
+                signing_key = Self::get_str_value("key", signing_data, Some(String::from("")))?;
+                signing_pubkey =
+                    Self::get_str_value("pubkey", signing_data, Some(String::from("")))?;
             }
             Err(_err) => {}
         }
@@ -125,12 +186,24 @@ impl WsSettings {
             docker_args,
             docker_disabled,
             docker_top_dir,
+            docker_runtime,
+            strict_ctx,
+            sstate_mirrors,
+            premirrors,
+            hashserve,
+            shared_sstate_dir,
+            shared_dl_dir,
+            signing_tool,
+            signing_key,
+            signing_pubkey,
         })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::configs::WsSettings;
+    use crate::error::BError;
     use crate::helper::Helper;
 
     #[test]
@@ -178,6 +251,16 @@ mod tests {
         assert_eq!(&settings.cache_dir, ".cache");
     }
 
+    #[test]
+    fn test_settings_config_numeric_version() {
+        let json_test_str = r#"
+        {
+            "version": 5
+        }"#;
+        let settings = Helper::setup_ws_settings(json_test_str);
+        assert_eq!(&settings.version, "5");
+    }
+
     #[test]
     fn test_settings_config_no_configs_workspace_node() {
         let json_test_str = r#"
@@ -404,6 +487,42 @@ mod tests {
         assert_eq!(&settings.docker_disabled, "true");
     }
 
+    #[test]
+    fn test_settings_config_docker_runtime() {
+        let json_test_str = r#"
+        {
+            "version": "6",
+            "docker": {
+                "runtime": "podman"
+            }
+        }"#;
+        let settings = Helper::setup_ws_settings(json_test_str);
+        assert_eq!(&settings.docker_runtime, "podman");
+    }
+
+    #[test]
+    fn test_settings_config_invalid_docker_runtime() {
+        let json_test_str = r#"
+        {
+            "version": "6",
+            "docker": {
+                "runtime": "lxc"
+            }
+        }"#;
+        let result: Result<WsSettings, BError> = WsSettings::from_str(json_test_str);
+        match result {
+            Ok(_s) => {
+                panic!("We should have recived an error because the runtime is invalid");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    String::from("Invalid container runtime 'lxc', expected one of 'docker', 'podman', 'podman-rootless'")
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_settings_config_docker_registry() {
         let json_test_str = r#"
@@ -519,4 +638,80 @@ mod tests {
         let settings = Helper::setup_ws_settings(json_test_str);
         assert_eq!(settings.supported.is_empty(), true);
     }
+
+    #[test]
+    fn test_settings_config_cache() {
+        let json_test_str = r#"
+        {
+            "version": "6",
+            "cache": {
+                "sstatemirrors": [
+                    "file://.* https://sstate.example.com/PATH;downloadfilename=PATH"
+                ],
+                "premirrors": [
+                    "git://.*/.* https://downloads.example.com/"
+                ],
+                "hashserve": "hashserv.example.com:8686",
+                "sharedsstatedir": "/nfs/sstate-cache",
+                "shareddldir": "/nfs/downloads"
+            }
+        }"#;
+        let settings = Helper::setup_ws_settings(json_test_str);
+        assert_eq!(
+            &settings.sstate_mirrors,
+            &vec![String::from(
+                "file://.* https://sstate.example.com/PATH;downloadfilename=PATH"
+            )]
+        );
+        assert_eq!(
+            &settings.premirrors,
+            &vec![String::from("git://.*/.* https://downloads.example.com/")]
+        );
+        assert_eq!(&settings.hashserve, "hashserv.example.com:8686");
+        assert_eq!(&settings.shared_sstate_dir, "/nfs/sstate-cache");
+        assert_eq!(&settings.shared_dl_dir, "/nfs/downloads");
+    }
+
+    #[test]
+    fn test_settings_config_no_cache_node() {
+        let json_test_str = r#"
+        {
+            "version": "6"
+        }"#;
+        let settings = Helper::setup_ws_settings(json_test_str);
+        assert!(settings.sstate_mirrors.is_empty());
+        assert!(settings.premirrors.is_empty());
+        assert!(settings.hashserve.is_empty());
+        assert!(settings.shared_sstate_dir.is_empty());
+        assert!(settings.shared_dl_dir.is_empty());
+    }
+
+    #[test]
+    fn test_settings_config_signing() {
+        let json_test_str = r#"
+        {
+            "version": "6",
+            "signing": {
+                "tool": "openssl",
+                "key": "keys/private.pem",
+                "pubkey": "keys/public.pem"
+            }
+        }"#;
+        let settings = Helper::setup_ws_settings(json_test_str);
+        assert_eq!(&settings.signing_tool, "openssl");
+        assert_eq!(&settings.signing_key, "keys/private.pem");
+        assert_eq!(&settings.signing_pubkey, "keys/public.pem");
+    }
+
+    #[test]
+    fn test_settings_config_no_signing_node() {
+        let json_test_str = r#"
+        {
+            "version": "6"
+        }"#;
+        let settings = Helper::setup_ws_settings(json_test_str);
+        assert!(settings.signing_tool.is_empty());
+        assert!(settings.signing_key.is_empty());
+        assert!(settings.signing_pubkey.is_empty());
+    }
 }
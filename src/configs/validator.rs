@@ -0,0 +1,1080 @@
+use indexmap::IndexMap;
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+
+use crate::configs::context::CTX_VAR_REGEX;
+use crate::data::{WsArtifactData, WsContextData, WsLayerData, WsSecretData, WsTaskData};
+use crate::error::BError;
+
+/*
+ * The kind of value expected for a key in the build config or the
+ * workspace settings. Most values are strings even booleans and integers
+ * so the schema is mostly about making sure the keys are known and the
+ * value is of the expected JSON type.
+ */
+#[derive(Clone, PartialEq, Debug)]
+enum Kind {
+    Str,
+    StrArray,
+    Object,
+    ObjectArray,
+    OneOf(&'static [&'static str]),
+}
+
+const BUILD_CONFIG_KEYS: &[(&str, Kind)] = &[
+    ("version", Kind::Str),
+    ("name", Kind::Str),
+    ("product", Kind::Str),
+    ("project", Kind::Str),
+    ("description", Kind::Str),
+    ("arch", Kind::Str),
+    ("bb", Kind::Object),
+    ("include", Kind::StrArray),
+    ("context", Kind::StrArray),
+    ("secrets", Kind::Object),
+    ("layers", Kind::Object),
+    ("tasks", Kind::Object),
+    ("deploy", Kind::Object),
+    ("upload", Kind::Object),
+    ("setup", Kind::Object),
+    ("sync", Kind::Object),
+];
+
+const INCLUDE_CONFIG_KEYS: &[(&str, Kind)] = &[
+    ("version", Kind::Str),
+    ("tasks", Kind::Object),
+    ("deploy", Kind::Object),
+    ("upload", Kind::Object),
+    ("setup", Kind::Object),
+    ("sync", Kind::Object),
+];
+
+const BITBAKE_KEYS: &[(&str, Kind)] = &[
+    ("machine", Kind::Str),
+    ("distro", Kind::Str),
+    ("docker", Kind::Str),
+    ("deploydir", Kind::Str),
+    ("initenv", Kind::Str),
+    ("localconf", Kind::StrArray),
+    ("bblayersconf", Kind::StrArray),
+    ("bblayers", Kind::StrArray),
+    ("collections", Kind::StrArray),
+    ("layerseries", Kind::Str),
+];
+
+const LAYER_KEYS: &[(&str, Kind)] = &[
+    ("url", Kind::Str),
+    ("branch", Kind::Str),
+    ("tag", Kind::Str),
+    ("commit", Kind::Str),
+    ("path", Kind::Str),
+];
+
+const SUBCMD_KEYS: &[(&str, Kind)] = &[
+    ("cmd", Kind::Str),
+    ("docker", Kind::Str),
+    ("container", Kind::Object),
+    ("secrets", Kind::StrArray),
+];
+
+const SECRET_KEYS: &[(&str, Kind)] = &[
+    ("source", Kind::OneOf(&["env", "file", "local"])),
+    ("from", Kind::Str),
+    ("expose", Kind::OneOf(&["env", "file"])),
+];
+
+const CONTAINER_KEYS: &[(&str, Kind)] = &[
+    ("cpus", Kind::Str),
+    ("memory", Kind::Str),
+    ("volumes", Kind::StrArray),
+    ("network", Kind::Str),
+    ("devices", Kind::StrArray),
+    ("privileged", Kind::Str),
+    ("args", Kind::StrArray),
+];
+
+const TASK_KEYS: &[(&str, Kind)] = &[
+    ("index", Kind::Str),
+    ("name", Kind::Str),
+    ("type", Kind::OneOf(&["bitbake", "non-bitbake"])),
+    ("disabled", Kind::Str),
+    ("builddir", Kind::Str),
+    ("docker", Kind::Str),
+    ("container", Kind::Object),
+    ("condition", Kind::Str),
+    ("build", Kind::Str),
+    ("clean", Kind::Str),
+    ("description", Kind::Str),
+    ("env", Kind::StrArray),
+    ("recipes", Kind::StrArray),
+    ("depends", Kind::StrArray),
+    ("secrets", Kind::StrArray),
+    ("artifacts", Kind::ObjectArray),
+];
+
+const ARTIFACT_KEYS: &[(&str, Kind)] = &[
+    (
+        "type",
+        Kind::OneOf(&[
+            "file",
+            "directory",
+            "archive",
+            "manifest",
+            "link",
+            "conditional",
+            "checksums",
+        ]),
+    ),
+    ("name", Kind::Str),
+    ("source", Kind::Str),
+    ("dest", Kind::Str),
+    ("content", Kind::Object),
+    ("condition", Kind::Str),
+    ("exclude", Kind::StrArray),
+    ("match", Kind::OneOf(&["glob", "regex"])),
+    ("select", Kind::OneOf(&["all", "newest", "exactly-one"])),
+    ("compression_level", Kind::Str),
+    ("reproducible", Kind::Str),
+    ("artifacts", Kind::ObjectArray),
+];
+
+const SETTINGS_KEYS: &[(&str, Kind)] = &[
+    ("version", Kind::Str),
+    ("workspace", Kind::Object),
+    ("builds", Kind::Object),
+    ("docker", Kind::Object),
+    ("context", Kind::Object),
+    ("cache", Kind::Object),
+    ("signing", Kind::Object),
+];
+
+const SETTINGS_WORKSPACE_KEYS: &[(&str, Kind)] = &[
+    ("configsdir", Kind::Str),
+    ("includedir", Kind::Str),
+    ("buildsdir", Kind::Str),
+    ("artifactsdir", Kind::Str),
+    ("layersdir", Kind::Str),
+    ("scriptsdir", Kind::Str),
+    ("dockerdir", Kind::Str),
+    ("cachedir", Kind::Str),
+];
+
+const SETTINGS_BUILDS_KEYS: &[(&str, Kind)] = &[("supported", Kind::StrArray)];
+
+const SETTINGS_CONTEXT_KEYS: &[(&str, Kind)] = &[("strict", Kind::Str)];
+
+const SETTINGS_CACHE_KEYS: &[(&str, Kind)] = &[
+    ("sstatemirrors", Kind::StrArray),
+    ("premirrors", Kind::StrArray),
+    ("hashserve", Kind::Str),
+    ("sharedsstatedir", Kind::Str),
+    ("shareddldir", Kind::Str),
+];
+
+const SETTINGS_SIGNING_KEYS: &[(&str, Kind)] = &[
+    ("tool", Kind::OneOf(&["gpg", "openssl"])),
+    ("key", Kind::Str),
+    ("pubkey", Kind::Str),
+];
+
+const SETTINGS_DOCKER_KEYS: &[(&str, Kind)] = &[
+    ("disabled", Kind::Str),
+    ("image", Kind::Str),
+    ("tag", Kind::Str),
+    ("registry", Kind::Str),
+    ("args", Kind::StrArray),
+    ("topdir", Kind::Str),
+    (
+        "runtime",
+        Kind::OneOf(&["docker", "podman", "podman-rootless"]),
+    ),
+];
+
+/*
+ * Validate the workspace settings, build configs and included build configs
+ * without failing on the first problem. Every problem is recorded together
+ * with the file and the JSON path of the node, e.g.
+ *
+ * configs/default.json: tasks.sign-image.artifacts[2].type: Invalid type 'fil'
+ *
+ * Once all the files have been validated call finish to verify the context
+ * variables defined and referenced across all the files.
+ */
+pub struct WsConfigValidator {
+    regexp: Regex,
+    file: String,
+    built_in: Vec<String>,
+    defined: IndexMap<String, String>,
+    referenced: IndexMap<String, Vec<String>>,
+    errors: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl WsConfigValidator {
+    pub fn new(variables: &IndexMap<String, String>) -> Result<Self, BError> {
+        /*
+         * The built-in context variables and any variables passed in on the
+         * command line are always available when expanding the build config
+         */
+        let mut built_in: Vec<String> = WsContextData::new(&IndexMap::new())?
+            .ctx()
+            .variables()
+            .keys()
+            .cloned()
+            .collect();
+        built_in.extend(variables.keys().map(|key| key.to_lowercase()));
+
+        Ok(WsConfigValidator {
+            regexp: Regex::new(CTX_VAR_REGEX).unwrap(),
+            file: String::new(),
+            built_in,
+            defined: IndexMap::new(),
+            referenced: IndexMap::new(),
+            errors: vec![],
+            warnings: vec![],
+        })
+    }
+
+    fn location(&self, path: &str) -> String {
+        if path.is_empty() {
+            return self.file.clone();
+        }
+        format!("{}: {}", self.file, path)
+    }
+
+    fn error(&mut self, path: &str, message: String) {
+        let location: String = self.location(path);
+        self.errors.push(format!("{}: {}", location, message));
+    }
+
+    fn join(path: &str, key: &str) -> String {
+        if path.is_empty() {
+            return key.to_string();
+        }
+        format!("{}.{}", path, key)
+    }
+
+    fn parse(&mut self, json_string: &str) -> Option<Value> {
+        match serde_json::from_str::<Value>(json_string) {
+            Ok(data) => {
+                if data.is_object() {
+                    return Some(data);
+                }
+                self.error("", String::from("Expected a JSON object"));
+                None
+            }
+            Err(err) => {
+                self.error("", format!("Failed to parse JSON: {}", err));
+                None
+            }
+        }
+    }
+
+    /*
+     * Verify that all keys in the object are known and that the values
+     * are of the expected kind. Returns false if any problem was found.
+     */
+    fn check_keys(
+        &mut self,
+        path: &str,
+        object: &Map<String, Value>,
+        keys: &[(&str, Kind)],
+    ) -> bool {
+        let mut valid: bool = true;
+        for (key, value) in object.iter() {
+            let key_path: String = Self::join(path, key);
+            match keys.iter().find(|(k, _)| k == key) {
+                Some((_, kind)) => {
+                    if !self.check_kind(&key_path, value, kind) {
+                        valid = false;
+                    }
+                }
+                None => {
+                    self.error(&key_path, format!("Unknown key '{}'", key));
+                    valid = false;
+                }
+            }
+        }
+        valid
+    }
+
+    fn check_kind(&mut self, path: &str, value: &Value, kind: &Kind) -> bool {
+        match kind {
+            Kind::Str => {
+                if !value.is_string() {
+                    self.error(path, format!("Expected a string but found '{}'", value));
+                    return false;
+                }
+            }
+            Kind::OneOf(values) => match value.as_str() {
+                Some(s) => {
+                    if !values.contains(&s) {
+                        self.error(
+                            path,
+                            format!(
+                                "Invalid value '{}', expected one of '{}'",
+                                s,
+                                values.join("', '")
+                            ),
+                        );
+                        return false;
+                    }
+                }
+                None => {
+                    self.error(path, format!("Expected a string but found '{}'", value));
+                    return false;
+                }
+            },
+            Kind::Object => {
+                if !value.is_object() {
+                    self.error(path, format!("Expected an object but found '{}'", value));
+                    return false;
+                }
+            }
+            Kind::StrArray | Kind::ObjectArray => match value.as_array() {
+                Some(array) => {
+                    let mut valid: bool = true;
+                    for (i, v) in array.iter().enumerate() {
+                        let ok: bool = if kind == &Kind::StrArray {
+                            v.is_string()
+                        } else {
+                            v.is_object()
+                        };
+                        if !ok {
+                            self.error(
+                                &format!("{}[{}]", path, i),
+                                format!(
+                                    "Expected {} but found '{}'",
+                                    if kind == &Kind::StrArray {
+                                        "a string"
+                                    } else {
+                                        "an object"
+                                    },
+                                    v
+                                ),
+                            );
+                            valid = false;
+                        }
+                    }
+                    return valid;
+                }
+                None => {
+                    self.error(path, format!("Expected an array but found '{}'", value));
+                    return false;
+                }
+            },
+        }
+        true
+    }
+
+    fn check_object(&mut self, path: &str, data: &Value, keys: &[(&str, Kind)]) -> bool {
+        match data.as_object() {
+            Some(object) => self.check_keys(path, object, keys),
+            None => false,
+        }
+    }
+
+    fn check_artifacts(&mut self, path: &str, data: &Value) {
+        if let Some(artifacts) = data.get("artifacts").and_then(|v| v.as_array()) {
+            for (i, artifact) in artifacts.iter().enumerate() {
+                let artifact_path: String = format!("{}.artifacts[{}]", path, i);
+                if !artifact.is_object() {
+                    continue;
+                }
+                /*
+                 * Only use the artifact parser to catch any remaining problem like
+                 * missing required keys if the keys and values are valid otherwise
+                 * the same problem would be reported twice
+                 */
+                if self.check_object(&artifact_path, artifact, ARTIFACT_KEYS) {
+                    if let Err(err) = WsArtifactData::from_value(artifact) {
+                        self.error(&artifact_path, err.to_string());
+                    }
+                }
+                self.check_artifacts(&artifact_path, artifact);
+            }
+        }
+    }
+
+    fn check_container(&mut self, path: &str, data: &Value) {
+        if let Some(container) = data.get("container") {
+            self.check_object(&format!("{}.container", path), container, CONTAINER_KEYS);
+        }
+    }
+
+    fn check_secrets(&mut self, data: &Value) {
+        if let Some(secrets) = data.get("secrets").and_then(|v| v.as_object()) {
+            for (name, secret) in secrets.iter() {
+                let secret_path: String = format!("secrets.{}", name);
+                if !secret.is_object() {
+                    self.error(
+                        &secret_path,
+                        format!("Expected an object but found '{}'", secret),
+                    );
+                    continue;
+                }
+                if self.check_object(&secret_path, secret, SECRET_KEYS) {
+                    if let Err(err) = WsSecretData::new(name, secret) {
+                        self.error(&secret_path, err.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_layers(&mut self, data: &Value) {
+        if let Some(layers) = data.get("layers").and_then(|v| v.as_object()) {
+            for (name, layer) in layers.iter() {
+                let layer_path: String = format!("layers.{}", name);
+                if !layer.is_object() {
+                    self.error(
+                        &layer_path,
+                        format!("Expected an object but found '{}'", layer),
+                    );
+                    continue;
+                }
+                if self.check_object(&layer_path, layer, LAYER_KEYS) {
+                    if let Err(err) = WsLayerData::new(name, layer, &PathBuf::new()) {
+                        self.error(&layer_path, err.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_tasks(&mut self, data: &Value) {
+        if let Some(tasks) = data.get("tasks").and_then(|v| v.as_object()) {
+            for (name, task) in tasks.iter() {
+                let task_path: String = format!("tasks.{}", name);
+                if !task.is_object() {
+                    self.error(
+                        &task_path,
+                        format!("Expected an object but found '{}'", task),
+                    );
+                    continue;
+                }
+                if self.check_object(&task_path, task, TASK_KEYS) {
+                    if let Err(err) = WsTaskData::new(task, &PathBuf::new(), &PathBuf::new()) {
+                        self.error(&task_path, err.to_string());
+                    }
+                }
+                self.check_container(&task_path, task);
+                self.check_artifacts(&task_path, task);
+            }
+        }
+    }
+
+    fn check_subcmds(&mut self, data: &Value) {
+        for name in ["deploy", "upload", "setup", "sync"] {
+            if let Some(subcmd) = data.get(name) {
+                self.check_object(name, subcmd, SUBCMD_KEYS);
+                self.check_container(name, subcmd);
+            }
+        }
+    }
+
+    fn check_version(&mut self, data: &Value) {
+        match data.get("version").and_then(|v| v.as_str()) {
+            Some(version) => {
+                if version != "6" {
+                    self.error(
+                        "version",
+                        BError::InvalidBuildConfigError(version.to_string()).to_string(),
+                    );
+                }
+            }
+            None => {
+                if data.get("version").is_none() {
+                    self.error("version", String::from("Missing required key 'version'"));
+                }
+            }
+        }
+    }
+
+    fn collect_context(&mut self, data: &Value) {
+        if let Some(context) = data.get("context").and_then(|v| v.as_array()) {
+            for (i, variable) in context.iter().enumerate() {
+                let path: String = format!("context[{}]", i);
+                if let Some(s) = variable.as_str() {
+                    match s.split_once('=') {
+                        Some((key, _value)) => {
+                            let location: String = self.location(&path);
+                            self.defined.insert(key.to_lowercase(), location);
+                        }
+                        None => {
+                            self.error(
+                                &path,
+                                format!("Invalid context variable '{}', expected 'KEY=VALUE'", s),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /*
+     * Record every context variable referenced by any string value in
+     * the build config together with the location of the reference. A
+     * reference with a fallback $#[VAR:-fallback] will always be expanded
+     * so only the locations of the references without one are recorded.
+     */
+    fn collect_references(&mut self, path: &str, data: &Value) {
+        match data {
+            Value::String(s) => {
+                let variables: Vec<(String, bool)> = self
+                    .regexp
+                    .captures_iter(s)
+                    .map(|caps| (caps[1].to_string(), caps.get(4).is_some()))
+                    .collect();
+                for (variable, fallback) in variables {
+                    let location: String = self.location(path);
+                    let locations: &mut Vec<String> = self.referenced.entry(variable).or_default();
+                    if !fallback {
+                        locations.push(location);
+                    }
+                }
+            }
+            Value::Array(array) => {
+                for (i, v) in array.iter().enumerate() {
+                    self.collect_references(&format!("{}[{}]", path, i), v);
+                }
+            }
+            Value::Object(object) => {
+                for (key, v) in object.iter() {
+                    self.collect_references(&Self::join(path, key), v);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn validate_settings(&mut self, file: &str, json_string: &str) {
+        self.file = file.to_string();
+        if let Some(data) = self.parse(json_string) {
+            self.check_object("", &data, SETTINGS_KEYS);
+            if data.get("version").is_none() {
+                self.error("version", String::from("Missing required key 'version'"));
+            }
+            if let Some(workspace) = data.get("workspace") {
+                self.check_object("workspace", workspace, SETTINGS_WORKSPACE_KEYS);
+            }
+            if let Some(builds) = data.get("builds") {
+                self.check_object("builds", builds, SETTINGS_BUILDS_KEYS);
+            }
+            if let Some(docker) = data.get("docker") {
+                self.check_object("docker", docker, SETTINGS_DOCKER_KEYS);
+            }
+            if let Some(context) = data.get("context") {
+                self.check_object("context", context, SETTINGS_CONTEXT_KEYS);
+            }
+            if let Some(cache) = data.get("cache") {
+                self.check_object("cache", cache, SETTINGS_CACHE_KEYS);
+            }
+            if let Some(signing) = data.get("signing") {
+                self.check_object("signing", signing, SETTINGS_SIGNING_KEYS);
+            }
+        }
+    }
+
+    /*
+     * Validate a product build config and return the list of build configs
+     * that it includes so they can be validated as well
+     */
+    pub fn validate_build_config(&mut self, file: &str, json_string: &str) -> Vec<String> {
+        self.file = file.to_string();
+        match self.parse(json_string) {
+            Some(data) => {
+                self.check_object("", &data, BUILD_CONFIG_KEYS);
+                self.check_version(&data);
+                if let Some(bb) = data.get("bb") {
+                    self.check_object("bb", bb, BITBAKE_KEYS);
+                }
+                self.check_secrets(&data);
+                self.check_layers(&data);
+                self.check_tasks(&data);
+                self.check_subcmds(&data);
+                self.collect_context(&data);
+                self.collect_references("", &data);
+                data.get("include")
+                    .and_then(|v| v.as_array())
+                    .map(|array| {
+                        array
+                            .iter()
+                            .filter_map(|v| v.as_str())
+                            .map(|s| s.to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            }
+            None => vec![],
+        }
+    }
+
+    pub fn validate_include_config(&mut self, file: &str, json_string: &str) {
+        self.file = file.to_string();
+        if let Some(data) = self.parse(json_string) {
+            self.check_object("", &data, INCLUDE_CONFIG_KEYS);
+            self.check_tasks(&data);
+            self.check_subcmds(&data);
+            self.collect_references("", &data);
+        }
+    }
+
+    pub fn missing_file(&mut self, file: &str, path: &str, message: String) {
+        self.file = file.to_string();
+        self.error(path, message);
+    }
+
+    /*
+     * Verify the context variables across all the validated files. Any
+     * reference to a variable that is not defined will never be expanded
+     * and any variable defined in the build config context but never
+     * referenced is most likely a left over or a typo.
+     */
+    pub fn finish(&mut self) {
+        let referenced: IndexMap<String, Vec<String>> = self.referenced.clone();
+        for (variable, locations) in referenced.iter() {
+            let key: String = variable.to_lowercase();
+            if !self.built_in.contains(&key) && !self.defined.contains_key(&key) {
+                for location in locations {
+                    self.errors.push(format!(
+                        "{}: Context variable '{}' is not defined and will never be expanded",
+                        location, variable
+                    ));
+                }
+            }
+        }
+
+        let defined: IndexMap<String, String> = self.defined.clone();
+        for (variable, location) in defined.iter() {
+            if self.built_in.contains(variable) {
+                continue;
+            }
+            if !referenced.keys().any(|r| &r.to_lowercase() == variable) {
+                self.warnings.push(format!(
+                    "{}: Context variable '{}' is never used",
+                    location,
+                    variable.to_uppercase()
+                ));
+            }
+        }
+    }
+
+    pub fn errors(&self) -> &Vec<String> {
+        &self.errors
+    }
+
+    pub fn warnings(&self) -> &Vec<String> {
+        &self.warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::{indexmap, IndexMap};
+
+    use crate::configs::WsConfigValidator;
+
+    #[test]
+    fn test_validator_build_config() {
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "bb": {
+                "machine": "test-machine",
+                "localconf": [
+                    "IMAGE_VERSION=\"$#[VERSION]\""
+                ]
+            },
+            "include": [
+                "tasks"
+            ],
+            "context": [
+                "VERSION=1.0.0"
+            ],
+            "tasks": {
+                "image": {
+                    "index": "1",
+                    "name": "image",
+                    "recipes": [
+                        "test-image"
+                    ],
+                    "artifacts": [
+                        {
+                            "type": "archive",
+                            "name": "image-$#[BKRY_MACHINE].zip",
+                            "artifacts": [
+                                {
+                                    "source": "test.img"
+                                }
+                            ]
+                        }
+                    ]
+                }
+            },
+            "deploy": {
+                "cmd": "$#[BKRY_SCRIPTS_DIR]/deploy.sh"
+            }
+        }"#;
+        let mut validator: WsConfigValidator =
+            WsConfigValidator::new(&IndexMap::new()).expect("Failed to setup validator");
+        let includes: Vec<String> =
+            validator.validate_build_config("configs/default.json", json_build_config);
+        validator.finish();
+        assert_eq!(includes, vec![String::from("tasks")]);
+        assert!(validator.errors().is_empty());
+        assert!(validator.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_validator_unknown_keys() {
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "descripton": "Test Description",
+            "bb": {
+                "machin": "test-machine"
+            },
+            "tasks": {
+                "sign-image": {
+                    "index": "1",
+                    "name": "sign-image",
+                    "type": "non-bitbake",
+                    "buidl": "sign.sh",
+                    "artifacts": [
+                        {
+                            "source": "image.bin"
+                        },
+                        {
+                            "type": "archive",
+                            "name": "image.zip",
+                            "artifacts": [
+                                {
+                                    "sorce": "image.bin"
+                                }
+                            ]
+                        }
+                    ]
+                }
+            },
+            "upload": {
+                "command": "upload.sh"
+            }
+        }"#;
+        let mut validator: WsConfigValidator =
+            WsConfigValidator::new(&IndexMap::new()).expect("Failed to setup validator");
+        validator.validate_build_config("configs/default.json", json_build_config);
+        validator.finish();
+        assert_eq!(
+            validator.errors(),
+            &vec![
+                String::from("configs/default.json: descripton: Unknown key 'descripton'"),
+                String::from("configs/default.json: bb.machin: Unknown key 'machin'"),
+                String::from("configs/default.json: tasks.sign-image.buidl: Unknown key 'buidl'"),
+                String::from("configs/default.json: tasks.sign-image.artifacts[1].artifacts[0].sorce: Unknown key 'sorce'"),
+                String::from("configs/default.json: upload.command: Unknown key 'command'"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validator_container() {
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "tasks": {
+                "sdk": {
+                    "index": "1",
+                    "name": "sdk",
+                    "type": "non-bitbake",
+                    "build": "sdk.sh",
+                    "container": {
+                        "cpus": "2",
+                        "memroy": "4g"
+                    }
+                }
+            },
+            "deploy": {
+                "cmd": "deploy.sh",
+                "container": {
+                    "devices": "/dev/hsm0"
+                }
+            }
+        }"#;
+        let mut validator: WsConfigValidator =
+            WsConfigValidator::new(&IndexMap::new()).expect("Failed to setup validator");
+        validator.validate_build_config("configs/default.json", json_build_config);
+        validator.finish();
+        assert_eq!(
+            validator.errors(),
+            &vec![
+                String::from("configs/default.json: tasks.sdk.container.memroy: Unknown key 'memroy'"),
+                String::from("configs/default.json: deploy.container.devices: Expected an array but found '\"/dev/hsm0\"'"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validator_secrets() {
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "secrets": {
+                "UPLOAD_TOKEN": {
+                    "source": "env"
+                },
+                "SIGN_KEY": {
+                    "source": "file"
+                },
+                "HSM_PIN": {
+                    "source": "local",
+                    "expose": "mount"
+                }
+            },
+            "upload": {
+                "cmd": "upload.sh",
+                "secrets": [
+                    "UPLOAD_TOKEN"
+                ]
+            }
+        }"#;
+        let mut validator: WsConfigValidator =
+            WsConfigValidator::new(&IndexMap::new()).expect("Failed to setup validator");
+        validator.validate_build_config("configs/default.json", json_build_config);
+        validator.finish();
+        assert_eq!(
+            validator.errors(),
+            &vec![
+                String::from("configs/default.json: secrets.HSM_PIN.expose: Invalid value 'mount', expected one of 'env', 'file'"),
+                String::from("configs/default.json: secrets.SIGN_KEY: The secret 'SIGN_KEY' is read from a file but no 'from' path is set"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validator_layers() {
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "bb": {
+                "bblayers": [
+                    "poky/meta",
+                    "meta-foo"
+                ],
+                "collections": [
+                    "openembedded-layer"
+                ],
+                "layerseries": "scarthgap"
+            },
+            "layers": {
+                "poky": {
+                    "url": "https://git.yoctoproject.org/poky",
+                    "branch": "scarthgap"
+                },
+                "meta-foo": {
+                    "url": "https://example.com/meta-foo.git",
+                    "branch": "main",
+                    "tag": "v1.0"
+                },
+                "meta-bar": {
+                    "branch": "main",
+                    "revision": "1234"
+                }
+            }
+        }"#;
+        let mut validator: WsConfigValidator =
+            WsConfigValidator::new(&IndexMap::new()).expect("Failed to setup validator");
+        validator.validate_build_config("configs/default.json", json_build_config);
+        validator.finish();
+        assert_eq!(
+            validator.errors(),
+            &vec![
+                String::from("configs/default.json: layers.meta-bar.revision: Unknown key 'revision'"),
+                String::from("configs/default.json: layers.meta-foo: The layer 'meta-foo' can only set one of 'branch' and 'tag'"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validator_invalid_values() {
+        let json_build_config: &str = r#"
+        {
+            "version": "5",
+            "name": "default",
+            "arch": 1,
+            "tasks": {
+                "image": {
+                    "name": "image",
+                    "recipes": [
+                        "test-image"
+                    ]
+                },
+                "sign-image": {
+                    "index": "2",
+                    "name": "sign-image",
+                    "type": "non-bitbake",
+                    "artifacts": [
+                        {
+                            "source": "image.bin"
+                        },
+                        {
+                            "type": "directory"
+                        },
+                        {
+                            "type": "fil",
+                            "source": "image.bin"
+                        },
+                        {
+                            "source": "image.bin",
+                            "select": "oldest",
+                            "exclude": "*.tmp"
+                        }
+                    ]
+                }
+            }
+        }"#;
+        let mut validator: WsConfigValidator =
+            WsConfigValidator::new(&IndexMap::new()).expect("Failed to setup validator");
+        validator.validate_build_config("default.json", json_build_config);
+        validator.finish();
+        assert_eq!(
+            validator.errors(),
+            &vec![
+                String::from("default.json: arch: Expected a string but found '1'"),
+                String::from("default.json: version: The build config version '5' is not compatible with current bakery version. Update config to match the format of version '6' using 'bakery migrate'"),
+                String::from("default.json: tasks.image: Failed to read string value 'index'"),
+                String::from("default.json: tasks.sign-image.artifacts[1]: Invalid 'artifact' node in build config. The 'directory' type requires a 'name'"),
+                String::from("default.json: tasks.sign-image.artifacts[2].type: Invalid value 'fil', expected one of 'file', 'directory', 'archive', 'manifest', 'link', 'conditional', 'checksums'"),
+                String::from("default.json: tasks.sign-image.artifacts[3].exclude: Expected an array but found '\"*.tmp\"'"),
+                String::from("default.json: tasks.sign-image.artifacts[3].select: Invalid value 'oldest', expected one of 'all', 'newest', 'exactly-one'"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validator_context() {
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "context": [
+                "VERSION=1.0.0",
+                "UNUSED=unused",
+                "RELEASE=$#[VERSION]-$#[BKRY_BUILD_ID]"
+            ],
+            "bb": {
+                "localconf": [
+                    "IMAGE_VERSION=\"$#[RELEASE]\"",
+                    "IMAGE_NAME=\"$#[IMAGE_NAME]\""
+                ]
+            },
+            "include": [
+                "tasks"
+            ]
+        }"#;
+        let json_include_config: &str = r#"
+        {
+            "version": "6",
+            "tasks": {
+                "sign": {
+                    "index": "1",
+                    "name": "sign",
+                    "type": "non-bitbake",
+                    "build": "sign.sh $#[KEY] $#[VARIANT] $#[VERSION] $#[MODE:-release]"
+                }
+            }
+        }"#;
+        let variables: IndexMap<String, String> = indexmap! {
+            "KEY".to_string() => "key.pem".to_string(),
+        };
+        let mut validator: WsConfigValidator =
+            WsConfigValidator::new(&variables).expect("Failed to setup validator");
+        validator.validate_build_config("default.json", json_build_config);
+        validator.validate_include_config("include/tasks.json", json_include_config);
+        validator.finish();
+        assert_eq!(
+            validator.errors(),
+            &vec![
+                String::from("default.json: bb.localconf[1]: Context variable 'IMAGE_NAME' is not defined and will never be expanded"),
+                String::from("include/tasks.json: tasks.sign.build: Context variable 'VARIANT' is not defined and will never be expanded"),
+            ]
+        );
+        assert_eq!(
+            validator.warnings(),
+            &vec![String::from(
+                "default.json: context[1]: Context variable 'UNUSED' is never used"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_validator_settings() {
+        let json_ws_settings: &str = r#"
+        {
+            "version": "6",
+            "workspace": {
+                "configsdir": "configs",
+                "buildir": "builds"
+            },
+            "builds": {
+                "supported": "default"
+            },
+            "docker": {
+                "image": "bakery-workspace",
+                "args": [
+                    "--rm"
+                ]
+            },
+            "cache": {
+                "sstatemirrors": "file://.* https://sstate.example.com/PATH"
+            },
+            "dockr": {}
+        }"#;
+        let mut validator: WsConfigValidator =
+            WsConfigValidator::new(&IndexMap::new()).expect("Failed to setup validator");
+        validator.validate_settings("workspace.json", json_ws_settings);
+        assert_eq!(
+            validator.errors(),
+            &vec![
+                String::from("workspace.json: dockr: Unknown key 'dockr'"),
+                String::from("workspace.json: workspace.buildir: Unknown key 'buildir'"),
+                String::from(
+                    "workspace.json: builds.supported: Expected an array but found '\"default\"'"
+                ),
+                String::from(
+                    "workspace.json: cache.sstatemirrors: Expected an array but found '\"file://.* https://sstate.example.com/PATH\"'"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validator_parse_error() {
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default"
+            "arch": "test-arch"
+        }"#;
+        let mut validator: WsConfigValidator =
+            WsConfigValidator::new(&IndexMap::new()).expect("Failed to setup validator");
+        let includes: Vec<String> =
+            validator.validate_build_config("default.json", json_build_config);
+        assert!(includes.is_empty());
+        assert_eq!(
+            validator.errors(),
+            &vec![String::from(
+                "default.json: Failed to parse JSON: expected `,` or `}` at line 5 column 13"
+            )]
+        );
+    }
+}
@@ -2,6 +2,7 @@ use serde_json::Value;
 
 use crate::configs::Config;
 use crate::configs::Context;
+use crate::data::context::CTX_KEY_SOURCE_DATE_EPOCH;
 use crate::error::BError;
 
 #[derive(Clone, Eq, Hash, PartialEq, Debug)]
@@ -12,6 +13,20 @@ pub enum AType {
     Manifest,
     Link,
     Conditional,
+    Checksums,
+}
+
+#[derive(Clone, Eq, Hash, PartialEq, Debug)]
+pub enum AMatch {
+    Glob,
+    Regex,
+}
+
+#[derive(Clone, Eq, Hash, PartialEq, Debug)]
+pub enum ASelect {
+    All,
+    Newest,
+    ExactlyOne,
 }
 
 // TODO: we should consider using IndexSet instead of vector to make sure we
@@ -19,10 +34,16 @@ pub enum AType {
 pub struct WsArtifactData {
     pub atype: AType, // Optional if not set for the task the default type 'file' is used
     pub name: String, // The name can be a name for a directory, archive, file or manifest
-    pub source: String, // The source is only used if the type is file
+    pub source: String, // The source is only used if the type is file or directory
     pub dest: String, // The dest is optional
     pub manifest: String, // The manifest content will be a json string that can be put in a file. The manifest can then be used by the CI to collect information from the build
     pub condition: String, // The condition is only used if the type is conditional
+    pub exclude: Vec<String>, // Glob patterns for files that should not be collected, only used if the type is file or directory
+    pub amatch: AMatch, // Optional if not set the source is a glob pattern, only used if the type is file or directory
+    pub select: ASelect, // Optional if not set all the files matching the source are collected, only used if the type is file or directory
+    pub compression_level: String, // Optional if not set the default level of the compression is used, only used if the type is archive
+    pub reproducible: String, // Optional if not set the archive is reproducible if a source date epoch is available, only used if the type is archive
+    pub source_date_epoch: String, // Set from the context when expanding the context, only used if the type is archive
 }
 
 impl Config for WsArtifactData {}
@@ -39,11 +60,18 @@ impl WsArtifactData {
 
     pub fn new(data: &Value) -> Result<Self, BError> {
         let ttype: String = Self::get_str_value("type", &data, Some(String::from("file")))?;
-        let name: String = Self::get_str_value("name", &data, Some(String::from("")))?;
+        let mut name: String = Self::get_str_value("name", &data, Some(String::from("")))?;
         let source: String = Self::get_str_value("source", &data, Some(String::from("")))?;
         let dest: String = Self::get_str_value("dest", &data, Some(String::from("")))?;
         let manifest: String = Self::get_str_manifest("content", &data, Some(String::from("{}")))?;
         let condition: String = Self::get_str_value("condition", &data, Some(String::from("")))?;
+        let exclude: Vec<String> = Self::get_array_value("exclude", &data, Some(vec![]))?;
+        let amatch: String = Self::get_str_value("match", &data, Some(String::from("glob")))?;
+        let select: String = Self::get_str_value("select", &data, Some(String::from("all")))?;
+        let compression_level: String =
+            Self::get_str_value("compression_level", &data, Some(String::from("")))?;
+        let reproducible: String =
+            Self::get_str_value("reproducible", &data, Some(String::from("")))?;
 
         if ttype != "file"
             && ttype != "directory"
@@ -51,6 +79,7 @@ impl WsArtifactData {
             && ttype != "manifest"
             && ttype != "link"
             && ttype != "conditional"
+            && ttype != "checksums"
         {
             return Err(BError::ParseArtifactsError(format!(
                 "Invalid type '{}'",
@@ -88,6 +117,45 @@ impl WsArtifactData {
             )));
         }
 
+        let enum_amatch: AMatch;
+        match amatch.as_str() {
+            "glob" => {
+                enum_amatch = AMatch::Glob;
+            }
+            "regex" => {
+                enum_amatch = AMatch::Regex;
+            }
+            _ => {
+                return Err(BError::ParseArtifactsError(format!(
+                    "Invalid match '{}'",
+                    amatch
+                )));
+            }
+        }
+
+        let enum_select: ASelect;
+        match select.as_str() {
+            "all" => {
+                enum_select = ASelect::All;
+            }
+            "newest" => {
+                enum_select = ASelect::Newest;
+            }
+            "exactly-one" => {
+                enum_select = ASelect::ExactlyOne;
+            }
+            _ => {
+                return Err(BError::ParseArtifactsError(format!(
+                    "Invalid select '{}'",
+                    select
+                )));
+            }
+        }
+
+        if ttype == "checksums" && name.is_empty() {
+            name = String::from("SHA256SUMS");
+        }
+
         let enum_ttype: AType;
         match ttype.as_str() {
             "file" => {
@@ -108,6 +176,9 @@ impl WsArtifactData {
             "conditional" => {
                 enum_ttype = AType::Conditional;
             }
+            "checksums" => {
+                enum_ttype = AType::Checksums;
+            }
             _ => {
                 return Err(BError::ParseArtifactsError(format!(
                     "Invalid type '{}'",
@@ -126,38 +197,53 @@ impl WsArtifactData {
             dest,
             manifest,
             condition,
+            exclude,
+            amatch: enum_amatch,
+            select: enum_select,
+            compression_level,
+            reproducible,
+            source_date_epoch: String::new(),
         })
     }
 
     pub fn expand_ctx(&mut self, ctx: &Context) -> Result<(), BError> {
         match self.atype {
             AType::File => {
-                self.name = ctx.expand_str(&self.name)?;
-                self.source = ctx.expand_str(&self.source)?;
-                self.dest = ctx.expand_str(&self.dest)?;
+                self.name = ctx.expand_key("name", &self.name)?;
+                self.source = ctx.expand_key("source", &self.source)?;
+                self.dest = ctx.expand_key("dest", &self.dest)?;
+                for (i, pattern) in self.exclude.iter_mut().enumerate() {
+                    *pattern = ctx.expand_key(&format!("exclude[{}]", i), pattern)?;
+                }
             }
             AType::Directory => {
-                self.name = ctx.expand_str(&self.name)?;
+                self.name = ctx.expand_key("name", &self.name)?;
+                self.source = ctx.expand_key("source", &self.source)?;
+                self.dest = ctx.expand_key("dest", &self.dest)?;
+                for (i, pattern) in self.exclude.iter_mut().enumerate() {
+                    *pattern = ctx.expand_key(&format!("exclude[{}]", i), pattern)?;
+                }
             }
             AType::Archive => {
-                self.name = ctx.expand_str(&self.name)?;
+                self.name = ctx.expand_key("name", &self.name)?;
+                self.compression_level =
+                    ctx.expand_key("compression_level", &self.compression_level)?;
+                self.reproducible = ctx.expand_key("reproducible", &self.reproducible)?;
+                self.source_date_epoch = ctx.value(CTX_KEY_SOURCE_DATE_EPOCH);
             }
             AType::Manifest => {
-                self.name = ctx.expand_str(&self.name)?;
-                self.manifest = ctx.expand_str(&self.manifest)?;
+                self.name = ctx.expand_key("name", &self.name)?;
+                self.manifest = ctx.expand_key("content", &self.manifest)?;
             }
             AType::Link => {
-                self.name = ctx.expand_str(&self.name)?;
-                self.source = ctx.expand_str(&self.source)?;
+                self.name = ctx.expand_key("name", &self.name)?;
+                self.source = ctx.expand_key("source", &self.source)?;
             }
             AType::Conditional => {
-                self.condition = ctx.expand_str(&self.condition)?;
+                self.condition = ctx.expand_key("condition", &self.condition)?;
             }
-            _ => {
-                panic!(
-                    "Invalid 'artifact' format in build config. Invalid type '{:?}'",
-                    self.atype
-                );
+            AType::Checksums => {
+                self.name = ctx.expand_key("name", &self.name)?;
             }
         }
         Ok(())
@@ -179,6 +265,30 @@ impl WsArtifactData {
         &self.dest
     }
 
+    pub fn exclude(&self) -> &Vec<String> {
+        &self.exclude
+    }
+
+    pub fn amatch(&self) -> &AMatch {
+        &self.amatch
+    }
+
+    pub fn select(&self) -> &ASelect {
+        &self.select
+    }
+
+    pub fn compression_level(&self) -> &str {
+        &self.compression_level
+    }
+
+    pub fn reproducible(&self) -> &str {
+        &self.reproducible
+    }
+
+    pub fn source_date_epoch(&self) -> &str {
+        &self.source_date_epoch
+    }
+
     pub fn manifest(&self) -> &str {
         &self.manifest
     }
@@ -197,7 +307,7 @@ mod tests {
     use serde_json::Value;
 
     use crate::configs::Context;
-    use crate::data::{AType, WsArtifactData};
+    use crate::data::{AMatch, ASelect, AType, WsArtifactData};
     use crate::error::BError;
     use crate::helper::Helper;
 
@@ -276,6 +386,70 @@ mod tests {
         assert_eq!(data.name(), "dir");
     }
 
+    #[test]
+    fn test_ws_artifact_data_select() {
+        let json_artifact_config: &str = r#"
+        {
+            "source": "images/image-[0-9]+\\.wic\\.bz2",
+            "match": "regex",
+            "select": "newest",
+            "exclude": [
+                "*-dbg*"
+            ]
+        }
+        "#;
+        let value: Value =
+            Helper::parse(json_artifact_config).expect("Failed to parse artifact config");
+        let data: WsArtifactData =
+            WsArtifactData::new(&value).expect("Failed to parse artifact data");
+        assert_eq!(data.atype(), &AType::File);
+        assert_eq!(data.source(), "images/image-[0-9]+\\.wic\\.bz2");
+        assert_eq!(data.amatch(), &AMatch::Regex);
+        assert_eq!(data.select(), &ASelect::Newest);
+        assert_eq!(data.exclude(), &vec![String::from("*-dbg*")]);
+    }
+
+    #[test]
+    fn test_ws_artifact_data_error_invalid_select() {
+        let json_artifact_config: &str = r#"
+        {
+            "source": "file1.txt",
+            "select": "oldest"
+        }
+        "#;
+        let value: Value =
+            Helper::parse(json_artifact_config).expect("Failed to parse artifact config");
+        let result: Result<WsArtifactData, BError> = WsArtifactData::new(&value);
+        match result {
+            Ok(_data) => {
+                panic!("We should have recived an error because the select is invalid!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    String::from(
+                        "Invalid 'artifact' node in build config. Invalid select 'oldest'"
+                    )
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_ws_artifact_data_checksums_type() {
+        let json_artifact_config: &str = r#"
+        {
+            "type": "checksums"
+        }
+        "#;
+        let value: Value =
+            Helper::parse(json_artifact_config).expect("Failed to parse artifact config");
+        let data: WsArtifactData =
+            WsArtifactData::new(&value).expect("Failed to parse artifact data");
+        assert_eq!(data.atype(), &AType::Checksums);
+        assert_eq!(data.name(), "SHA256SUMS");
+    }
+
     #[test]
     fn test_ws_artifact_data_error_invalid_type() {
         let json_artifact_config: &str = r#"
@@ -453,6 +627,36 @@ mod tests {
         assert_eq!(data.name(), "link.txt");
     }
 
+    #[test]
+    fn test_ws_artifact_data_dir_ctx() {
+        let ctx_variables: IndexMap<String, String> = indexmap! {
+            "DIR_NAME".to_string() => "ctx-dir".to_string(),
+            "IMAGE_DIR".to_string() => "tmp/deploy/images".to_string(),
+            "DEST_DIR".to_string() => "images".to_string(),
+            "EXCLUDE".to_string() => "*.bin".to_string()
+        };
+        let json_artifact_config: &str = r#"
+        {
+            "type": "directory",
+            "name": "$#[DIR_NAME]",
+            "source": "$#[IMAGE_DIR]/*",
+            "dest": "$#[DEST_DIR]",
+            "exclude": ["$#[EXCLUDE]"]
+        }
+        "#;
+        let context: Context = Context::new(&ctx_variables);
+        let value: Value =
+            Helper::parse(json_artifact_config).expect("Failed to parse artifact config");
+        let mut data: WsArtifactData =
+            WsArtifactData::new(&value).expect("Failed to parse artifact data");
+        data.expand_ctx(&context).unwrap();
+        assert_eq!(data.atype(), &AType::Directory);
+        assert_eq!(data.name(), "ctx-dir");
+        assert_eq!(data.source(), "tmp/deploy/images/*");
+        assert_eq!(data.dest(), "images");
+        assert_eq!(data.exclude(), &vec![String::from("*.bin")]);
+    }
+
     #[test]
     fn test_ws_artifact_data_link_ctx() {
         let ctx_variables: IndexMap<String, String> = indexmap! {
@@ -4,7 +4,8 @@ use std::path::PathBuf;
 use crate::configs::Config;
 use crate::configs::Context;
 use crate::error::BError;
-use crate::workspace::WsSettingsHandler;
+use crate::fs::{LayerCollections, LocalConf};
+use crate::workspace::{WsLayersHandler, WsSettingsHandler};
 
 pub struct WsBitbakeData {
     data: Value,
@@ -16,7 +17,10 @@ pub struct WsBitbakeData {
     docker: String, // Optional if nothing is set the bitbake task will be executed inside the bakery container. Default is an empty string
     bblayers_conf: Vec<String>, // Optional but if there is a task with type bitbake defined it will fail without a bblayers.conf
     local_conf: Vec<String>, // Optional but if there is a task with type bitbake defined it will fail without a local.conf
-    init_env: String, // Optional but if not set the default oe-init-build-env file will be used
+    bblayers: Vec<String>, // Optional layers from the layers segment referenced by name that are added to BBLAYERS
+    collections: Vec<String>, // Optional layer collections that are looked up in the layers dir together with their dependencies and added to BBLAYERS
+    layer_series: String, // Optional series the layers has to be compatible with, if not set the series of the core layer is used
+    init_env: String,     // Optional but if not set the default oe-init-build-env file will be used
     settings: WsSettingsHandler,
 }
 
@@ -53,6 +57,10 @@ impl WsBitbakeData {
         let bblayers_conf: Vec<String> =
             Self::get_array_value("bblayersconf", bb_data, Some(vec![]))?;
         let local_conf: Vec<String> = Self::get_array_value("localconf", bb_data, Some(vec![]))?;
+        let bblayers: Vec<String> = Self::get_array_value("bblayers", bb_data, Some(vec![]))?;
+        let collections: Vec<String> = Self::get_array_value("collections", bb_data, Some(vec![]))?;
+        let layer_series: String =
+            Self::get_str_value("layerseries", bb_data, Some(String::new()))?;
         let init_env: String = Self::get_str_value(
             "initenv",
             bb_data,
@@ -69,15 +77,23 @@ impl WsBitbakeData {
             deploy_dir,
             bblayers_conf,
             local_conf,
+            bblayers,
+            collections,
+            layer_series,
             settings: settings.clone(),
             init_env,
         })
     }
 
-    pub fn expand_conf(&self, ctx: &Context, conf: &Vec<String>) -> Result<Vec<String>, BError> {
+    pub fn expand_conf(
+        &self,
+        ctx: &Context,
+        key: &str,
+        conf: &Vec<String>,
+    ) -> Result<Vec<String>, BError> {
         let mut config: Vec<String> = Vec::new();
-        for line in conf.clone() {
-            let expanded: String = ctx.expand_str(&line)?;
+        for (i, line) in conf.iter().enumerate() {
+            let expanded: String = ctx.expand_key(&format!("{}[{}]", key, i), line)?;
             config.push(expanded);
         }
 
@@ -85,41 +101,155 @@ impl WsBitbakeData {
     }
 
     pub fn expand_ctx(&mut self, ctx: &Context) -> Result<(), BError> {
-        self.machine = ctx.expand_str(&self.machine)?;
-        self.distro = ctx.expand_str(&self.distro)?;
-        self.docker = ctx.expand_str(&self.docker)?;
-        self.deploy_dir = ctx.expand_str(&self.deploy_dir)?;
-        self.init_env = ctx.expand_str(&self.init_env)?;
-        self.bblayers_conf = self.expand_conf(ctx, &self.bblayers_conf)?;
-        self.local_conf = self.expand_conf(ctx, &self.local_conf)?;
+        self.machine = ctx.expand_key("machine", &self.machine)?;
+        self.distro = ctx.expand_key("distro", &self.distro)?;
+        self.docker = ctx.expand_key("docker", &self.docker)?;
+        self.deploy_dir = ctx.expand_key("deploydir", &self.deploy_dir)?;
+        self.init_env = ctx.expand_key("initenv", &self.init_env)?;
+        self.bblayers_conf = self.expand_conf(ctx, "bblayersconf", &self.bblayers_conf)?;
+        self.local_conf = self.expand_conf(ctx, "localconf", &self.local_conf)?;
+        self.bblayers = self.expand_conf(ctx, "bblayers", &self.bblayers)?;
+        self.collections = self.expand_conf(ctx, "collections", &self.collections)?;
+        self.layer_series = ctx.expand_key("layerseries", &self.layer_series)?;
         Ok(())
     }
 
-    pub fn bblayers_conf(&self) -> String {
+    /*
+     * Replace the layers referenced by name with the path to the layer
+     */
+    pub fn resolve_layers(&mut self, layers: &WsLayersHandler) -> Result<(), BError> {
+        let mut bblayers: Vec<String> = vec![];
+        for reference in self.bblayers.iter() {
+            bblayers.push(layers.layer_path(reference)?.to_string_lossy().to_string());
+        }
+        self.bblayers = bblayers;
+        Ok(())
+    }
+
+    fn bblayers_conf_layers(&self, layers: &[String]) -> String {
         let mut conf_str: String = String::new();
         for line in self.bblayers_conf.clone() {
             conf_str.push_str(format!("{}\n", line).as_str());
         }
+        if !layers.is_empty() {
+            /*
+             * Extend BBLAYERS if it is already set in the bblayersconf, only
+             * the name is matched so that BBLAYERS_NON_REMOVABLE is not a match
+             */
+            let operator: &str = if self.bblayers_conf.iter().any(|l| {
+                l.trim_start()
+                    .split(|c: char| c.is_whitespace() || "?+:=".contains(c))
+                    .next()
+                    == Some("BBLAYERS")
+            }) {
+                "+="
+            } else {
+                "?="
+            };
+            conf_str.push_str(&format!("BBLAYERS {} \" \\\n", operator));
+            for layer in layers.iter() {
+                conf_str.push_str(&format!("{} \\\n", layer));
+            }
+            conf_str.push_str("\"\n");
+        }
         conf_str
     }
 
-    pub fn local_conf(&self) -> String {
-        let mut conf_str: String = String::new();
-        for line in self.local_conf.clone() {
-            conf_str.push_str(format!("{}\n", line).as_str());
+    /*
+     * Look up the layer collections in the layers dir. This can only be done
+     * once the layers have been fetched so it is not done when the build
+     * config is loaded.
+     */
+    pub fn discover_layers(&self) -> Result<Vec<String>, BError> {
+        if self.collections.is_empty() {
+            return Ok(vec![]);
+        }
+        let collections: LayerCollections = LayerCollections::scan(&self.settings.layers_dir())?;
+        Ok(collections
+            .resolve(&self.collections, &self.layer_series)?
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect())
+    }
+
+    /*
+     * The bblayers.conf including the layers discovered from the collections
+     */
+    pub fn bblayers_conf_discovered(&self) -> Result<String, BError> {
+        let mut layers: Vec<String> = self.bblayers.clone();
+        for layer in self.discover_layers()? {
+            if !layers.contains(&layer) {
+                layers.push(layer);
+            }
         }
-        conf_str.push_str(&format!("MACHINE ?= \"{}\"\n", self.machine()));
+        Ok(self.bblayers_conf_layers(&layers))
+    }
+
+    /*
+     * The local.conf as a list of bitbake variables recording which part of
+     * the build config or workspace settings each line is coming from
+     */
+    pub fn local_conf_vars(&self) -> LocalConf {
+        let mut local_conf: LocalConf = LocalConf::new();
+        local_conf.add_lines(&self.local_conf, "build config localconf");
+        /*
+         * The machine and distro are set to NA when not defined in the build
+         * config which should not conflict with setting them in localconf
+         */
+        local_conf.add_unchecked(
+            &format!("MACHINE ?= \"{}\"", self.machine()),
+            "build config bb.machine",
+            self.machine == "NA",
+        );
         // TODO: we should define a method product_name() call that instead
-        conf_str.push_str(&format!("PRODUCT_NAME ?= \"{}\"\n", self.product));
-        conf_str.push_str(&format!("DISTRO ?= \"{}\"\n", self.distro));
-        conf_str.push_str(&format!(
-            "SSTATE_DIR ?= \"{}\"\n",
-            self.sstate_dir().to_str().unwrap()
-        ));
-        conf_str.push_str(&format!(
-            "DL_DIR ?= \"{}\"\n",
-            self.dl_dir().to_str().unwrap()
-        ));
+        local_conf.add_line(
+            &format!("PRODUCT_NAME ?= \"{}\"", self.product),
+            "build config name",
+        );
+        local_conf.add_unchecked(
+            &format!("DISTRO ?= \"{}\"", self.distro),
+            "build config bb.distro",
+            self.distro == "NA",
+        );
+        local_conf.add_line(
+            &format!("SSTATE_DIR ?= \"{}\"", self.sstate_dir().to_str().unwrap()),
+            "workspace settings",
+        );
+        local_conf.add_line(
+            &format!("DL_DIR ?= \"{}\"", self.dl_dir().to_str().unwrap()),
+            "workspace settings",
+        );
+        local_conf.add_conf(&self.cache_conf(), "workspace settings cache");
+        local_conf
+    }
+
+    fn mirrors_conf(&self, var: &str, mirrors: &[String]) -> String {
+        if mirrors.is_empty() {
+            return String::new();
+        }
+        let mut conf_str: String = format!("{} ?= \"\\\n", var);
+        for mirror in mirrors {
+            conf_str.push_str(&format!("{} \\\n", mirror));
+        }
+        conf_str.push_str("\"\n");
+        conf_str
+    }
+
+    /*
+     * The cache settings in the workspace config are shared by all the
+     * build configs in the workspace
+     */
+    pub fn cache_conf(&self) -> String {
+        let mut conf_str: String = String::new();
+        conf_str.push_str(&self.mirrors_conf("SSTATE_MIRRORS", &self.settings.sstate_mirrors()));
+        conf_str.push_str(&self.mirrors_conf("PREMIRRORS", &self.settings.premirrors()));
+        if !self.settings.hashserve().is_empty() {
+            conf_str.push_str(&format!(
+                "BB_HASHSERVE ?= \"{}\"\n",
+                self.settings.hashserve()
+            ));
+            conf_str.push_str("BB_SIGNATURE_HANDLER ?= \"OEEquivHash\"\n");
+        }
         conf_str
     }
 
@@ -179,6 +309,10 @@ impl WsBitbakeData {
             .join("download".to_string())
     }
 
+    pub fn cache_volumes(&self) -> Vec<String> {
+        self.settings.cache_volumes()
+    }
+
     pub fn init_env_file(&self) -> PathBuf {
         // TODO: we should probably setup an option to configure what OE init script
         // to source to setup the env.
@@ -190,6 +324,7 @@ impl WsBitbakeData {
 mod tests {
     use indexmap::{indexmap, IndexMap};
     use std::path::PathBuf;
+    use tempdir::TempDir;
 
     use crate::configs::Context;
     use crate::data::WsBitbakeData;
@@ -233,8 +368,8 @@ mod tests {
             data.local_conf_path(),
             PathBuf::from(String::from("/workspace/builds/NA/conf/local.conf"))
         );
-        assert!(data.bblayers_conf().is_empty());
-        assert!(!data.local_conf().is_empty());
+        assert!(data.bblayers_conf_discovered().unwrap().is_empty());
+        assert!(!data.local_conf_vars().to_string().is_empty());
         assert_eq!(
             data.sstate_dir(),
             PathBuf::from(String::from("/workspace/.cache/NA/sstate-cache"))
@@ -308,13 +443,13 @@ mod tests {
             data.local_conf_path(),
             PathBuf::from(String::from("/workspace/builds/test-name/conf/local.conf"))
         );
-        assert!(!data.bblayers_conf().is_empty());
+        assert!(!data.bblayers_conf_discovered().unwrap().is_empty());
         let mut conf_str: String = String::new();
         conf_str.push_str("BB_LAYERS_CONF_TEST_LINE_1\n");
         conf_str.push_str("BB_LAYERS_CONF_TEST_LINE_2\n");
         conf_str.push_str("BB_LAYERS_CONF_TEST_LINE_3\n");
-        assert_eq!(data.bblayers_conf(), conf_str);
-        assert!(!data.local_conf().is_empty());
+        assert_eq!(data.bblayers_conf_discovered().unwrap(), conf_str);
+        assert!(!data.local_conf_vars().to_string().is_empty());
         let mut conf_str: String = String::new();
         conf_str.push_str("BB_LOCAL_CONF_TEST_LINE_1\n");
         conf_str.push_str("BB_LOCAL_CONF_TEST_LINE_2\n");
@@ -324,7 +459,7 @@ mod tests {
         conf_str.push_str("DISTRO ?= \"test-distro\"\n");
         conf_str.push_str("SSTATE_DIR ?= \"/workspace/.cache/test-arch/sstate-cache\"\n");
         conf_str.push_str("DL_DIR ?= \"/workspace/.cache/download\"\n");
-        assert_eq!(data.local_conf(), conf_str);
+        assert_eq!(data.local_conf_vars().to_string(), conf_str);
         assert_eq!(
             data.sstate_dir(),
             PathBuf::from(String::from("/workspace/.cache/test-arch/sstate-cache"))
@@ -385,8 +520,153 @@ mod tests {
         };
         let context: Context = Context::new(&variables);
         data.expand_ctx(&context).unwrap();
-        assert_eq!(data.bblayers_conf(), "BAKERY_WORKDIR=\"${TOPDIR}/../..\"\nBBLAYERS ?= \" \\\n       /bakery-ws/layers/meta-test \\\n       /bakery-ws/builds/workspace \\\n\"\n");
-        assert_eq!(data.local_conf(), "BAKERY_WORKSPACE_DIR ?= \"/bakery-ws/builds/workspace\"\nPACKAGE_CLASSES ?= \"package_rpm\"\nBB_DISKMON_DIRS ?= \"\\\n    STOPTASKS,${TMPDIR},1G,100K \\\n    HALT,${SSTATE_DIR},100M,1K \\\n    HALT,/tmp,10M,1K\"\nCONF_VERSION = \"2\"\nMACHINE ?= \"test-machine\"\nPRODUCT_NAME ?= \"test-name\"\nDISTRO ?= \"test-distro\"\nSSTATE_DIR ?= \"/bakery-ws/.cache/test-arch/sstate-cache\"\nDL_DIR ?= \"/bakery-ws/.cache/download\"\n");
+        assert_eq!(data.bblayers_conf_discovered().unwrap(), "BAKERY_WORKDIR=\"${TOPDIR}/../..\"\nBBLAYERS ?= \" \\\n       /bakery-ws/layers/meta-test \\\n       /bakery-ws/builds/workspace \\\n\"\n");
+        assert_eq!(data.local_conf_vars().to_string(), "BAKERY_WORKSPACE_DIR ?= \"/bakery-ws/builds/workspace\"\nPACKAGE_CLASSES ?= \"package_rpm\"\nBB_DISKMON_DIRS ?= \"\\\n    STOPTASKS,${TMPDIR},1G,100K \\\n    HALT,${SSTATE_DIR},100M,1K \\\n    HALT,/tmp,10M,1K\"\nCONF_VERSION = \"2\"\nMACHINE ?= \"test-machine\"\nPRODUCT_NAME ?= \"test-name\"\nDISTRO ?= \"test-distro\"\nSSTATE_DIR ?= \"/bakery-ws/.cache/test-arch/sstate-cache\"\nDL_DIR ?= \"/bakery-ws/.cache/download\"\n");
+    }
+
+    #[test]
+    fn test_ws_bitbake_data_cache() {
+        let json_settings: &str = r#"
+        {
+            "version": "6",
+            "cache": {
+                "sstatemirrors": [
+                    "file://.* https://sstate.example.com/PATH;downloadfilename=PATH"
+                ],
+                "hashserve": "hashserv.example.com:8686",
+                "shareddldir": "/nfs/downloads"
+            }
+        }"#;
+        let json_build_config = r#"
+        {
+            "version": "6",
+            "name": "test-name",
+            "arch": "test-arch",
+            "bb": {
+                "machine": "test-machine",
+                "distro": "test-distro"
+            }
+        }"#;
+        let work_dir: PathBuf = PathBuf::from("/workspace");
+        let settings: WsSettingsHandler = WsSettingsHandler::from_str(&work_dir, json_settings)
+            .expect("Failed to parse settings");
+        let data: WsBitbakeData = WsBitbakeData::from_str(json_build_config, &settings)
+            .expect("Failed to parse product data");
+        let local_conf: String = String::from(
+            "MACHINE ?= \"test-machine\"\n\
+            PRODUCT_NAME ?= \"test-name\"\n\
+            DISTRO ?= \"test-distro\"\n\
+            SSTATE_DIR ?= \"/workspace/.cache/test-arch/sstate-cache\"\n\
+            DL_DIR ?= \"/workspace/.cache/download\"\n\
+            SSTATE_MIRRORS ?= \"\\\n\
+            file://.* https://sstate.example.com/PATH;downloadfilename=PATH \\\n\
+            \"\n\
+            PREMIRRORS ?= \"\\\n\
+            git://.*/.* file:///nfs/downloads/ \\\n\
+            gitsm://.*/.* file:///nfs/downloads/ \\\n\
+            ftp://.*/.* file:///nfs/downloads/ \\\n\
+            http://.*/.* file:///nfs/downloads/ \\\n\
+            https://.*/.* file:///nfs/downloads/ \\\n\
+            \"\n\
+            BB_HASHSERVE ?= \"hashserv.example.com:8686\"\n\
+            BB_SIGNATURE_HANDLER ?= \"OEEquivHash\"\n",
+        );
+        assert_eq!(data.local_conf_vars().to_string(), local_conf);
+    }
+
+    #[test]
+    fn test_ws_bitbake_data_collections() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.path().to_path_buf();
+        let layers_dir: PathBuf = work_dir.join("layers");
+        for (path, layer_conf) in [
+            (
+                "poky/meta",
+                "BBFILE_COLLECTIONS += \"core\"\nLAYERSERIES_CORENAMES = \"scarthgap\"\n",
+            ),
+            (
+                "meta-bsp",
+                "BBFILE_COLLECTIONS += \"bsp\"\nLAYERDEPENDS_bsp = \"core\"\nLAYERSERIES_COMPAT_bsp = \"scarthgap\"\n",
+            ),
+        ] {
+            let conf_dir: PathBuf = layers_dir.join(path).join("conf");
+            std::fs::create_dir_all(&conf_dir).expect("Failed to create layer dir");
+            std::fs::write(conf_dir.join("layer.conf"), layer_conf)
+                .expect("Failed to write layer.conf");
+        }
+        let json_settings: &str = r#"
+        {
+            "version": "6"
+        }"#;
+        let json_build_config = r#"
+        {
+            "version": "6",
+            "name": "test-name",
+            "arch": "test-arch",
+            "bb": {
+                "bblayersconf": [
+                    "BBLAYERS ?= \"/workspace/meta-extra\""
+                ],
+                "collections": [
+                    "bsp"
+                ],
+                "layerseries": "scarthgap"
+            }
+        }"#;
+        let settings: WsSettingsHandler = WsSettingsHandler::from_str(&work_dir, json_settings)
+            .expect("Failed to parse settings");
+        let data: WsBitbakeData = WsBitbakeData::from_str(json_build_config, &settings)
+            .expect("Failed to parse product data");
+        assert_eq!(
+            data.bblayers_conf_discovered()
+                .expect("Failed to discover layers"),
+            format!(
+                "BBLAYERS ?= \"/workspace/meta-extra\"\n\
+                BBLAYERS += \" \\\n\
+                {} \\\n\
+                {} \\\n\
+                \"\n",
+                layers_dir.join("poky/meta").display(),
+                layers_dir.join("meta-bsp").display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_ws_bitbake_data_bblayers_non_removable() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let json_settings: &str = r#"
+        {
+            "version": "6"
+        }"#;
+        let json_build_config = r#"
+        {
+            "version": "6",
+            "name": "test-name",
+            "arch": "test-arch",
+            "bb": {
+                "bblayersconf": [
+                    "BBLAYERS_NON_REMOVABLE ?= \"/workspace/poky/meta\""
+                ]
+            }
+        }"#;
+        let settings: WsSettingsHandler = WsSettingsHandler::from_str(&work_dir, json_settings)
+            .expect("Failed to parse settings");
+        let data: WsBitbakeData = WsBitbakeData::from_str(json_build_config, &settings)
+            .expect("Failed to parse product data");
+        /* BBLAYERS is not set so it should not be extended */
+        assert_eq!(
+            data.bblayers_conf_layers(&[String::from("/workspace/meta-test")]),
+            String::from(
+                "BBLAYERS_NON_REMOVABLE ?= \"/workspace/poky/meta\"\n\
+                BBLAYERS ?= \" \\\n\
+                /workspace/meta-test \\\n\
+                \"\n"
+            )
+        );
     }
 
     #[test]
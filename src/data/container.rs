@@ -0,0 +1,205 @@
+use serde_json::Value;
+
+use crate::configs::Config;
+use crate::configs::Context;
+use crate::error::BError;
+
+/*
+ * Container options for a task or a custom sub-command that is run inside
+ * a docker container. The options are translated to docker run arguments
+ * so that resources can be limited and devices or volumes made available
+ * for a specific task.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WsContainerData {
+    cpus: String,
+    memory: String,
+    volumes: Vec<String>,
+    network: String,
+    devices: Vec<String>,
+    privileged: String,
+    args: Vec<String>,
+}
+
+impl Config for WsContainerData {}
+
+impl WsContainerData {
+    /*
+     * Read the container object from the task or the custom sub-command,
+     * if there is no container object the default options are used
+     */
+    pub fn from_value(data: &Value) -> Result<Self, BError> {
+        match data.get("container") {
+            Some(container) => Self::new(container),
+            None => Ok(WsContainerData::default()),
+        }
+    }
+
+    pub fn new(data: &Value) -> Result<Self, BError> {
+        if !data.is_object() {
+            return Err(BError::ParseError(format!(
+                "Expected container to be an object but found '{}'",
+                data
+            )));
+        }
+        let cpus: String = Self::get_str_value("cpus", data, Some(String::from("")))?;
+        let memory: String = Self::get_str_value("memory", data, Some(String::from("")))?;
+        let volumes: Vec<String> = Self::get_array_value("volumes", data, Some(vec![]))?;
+        let network: String = Self::get_str_value("network", data, Some(String::from("")))?;
+        let devices: Vec<String> = Self::get_array_value("devices", data, Some(vec![]))?;
+        let privileged: String =
+            Self::get_str_value("privileged", data, Some(String::from("false")))?;
+        let args: Vec<String> = Self::get_array_value("args", data, Some(vec![]))?;
+
+        Ok(WsContainerData {
+            cpus,
+            memory,
+            volumes,
+            network,
+            devices,
+            privileged,
+            args,
+        })
+    }
+
+    pub fn expand_ctx(&mut self, ctx: &Context) -> Result<(), BError> {
+        self.cpus = ctx.expand_key("cpus", &self.cpus)?;
+        self.memory = ctx.expand_key("memory", &self.memory)?;
+        self.network = ctx.expand_key("network", &self.network)?;
+        self.privileged = ctx.expand_key("privileged", &self.privileged)?;
+        for (i, v) in self.volumes.iter_mut().enumerate() {
+            *v = ctx.expand_key(&format!("volumes[{}]", i), v)?;
+        }
+        for (i, d) in self.devices.iter_mut().enumerate() {
+            *d = ctx.expand_key(&format!("devices[{}]", i), d)?;
+        }
+        for (i, a) in self.args.iter_mut().enumerate() {
+            *a = ctx.expand_key(&format!("args[{}]", i), a)?;
+        }
+        Ok(())
+    }
+
+    pub fn privileged(&self) -> bool {
+        self.privileged == "true"
+    }
+
+    /*
+     * The docker run arguments for the container options
+     */
+    pub fn docker_args(&self) -> Vec<String> {
+        let mut args: Vec<String> = vec![];
+        if !self.cpus.is_empty() {
+            args.append(&mut vec![String::from("--cpus"), self.cpus.clone()]);
+        }
+        if !self.memory.is_empty() {
+            args.append(&mut vec![String::from("--memory"), self.memory.clone()]);
+        }
+        self.volumes.iter().for_each(|v| {
+            args.append(&mut vec![String::from("-v"), v.clone()]);
+        });
+        if !self.network.is_empty() {
+            args.append(&mut vec![String::from("--network"), self.network.clone()]);
+        }
+        self.devices.iter().for_each(|d| {
+            args.append(&mut vec![String::from("--device"), d.clone()]);
+        });
+        if self.privileged() {
+            args.push(String::from("--privileged"));
+        }
+        args.append(&mut self.args.clone());
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::{indexmap, IndexMap};
+    use serde_json::Value;
+
+    use crate::configs::Context;
+    use crate::data::WsContainerData;
+    use crate::helper::Helper;
+
+    #[test]
+    fn test_ws_container_data_default() {
+        let json_task_config = r#"
+        {
+            "name": "task-name"
+        }"#;
+        let value: Value = Helper::parse(json_task_config).expect("Failed to parse task config");
+        let data: WsContainerData =
+            WsContainerData::from_value(&value).expect("Failed to parse container data");
+        assert_eq!(data, WsContainerData::default());
+        assert!(!data.privileged());
+        assert!(data.docker_args().is_empty());
+    }
+
+    #[test]
+    fn test_ws_container_data() {
+        let json_task_config = r#"
+        {
+            "container": {
+                "cpus": "4",
+                "memory": "8g",
+                "volumes": [
+                    "/opt/keys:/opt/keys:ro"
+                ],
+                "network": "host",
+                "devices": [
+                    "/dev/hsm0"
+                ],
+                "privileged": "true",
+                "args": [
+                    "--shm-size=2g"
+                ]
+            }
+        }"#;
+        let value: Value = Helper::parse(json_task_config).expect("Failed to parse task config");
+        let data: WsContainerData =
+            WsContainerData::from_value(&value).expect("Failed to parse container data");
+        assert!(data.privileged());
+        assert_eq!(
+            data.docker_args(),
+            vec![
+                "--cpus",
+                "4",
+                "--memory",
+                "8g",
+                "-v",
+                "/opt/keys:/opt/keys:ro",
+                "--network",
+                "host",
+                "--device",
+                "/dev/hsm0",
+                "--privileged",
+                "--shm-size=2g",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ws_container_data_ctx() {
+        let variables: IndexMap<String, String> = indexmap! {
+            "HSM_DEVICE".to_string() => "/dev/hsm0".to_string(),
+            "SDK_CPUS".to_string() => "2".to_string(),
+        };
+        let ctx: Context = Context::new(&variables);
+        let json_task_config = r#"
+        {
+            "container": {
+                "cpus": "$#[SDK_CPUS]",
+                "devices": [
+                    "$#[HSM_DEVICE]"
+                ]
+            }
+        }"#;
+        let value: Value = Helper::parse(json_task_config).expect("Failed to parse task config");
+        let mut data: WsContainerData =
+            WsContainerData::from_value(&value).expect("Failed to parse container data");
+        data.expand_ctx(&ctx).unwrap();
+        assert_eq!(
+            data.docker_args(),
+            vec!["--cpus", "2", "--device", "/dev/hsm0"]
+        );
+    }
+}
@@ -36,6 +36,7 @@ pub const CTX_KEY_TIME: &str = "BKRY_TIME";
 pub const CTX_KEY_BRANCH: &str = "BKRY_BRANCH";
 pub const CTX_KEY_RESET: &str = "BKRY_RESET";
 pub const CTX_KEY_EYECANDY: &str = "BKRY_EYECANDY";
+pub const CTX_KEY_SOURCE_DATE_EPOCH: &str = "BKRY_SOURCE_DATE_EPOCH";
 
 // TODO: we should clean this up in some way it should
 // not have to be this many context variables for
@@ -71,6 +72,7 @@ impl WsContextData {
             | CTX_KEY_BRANCH
             | CTX_KEY_RESET
             | CTX_KEY_EYECANDY
+            | CTX_KEY_SOURCE_DATE_EPOCH
             | CTX_KEY_DEBUG_SYMBOLS => true,
             CTX_KEY_MACHINE
             | CTX_KEY_ARCH
@@ -143,6 +145,7 @@ impl WsContextData {
             CTX_KEY_RESET.to_string() => "false".to_string(),
             CTX_KEY_CONFIG.to_string() => "NA".to_string(),
             CTX_KEY_EYECANDY.to_string() => "false".to_string(),
+            CTX_KEY_SOURCE_DATE_EPOCH.to_string() => "".to_string(),
         };
         let mut ctx: Context = Context::new(&ctx_default_variables);
         ctx.update(&variables);
@@ -181,6 +184,10 @@ impl WsContextData {
         self.update(context.variables());
     }
 
+    pub fn set_strict(&mut self, strict: bool) {
+        self.context.set_strict(strict);
+    }
+
     pub fn get_ctx_path(&self, key: &str) -> PathBuf {
         PathBuf::from(self.get_ctx_value(key))
     }
@@ -202,7 +209,8 @@ mod tests {
         CTX_KEY_DEBUG_SYMBOLS, CTX_KEY_DEVICE, CTX_KEY_DISTRO, CTX_KEY_EYECANDY, CTX_KEY_IMAGE,
         CTX_KEY_LAYERS_DIR, CTX_KEY_MACHINE, CTX_KEY_NAME, CTX_KEY_PLATFORM_RELEASE,
         CTX_KEY_PLATFORM_VERSION, CTX_KEY_PRODUCT_NAME, CTX_KEY_PROJECT_NAME,
-        CTX_KEY_RELEASE_BUILD, CTX_KEY_RESET, CTX_KEY_SCRIPTS_DIR, CTX_KEY_TIME, CTX_KEY_WORK_DIR,
+        CTX_KEY_RELEASE_BUILD, CTX_KEY_RESET, CTX_KEY_SCRIPTS_DIR, CTX_KEY_SOURCE_DATE_EPOCH,
+        CTX_KEY_TIME, CTX_KEY_WORK_DIR,
     };
     use crate::data::WsContextData;
     use crate::workspace::WsSettingsHandler;
@@ -250,6 +258,7 @@ mod tests {
         assert_eq!(data.get_ctx_value(CTX_KEY_RESET), String::from("false"));
         assert_eq!(data.get_ctx_value(CTX_KEY_CONFIG), String::from("NA"));
         assert_eq!(data.get_ctx_value(CTX_KEY_EYECANDY), String::from("false"));
+        assert!(data.get_ctx_value(CTX_KEY_SOURCE_DATE_EPOCH).is_empty());
     }
 
     #[test]
@@ -2,12 +2,15 @@ use serde_json::Value;
 
 use crate::configs::Config;
 use crate::configs::Context;
+use crate::data::WsContainerData;
 use crate::error::BError;
 
 pub struct WsCustomSubCmdData {
     name: String,
     cmd: String,
     docker: String,
+    container: WsContainerData,
+    secrets: Vec<String>,
 }
 
 impl Config for WsCustomSubCmdData {}
@@ -39,17 +42,24 @@ impl WsCustomSubCmdData {
             )),
         )?;
         let docker: String = Self::get_str_value("docker", data, Some(String::from("NA")))?;
+        let container: WsContainerData = WsContainerData::from_value(data)?;
+        let secrets: Vec<String> = Self::get_array_value("secrets", data, Some(vec![]))?;
 
         Ok(WsCustomSubCmdData {
             name: String::from(name),
             cmd,
             docker,
+            container,
+            secrets,
         })
     }
 
     pub fn expand_ctx(&mut self, ctx: &Context) -> Result<(), BError> {
-        self.cmd = ctx.expand_str(&self.cmd)?;
-        self.docker = ctx.expand_str(&self.docker)?;
+        self.cmd = ctx.expand_key("cmd", &self.cmd)?;
+        self.docker = ctx.expand_key("docker", &self.docker)?;
+        self.container
+            .expand_ctx(ctx)
+            .map_err(|err| Context::key_error(err, "container"))?;
         Ok(())
     }
 
@@ -60,6 +70,18 @@ impl WsCustomSubCmdData {
     pub fn name(&self) -> &String {
         &self.name
     }
+
+    pub fn docker_image(&self) -> &str {
+        &self.docker
+    }
+
+    pub fn container(&self) -> &WsContainerData {
+        &self.container
+    }
+
+    pub fn secrets(&self) -> &Vec<String> {
+        &self.secrets
+    }
 }
 
 #[cfg(test)]
@@ -9,7 +9,8 @@ use crate::data::{WsBitbakeData, WsConfigData, WsContextData, WsIncludeData, WsP
 use crate::error::BError;
 use crate::fs::ConfigFileReader;
 use crate::workspace::{
-    WsArtifactsHandler, WsCustomSubCmdHandler, WsSettingsHandler, WsTaskHandler,
+    WsArtifactsHandler, WsCustomSubCmdHandler, WsLayersHandler, WsSecretsHandler,
+    WsSettingsHandler, WsTaskHandler,
 };
 
 pub struct WsBuildData {
@@ -19,6 +20,8 @@ pub struct WsBuildData {
     bitbake: WsBitbakeData,
     include: WsIncludeData,
     context: WsContextData,
+    secrets: WsSecretsHandler,
+    layers: WsLayersHandler,
     settings: WsSettingsHandler,
 }
 
@@ -53,13 +56,24 @@ impl WsBuildData {
         // The bitbake segment contains all the bitbake related data
         // needed when executing a bitbake task defined in the build
         // config
-        let bitbake: WsBitbakeData = WsBitbakeData::from_value(data, settings)?;
+        let mut bitbake: WsBitbakeData = WsBitbakeData::from_value(data, settings)?;
+        // The layers segment defines the meta layers git repositories that are
+        // fetched into the layers dir. The bitbake segment can reference the
+        // layers by name when generating the bblayers.conf
+        let layers: WsLayersHandler = WsLayersHandler::from_value(data, &settings.layers_dir())?;
+        bitbake.resolve_layers(&layers)?;
         // The include segment is to define additional json files that contains
         // defined tasks that are used by multiple build configs
         let include: WsIncludeData = WsIncludeData::from_value(data, settings)?;
         // The context segment contains all the context variables used
         // by other parts of the build config
         let mut context: WsContextData = WsContextData::from_value(data)?;
+        // The secrets segment defines where the values of the secrets used
+        // by the tasks and sub-commands are read from. The values are not
+        // part of the context and are only resolved when they are used
+        let bakery_dir: PathBuf = PathBuf::from(std::env::var("HOME").unwrap_or_default())
+            .join(".bakery");
+        let secrets: WsSecretsHandler = WsSecretsHandler::from_value(data, &bakery_dir)?;
 
         // Setup context with "built-in" variables that will always
         // be available
@@ -103,6 +117,8 @@ impl WsBuildData {
             bitbake,
             include,
             context,
+            secrets,
+            layers,
             settings: settings.clone(), // for now lets clone it
         })
     }
@@ -192,10 +208,16 @@ impl WsBuildData {
         data: &Value,
     ) -> Result<IndexMap<String, WsCustomSubCmdHandler>, BError> {
         let names = ["deploy", "upload", "setup", "sync"];
+        /*
+         * A sub-command that is not defined is using the default values, the rest
+         * of the build config like the secrets should not be read as the sub-command
+         */
+        let undefined: Value = Value::Object(serde_json::Map::new());
         let subcmds: IndexMap<String, WsCustomSubCmdHandler> = names
             .iter()
             .map(|&cmd| {
-                let subcmd: WsCustomSubCmdHandler = WsCustomSubCmdHandler::new(cmd, data)?;
+                let subcmd: WsCustomSubCmdHandler =
+                    WsCustomSubCmdHandler::new(cmd, data.get(cmd).unwrap_or(&undefined))?;
                 Ok((cmd.to_owned(), subcmd))
             })
             .collect::<Result<IndexMap<_, _>, BError>>()?;
@@ -232,10 +254,22 @@ impl WsBuildData {
         self.context.update_ctx(context);
     }
 
+    pub fn set_strict_ctx(&mut self, strict: bool) {
+        self.context.set_strict(strict);
+    }
+
     pub fn expand_ctx(&mut self) -> Result<(), BError> {
         //self.config.expand_ctx(self.context.ctx());
         //self.product.expand_ctx(self.context.ctx());
-        self.bitbake.expand_ctx(self.context.ctx())?;
+        self.bitbake
+            .expand_ctx(self.context.ctx())
+            .map_err(|err| Context::key_error(err, "bb"))?;
+        self.secrets
+            .expand_ctx(self.context.ctx())
+            .map_err(|err| Context::key_error(err, "secrets"))?;
+        self.layers
+            .expand_ctx(self.context.ctx())
+            .map_err(|err| Context::key_error(err, "layers"))?;
         Ok(())
     }
 
@@ -246,6 +280,14 @@ impl WsBuildData {
     pub fn bitbake(&self) -> &WsBitbakeData {
         &self.bitbake
     }
+
+    pub fn secrets(&self) -> &WsSecretsHandler {
+        &self.secrets
+    }
+
+    pub fn layers(&self) -> &WsLayersHandler {
+        &self.layers
+    }
 }
 
 #[cfg(test)]
@@ -648,11 +690,46 @@ mod tests {
             Helper::setup_build_data(&work_dir, Some(json_build_config), None);
         data.expand_ctx().unwrap();
         let bitbake: &WsBitbakeData = data.bitbake();
-        assert_eq!(bitbake.local_conf(), "ARTIFACTS_DIR ?= /workspace/artifacts\nLAYERS_DIR ?= /workspace/layers\nSCRIPTS_DIR ?= /workspace/scripts\nBUILDS_DIR ?= /workspace/builds\nWORK_DIR ?= /workspace\nMACHINE ?= \"test-machine\"\nPRODUCT_NAME ?= \"test-name\"\nDISTRO ?= \"test-distro\"\nSSTATE_DIR ?= \"/workspace/.cache/test-arch/sstate-cache\"\nDL_DIR ?= \"/workspace/.cache/download\"\n");
-        assert_eq!(bitbake.bblayers_conf(), "BAKERY_WORKDIR=\"${TOPDIR}/../..\"\nBBLAYERS ?= \" \\\n       /workspace/layers/meta-test \\\n       /workspace/builds/workspace \\\n\"\n");
+        assert_eq!(bitbake.local_conf_vars().to_string(), "ARTIFACTS_DIR ?= /workspace/artifacts\nLAYERS_DIR ?= /workspace/layers\nSCRIPTS_DIR ?= /workspace/scripts\nBUILDS_DIR ?= /workspace/builds\nWORK_DIR ?= /workspace\nMACHINE ?= \"test-machine\"\nPRODUCT_NAME ?= \"test-name\"\nDISTRO ?= \"test-distro\"\nSSTATE_DIR ?= \"/workspace/.cache/test-arch/sstate-cache\"\nDL_DIR ?= \"/workspace/.cache/download\"\n");
+        assert_eq!(bitbake.bblayers_conf_discovered().unwrap(), "BAKERY_WORKDIR=\"${TOPDIR}/../..\"\nBBLAYERS ?= \" \\\n       /workspace/layers/meta-test \\\n       /workspace/builds/workspace \\\n\"\n");
         assert_eq!(
             bitbake.init_env_file(),
             PathBuf::from("/workspace/layers/meta-test/oe-my-init-env")
         );
     }
+
+    #[test]
+    fn test_ws_build_data_layers() {
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "test-name",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "bb": {
+                "bblayers": [
+                    "poky/meta",
+                    "poky/meta-poky",
+                    "meta-foo"
+                ]
+            },
+            "layers": {
+                "poky": {
+                    "url": "https://git.yoctoproject.org/poky",
+                    "branch": "scarthgap"
+                },
+                "meta-foo": {
+                    "url": "https://example.com/meta-foo.git",
+                    "tag": "v1.0",
+                    "path": "vendor/meta-foo"
+                }
+            }
+        }"#;
+        let work_dir: PathBuf = PathBuf::from("/workspace");
+        let mut data: WsBuildData =
+            Helper::setup_build_data(&work_dir, Some(json_build_config), None);
+        data.expand_ctx().unwrap();
+        assert_eq!(data.layers().layers().len(), 2);
+        assert_eq!(data.bitbake().bblayers_conf_discovered().unwrap(), "BBLAYERS ?= \" \\\n/workspace/layers/poky/meta \\\n/workspace/layers/poky/meta-poky \\\n/workspace/layers/vendor/meta-foo \\\n\"\n");
+    }
 }
@@ -0,0 +1,180 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::configs::Config;
+use crate::configs::Context;
+use crate::error::BError;
+
+/*
+ * A meta layer that is fetched from a git repository into the layers dir.
+ * The layer is checked out on a branch, a tag or a specific commit. If
+ * a commit is set together with a branch the commit is checked out but
+ * the branch is used when fetching.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct WsLayerData {
+    name: String,
+    url: String,
+    branch: String,
+    tag: String,
+    commit: String,
+    path: String,
+    layers_dir: PathBuf,
+}
+
+impl Config for WsLayerData {}
+
+impl WsLayerData {
+    pub fn new(name: &str, data: &Value, layers_dir: &Path) -> Result<Self, BError> {
+        if data.get("url").is_none() {
+            return Err(BError::ParseError(format!(
+                "The layer '{}' has no 'url'",
+                name
+            )));
+        }
+        let url: String = Self::get_str_value("url", data, None)?;
+        let branch: String = Self::get_str_value("branch", data, Some(String::new()))?;
+        let tag: String = Self::get_str_value("tag", data, Some(String::new()))?;
+        let commit: String = Self::get_str_value("commit", data, Some(String::new()))?;
+        let path: String = Self::get_str_value("path", data, Some(String::from(name)))?;
+        if !branch.is_empty() && !tag.is_empty() {
+            return Err(BError::ParseError(format!(
+                "The layer '{}' can only set one of 'branch' and 'tag'",
+                name
+            )));
+        }
+
+        Ok(WsLayerData {
+            name: String::from(name),
+            url,
+            branch,
+            tag,
+            commit,
+            path,
+            layers_dir: layers_dir.to_path_buf(),
+        })
+    }
+
+    pub fn expand_ctx(&mut self, ctx: &Context) -> Result<(), BError> {
+        self.url = ctx.expand_key("url", &self.url)?;
+        self.branch = ctx.expand_key("branch", &self.branch)?;
+        self.tag = ctx.expand_key("tag", &self.tag)?;
+        self.commit = ctx.expand_key("commit", &self.commit)?;
+        self.path = ctx.expand_key("path", &self.path)?;
+        Ok(())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn branch(&self) -> &str {
+        &self.branch
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn commit(&self) -> &str {
+        &self.commit
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /*
+     * The branch or tag to clone, if none is set the default branch
+     * of the remote is used
+     */
+    pub fn reference(&self) -> Option<&str> {
+        if !self.tag.is_empty() {
+            return Some(&self.tag);
+        }
+        if !self.branch.is_empty() {
+            return Some(&self.branch);
+        }
+        None
+    }
+
+    /*
+     * The path of the layer checkout, relative paths are relative to the
+     * layers dir
+     */
+    pub fn dir(&self) -> PathBuf {
+        self.layers_dir.join(&self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use std::path::PathBuf;
+
+    use crate::data::WsLayerData;
+    use crate::helper::Helper;
+
+    #[test]
+    fn test_ws_layer_data() {
+        let layers_dir: PathBuf = PathBuf::from("/workspace/layers");
+        let value: Value =
+            Helper::parse(r#"{"url": "https://git.yoctoproject.org/poky", "branch": "scarthgap"}"#)
+                .expect("Failed to parse layer config");
+        let data: WsLayerData =
+            WsLayerData::new("poky", &value, &layers_dir).expect("Failed to parse layer data");
+        assert_eq!(data.name(), "poky");
+        assert_eq!(data.url(), "https://git.yoctoproject.org/poky");
+        assert_eq!(data.reference(), Some("scarthgap"));
+        assert!(data.commit().is_empty());
+        assert_eq!(data.dir(), PathBuf::from("/workspace/layers/poky"));
+
+        let value: Value = Helper::parse(
+            r#"{"url": "https://example.com/meta-foo.git", "tag": "v1.0", "path": "vendor/meta-foo"}"#,
+        )
+        .expect("Failed to parse layer config");
+        let data: WsLayerData =
+            WsLayerData::new("meta-foo", &value, &layers_dir).expect("Failed to parse layer data");
+        assert_eq!(data.reference(), Some("v1.0"));
+        assert_eq!(
+            data.dir(),
+            PathBuf::from("/workspace/layers/vendor/meta-foo")
+        );
+    }
+
+    #[test]
+    fn test_ws_layer_data_error() {
+        let layers_dir: PathBuf = PathBuf::from("/workspace/layers");
+        let value: Value =
+            Helper::parse(r#"{"branch": "scarthgap"}"#).expect("Failed to parse layer config");
+        let result = WsLayerData::new("poky", &value, &layers_dir);
+        match result {
+            Ok(_data) => {
+                panic!("We should have recived an error because the url is missing");
+            }
+            Err(e) => {
+                assert_eq!(e.to_string(), "The layer 'poky' has no 'url'");
+            }
+        }
+        let value: Value = Helper::parse(
+            r#"{"url": "https://git.yoctoproject.org/poky", "branch": "scarthgap", "tag": "yocto-5.0"}"#,
+        )
+        .expect("Failed to parse layer config");
+        let result = WsLayerData::new("poky", &value, &layers_dir);
+        match result {
+            Ok(_data) => {
+                panic!("We should have recived an error because both branch and tag are set");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "The layer 'poky' can only set one of 'branch' and 'tag'"
+                );
+            }
+        }
+    }
+}
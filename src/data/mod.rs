@@ -1,22 +1,27 @@
 pub mod artifact;
 pub mod bitbake;
 pub mod config;
+pub mod container;
 pub mod context;
 pub mod customsubcmd;
 pub mod data;
 pub mod include;
+pub mod layer;
 pub mod product;
+pub mod secret;
 pub mod task;
 
-pub use artifact::{AType, WsArtifactData};
+pub use artifact::{AMatch, ASelect, AType, WsArtifactData};
 pub use bitbake::WsBitbakeData;
 pub use config::WsConfigData;
+pub use container::WsContainerData;
 pub use context::{
-    WsContextData, CTX_KEY_BRANCH, CTX_KEY_CONFIG, CTX_KEY_DEVICE, CTX_KEY_EYECANDY, CTX_KEY_IMAGE,
-    CTX_KEY_RESET,
+    WsContextData, CTX_KEY_BRANCH, CTX_KEY_DEVICE, CTX_KEY_EYECANDY, CTX_KEY_IMAGE, CTX_KEY_RESET,
 };
 pub use customsubcmd::WsCustomSubCmdData;
 pub use data::WsBuildData;
 pub use include::WsIncludeData;
+pub use layer::WsLayerData;
 pub use product::WsProductData;
+pub use secret::WsSecretData;
 pub use task::{TType, WsTaskData};
@@ -0,0 +1,257 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::configs::Config;
+use crate::configs::Context;
+use crate::error::BError;
+
+pub const SECRETS_FILE: &str = "secrets.json";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SSource {
+    /* The value is read from an env variable of the bakery process */
+    Env,
+    /* The value is the content of a file */
+    File,
+    /* The value is read from the secrets.json file under ${HOME}/.bakery */
+    Local,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SExpose {
+    /* The value is exposed to the task as an env variable */
+    Env,
+    /* The value is written to a file and the path is exposed as an env variable */
+    File,
+}
+
+/*
+ * A secret is a value such as a token or a signing key that is needed by a
+ * task or a custom sub-command. The value is never part of the build config
+ * or the context it is only resolved when the task is executed.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct WsSecretData {
+    name: String,
+    source: SSource,
+    from: String,
+    expose: SExpose,
+}
+
+impl Config for WsSecretData {}
+
+impl WsSecretData {
+    pub fn new(name: &str, data: &Value) -> Result<Self, BError> {
+        let source: String = Self::get_str_value("source", data, Some(String::from("local")))?;
+        let expose: String = Self::get_str_value("expose", data, Some(String::from("env")))?;
+        let enum_source: SSource = match source.as_str() {
+            "env" => SSource::Env,
+            "file" => SSource::File,
+            "local" => SSource::Local,
+            _ => {
+                return Err(BError::ParseError(format!(
+                    "Invalid source '{}' for secret '{}'",
+                    source, name
+                )));
+            }
+        };
+        let enum_expose: SExpose = match expose.as_str() {
+            "env" => SExpose::Env,
+            "file" => SExpose::File,
+            _ => {
+                return Err(BError::ParseError(format!(
+                    "Invalid expose '{}' for secret '{}'",
+                    expose, name
+                )));
+            }
+        };
+        /* A file secret needs a path but for env and local the name is used as key */
+        if enum_source == SSource::File && data.get("from").is_none() {
+            return Err(BError::ParseError(format!(
+                "The secret '{}' is read from a file but no 'from' path is set",
+                name
+            )));
+        }
+        let from: String = Self::get_str_value("from", data, Some(String::from(name)))?;
+
+        Ok(WsSecretData {
+            name: String::from(name),
+            source: enum_source,
+            from,
+            expose: enum_expose,
+        })
+    }
+
+    pub fn expand_ctx(&mut self, ctx: &Context) -> Result<(), BError> {
+        self.from = ctx.expand_key("from", &self.from)?;
+        Ok(())
+    }
+
+    pub fn source(&self) -> &SSource {
+        &self.source
+    }
+
+    pub fn from(&self) -> &str {
+        &self.from
+    }
+
+    pub fn expose(&self) -> &SExpose {
+        &self.expose
+    }
+
+    /*
+     * Resolve the value of the secret. The env is the env of the bakery process
+     * and the bakery dir is where the local secrets file is located normally
+     * ${HOME}/.bakery
+     */
+    pub fn value(
+        &self,
+        env: &HashMap<String, String>,
+        bakery_dir: &Path,
+    ) -> Result<String, BError> {
+        match self.source {
+            SSource::Env => match env.get(&self.from) {
+                Some(value) => Ok(value.clone()),
+                None => Err(BError::ValueError(format!(
+                    "Secret '{}' is not available, the env variable '{}' is not set",
+                    self.name, self.from
+                ))),
+            },
+            SSource::File => {
+                let path: PathBuf = PathBuf::from(&self.from);
+                match std::fs::read_to_string(&path) {
+                    Ok(content) => Ok(content.trim_end_matches(['\n', '\r']).to_string()),
+                    Err(e) => Err(BError::ValueError(format!(
+                        "Secret '{}' is not available, failed to read '{}': {}",
+                        self.name,
+                        path.display(),
+                        e
+                    ))),
+                }
+            }
+            SSource::Local => {
+                let path: PathBuf = bakery_dir.join(SECRETS_FILE);
+                if !path.exists() {
+                    return Err(BError::ValueError(format!(
+                        "Secret '{}' is not available, no secrets file '{}'",
+                        self.name,
+                        path.display()
+                    )));
+                }
+                let data: Value = Self::parse(&std::fs::read_to_string(&path)?)?;
+                match data.get(&self.from).and_then(|v| v.as_str()) {
+                    Some(value) => Ok(value.to_string()),
+                    None => Err(BError::ValueError(format!(
+                        "Secret '{}' is not available, '{}' is missing in '{}'",
+                        self.name,
+                        self.from,
+                        path.display()
+                    ))),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    use crate::data::secret::{SExpose, SSource};
+    use crate::data::WsSecretData;
+    use crate::helper::Helper;
+
+    #[test]
+    fn test_ws_secret_data_default() {
+        let value: Value = Helper::parse("{}").expect("Failed to parse secret config");
+        let data: WsSecretData =
+            WsSecretData::new("UPLOAD_TOKEN", &value).expect("Failed to parse secret data");
+        assert_eq!(data.source(), &SSource::Local);
+        assert_eq!(data.from(), "UPLOAD_TOKEN");
+        assert_eq!(data.expose(), &SExpose::Env);
+    }
+
+    #[test]
+    fn test_ws_secret_data_value() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.path().to_path_buf();
+        std::fs::write(work_dir.join("token.txt"), "file-value\n").expect("Failed to write");
+        std::fs::write(
+            work_dir.join("secrets.json"),
+            r#"{"SIGN_KEY": "local-value"}"#,
+        )
+        .expect("Failed to write");
+        let env: HashMap<String, String> =
+            HashMap::from([(String::from("CI_TOKEN"), String::from("env-value"))]);
+
+        let value: Value = Helper::parse(r#"{"source": "env", "from": "CI_TOKEN"}"#)
+            .expect("Failed to parse secret config");
+        let data: WsSecretData =
+            WsSecretData::new("UPLOAD_TOKEN", &value).expect("Failed to parse secret data");
+        assert_eq!(data.value(&env, &work_dir).unwrap(), "env-value");
+
+        let value: Value = Helper::parse(
+            format!(
+                r#"{{"source": "file", "from": "{}"}}"#,
+                work_dir.join("token.txt").display()
+            )
+            .as_str(),
+        )
+        .expect("Failed to parse secret config");
+        let data: WsSecretData =
+            WsSecretData::new("UPLOAD_TOKEN", &value).expect("Failed to parse secret data");
+        assert_eq!(data.value(&env, &work_dir).unwrap(), "file-value");
+
+        let value: Value =
+            Helper::parse(r#"{"expose": "file"}"#).expect("Failed to parse secret config");
+        let data: WsSecretData =
+            WsSecretData::new("SIGN_KEY", &value).expect("Failed to parse secret data");
+        assert_eq!(data.expose(), &SExpose::File);
+        assert_eq!(data.value(&env, &work_dir).unwrap(), "local-value");
+    }
+
+    #[test]
+    fn test_ws_secret_data_missing() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let value: Value =
+            Helper::parse(r#"{"source": "env"}"#).expect("Failed to parse secret config");
+        let data: WsSecretData =
+            WsSecretData::new("UPLOAD_TOKEN", &value).expect("Failed to parse secret data");
+        let result = data.value(&HashMap::new(), temp_dir.path());
+        match result {
+            Ok(_value) => {
+                panic!("We should have recived an error because the env variable is not set");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Secret 'UPLOAD_TOKEN' is not available, the env variable 'UPLOAD_TOKEN' is not set"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_ws_secret_data_invalid_source() {
+        let value: Value =
+            Helper::parse(r#"{"source": "vault"}"#).expect("Failed to parse secret config");
+        let result = WsSecretData::new("UPLOAD_TOKEN", &value);
+        match result {
+            Ok(_data) => {
+                panic!("We should have recived an error because the source is invalid");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Invalid source 'vault' for secret 'UPLOAD_TOKEN'"
+                );
+            }
+        }
+    }
+}
@@ -1,10 +1,10 @@
 use indexmap::IndexMap;
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::path::PathBuf;
 
 use crate::configs::Config;
 use crate::configs::Context;
-use crate::data::WsBuildData;
+use crate::data::{WsBuildData, WsContainerData};
 use crate::error::BError;
 
 #[derive(Clone, PartialEq, Debug)]
@@ -21,11 +21,14 @@ pub struct WsTaskData {
     build_dir: PathBuf,
     build: String,
     docker: String,
+    container: WsContainerData,
     condition: String,
     clean: String,
     recipes: Vec<String>, // The list of recipes will be empty if the type for the task is 'non-bitbake'
     description: String,
     env: IndexMap<String, String>,
+    depends: Vec<String>, // Optional list of task names that needs to be built before this task
+    secrets: Vec<String>, // Optional list of secrets from the build config exposed to the task
 }
 
 impl Config for WsTaskData {}
@@ -66,6 +69,7 @@ impl WsTaskData {
         let disabled: String = Self::get_str_value("disabled", &data, Some(String::from("false")))?;
         let build_dir: String = Self::get_str_value("builddir", &data, Some(String::from("")))?;
         let docker: String = Self::get_str_value("docker", data, Some(String::from("")))?;
+        let container: WsContainerData = WsContainerData::from_value(data)?;
         let condition: String = Self::get_str_value("condition", data, Some(String::from("true")))?;
         let build: String = Self::get_str_value("build", &data, Some(String::from("")))?;
         let clean: String = Self::get_str_value("clean", &data, Some(String::from("")))?;
@@ -73,6 +77,8 @@ impl WsTaskData {
             Self::get_str_value("description", &data, Some(String::from("NA")))?;
         let env: IndexMap<String, String> = Self::get_hashmap_value("env", &data)?;
         let recipes: Vec<String> = Self::get_array_value("recipes", &data, Some(vec![]))?;
+        let depends: Vec<String> = Self::get_array_value("depends", &data, Some(vec![]))?;
+        let secrets: Vec<String> = Self::get_array_value("secrets", data, Some(vec![]))?;
 
         let enum_ttype: TType;
         match ttype.as_str() {
@@ -103,6 +109,7 @@ impl WsTaskData {
             ttype: enum_ttype,
             disabled,
             docker,
+            container,
             condition,
             build_dir: task_build_dir,
             build,
@@ -110,22 +117,30 @@ impl WsTaskData {
             recipes,
             description,
             env,
+            depends,
+            secrets,
         })
     }
 
     pub fn expand_ctx(&mut self, ctx: &Context) -> Result<(), BError> {
-        self.name = ctx.expand_str(&self.name)?;
-        self.build_dir = ctx.expand_path(&self.build_dir)?;
-        self.build = ctx.expand_str(&self.build)?;
-        self.clean = ctx.expand_str(&self.clean)?;
-        self.condition = ctx.expand_str(&self.condition)?;
-        self.disabled = ctx.expand_str(&self.disabled)?;
-        self.description = ctx.expand_str(&self.description)?;
-        for r in self.recipes.iter_mut() {
-            *r = ctx.expand_str(r)?;
+        self.name = ctx.expand_key("name", &self.name)?;
+        self.build_dir = ctx.expand_path("builddir", &self.build_dir)?;
+        self.build = ctx.expand_key("build", &self.build)?;
+        self.clean = ctx.expand_key("clean", &self.clean)?;
+        self.condition = ctx.expand_key("condition", &self.condition)?;
+        self.disabled = ctx.expand_key("disabled", &self.disabled)?;
+        self.description = ctx.expand_key("description", &self.description)?;
+        self.container
+            .expand_ctx(ctx)
+            .map_err(|err| Context::key_error(err, "container"))?;
+        for (i, r) in self.recipes.iter_mut().enumerate() {
+            *r = ctx.expand_key(&format!("recipes[{}]", i), r)?;
         }
-        for (_key, value) in self.env.iter_mut() {
-            *value = ctx.expand_str(value)?;
+        for (key, value) in self.env.iter_mut() {
+            *value = ctx.expand_key(&format!("env.{}", key), value)?;
+        }
+        for (i, d) in self.depends.iter_mut().enumerate() {
+            *d = ctx.expand_key(&format!("depends[{}]", i), d)?;
         }
         Ok(())
     }
@@ -157,6 +172,10 @@ impl WsTaskData {
         &self.docker
     }
 
+    pub fn container(&self) -> &WsContainerData {
+        &self.container
+    }
+
     pub fn condition(&self) -> bool {
         let condition: &str = &self.condition;
 
@@ -189,6 +208,45 @@ impl WsTaskData {
     pub fn env(&self) -> &IndexMap<String, String> {
         &self.env
     }
+
+    pub fn depends(&self) -> &Vec<String> {
+        &self.depends
+    }
+
+    pub fn secrets(&self) -> &Vec<String> {
+        &self.secrets
+    }
+
+    /*
+     * The expanded task definition, it is used to determine if the
+     * task has changed since the last time it was built
+     */
+    pub fn to_string(&self) -> String {
+        let ttype: &str = match self.ttype {
+            TType::Bitbake => "bitbake",
+            TType::NonBitbake => "non-bitbake",
+        };
+        let env: Vec<String> = self
+            .env
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        json!({
+            "index": self.index.to_string(),
+            "name": self.name,
+            "type": ttype,
+            "builddir": self.build_dir.to_string_lossy(),
+            "docker": self.docker,
+            "container": self.container.docker_args(),
+            "build": self.build,
+            "clean": self.clean,
+            "recipes": self.recipes,
+            "env": env,
+            "depends": self.depends,
+            "secrets": self.secrets,
+        })
+        .to_string()
+    }
 }
 
 #[cfg(test)]
@@ -214,7 +272,10 @@ mod tests {
             "builddir": "test/builddir",
             "docker": "test-registry/test-image:0.1",
             "build": "build-cmd",
-            "clean": "clean-cmd"
+            "clean": "clean-cmd",
+            "depends": [
+                "task0-name"
+            ]
         }"#;
         let work_dir: PathBuf = PathBuf::from("/workspace");
         let bb_build_dir: PathBuf = work_dir.clone().join(String::from("test/builddir"));
@@ -231,6 +292,7 @@ mod tests {
         assert_eq!(task.build_cmd(), "build-cmd");
         assert_eq!(task.clean_cmd(), "clean-cmd");
         assert_eq!(task.docker_image(), "test-registry/test-image:0.1");
+        assert_eq!(task.depends(), &vec![String::from("task0-name")]);
     }
 
     #[test]
@@ -262,6 +324,7 @@ mod tests {
         assert_eq!(task.clean_cmd(), "");
         assert_eq!(task.docker_image(), "");
         assert_eq!(task.description(), "NA");
+        assert!(task.depends().is_empty());
         assert_eq!(
             task.recipes(),
             &vec![String::from("test-image"), String::from("test-image:sdk")]
@@ -15,7 +15,7 @@ pub enum BError {
     ParseManifestError(String),
     #[error("Failed to parse JSON. '{0}'")]
     JsonParseError(String),
-    #[error("The build config version '{0}' is not compatible with current bakery version. Update config to match the format of version '6'")]
+    #[error("The build config version '{0}' is not compatible with current bakery version. Update config to match the format of version '6' using 'bakery migrate'")]
     InvalidBuildConfigError(String),
     #[error("{0}")]
     IOError(String),
@@ -33,10 +33,18 @@ pub enum BError {
     CtxKeyError(String),
     #[error("{0}")]
     CollectorError(String),
-    #[error("Docker is enabled for the workspace but cannot locate /usr/bin/docker")]
-    DockerError(),
+    #[error("{0}")]
+    ChecksumError(String),
+    #[error("{0}")]
+    ValidateError(String),
+    #[error("Docker is enabled for the workspace but cannot locate '{0}'")]
+    DockerError(String),
     #[error("{0}")]
     DockerImageError(String),
+    #[error("{0}")]
+    DockerApiError(String),
+    #[error("{0}")]
+    TaskError(String),
 }
 
 impl std::convert::From<serde_json::Error> for BError {
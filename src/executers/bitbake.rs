@@ -2,10 +2,11 @@ use crate::cli::Cli;
 use crate::data::{WsBitbakeData, WsTaskData};
 use crate::error::BError;
 use crate::executers::{Docker, DockerImage, Recipe, TaskExecuter};
-use crate::fs::BitbakeConf;
+use crate::fs::{BBFailures, BitbakeConf};
 
 use indexmap::IndexMap;
 use std::collections::HashMap;
+use std::time::SystemTime;
 
 pub struct BBCleanExecuter<'a> {
     bb_data: &'a WsBitbakeData,
@@ -98,7 +99,7 @@ impl<'a> TaskExecuter for BBBuildExecuter<'a> {
         // When not a dry run it will be determined if it is needed or not to
         // regenerate the bb configs based on the content of the existing configs
         // comparted to the new content
-        let conf: BitbakeConf = BitbakeConf::new(self.bb_data, self.bb_variables, force);
+        let conf: BitbakeConf = BitbakeConf::new(self.bb_data, self.bb_variables, force)?;
         conf.create_bitbake_configs(self.cli)?;
 
         if dry_run {
@@ -109,7 +110,9 @@ impl<'a> TaskExecuter for BBBuildExecuter<'a> {
             ]);
             if !docker_str.is_empty() {
                 let image: DockerImage = DockerImage::new(docker_str)?;
-                let docker: Docker = Docker::new(image, interactive);
+                let mut docker: Docker = Docker::new(image, interactive);
+                docker.set_container(self.task_data.container());
+                docker.set_cache_volumes(&self.bb_data.cache_volumes());
                 docker.run_cmd(&mut cmd_line, &env, &exec_dir, &self.cli)?;
             } else {
                 self.cli.check_call(&cmd_line, &env, true)?;
@@ -150,12 +153,18 @@ impl<'a> TaskExecuter for BBBuildExecuter<'a> {
             }
             */
 
-            if !docker_str.is_empty() {
+            let started: SystemTime = SystemTime::now();
+            let result: Result<(), BError> = if !docker_str.is_empty() {
                 let image: DockerImage = DockerImage::new(docker_str)?;
-                let docker: Docker = Docker::new(image, interactive);
-                docker.run_cmd(&mut cmd_line, &env, &exec_dir, &self.cli)?;
+                let mut docker: Docker = Docker::new(image, interactive);
+                docker.set_container(self.task_data.container());
+                docker.set_cache_volumes(&self.bb_data.cache_volumes());
+                docker.run_cmd(&mut cmd_line, &env, &exec_dir, &self.cli)
             } else {
-                self.cli.check_call(&cmd_line, &env, true)?;
+                self.cli.check_call(&cmd_line, &env, true)
+            };
+            if let Err(err) = result {
+                return Err(self.bb_failure(err, started));
             }
         }
         Ok(())
@@ -163,6 +172,19 @@ impl<'a> TaskExecuter for BBBuildExecuter<'a> {
 }
 
 impl<'a> BBBuildExecuter<'a> {
+    /*
+     * When bitbake fails the exit status is not telling much, summarize the
+     * failed recipes and tasks from the bitbake logs and add it to the error
+     */
+    fn bb_failure(&self, err: BError, started: SystemTime) -> BError {
+        match BBFailures::from_build_dir(&self.bb_data.build_dir(), started) {
+            Ok(failures) if !failures.is_empty() => {
+                BError::TaskError(format!("{}\n{}", err, failures.summary()))
+            }
+            _ => err,
+        }
+    }
+
     fn bb_build_env(
         &self,
         args_env_variables: &HashMap<String, String>,
@@ -241,6 +263,7 @@ mod tests {
 
     use crate::cli::*;
     use crate::data::{WsBuildData, WsTaskData};
+    use crate::error::BError;
     use crate::executers::{BBBuildExecuter, BBCleanExecuter, TaskExecuter};
     use crate::helper::Helper;
 
@@ -1098,4 +1121,138 @@ mod tests {
             .exec(&env_variables, true, true)
             .expect("Failed to execute task");
     }
+
+    #[test]
+    fn test_bitbake_executer_failure() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = temp_dir.into_path();
+        let build_dir: PathBuf = work_dir.join("builds/default");
+        let bb_variables: Vec<String> = vec![];
+        let env_variables: HashMap<String, String> = HashMap::new();
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "default",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "bb": {
+                "machine": "raspberrypi3",
+                "variant": "release",
+                "distro": "strix",
+                "bblayersconf": [
+                    "LCONF_VERSION=\"7\"",
+                    "BBPATH=\"${TOPDIR}\""
+                ],
+                "localconf": [
+                    "BB_NUMBER_THREADS ?= \"${@oe.utils.cpu_count()}\"",
+                    "PARALLEL_MAKE ?= \"-j ${@oe.utils.cpu_count()}\""
+                ]
+            }
+        }"#;
+        let json_task_config: &str = r#"
+        {
+            "index": "0",
+            "name": "task1-name",
+            "recipes": [
+                "test-image"
+            ]
+        }"#;
+        let data: WsBuildData = Helper::setup_build_data(&work_dir, Some(json_build_config), None);
+        let task_data: WsTaskData =
+            WsTaskData::from_str(json_task_config, &data).expect("Failed to parse task config");
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Autogenerate {}",
+                data.bitbake().local_conf_path().display()
+            )))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Autogenerate {}",
+                data.bitbake().bblayers_conf_path().display()
+            )))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "source init env file {}",
+                data.bitbake().init_env_file().display()
+            )))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "execute bitbake build task '{}'",
+                task_data.name()
+            )))
+            .once()
+            .returning(|_x| ());
+        let console_dir: PathBuf = build_dir.join("tmp/log/cooker/raspberrypi3");
+        let mut mocked_system: MockSystem = MockSystem::new();
+        mocked_system
+            .expect_check_call()
+            .with(mockall::predicate::eq(CallParams {
+                cmd_line: vec![
+                    "cd",
+                    &build_dir.to_string_lossy().to_string(),
+                    "&&",
+                    "devtool",
+                    "create-workspace",
+                    "&&",
+                    "bitbake",
+                    "test-image",
+                ]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+                env: HashMap::from([(
+                    String::from("BB_ENV_PASSTHROUGH_ADDITIONS"),
+                    String::from("SSTATE_DIR DL_DIR TMPDIR"),
+                )]),
+                shell: true,
+            }))
+            .once()
+            .returning(move |_x| {
+                /* Bitbake is writing the console log before it fails */
+                let cooker_dir: PathBuf = console_dir.clone();
+                std::fs::create_dir_all(&cooker_dir).expect("Failed to create cooker dir");
+                std::fs::write(
+                    cooker_dir.join("console-latest.log"),
+                    "ERROR: Logfile of failure stored in: /build/tmp/work/armv8a-poky-linux/foo/1.0-r0/temp/log.do_compile.42\n\
+                     ERROR: Task (/layers/meta-foo/recipes-foo/foo/foo_1.0.bb:do_compile) failed with exit code '1'\n",
+                )
+                .expect("Failed to write console log");
+                Err(BError::CliError(String::from("exit status: 1")))
+            });
+        mocked_system
+            .expect_init_env_file()
+            .returning(|_x, _y| Ok(HashMap::new()));
+        let cli: Cli = Cli::new(
+            Box::new(mocked_logger),
+            Box::new(mocked_system),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery"]),
+        );
+        let executer: BBBuildExecuter =
+            BBBuildExecuter::new(&cli, &task_data, data.bitbake(), &bb_variables);
+        let result = executer.exec(&env_variables, false, true);
+        match result {
+            Ok(_status) => {
+                panic!("We should have recived an error because bitbake failed");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "exit status: 1\nBitbake failed, 1 task(s) failed:\n  foo do_compile (/layers/meta-foo/recipes-foo/foo/foo_1.0.bb)\n    log: /build/tmp/work/armv8a-poky-linux/foo/1.0-r0/temp/log.do_compile.42"
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,438 @@
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::cli::Cli;
+use crate::error::BError;
+use crate::executers::{Docker, DockerApi};
+
+pub const LABEL_WORKSPACE: &str = "bakery.workspace";
+pub const LABEL_IMAGE: &str = "bakery.image";
+pub const LABEL_ARGS: &str = "bakery.args";
+pub const LABEL_VOLUMES: &str = "bakery.volumes";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContainerStatus {
+    /* There is no workspace container */
+    Missing,
+    /* The workspace container exists but is not running */
+    Stopped,
+    /* The workspace container is running the image of the workspace */
+    Running,
+    /* The workspace container is running an image that is no longer used by the workspace */
+    Outdated(String),
+}
+
+/*
+ * A long-lived container for the workspace. Once started bakery will exec
+ * into the running container instead of starting a new container for every
+ * command which removes the startup latency of small commands. The container
+ * is keyed by the workspace path and the image is stored as a label so that
+ * the container can be recreated when the image of the workspace changes.
+ */
+pub struct WsContainer<'a> {
+    docker: &'a Docker,
+    api: DockerApi,
+    work_dir: PathBuf,
+}
+
+impl<'a> WsContainer<'a> {
+    pub fn new(docker: &'a Docker, api: DockerApi, work_dir: &Path) -> Self {
+        WsContainer {
+            docker,
+            api,
+            work_dir: work_dir.to_path_buf(),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.work_dir.to_string_lossy().as_bytes());
+        let digest: String = format!("{:x}", hasher.finalize());
+        format!("bakery-workspace-{}", &digest[..12])
+    }
+
+    fn args_hash(docker_args: &[String]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(docker_args.join("\n").as_bytes());
+        let digest: String = format!("{:x}", hasher.finalize());
+        digest[..12].to_string()
+    }
+
+    /*
+     * The docker args and the volumes the container is created with are
+     * stored as labels so that a command that requires other docker args
+     * or more volumes can recreate the container. The labels are passed on
+     * the command line so the docker args are stored as a hash.
+     */
+    pub fn labels(&self, docker_args: &[String], volumes: &[String]) -> Vec<String> {
+        vec![
+            format!("{}={}", LABEL_WORKSPACE, self.work_dir.display()),
+            format!("{}={}", LABEL_IMAGE, self.docker.image()),
+            format!("{}={}", LABEL_ARGS, Self::args_hash(docker_args)),
+            format!("{}={}", LABEL_VOLUMES, volumes.join(",")),
+        ]
+    }
+
+    /*
+     * Returns the volumes to recreate the running container with if it was
+     * created using other docker args or if it is missing any of the volumes,
+     * the volumes of the running container are kept. None is returned if the
+     * running container can be used as is.
+     */
+    pub fn recreate_volumes(
+        &self,
+        docker_args: &[String],
+        volumes: &[String],
+    ) -> Result<Option<Vec<String>>, BError> {
+        let value: Value = match self.api.inspect_container(&self.name())? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let label = |name: &str| -> String {
+            value
+                .pointer("/Config/Labels")
+                .and_then(|l| l.get(name))
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+        let mut recreate: Vec<String> = label(LABEL_VOLUMES)
+            .split(',')
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string())
+            .collect();
+        let missing: Vec<String> = volumes
+            .iter()
+            .filter(|v| !recreate.contains(v))
+            .cloned()
+            .collect();
+        if missing.is_empty() && label(LABEL_ARGS) == Self::args_hash(docker_args) {
+            return Ok(None);
+        }
+        recreate.extend(missing);
+        Ok(Some(recreate))
+    }
+
+    pub fn status(&self) -> Result<ContainerStatus, BError> {
+        /*
+         * Without the socket there is no way of knowing if the container is
+         * running so we treat it as if there is no workspace container
+         */
+        if !self.api.available() {
+            return Ok(ContainerStatus::Missing);
+        }
+        let value: Value = match self.api.inspect_container(&self.name())? {
+            Some(v) => v,
+            None => return Ok(ContainerStatus::Missing),
+        };
+        let running: bool = value
+            .pointer("/State/Running")
+            .and_then(|r| r.as_bool())
+            .unwrap_or(false);
+        if !running {
+            return Ok(ContainerStatus::Stopped);
+        }
+        let image: String = value
+            .pointer("/Config/Labels")
+            .and_then(|l| l.get(LABEL_IMAGE))
+            .and_then(|i| i.as_str())
+            .unwrap_or("")
+            .to_string();
+        if image != self.docker.image().to_string() {
+            return Ok(ContainerStatus::Outdated(image));
+        }
+        Ok(ContainerStatus::Running)
+    }
+
+    pub fn start(
+        &self,
+        cli: &Cli,
+        docker_top_dir: &PathBuf,
+        docker_args: &[String],
+        volumes: &Vec<String>,
+    ) -> Result<(), BError> {
+        if self.status()? != ContainerStatus::Missing {
+            self.api.remove_container(&self.name())?;
+        }
+        self.docker.check_call(
+            cli,
+            &self.api,
+            &self.docker.container_cmd_line(
+                &self.name(),
+                &self.labels(docker_args, volumes),
+                docker_top_dir,
+                &self.work_dir,
+                docker_args,
+                volumes,
+            ),
+            &HashMap::new(),
+        )
+    }
+
+    /*
+     * Stop the container before removing it so that the processes in the
+     * container are given a chance to exit cleanly
+     */
+    pub fn stop(&self) -> Result<(), BError> {
+        self.api.stop_container(&self.name())?;
+        self.api.remove_container(&self.name())
+    }
+
+    pub fn exec(
+        &self,
+        cmd_line: &[String],
+        cli: &Cli,
+        env: &HashMap<String, String>,
+        secrets: &[String],
+    ) -> Result<(), BError> {
+        self.docker.check_call(
+            cli,
+            &self.api,
+            &self
+                .docker
+                .exec_cmd_line(&self.name(), cmd_line, &self.work_dir, secrets),
+            env,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    use crate::cli::*;
+    use crate::executers::container::{ContainerStatus, WsContainer};
+    use crate::executers::{Docker, DockerApi, DockerImage};
+    use crate::helper::Helper;
+
+    fn inspect_response(running: bool, image: &str) -> Vec<u8> {
+        Helper::http_response(
+            200,
+            format!(
+                r#"{{"Id": "1234", "State": {{"Running": {}}}, "Config": {{"Labels": {{"bakery.image": "{}"}}}}}}"#,
+                running, image
+            )
+            .into_bytes(),
+            false,
+        )
+    }
+
+    #[test]
+    fn test_ws_container_name() {
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let docker: Docker = Docker::new(image, false);
+        let work_dir: PathBuf = PathBuf::from("/test/work");
+        let container: WsContainer = WsContainer::new(
+            &docker,
+            DockerApi::new(&PathBuf::from("/test/docker.sock")),
+            &work_dir,
+        );
+        let name: String = container.name();
+        assert!(name.starts_with("bakery-workspace-"));
+        assert_eq!(name.len(), "bakery-workspace-".len() + 12);
+        assert_eq!(
+            name,
+            WsContainer::new(
+                &docker,
+                DockerApi::new(&PathBuf::from("/test/docker.sock")),
+                &work_dir
+            )
+            .name()
+        );
+        assert_ne!(
+            name,
+            WsContainer::new(
+                &docker,
+                DockerApi::new(&PathBuf::from("/test/docker.sock")),
+                &PathBuf::from("/test/other")
+            )
+            .name()
+        );
+        assert_eq!(
+            container.labels(
+                &[String::from("--cpus 2")],
+                &[String::from("/a:/a"), String::from("/b:/b")]
+            ),
+            vec![
+                String::from("bakery.workspace=/test/work"),
+                String::from("bakery.image=test-registry/test-image:0.1"),
+                String::from("bakery.args=6bd272ec528e"),
+                String::from("bakery.volumes=/a:/a,/b:/b"),
+            ]
+        );
+        /* Without a socket there is no workspace container */
+        assert_eq!(
+            container.status().expect("Failed to get status"),
+            ContainerStatus::Missing
+        );
+    }
+
+    #[test]
+    fn test_ws_container_status() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from("/test/work");
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let docker: Docker = Docker::new(image, false);
+        let name: String = WsContainer::new(
+            &docker,
+            DockerApi::new(&temp_dir.path().join("none.sock")),
+            &work_dir,
+        )
+        .name();
+        let route: String = format!("GET /containers/{}/json", name);
+        let cases: Vec<(Vec<u8>, ContainerStatus)> = vec![
+            (
+                inspect_response(true, "test-registry/test-image:0.1"),
+                ContainerStatus::Running,
+            ),
+            (
+                inspect_response(false, "test-registry/test-image:0.1"),
+                ContainerStatus::Stopped,
+            ),
+            (
+                inspect_response(true, "test-registry/test-image:0.0"),
+                ContainerStatus::Outdated(String::from("test-registry/test-image:0.0")),
+            ),
+        ];
+        for (i, (response, status)) in cases.into_iter().enumerate() {
+            let socket: PathBuf = temp_dir.path().join(format!("docker{}.sock", i));
+            let _requests = Helper::docker_api_stub(&socket, vec![(route.as_str(), response)]);
+            let container: WsContainer =
+                WsContainer::new(&docker, DockerApi::new(&socket), &work_dir);
+            assert_eq!(container.status().expect("Failed to get status"), status);
+        }
+        let socket: PathBuf = temp_dir.path().join("missing.sock");
+        let _requests = Helper::docker_api_stub(&socket, vec![]);
+        let container: WsContainer = WsContainer::new(&docker, DockerApi::new(&socket), &work_dir);
+        assert_eq!(
+            container.status().expect("Failed to get status"),
+            ContainerStatus::Missing
+        );
+    }
+
+    #[test]
+    fn test_ws_container_recreate() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from("/test/work");
+        let socket: PathBuf = temp_dir.path().join("docker.sock");
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.2").expect("Invalid docker image format");
+        let docker: Docker = Docker::new(image, false);
+        let container: WsContainer = WsContainer::new(&docker, DockerApi::new(&socket), &work_dir);
+        let name: String = container.name();
+        let requests = Helper::docker_api_stub(
+            &socket,
+            vec![
+                (
+                    format!("GET /containers/{}/json", name).as_str(),
+                    inspect_response(true, "test-registry/test-image:0.1"),
+                ),
+                (
+                    format!("DELETE /containers/{}", name).as_str(),
+                    Helper::http_response(204, vec![], false),
+                ),
+                (
+                    "GET /_ping",
+                    Helper::http_response(200, b"OK".to_vec(), false),
+                ),
+                (
+                    format!("POST /containers/create?name={}", name).as_str(),
+                    Helper::http_response(201, br#"{"Id": "abc123"}"#.to_vec(), false),
+                ),
+                (
+                    "POST /containers/abc123/start",
+                    Helper::http_response(204, vec![], false),
+                ),
+            ],
+        );
+        /* The container is created and started using the docker engine API */
+        let cli: Cli = Cli::new(
+            Box::new(MockLogger::new()),
+            Box::new(MockSystem::new()),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery"]),
+        );
+        container
+            .start(&cli, &PathBuf::from("/test"), &[], &vec![])
+            .expect("Failed to start container");
+        /* The container is running an old image so it should be removed before started */
+        assert_eq!(
+            requests.lock().unwrap().clone(),
+            vec![
+                format!("GET /containers/{}/json", name),
+                format!("DELETE /containers/{}?force=true", name),
+                String::from("GET /_ping"),
+                format!("POST /containers/create?name={}", name),
+                String::from("POST /containers/abc123/start"),
+            ]
+        );
+        assert_eq!(
+            cli.take_calls(),
+            vec![docker.container_cmd_line(
+                &name,
+                &container.labels(&[], &[]),
+                &PathBuf::from("/test"),
+                &work_dir,
+                &[],
+                &vec![],
+            )]
+        );
+    }
+
+    #[test]
+    fn test_ws_container_recreate_volumes() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from("/test/work");
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let docker: Docker = Docker::new(image, false);
+        let name: String = WsContainer::new(
+            &docker,
+            DockerApi::new(&temp_dir.path().join("none.sock")),
+            &work_dir,
+        )
+        .name();
+        let socket: PathBuf = temp_dir.path().join("docker.sock");
+        let _requests = Helper::docker_api_stub(
+            &socket,
+            vec![(
+                format!("GET /containers/{}/json", name).as_str(),
+                Helper::http_response(
+                    200,
+                    br#"{"State": {"Running": true}, "Config": {"Labels": {"bakery.args": "e3b0c44298fc", "bakery.volumes": "/a:/a"}}}"#.to_vec(),
+                    false,
+                ),
+            )],
+        );
+        let container: WsContainer = WsContainer::new(&docker, DockerApi::new(&socket), &work_dir);
+        /* The running container has the volume and was started without docker args */
+        assert_eq!(
+            container
+                .recreate_volumes(&[], &[String::from("/a:/a")])
+                .expect("Failed to inspect container"),
+            None
+        );
+        /* A missing volume is added to the volumes of the running container */
+        assert_eq!(
+            container
+                .recreate_volumes(&[], &[String::from("/b:/b")])
+                .expect("Failed to inspect container"),
+            Some(vec![String::from("/a:/a"), String::from("/b:/b")])
+        );
+        /* Other docker args requires the container to be recreated */
+        assert_eq!(
+            container
+                .recreate_volumes(&[String::from("--cpus 2")], &[])
+                .expect("Failed to inspect container"),
+            Some(vec![String::from("/a:/a")])
+        );
+    }
+}
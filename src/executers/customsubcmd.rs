@@ -1,7 +1,7 @@
 use crate::cli::Cli;
 use crate::data::WsCustomSubCmdData;
 use crate::error::BError;
-use crate::executers::TaskExecuter;
+use crate::executers::{Docker, DockerImage, TaskExecuter};
 
 use std::collections::HashMap;
 
@@ -15,7 +15,7 @@ impl<'a> TaskExecuter for CustomSubCmdExecuter<'a> {
         &self,
         env_variables: &HashMap<String, String>,
         dry_run: bool,
-        _interactive: bool,
+        interactive: bool,
     ) -> Result<(), BError> {
         let cmd: Vec<String> = self.data.cmd().split(' ').map(|c| c.to_string()).collect();
 
@@ -24,6 +24,13 @@ impl<'a> TaskExecuter for CustomSubCmdExecuter<'a> {
             return Ok(());
         }
 
+        if !self.data.docker_image().is_empty() && self.data.docker_image() != "NA" {
+            let image: DockerImage = DockerImage::new(self.data.docker_image())?;
+            let mut docker: Docker = Docker::new(image, interactive);
+            docker.set_container(self.data.container());
+            return docker.run_cmd(&cmd, env_variables, &std::env::current_dir()?, self.cli);
+        }
+
         self.cli.check_call(&cmd, env_variables, true)
     }
 }
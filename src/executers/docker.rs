@@ -1,17 +1,26 @@
+use serde_json::{json, Map, Value};
 use std::collections::HashMap;
 use std::fmt;
 use std::fs::File;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
 use std::path::{Path, PathBuf};
 use tempdir::TempDir;
-use users::Groups;
 
 use crate::cli::Cli;
+use crate::data::WsContainerData;
 use crate::error::BError;
+use crate::executers::dockerapi::STREAM_STDERR;
+use crate::executers::{ContainerRuntime, DockerApi};
+use crate::fs::Stamp;
+
+pub const LABEL_CONTEXT: &str = "bakery.context";
 
 pub struct Docker {
     image: DockerImage,
     _interactive: bool,
+    runtime: ContainerRuntime,
+    container: Vec<String>,
+    cache: Vec<String>,
 }
 
 #[derive(Clone)]
@@ -63,6 +72,287 @@ impl DockerImage {
     }
 }
 
+/*
+ * The options of a docker run or docker exec command line translated into a
+ * config for the docker engine API. When using the docker CLI the command
+ * line is run by bash so the options are split on whitespace and the env
+ * variables are expanded before they are translated. The command is run by
+ * bash inside the container.
+ */
+#[derive(Default)]
+struct DockerOptions {
+    name: String,
+    target: String,
+    cmd: String,
+    detach: bool,
+    tty: bool,
+    work_dir: String,
+    user: String,
+    env: Vec<String>,
+    binds: Vec<String>,
+    labels: Map<String, Value>,
+    group_add: Vec<String>,
+    devices: Vec<Value>,
+    host_config: Map<String, Value>,
+}
+
+impl DockerOptions {
+    /*
+     * Returns None if the command line is using an option or shell quoting
+     * that is not supported by the translation
+     */
+    fn parse(cmd_line: &[String], env: &HashMap<String, String>) -> Result<Option<Self>, BError> {
+        let mut options: DockerOptions = DockerOptions::default();
+        let mut words = cmd_line
+            .iter()
+            .skip(2)
+            .flat_map(|c| c.split_whitespace())
+            .map(|w| w.to_string());
+        while let Some(word) = words.next() {
+            if !word.starts_with('-') {
+                options.target = Self::expand(&word, env);
+                break;
+            }
+            if Self::quoted(&word) {
+                return Ok(None);
+            }
+            let word: String = Self::expand(&word, env);
+            let (opt, inline): (String, Option<String>) = match word.split_once('=') {
+                Some((o, v)) if o.starts_with("--") => (o.to_string(), Some(v.to_string())),
+                _ => (word.clone(), None),
+            };
+            match opt.as_str() {
+                "-t" | "--tty" | "-it" | "-ti" => options.tty = true,
+                /* The stdin is not forwarded by the API client */
+                "-i" | "--interactive" | "--rm" => {}
+                "-d" | "--detach" => options.detach = true,
+                "--privileged" => {
+                    options
+                        .host_config
+                        .insert("Privileged".to_string(), json!(true));
+                }
+                "--init" => {
+                    options.host_config.insert("Init".to_string(), json!(true));
+                }
+                _ => {
+                    let value: String = match inline {
+                        Some(v) => v,
+                        None => match words.next() {
+                            Some(v) if !Self::quoted(&v) => Self::expand(&v, env),
+                            _ => return Ok(None),
+                        },
+                    };
+                    if !options.set(&opt, value, env)? {
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+        if options.target.is_empty() {
+            return Ok(None);
+        }
+        options.cmd = words.collect::<Vec<String>>().join(" ");
+        Ok(Some(options))
+    }
+
+    fn quoted(word: &str) -> bool {
+        word.contains(['\'', '"', '`', '\\'])
+    }
+
+    /*
+     * Expand $VAR and ${VAR} the same way bash would, a variable that is not
+     * set is expanded to an empty string
+     */
+    fn expand(word: &str, env: &HashMap<String, String>) -> String {
+        let mut result: String = String::new();
+        let mut chars = word.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '$' {
+                result.push(c);
+                continue;
+            }
+            let braces: bool = chars.peek() == Some(&'{');
+            if braces {
+                chars.next();
+            }
+            let mut name: String = String::new();
+            while let Some(n) = chars.peek() {
+                if !(n.is_ascii_alphanumeric() || *n == '_') {
+                    break;
+                }
+                name.push(*n);
+                chars.next();
+            }
+            if braces {
+                chars.next();
+            } else if name.is_empty() {
+                result.push(c);
+                continue;
+            }
+            result.push_str(env.get(&name).map(|v| v.as_str()).unwrap_or(""));
+        }
+        result
+    }
+
+    /*
+     * The size in bytes of a docker memory option like 512m or 2g
+     */
+    fn bytes(value: &str) -> Option<i64> {
+        let lower: String = value.to_lowercase();
+        let mut number: &str = lower.trim_end_matches('b');
+        let mut unit: i64 = 1;
+        for (suffix, size) in [
+            ("k", 1i64 << 10),
+            ("m", 1 << 20),
+            ("g", 1 << 30),
+            ("t", 1 << 40),
+        ] {
+            if let Some(n) = number.strip_suffix(suffix) {
+                number = n;
+                unit = size;
+                break;
+            }
+        }
+        number.parse::<f64>().ok().map(|n| (n * unit as f64) as i64)
+    }
+
+    /*
+     * An env variable without a value is read from the env like docker does,
+     * if it is not set it is skipped
+     */
+    fn add_env(&mut self, value: &str, env: &HashMap<String, String>) {
+        if value.contains('=') {
+            self.env.push(value.to_string());
+        } else if let Some(v) = env.get(value) {
+            self.env.push(format!("{}={}", value, v));
+        }
+    }
+
+    fn set(
+        &mut self,
+        opt: &str,
+        value: String,
+        env: &HashMap<String, String>,
+    ) -> Result<bool, BError> {
+        match opt {
+            "-v" | "--volume" => self.binds.push(value),
+            "-w" | "--workdir" => self.work_dir = value,
+            "-u" | "--user" => self.user = value,
+            "-e" | "--env" => self.add_env(&value, env),
+            "--env-file" => {
+                for line in std::fs::read_to_string(&value)?.lines() {
+                    let line: &str = line.trim();
+                    if !line.is_empty() && !line.starts_with('#') {
+                        self.add_env(line, env);
+                    }
+                }
+            }
+            "--name" => self.name = value,
+            "-l" | "--label" => {
+                let (key, v) = value.split_once('=').unwrap_or((&value, ""));
+                self.labels.insert(key.to_string(), json!(v));
+            }
+            "--group-add" => self.group_add.push(value),
+            "--userns" => {
+                self.host_config
+                    .insert("UsernsMode".to_string(), json!(value));
+            }
+            "--network" | "--net" => {
+                self.host_config
+                    .insert("NetworkMode".to_string(), json!(value));
+            }
+            "--device" => {
+                let parts: Vec<&str> = value.split(':').collect();
+                self.devices.push(json!({
+                    "PathOnHost": parts[0],
+                    "PathInContainer": parts.get(1).unwrap_or(&parts[0]),
+                    "CgroupPermissions": parts.get(2).unwrap_or(&"rwm"),
+                }));
+            }
+            "--cpus" => match value.parse::<f64>() {
+                Ok(cpus) => {
+                    self.host_config
+                        .insert("NanoCpus".to_string(), json!((cpus * 1e9) as i64));
+                }
+                Err(_e) => return Ok(false),
+            },
+            "-m" | "--memory" | "--shm-size" => match Self::bytes(&value) {
+                Some(size) => {
+                    let key: &str = if opt == "--shm-size" {
+                        "ShmSize"
+                    } else {
+                        "Memory"
+                    };
+                    self.host_config.insert(key.to_string(), json!(size));
+                }
+                None => return Ok(false),
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn cmd(&self) -> Option<Value> {
+        if self.cmd.is_empty() {
+            return None;
+        }
+        Some(json!(["/bin/bash", "-c", self.cmd]))
+    }
+
+    /*
+     * The config used when creating a container from the image
+     */
+    fn config(&self) -> Value {
+        let mut host_config: Map<String, Value> = self.host_config.clone();
+        host_config.insert("Binds".to_string(), json!(self.binds));
+        if !self.group_add.is_empty() {
+            host_config.insert("GroupAdd".to_string(), json!(self.group_add));
+        }
+        if !self.devices.is_empty() {
+            host_config.insert("Devices".to_string(), json!(self.devices));
+        }
+        let mut config: Value = json!({
+            "Image": self.target,
+            "Env": self.env,
+            "Tty": self.tty,
+            "AttachStdout": !self.detach,
+            "AttachStderr": !self.detach,
+            "Labels": self.labels,
+            "HostConfig": host_config,
+        });
+        if let Some(cmd) = self.cmd() {
+            config["Cmd"] = cmd;
+        }
+        if !self.work_dir.is_empty() {
+            config["WorkingDir"] = json!(self.work_dir);
+        }
+        if !self.user.is_empty() {
+            config["User"] = json!(self.user);
+        }
+        config
+    }
+
+    /*
+     * The config used when running the command in a running container
+     */
+    fn exec_config(&self) -> Value {
+        let mut config: Value = json!({
+            "Cmd": self.cmd().unwrap_or(json!(["/bin/bash"])),
+            "Env": self.env,
+            "Tty": self.tty,
+            "AttachStdout": true,
+            "AttachStderr": true,
+        });
+        if !self.work_dir.is_empty() {
+            config["WorkingDir"] = json!(self.work_dir);
+        }
+        if !self.user.is_empty() {
+            config["User"] = json!(self.user);
+        }
+        config
+    }
+}
+
 /*
  * TODO: we should convert Docker into a TaskExecuter
  */
@@ -82,10 +372,7 @@ impl Docker {
     }
 
     fn user(&self) -> Vec<String> {
-        vec![
-            String::from("-u"),
-            format!("{}:{}", users::get_current_uid(), users::get_current_gid()),
-        ]
+        self.runtime.user()
     }
 
     fn etc_files(&self) -> Vec<String> {
@@ -135,18 +422,11 @@ impl Docker {
     }
 
     fn docker_sock(&self) -> Vec<String> {
-        vec![
-            String::from("-v"),
-            String::from("/var/run/docker.sock:/var/run/docker.sock"),
-        ]
+        self.runtime.socket_volume()
     }
 
     fn group(&self) -> Vec<String> {
-        let cache: users::UsersCache = users::UsersCache::new();
-        vec![
-            String::from("--group-add"),
-            cache.get_group_by_name("docker").unwrap().gid().to_string(),
-        ]
+        self.runtime.group()
     }
 
     fn env_file(&self, env_file: &PathBuf) -> Vec<String> {
@@ -193,10 +473,40 @@ impl Docker {
         &self.image
     }
 
+    pub fn runtime(&self) -> &ContainerRuntime {
+        &self.runtime
+    }
+
+    pub fn set_runtime(&mut self, runtime: ContainerRuntime) {
+        self.runtime = runtime;
+    }
+
+    /*
+     * Container options like resource limits, devices and extra volumes
+     * for a specific task or custom sub-command
+     */
+    pub fn set_container(&mut self, container: &WsContainerData) {
+        self.container = container.docker_args();
+    }
+
+    /*
+     * The shared cache dirs from the workspace settings so that a task
+     * running bitbake in its own docker image can use them
+     */
+    pub fn set_cache_volumes(&mut self, volumes: &[String]) {
+        self.cache = vec![];
+        volumes.iter().for_each(|v| {
+            self.cache.append(&mut vec![String::from("-v"), v.clone()]);
+        });
+    }
+
     pub fn new(image: DockerImage, interactive: bool) -> Self {
         Docker {
             image,
             _interactive: interactive,
+            runtime: ContainerRuntime::Docker,
+            container: vec![],
+            cache: vec![],
         }
     }
 
@@ -208,7 +518,8 @@ impl Docker {
         docker_args: &Vec<String>,
         volumes: &Vec<String>,
     ) -> Vec<String> {
-        let mut docker_cmd: Vec<String> = vec!["docker".to_string(), "run".to_string()];
+        let mut docker_cmd: Vec<String> =
+            vec![self.runtime.binary().to_string(), "run".to_string()];
         docker_cmd.append(&mut self.container_name("bakery-workspace"));
         docker_cmd.append(&mut vec!["-t".to_string(), "--rm".to_string()]);
         if self._interactive {
@@ -227,13 +538,85 @@ impl Docker {
         docker_cmd
     }
 
+    /*
+     * Start a detached long-lived workspace container that is kept running
+     * so that bakery can exec into it instead of starting a new container
+     * for every command
+     */
+    pub fn container_cmd_line(
+        &self,
+        name: &str,
+        labels: &[String],
+        docker_top_dir: &PathBuf,
+        work_dir: &PathBuf,
+        docker_args: &[String],
+        volumes: &Vec<String>,
+    ) -> Vec<String> {
+        let mut docker_cmd: Vec<String> = vec![
+            self.runtime.binary().to_string(),
+            "run".to_string(),
+            "-d".to_string(),
+            "--name".to_string(),
+            name.to_string(),
+        ];
+        labels.iter().for_each(|l| {
+            docker_cmd.append(&mut vec![String::from("--label"), l.to_string()]);
+        });
+        docker_cmd.append(&mut self.group());
+        docker_cmd.append(&mut self.volumes(volumes));
+        docker_cmd.append(&mut self.user());
+        docker_cmd.append(&mut self.top_dir(docker_top_dir));
+        docker_cmd.append(&mut self.work_dir(work_dir));
+        if !docker_args.is_empty() {
+            docker_cmd.append(&mut docker_args.to_vec());
+        }
+        docker_cmd.push(format!("{}", self.image));
+        docker_cmd.append(&mut vec!["sleep".to_string(), "infinity".to_string()]);
+        docker_cmd
+    }
+
+    /*
+     * Like for docker run only the names of the secrets are passed and docker
+     * will read the values from the env of the docker client process
+     */
+    pub fn exec_cmd_line(
+        &self,
+        name: &str,
+        cmd_line: &[String],
+        work_dir: &PathBuf,
+        secrets: &[String],
+    ) -> Vec<String> {
+        let mut docker_cmd: Vec<String> = vec![
+            self.runtime.binary().to_string(),
+            "exec".to_string(),
+            "-t".to_string(),
+        ];
+        if self._interactive {
+            docker_cmd.push("-i".to_string());
+        }
+        docker_cmd.append(&mut self.work_dir(work_dir));
+        secrets.iter().for_each(|s| {
+            docker_cmd.append(&mut vec![String::from("-e"), s.clone()]);
+        });
+        docker_cmd.push(name.to_string());
+        docker_cmd.append(&mut cmd_line.to_vec());
+        docker_cmd
+    }
+
+    /*
+     * The secrets are not written to the env file instead only the names are
+     * passed to docker which will read the values from the env of the docker
+     * client process
+     */
     pub fn cmd_line(
         &self,
-        cmd_line: &Vec<String>,
-        env_file: &PathBuf,
+        cmd_line: &[String],
+        env_file: &Path,
         dir: &PathBuf,
+        secrets: &[String],
     ) -> Vec<String> {
-        let mut docker_cmd: Vec<String> = vec!["docker".to_string(), "run".to_string()];
+        let mut docker_cmd: Vec<String> =
+            vec![self.runtime.binary().to_string(), "run".to_string()];
         docker_cmd.append(&mut self.user());
         docker_cmd.append(&mut self.etc_files());
         docker_cmd.append(&mut self.home_dir());
@@ -243,9 +626,14 @@ impl Docker {
             docker_cmd.push("-i".to_string());
         }
         docker_cmd.append(&mut self.group());
-        docker_cmd.append(&mut self.env_file(env_file));
+        docker_cmd.append(&mut self.env_file(&env_file.to_path_buf()));
+        secrets.iter().for_each(|s| {
+            docker_cmd.append(&mut vec![String::from("-e"), s.clone()]);
+        });
+        docker_cmd.append(&mut self.cache.clone());
+        docker_cmd.append(&mut self.container.clone());
         docker_cmd.push(format!("{}", self.image));
-        docker_cmd.append(&mut cmd_line.clone());
+        docker_cmd.append(&mut cmd_line.to_vec());
         docker_cmd
     }
 
@@ -264,13 +652,161 @@ impl Docker {
         Ok(env_file_path)
     }
 
+    pub fn api(&self) -> DockerApi {
+        DockerApi::new(&self.runtime.socket())
+    }
+
     pub fn pull(&self, cli: &Cli) -> Result<(), BError> {
-        let cmd_line: Vec<String> = vec![
-            "docker".to_string(),
-            "pull".to_string(),
+        let api: DockerApi = self.api();
+        api.pull_image(&self.image, cli)
+    }
+
+    /*
+     * Pull the image using the docker engine API but only if it is not
+     * available locally. If the socket of the runtime is not available we
+     * leave it to the runtime to pull the image when running it.
+     */
+    pub fn pull_if_missing(&self, cli: &Cli) -> Result<(), BError> {
+        let api: DockerApi = self.api();
+        if !api.available() {
+            cli.debug(format!(
+                "Docker socket '{}' is not available",
+                api.socket().display()
+            ));
+            return Ok(());
+        }
+        api.ensure_image(&self.image, cli)
+    }
+
+    pub fn build_cmd_line(&self, docker_dir: &Path, context_hash: &str) -> Vec<String> {
+        vec![
+            self.runtime.binary().to_string(),
+            "build".to_string(),
+            "-t".to_string(),
             format!("{}", self.image),
-        ];
-        cli.check_call(&cmd_line, &HashMap::new(), true)?;
+            "--label".to_string(),
+            format!("{}={}", LABEL_CONTEXT, context_hash),
+            "-f".to_string(),
+            docker_dir.join("Dockerfile").to_string_lossy().to_string(),
+            docker_dir.to_string_lossy().to_string(),
+        ]
+    }
+
+    /*
+     * Build the image from the Dockerfile in the docker dir. The docker dir is
+     * the build context so a hash of all the files in it is stored as a label
+     * on the image, if the image is already built from the same context the
+     * build is skipped unless forced. Hidden files are not part of the hash.
+     * Returns true if the image was built.
+     */
+    pub fn build(
+        &self,
+        cli: &Cli,
+        api: &DockerApi,
+        docker_dir: &Path,
+        force: bool,
+    ) -> Result<bool, BError> {
+        let dockerfile: PathBuf = docker_dir.join("Dockerfile");
+        let hash: String = Stamp::dir_hash(docker_dir, &[]);
+
+        if !force && api.available() {
+            if let Some(value) = api.inspect_image(&self.image)? {
+                let label: &str = value
+                    .pointer("/Config/Labels")
+                    .and_then(|l| l.get(LABEL_CONTEXT))
+                    .and_then(|h| h.as_str())
+                    .unwrap_or("");
+                if label == hash {
+                    return Ok(false);
+                }
+            }
+        }
+
+        cli.info(format!(
+            "Build image '{}' from '{}'",
+            self.image,
+            dockerfile.display()
+        ));
+        cli.check_call(
+            &self.build_cmd_line(docker_dir, &hash),
+            &HashMap::new(),
+            true,
+        )?;
+        Ok(true)
+    }
+
+    fn output(cli: &Cli, pending: &mut Vec<u8>, data: &[u8], stderr: bool) {
+        pending.extend_from_slice(data);
+        while let Some(pos) = pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=pos).collect();
+            cli.output(
+                String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']),
+                stderr,
+            );
+        }
+    }
+
+    /*
+     * Run a docker run or docker exec command line using the docker engine
+     * API. The API client is not forwarding the terminal so when running
+     * interactively from a terminal, or if the docker engine is not answering
+     * on the socket or the command line cannot be translated, the docker CLI
+     * is used instead.
+     * The output of the container is written to the terminal and the logs
+     * and a non zero exit code from the container is returned as an error.
+     */
+    pub fn check_call(
+        &self,
+        cli: &Cli,
+        api: &DockerApi,
+        cmd_line: &Vec<String>,
+        env: &HashMap<String, String>,
+    ) -> Result<(), BError> {
+        if (self._interactive && std::io::stdin().is_terminal())
+            || !api.available()
+            || !api.ping().unwrap_or(false)
+        {
+            return cli.check_call(cmd_line, env, true);
+        }
+        let options: DockerOptions = match DockerOptions::parse(cmd_line, env)? {
+            Some(o) => o,
+            None => {
+                cli.debug(String::from(
+                    "The docker command line cannot be run using the docker engine API, using the docker CLI",
+                ));
+                return cli.check_call(cmd_line, env, true);
+            }
+        };
+        cli.record_call(cmd_line);
+        let exec: bool = cmd_line.get(1).is_some_and(|c| c == "exec");
+        if !exec && options.detach {
+            let id: String = api.create_container(&options.name, &options.config())?;
+            return api.start_container(&id);
+        }
+
+        let mut stdout: Vec<u8> = vec![];
+        let mut stderr: Vec<u8> = vec![];
+        let mut output = |stream: u8, data: &[u8]| {
+            if stream == STREAM_STDERR {
+                Self::output(cli, &mut stderr, data, true);
+            } else {
+                Self::output(cli, &mut stdout, data, false);
+            }
+        };
+        let code: i64 = if exec {
+            api.exec(&options.target, &options.exec_config(), &mut output)?
+        } else {
+            api.run(&options.name, &options.config(), &mut output)?
+        };
+        if !stdout.is_empty() {
+            cli.output(&String::from_utf8_lossy(&stdout), false);
+        }
+        if !stderr.is_empty() {
+            cli.output(&String::from_utf8_lossy(&stderr), true);
+        }
+        if code != 0 {
+            return Err(BError::CliError(format!("exit status: {}", code)));
+        }
         Ok(())
     }
 
@@ -278,18 +814,19 @@ impl Docker {
         &self,
         cmd_line: &Vec<String>,
         cli: &Cli,
+        api: &DockerApi,
         docker_top_dir: &PathBuf,
         work_dir: &PathBuf,
         docker_args: &Vec<String>,
         volumes: &Vec<String>,
         env: &HashMap<String, String>,
     ) -> Result<(), BError> {
-        cli.check_call(
+        self.check_call(
+            cli,
+            api,
             &self.bootstrap_cmd_line(cmd_line, docker_top_dir, work_dir, docker_args, volumes),
-            &env,
-            true,
-        )?;
-        Ok(())
+            env,
+        )
     }
 
     pub fn run_cmd(
@@ -300,25 +837,37 @@ impl Docker {
         cli: &Cli,
     ) -> Result<(), BError> {
         let temp_dir: TempDir = TempDir::new("bakery")?;
-        let env_file_path: PathBuf = self.setup_env_file(temp_dir.path(), env)?;
-        cli.check_call(
-            &self.cmd_line(cmd_line, &env_file_path, exec_dir),
-            &HashMap::new(),
-            true,
-        )?;
-        Ok(())
+        let (secret_env, plain_env): (HashMap<String, String>, HashMap<String, String>) = env
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .partition(|(_k, v)| cli.is_secret(v));
+        let env_file_path: PathBuf = self.setup_env_file(temp_dir.path(), &plain_env)?;
+        let mut secrets: Vec<String> = secret_env.keys().cloned().collect();
+        secrets.sort();
+        self.check_call(
+            cli,
+            &self.api(),
+            &self.cmd_line(cmd_line, &env_file_path, exec_dir, &secrets),
+            &secret_env,
+        )
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use serde_json::{json, Value};
     use std::collections::HashMap;
     use std::fs::File;
     use std::io::Read;
     use std::path::PathBuf;
     use tempdir::TempDir;
 
-    use crate::executers::{Docker, DockerImage};
+    use crate::cli::*;
+    use crate::data::WsContainerData;
+    use crate::error::BError;
+    use crate::executers::docker::DockerOptions;
+    use crate::executers::{ContainerRuntime, Docker, DockerApi, DockerImage};
+    use crate::fs::Stamp;
     use crate::helper::Helper;
 
     #[test]
@@ -587,7 +1136,7 @@ TEST_KEY1=TEST_VALUE1
         let image: DockerImage =
             DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
         let docker: Docker = Docker::new(image.clone(), interactive);
-        let result: Vec<String> = docker.cmd_line(&test_cmd, &env_file, &work_dir);
+        let result: Vec<String> = docker.cmd_line(&test_cmd, &env_file, &work_dir, &[]);
         let cmd_line: Vec<String> =
             Helper::docker_cmdline_string(interactive, &work_dir, &image, &test_cmd, &env_file);
         assert_eq!(result, cmd_line);
@@ -610,9 +1159,438 @@ TEST_KEY1=TEST_VALUE1
         let image: DockerImage =
             DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
         let docker: Docker = Docker::new(image.clone(), interactive);
-        let result: Vec<String> = docker.cmd_line(&test_cmd, &env_file, &work_dir);
+        let result: Vec<String> = docker.cmd_line(&test_cmd, &env_file, &work_dir, &[]);
         let cmd_line: Vec<String> =
             Helper::docker_cmdline_string(interactive, &work_dir, &image, &test_cmd, &env_file);
         assert_eq!(result, cmd_line);
     }
+
+    #[test]
+    fn test_docker_cmdline_rootless_podman() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from(temp_dir.path());
+        let env_file: PathBuf = work_dir.clone().join("test-docker.env");
+        let test_cmd: Vec<String> = vec![String::from("test")];
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let mut docker: Docker = Docker::new(image.clone(), false);
+        docker.set_runtime(ContainerRuntime::RootlessPodman);
+        let result: Vec<String> = docker.cmd_line(&test_cmd, &env_file, &work_dir, &[]);
+        assert_eq!(
+            result,
+            vec![
+                String::from("podman"),
+                String::from("run"),
+                String::from("--userns=keep-id"),
+                String::from("-v"),
+                String::from("/etc/passwd:/etc/passwd:ro"),
+                String::from("-v"),
+                String::from("/etc/group:/etc/group:ro"),
+                String::from("-v"),
+                format!("{}:{}", Helper::env_home(), Helper::env_home()),
+                String::from("-w"),
+                format!("{}", work_dir.display()),
+                String::from("-t"),
+                String::from("--rm"),
+                String::from("--group-add"),
+                String::from("keep-groups"),
+                String::from("--env-file"),
+                env_file.to_string_lossy().to_string(),
+                String::from("test-registry/test-image:0.1"),
+                String::from("test"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_docker_exec_cmdline() {
+        let work_dir: PathBuf = PathBuf::from("/test/work");
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let docker: Docker = Docker::new(image, true);
+        let result: Vec<String> = docker.exec_cmd_line(
+            "bakery-workspace-test",
+            &[String::from("bakery"), String::from("list")],
+            &work_dir,
+            &[String::from("SIGN_KEY")],
+        );
+        assert_eq!(
+            result,
+            vec![
+                String::from("docker"),
+                String::from("exec"),
+                String::from("-t"),
+                String::from("-i"),
+                String::from("-w"),
+                String::from("/test/work"),
+                String::from("-e"),
+                String::from("SIGN_KEY"),
+                String::from("bakery-workspace-test"),
+                String::from("bakery"),
+                String::from("list"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_docker_build() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let docker_dir: PathBuf = temp_dir.path().join("docker");
+        std::fs::create_dir_all(&docker_dir).expect("Failed to create docker dir");
+        std::fs::write(docker_dir.join("Dockerfile"), "FROM ubuntu:22.04\n")
+            .expect("Failed to write Dockerfile");
+        let hash: String = Stamp::dir_hash(&docker_dir, &[]);
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let docker: Docker = Docker::new(image, false);
+        let cmd_line: Vec<String> = docker.build_cmd_line(&docker_dir, &hash);
+        assert_eq!(
+            cmd_line,
+            vec![
+                String::from("docker"),
+                String::from("build"),
+                String::from("-t"),
+                String::from("test-registry/test-image:0.1"),
+                String::from("--label"),
+                format!("bakery.context={}", hash),
+                String::from("-f"),
+                docker_dir.join("Dockerfile").display().to_string(),
+                docker_dir.display().to_string(),
+            ]
+        );
+        /* The image is missing so it should be built */
+        let socket: PathBuf = temp_dir.path().join("docker.sock");
+        let _requests = Helper::docker_api_stub(&socket, vec![]);
+        let mut mocked_system: MockSystem = MockSystem::new();
+        mocked_system
+            .expect_check_call()
+            .with(mockall::predicate::eq(CallParams {
+                cmd_line,
+                env: HashMap::new(),
+                shell: true,
+            }))
+            .once()
+            .returning(|_x| Ok(()));
+        let cli: Cli = Cli::new(
+            Box::new(BLogger::new()),
+            Box::new(mocked_system),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery"]),
+        );
+        let built: bool = docker
+            .build(&cli, &DockerApi::new(&socket), &docker_dir, false)
+            .expect("Failed to build image");
+        assert!(built);
+    }
+
+    #[test]
+    fn test_docker_build_cached() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let docker_dir: PathBuf = temp_dir.path().join("docker");
+        std::fs::create_dir_all(&docker_dir).expect("Failed to create docker dir");
+        std::fs::write(docker_dir.join("Dockerfile"), "FROM ubuntu:22.04\n")
+            .expect("Failed to write Dockerfile");
+        let hash: String = Stamp::dir_hash(&docker_dir, &[]);
+        let socket: PathBuf = temp_dir.path().join("docker.sock");
+        let requests = Helper::docker_api_stub(
+            &socket,
+            vec![(
+                "GET /images/test-registry/test-image:0.1/json",
+                Helper::http_response(
+                    200,
+                    format!(
+                        r#"{{"Id": "sha256:1234", "Config": {{"Labels": {{"bakery.context": "{}"}}}}}}"#,
+                        hash
+                    )
+                    .into_bytes(),
+                    false,
+                ),
+            )],
+        );
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let docker: Docker = Docker::new(image, false);
+        /* The image is built from the same context so nothing should be built */
+        let cli: Cli = Cli::new(
+            Box::new(MockLogger::new()),
+            Box::new(MockSystem::new()),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery"]),
+        );
+        let built: bool = docker
+            .build(&cli, &DockerApi::new(&socket), &docker_dir, false)
+            .expect("Failed to build image");
+        assert!(!built);
+        assert_eq!(
+            requests.lock().unwrap().clone(),
+            vec!["GET /images/test-registry/test-image:0.1/json"]
+        );
+        /* Any file in the context is part of the hash not only the Dockerfile */
+        std::fs::write(docker_dir.join("entrypoint.sh"), "#!/bin/sh\n")
+            .expect("Failed to write entrypoint");
+        let mut mocked_system: MockSystem = MockSystem::new();
+        mocked_system
+            .expect_check_call()
+            .once()
+            .returning(|_x| Ok(()));
+        let cli: Cli = Cli::new(
+            Box::new(BLogger::new()),
+            Box::new(mocked_system),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery"]),
+        );
+        let built: bool = docker
+            .build(&cli, &DockerApi::new(&socket), &docker_dir, false)
+            .expect("Failed to build image");
+        assert!(built);
+    }
+
+    #[test]
+    fn test_docker_cmdline_secrets() {
+        let work_dir: PathBuf = PathBuf::from("/test/work");
+        let env_file: PathBuf = work_dir.join("test-docker.env");
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let docker: Docker = Docker::new(image, false);
+        let result: Vec<String> = docker.cmd_line(
+            &[String::from("test")],
+            &env_file,
+            &work_dir,
+            &[String::from("SIGN_KEY"), String::from("UPLOAD_TOKEN")],
+        );
+        /* Only the names of the secrets should be passed to docker */
+        let image_index: usize = result
+            .iter()
+            .position(|a| a == "test-registry/test-image:0.1")
+            .expect("Image missing in cmd line");
+        assert_eq!(
+            result[image_index - 6..image_index].to_vec(),
+            vec![
+                "--env-file",
+                "/test/work/test-docker.env",
+                "-e",
+                "SIGN_KEY",
+                "-e",
+                "UPLOAD_TOKEN"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_docker_cmdline_container() {
+        let work_dir: PathBuf = PathBuf::from("/test/work");
+        let env_file: PathBuf = work_dir.join("test-docker.env");
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let value: Value = Helper::parse(
+            r#"
+            {
+                "container": {
+                    "memory": "4g",
+                    "devices": [
+                        "/dev/hsm0"
+                    ]
+                }
+            }"#,
+        )
+        .expect("Failed to parse task config");
+        let container: WsContainerData =
+            WsContainerData::from_value(&value).expect("Failed to parse container data");
+        let mut docker: Docker = Docker::new(image, false);
+        docker.set_container(&container);
+        let result: Vec<String> =
+            docker.cmd_line(&[String::from("test")], &env_file, &work_dir, &[]);
+        /* The container options should be passed right before the image */
+        let image_index: usize = result
+            .iter()
+            .position(|a| a == "test-registry/test-image:0.1")
+            .expect("Image missing in cmd line");
+        assert_eq!(
+            result[image_index - 4..image_index].to_vec(),
+            vec!["--memory", "4g", "--device", "/dev/hsm0"]
+        );
+        assert_eq!(result.last(), Some(&String::from("test")));
+    }
+
+    #[test]
+    fn test_docker_cmdline_cache_volumes() {
+        let work_dir: PathBuf = PathBuf::from("/test/work");
+        let env_file: PathBuf = work_dir.join("test-docker.env");
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let mut docker: Docker = Docker::new(image, false);
+        docker.set_cache_volumes(&[String::from("/nfs/sstate:/nfs/sstate:ro")]);
+        let result: Vec<String> =
+            docker.cmd_line(&[String::from("test")], &env_file, &work_dir, &[]);
+        let image_index: usize = result
+            .iter()
+            .position(|a| a == "test-registry/test-image:0.1")
+            .expect("Image missing in cmd line");
+        assert_eq!(
+            result[image_index - 2..image_index].to_vec(),
+            vec!["-v", "/nfs/sstate:/nfs/sstate:ro"]
+        );
+    }
+
+    #[test]
+    fn test_docker_api_options() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from("/test/work");
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let value: Value = Helper::parse(
+            r#"
+            {
+                "container": {
+                    "cpus": "2",
+                    "memory": "4g",
+                    "devices": [
+                        "/dev/hsm0"
+                    ],
+                    "args": [
+                        "--label test=$LABEL"
+                    ]
+                }
+            }"#,
+        )
+        .expect("Failed to parse task config");
+        let container: WsContainerData =
+            WsContainerData::from_value(&value).expect("Failed to parse container data");
+        let mut docker: Docker = Docker::new(image, false);
+        docker.set_container(&container);
+        let env: HashMap<String, String> = HashMap::from([
+            (String::from("VAR1"), String::from("value1")),
+            (String::from("SIGN_KEY"), String::from("secret")),
+            (String::from("LABEL"), String::from("label1")),
+        ]);
+        let env_file: PathBuf = docker
+            .setup_env_file(
+                temp_dir.path(),
+                &HashMap::from([(String::from("VAR1"), String::from("value1"))]),
+            )
+            .expect("Failed to setup env file");
+        let cmd_line: Vec<String> = docker.cmd_line(
+            &[String::from("cd /test/work && ./build.sh")],
+            &env_file,
+            &work_dir,
+            &[String::from("SIGN_KEY")],
+        );
+        let options: DockerOptions = DockerOptions::parse(&cmd_line, &env)
+            .expect("Failed to parse options")
+            .expect("Options should be supported");
+        let config: Value = options.config();
+        assert_eq!(config["Image"], json!("test-registry/test-image:0.1"));
+        assert_eq!(
+            config["Cmd"],
+            json!(["/bin/bash", "-c", "cd /test/work && ./build.sh"])
+        );
+        assert_eq!(config["WorkingDir"], json!("/test/work"));
+        assert_eq!(config["Tty"], json!(true));
+        assert_eq!(config["Env"], json!(["VAR1=value1", "SIGN_KEY=secret"]));
+        assert_eq!(config["Labels"], json!({"test": "label1"}));
+        assert_eq!(config["HostConfig"]["NanoCpus"], json!(2000000000i64));
+        assert_eq!(config["HostConfig"]["Memory"], json!(4i64 << 30));
+        assert_eq!(
+            config["HostConfig"]["Devices"],
+            json!([{"PathOnHost": "/dev/hsm0", "PathInContainer": "/dev/hsm0", "CgroupPermissions": "rwm"}])
+        );
+        assert!(config["HostConfig"]["Binds"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("/etc/passwd:/etc/passwd:ro")));
+        /* Options that cannot be translated are left to the docker CLI */
+        let cmd_line: Vec<String> = vec![
+            String::from("docker"),
+            String::from("run"),
+            String::from("--mount type=bind,src=/test,dst=/test"),
+            String::from("test-registry/test-image:0.1"),
+        ];
+        assert!(DockerOptions::parse(&cmd_line, &env)
+            .expect("Failed to parse options")
+            .is_none());
+    }
+
+    #[test]
+    fn test_docker_check_call_api() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let socket: PathBuf = temp_dir.path().join("docker.sock");
+        let mut attach: Vec<u8> = vec![1, 0, 0, 0, 0, 0, 0, 6];
+        attach.extend_from_slice(b"hello\n");
+        let requests = Helper::docker_api_stub(
+            &socket,
+            vec![
+                (
+                    "GET /_ping",
+                    Helper::http_response(200, b"OK".to_vec(), false),
+                ),
+                (
+                    "POST /containers/create",
+                    Helper::http_response(201, br#"{"Id": "abc123"}"#.to_vec(), false),
+                ),
+                (
+                    "POST /containers/abc123/attach",
+                    Helper::http_upgrade_response(attach),
+                ),
+                (
+                    "POST /containers/abc123/start",
+                    Helper::http_response(204, vec![], false),
+                ),
+                (
+                    "POST /containers/abc123/wait",
+                    Helper::http_response(200, br#"{"StatusCode": 2}"#.to_vec(), false),
+                ),
+                (
+                    "DELETE /containers/abc123?force=true",
+                    Helper::http_response(204, vec![], false),
+                ),
+            ],
+        );
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let docker: Docker = Docker::new(image, false);
+        let cmd_line: Vec<String> = docker.bootstrap_cmd_line(
+            &vec![String::from("bakery"), String::from("build")],
+            &PathBuf::from("/test"),
+            &PathBuf::from("/test/work"),
+            &vec![],
+            &vec![],
+        );
+        /* The container should be run using the API instead of the docker CLI */
+        let cli: Cli = Cli::new(
+            Box::new(MockLogger::new()),
+            Box::new(MockSystem::new()),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery"]),
+        );
+        let result: Result<(), BError> =
+            docker.check_call(&cli, &DockerApi::new(&socket), &cmd_line, &HashMap::new());
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because the container exit code is not 0");
+            }
+            Err(e) => {
+                assert_eq!(e.to_string(), String::from("exit status: 2"));
+            }
+        }
+        assert_eq!(
+            requests.lock().unwrap().clone(),
+            vec![
+                String::from("GET /_ping"),
+                format!(
+                    "POST /containers/create?name=bakery-workspace-{}",
+                    std::process::id()
+                ),
+                String::from("POST /containers/abc123/attach?stream=true&stdout=true&stderr=true"),
+                String::from("POST /containers/abc123/start"),
+                String::from("POST /containers/abc123/wait"),
+                String::from("DELETE /containers/abc123?force=true"),
+            ]
+        );
+        assert_eq!(cli.take_calls(), vec![cmd_line]);
+    }
 }
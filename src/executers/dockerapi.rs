@@ -0,0 +1,1016 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+use crate::cli::Cli;
+use crate::error::BError;
+use crate::executers::DockerImage;
+
+pub const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+
+/*
+ * Output streams used by the docker engine when multiplexing stdout and
+ * stderr of a container that is not using a tty
+ */
+pub const STREAM_STDOUT: u8 = 1;
+pub const STREAM_STDERR: u8 = 2;
+
+pub struct ApiResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl ApiResponse {
+    pub fn json(&self) -> Result<Value, BError> {
+        if self.body.is_empty() {
+            return Ok(Value::Null);
+        }
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+/*
+ * The docker engine is multiplexing stdout and stderr into one stream where
+ * each frame starts with an 8 byte header. The first byte is the stream and
+ * the last 4 bytes is the size of the payload. The frames are not aligned
+ * with the chunks read from the socket so we have to buffer any incomplete
+ * frame until the rest of it is available.
+ */
+struct Demuxer {
+    buffer: Vec<u8>,
+}
+
+impl Demuxer {
+    fn new() -> Self {
+        Demuxer { buffer: vec![] }
+    }
+
+    fn feed(&mut self, data: &[u8], output: &mut dyn FnMut(u8, &[u8])) {
+        self.buffer.extend_from_slice(data);
+        while self.buffer.len() >= 8 {
+            let size: usize = u32::from_be_bytes([
+                self.buffer[4],
+                self.buffer[5],
+                self.buffer[6],
+                self.buffer[7],
+            ]) as usize;
+            if self.buffer.len() < 8 + size {
+                break;
+            }
+            let frame: Vec<u8> = self.buffer.drain(..8 + size).collect();
+            output(frame[0], &frame[8..]);
+        }
+    }
+}
+
+/*
+ * Minimal client for the Docker Engine API talking HTTP/1.1 directly over
+ * the unix socket of the docker daemon. It is only implementing the parts
+ * of the API that bakery needs so that we are not depending on a specific
+ * version of the docker CLI.
+ */
+pub struct DockerApi {
+    socket: PathBuf,
+}
+
+impl DockerApi {
+    /*
+     * Use the socket from DOCKER_HOST if it is pointing to a unix socket
+     * otherwise fallback to the default location of the docker socket
+     */
+    pub fn default_socket() -> PathBuf {
+        if let Ok(host) = std::env::var("DOCKER_HOST") {
+            if let Some(path) = host.strip_prefix("unix://") {
+                return PathBuf::from(path);
+            }
+        }
+        PathBuf::from(DOCKER_SOCKET)
+    }
+
+    pub fn new(socket: &Path) -> Self {
+        DockerApi {
+            socket: socket.to_path_buf(),
+        }
+    }
+
+    pub fn socket(&self) -> &PathBuf {
+        &self.socket
+    }
+
+    pub fn available(&self) -> bool {
+        self.socket.exists()
+    }
+
+    fn encode(value: &str) -> String {
+        value
+            .bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                    (b as char).to_string()
+                }
+                _ => format!("%{:02X}", b),
+            })
+            .collect()
+    }
+
+    fn connect(&self) -> Result<UnixStream, BError> {
+        UnixStream::connect(&self.socket).map_err(|err| {
+            BError::DockerApiError(format!(
+                "Failed to connect to '{}', {}",
+                self.socket.display(),
+                err
+            ))
+        })
+    }
+
+    fn send(
+        &self,
+        stream: &mut UnixStream,
+        method: &str,
+        path: &str,
+        body: Option<&Value>,
+        upgrade: bool,
+    ) -> Result<(), BError> {
+        let payload: String = match body {
+            Some(value) => value.to_string(),
+            None => String::new(),
+        };
+        let mut request: String = format!(
+            "{} {} HTTP/1.1\r\nHost: docker\r\nUser-Agent: bakery/{}\r\n",
+            method,
+            path,
+            env!("CARGO_PKG_VERSION")
+        );
+        if upgrade {
+            request.push_str("Connection: Upgrade\r\nUpgrade: tcp\r\n");
+        } else {
+            request.push_str("Connection: close\r\n");
+        }
+        if body.is_some() {
+            request.push_str("Content-Type: application/json\r\n");
+        }
+        request.push_str(&format!("Content-Length: {}\r\n\r\n", payload.len()));
+        request.push_str(&payload);
+        stream.write_all(request.as_bytes())?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    fn read_head(
+        reader: &mut BufReader<UnixStream>,
+    ) -> Result<(u16, HashMap<String, String>), BError> {
+        let mut line: String = String::new();
+        reader.read_line(&mut line)?;
+        let status: u16 = line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|s| s.parse::<u16>().ok())
+            .ok_or_else(|| {
+                BError::DockerApiError(format!("Invalid response from docker '{}'", line.trim()))
+            })?;
+
+        let mut headers: HashMap<String, String> = HashMap::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            if let Some((key, value)) = line.split_once(':') {
+                headers.insert(key.trim().to_lowercase(), value.trim().to_string());
+            }
+        }
+        Ok((status, headers))
+    }
+
+    fn read_body(
+        reader: &mut BufReader<UnixStream>,
+        headers: &HashMap<String, String>,
+        on_data: &mut dyn FnMut(&[u8]) -> Result<(), BError>,
+    ) -> Result<(), BError> {
+        let chunked: bool = headers
+            .get("transfer-encoding")
+            .is_some_and(|v| v.eq_ignore_ascii_case("chunked"));
+
+        if chunked {
+            let mut line: String = String::new();
+            loop {
+                line.clear();
+                if reader.read_line(&mut line)? == 0 {
+                    break;
+                }
+                let size_str: &str = line.trim().split(';').next().unwrap_or("");
+                let size: usize = usize::from_str_radix(size_str, 16).map_err(|_e| {
+                    BError::DockerApiError(format!("Invalid chunk size '{}'", size_str))
+                })?;
+                if size == 0 {
+                    break;
+                }
+                let mut chunk: Vec<u8> = vec![0; size];
+                reader.read_exact(&mut chunk)?;
+                on_data(&chunk)?;
+                /* Every chunk is terminated by CRLF */
+                line.clear();
+                reader.read_line(&mut line)?;
+            }
+            return Ok(());
+        }
+
+        if let Some(length) = headers.get("content-length") {
+            let length: usize = length.parse::<usize>().unwrap_or(0);
+            let mut body: Vec<u8> = vec![0; length];
+            reader.read_exact(&mut body)?;
+            if !body.is_empty() {
+                on_data(&body)?;
+            }
+            return Ok(());
+        }
+
+        /* No length is available so read until the connection is closed */
+        let mut buffer: [u8; 4096] = [0; 4096];
+        loop {
+            let n: usize = reader.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            on_data(&buffer[..n])?;
+        }
+        Ok(())
+    }
+
+    fn api_error(method: &str, path: &str, status: u16, body: &[u8]) -> BError {
+        let message: String = match serde_json::from_slice::<Value>(body) {
+            Ok(value) => value
+                .get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("")
+                .to_string(),
+            Err(_e) => String::from_utf8_lossy(body).trim().to_string(),
+        };
+        BError::DockerApiError(format!(
+            "Docker API '{} {}' failed with status {}: {}",
+            method, path, status, message
+        ))
+    }
+
+    /*
+     * Send a request and stream the body of the response to the callback
+     * returning the status code. It is up to the caller to handle the status.
+     */
+    pub fn stream(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&Value>,
+        on_data: &mut dyn FnMut(&[u8]) -> Result<(), BError>,
+    ) -> Result<u16, BError> {
+        let mut stream: UnixStream = self.connect()?;
+        self.send(&mut stream, method, path, body, false)?;
+        let mut reader: BufReader<UnixStream> = BufReader::new(stream);
+        let (status, headers) = Self::read_head(&mut reader)?;
+        Self::read_body(&mut reader, &headers, on_data)?;
+        Ok(status)
+    }
+
+    pub fn request(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<ApiResponse, BError> {
+        let mut data: Vec<u8> = vec![];
+        let status: u16 = self.stream(method, path, body, &mut |chunk| {
+            data.extend_from_slice(chunk);
+            Ok(())
+        })?;
+        Ok(ApiResponse { status, body: data })
+    }
+
+    fn expect(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<ApiResponse, BError> {
+        let response: ApiResponse = self.request(method, path, body)?;
+        if response.status >= 400 {
+            return Err(Self::api_error(
+                method,
+                path,
+                response.status,
+                &response.body,
+            ));
+        }
+        Ok(response)
+    }
+
+    pub fn ping(&self) -> Result<bool, BError> {
+        let response: ApiResponse = self.request("GET", "/_ping", None)?;
+        Ok(response.status == 200)
+    }
+
+    /*
+     * Returns the image information if the image is available locally
+     * otherwise None
+     */
+    pub fn inspect_image(&self, image: &DockerImage) -> Result<Option<Value>, BError> {
+        let path: String = format!("/images/{}/json", image);
+        let response: ApiResponse = self.request("GET", &path, None)?;
+        match response.status {
+            200 => Ok(Some(response.json()?)),
+            404 => Ok(None),
+            status => Err(Self::api_error("GET", &path, status, &response.body)),
+        }
+    }
+
+    /*
+     * Returns the digest of the image in the registry if the image has been
+     * pulled from or pushed to a registry otherwise the id of the local image.
+     * None is returned if the image is not available locally.
+     */
+    pub fn image_digest(&self, image: &DockerImage) -> Result<Option<String>, BError> {
+        let value: Value = match self.inspect_image(image)? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let repo_digest: Option<String> = value
+            .get("RepoDigests")
+            .and_then(|d| d.as_array())
+            .and_then(|d| d.first())
+            .and_then(|d| d.as_str())
+            .and_then(|d| d.split_once('@'))
+            .map(|(_repo, digest)| digest.to_string());
+        match repo_digest {
+            Some(digest) => Ok(Some(digest)),
+            None => Ok(value
+                .get("Id")
+                .and_then(|id| id.as_str())
+                .map(|id| id.to_string())),
+        }
+    }
+
+    pub fn pull_image(&self, image: &DockerImage, cli: &Cli) -> Result<(), BError> {
+        let path: String = format!(
+            "/images/create?fromImage={}&tag={}",
+            Self::encode(&format!("{}/{}", image.registry, image.image)),
+            Self::encode(&image.tag)
+        );
+        cli.info(format!("Pull image '{}'", image));
+
+        /*
+         * The progress is reported as a stream of JSON objects separated by
+         * newline, any error during the pull is reported in the stream and
+         * not using the status code.
+         */
+        let mut pending: Vec<u8> = vec![];
+        let mut error: Option<String> = None;
+        let mut body: Vec<u8> = vec![];
+        let status: u16 = self.stream("POST", &path, None, &mut |data| {
+            body.extend_from_slice(data);
+            pending.extend_from_slice(data);
+            while let Some(pos) = pending.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=pos).collect();
+                if let Ok(progress) = serde_json::from_slice::<Value>(&line) {
+                    if let Some(e) = progress.get("error").and_then(|e| e.as_str()) {
+                        error = Some(e.to_string());
+                    } else if let Some(status) = progress.get("status").and_then(|s| s.as_str()) {
+                        cli.debug(status.to_string());
+                    }
+                }
+            }
+            Ok(())
+        })?;
+        if status >= 400 {
+            return Err(Self::api_error("POST", &path, status, &body));
+        }
+        if let Some(e) = error {
+            return Err(BError::DockerApiError(format!(
+                "Failed to pull image '{}': {}",
+                image, e
+            )));
+        }
+        Ok(())
+    }
+
+    /*
+     * Only pull the image if it is not available locally so that an image that
+     * is only available locally will not fail because the registry is missing
+     */
+    pub fn ensure_image(&self, image: &DockerImage, cli: &Cli) -> Result<(), BError> {
+        if self.inspect_image(image)?.is_some() {
+            cli.debug(format!("Image '{}' is available locally", image));
+            return Ok(());
+        }
+        self.pull_image(image, cli)
+    }
+
+    /*
+     * Create a container from the config, if the name is empty the docker
+     * engine will generate a name for the container
+     */
+    pub fn create_container(&self, name: &str, config: &Value) -> Result<String, BError> {
+        let mut path: String = String::from("/containers/create");
+        if !name.is_empty() {
+            path.push_str(&format!("?name={}", Self::encode(name)));
+        }
+        let response: ApiResponse = self.expect("POST", &path, Some(config))?;
+        match response.json()?.get("Id").and_then(|id| id.as_str()) {
+            Some(id) => Ok(id.to_string()),
+            None => Err(BError::DockerApiError(format!(
+                "Failed to create container '{}', no id returned",
+                name
+            ))),
+        }
+    }
+
+    pub fn start_container(&self, id: &str) -> Result<(), BError> {
+        self.expect("POST", &format!("/containers/{}/start", id), None)?;
+        Ok(())
+    }
+
+    pub fn stop_container(&self, id: &str) -> Result<(), BError> {
+        self.expect("POST", &format!("/containers/{}/stop", id), None)?;
+        Ok(())
+    }
+
+    pub fn remove_container(&self, id: &str) -> Result<(), BError> {
+        self.expect("DELETE", &format!("/containers/{}?force=true", id), None)?;
+        Ok(())
+    }
+
+    pub fn inspect_container(&self, id: &str) -> Result<Option<Value>, BError> {
+        let path: String = format!("/containers/{}/json", id);
+        let response: ApiResponse = self.request("GET", &path, None)?;
+        match response.status {
+            200 => Ok(Some(response.json()?)),
+            404 => Ok(None),
+            status => Err(Self::api_error("GET", &path, status, &response.body)),
+        }
+    }
+
+    /*
+     * Block until the container has exited and return the exit code
+     */
+    pub fn wait_container(&self, id: &str) -> Result<i64, BError> {
+        let response: ApiResponse =
+            self.expect("POST", &format!("/containers/{}/wait", id), None)?;
+        let value: Value = response.json()?;
+        if let Some(message) = value.pointer("/Error/Message").and_then(|m| m.as_str()) {
+            return Err(BError::DockerApiError(message.to_string()));
+        }
+        Ok(value
+            .get("StatusCode")
+            .and_then(|c| c.as_i64())
+            .unwrap_or(-1))
+    }
+
+    fn read_output(
+        reader: &mut dyn Read,
+        tty: bool,
+        output: &mut dyn FnMut(u8, &[u8]),
+    ) -> Result<(), BError> {
+        let mut demuxer: Demuxer = Demuxer::new();
+        let mut buffer: [u8; 4096] = [0; 4096];
+        loop {
+            let n: usize = reader.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            if tty {
+                output(STREAM_STDOUT, &buffer[..n]);
+            } else {
+                demuxer.feed(&buffer[..n], output);
+            }
+        }
+        Ok(())
+    }
+
+    /*
+     * Stream the logs of a container, if follow is set it will continue to
+     * stream the logs until the container has exited
+     */
+    pub fn logs(
+        &self,
+        id: &str,
+        tty: bool,
+        follow: bool,
+        output: &mut dyn FnMut(u8, &[u8]),
+    ) -> Result<(), BError> {
+        let path: String = format!(
+            "/containers/{}/logs?stdout=true&stderr=true&follow={}",
+            id, follow
+        );
+        let mut demuxer: Demuxer = Demuxer::new();
+        let mut body: Vec<u8> = vec![];
+        let status: u16 = self.stream("GET", &path, None, &mut |data| {
+            if tty {
+                output(STREAM_STDOUT, data);
+            } else {
+                demuxer.feed(data, output);
+            }
+            body.extend_from_slice(data);
+            Ok(())
+        })?;
+        if status >= 400 {
+            return Err(Self::api_error("GET", &path, status, &body));
+        }
+        Ok(())
+    }
+
+    /*
+     * Send a request where the connection is hijacked by the docker engine,
+     * after the response header it is a raw stream of the output until the
+     * container or the exec has exited.
+     */
+    fn hijack(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<BufReader<UnixStream>, BError> {
+        let mut stream: UnixStream = self.connect()?;
+        self.send(&mut stream, method, path, body, true)?;
+        let mut reader: BufReader<UnixStream> = BufReader::new(stream);
+        let (status, headers) = Self::read_head(&mut reader)?;
+        if status >= 400 {
+            let mut body: Vec<u8> = vec![];
+            Self::read_body(&mut reader, &headers, &mut |data| {
+                body.extend_from_slice(data);
+                Ok(())
+            })?;
+            return Err(Self::api_error(method, path, status, &body));
+        }
+        Ok(reader)
+    }
+
+    /*
+     * Attach to the output of a container
+     */
+    pub fn attach_container(&self, id: &str) -> Result<BufReader<UnixStream>, BError> {
+        let path: String = format!(
+            "/containers/{}/attach?stream=true&stdout=true&stderr=true",
+            id
+        );
+        self.hijack("POST", &path, None)
+    }
+
+    /*
+     * Run a command in a running container. The output is passed to the
+     * callback while the command is running and the exit code of the
+     * command is returned.
+     */
+    pub fn exec(
+        &self,
+        id: &str,
+        config: &Value,
+        output: &mut dyn FnMut(u8, &[u8]),
+    ) -> Result<i64, BError> {
+        let tty: bool = config.get("Tty").and_then(|t| t.as_bool()).unwrap_or(false);
+        let response: ApiResponse =
+            self.expect("POST", &format!("/containers/{}/exec", id), Some(config))?;
+        let exec_id: String = match response.json()?.get("Id").and_then(|id| id.as_str()) {
+            Some(exec_id) => exec_id.to_string(),
+            None => {
+                return Err(BError::DockerApiError(format!(
+                    "Failed to exec in container '{}', no id returned",
+                    id
+                )));
+            }
+        };
+        let mut reader: BufReader<UnixStream> = self.hijack(
+            "POST",
+            &format!("/exec/{}/start", exec_id),
+            Some(&json!({ "Detach": false, "Tty": tty })),
+        )?;
+        Self::read_output(&mut reader, tty, output)?;
+        let response: ApiResponse = self.expect("GET", &format!("/exec/{}/json", exec_id), None)?;
+        Ok(response
+            .json()?
+            .get("ExitCode")
+            .and_then(|c| c.as_i64())
+            .unwrap_or(-1))
+    }
+
+    /*
+     * Create a container, attach to it and start it. The output is passed to
+     * the callback while the container is running. The container is removed
+     * once it has exited and the exit code of the container is returned.
+     */
+    pub fn run(
+        &self,
+        name: &str,
+        config: &Value,
+        output: &mut dyn FnMut(u8, &[u8]),
+    ) -> Result<i64, BError> {
+        let tty: bool = config.get("Tty").and_then(|t| t.as_bool()).unwrap_or(false);
+        let id: String = self.create_container(name, config)?;
+        let result: Result<i64, BError> = (|| {
+            let mut reader: BufReader<UnixStream> = self.attach_container(&id)?;
+            self.start_container(&id)?;
+            Self::read_output(&mut reader, tty, output)?;
+            self.wait_container(&id)
+        })();
+        self.remove_container(&id)?;
+        result
+    }
+
+    /*
+     * Basic container config used when creating a container running the
+     * command line in the image
+     */
+    pub fn container_config(
+        image: &DockerImage,
+        cmd_line: &[String],
+        env: &HashMap<String, String>,
+        work_dir: &Path,
+        binds: &[String],
+        tty: bool,
+    ) -> Value {
+        let mut env_list: Vec<String> = env.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        env_list.sort();
+        json!({
+            "Image": image.to_string(),
+            "Cmd": cmd_line,
+            "Env": env_list,
+            "WorkingDir": work_dir.to_string_lossy(),
+            "User": format!("{}:{}", users::get_current_uid(), users::get_current_gid()),
+            "Tty": tty,
+            "AttachStdout": true,
+            "AttachStderr": true,
+            "HostConfig": {
+                "Binds": binds,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    use crate::cli::*;
+    use crate::error::BError;
+    use crate::executers::dockerapi::{STREAM_STDERR, STREAM_STDOUT};
+    use crate::executers::{DockerApi, DockerImage};
+    use crate::helper::Helper;
+
+    fn frame(stream: u8, data: &str) -> Vec<u8> {
+        let mut f: Vec<u8> = vec![stream, 0, 0, 0];
+        f.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        f.extend_from_slice(data.as_bytes());
+        f
+    }
+
+    #[test]
+    fn test_docker_api_ping() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let socket: PathBuf = temp_dir.path().join("docker.sock");
+        let _requests = Helper::docker_api_stub(
+            &socket,
+            vec![(
+                "GET /_ping",
+                Helper::http_response(200, b"OK".to_vec(), false),
+            )],
+        );
+        let api: DockerApi = DockerApi::new(&socket);
+        assert!(api.available());
+        assert!(api.ping().expect("Failed to ping docker"));
+    }
+
+    #[test]
+    fn test_docker_api_ensure_image_local() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let socket: PathBuf = temp_dir.path().join("docker.sock");
+        let requests = Helper::docker_api_stub(
+            &socket,
+            vec![(
+                "GET /images/test-registry/test-image:0.1/json",
+                Helper::http_response(200, br#"{"Id": "sha256:1234"}"#.to_vec(), false),
+            )],
+        );
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let mocked_logger: MockLogger = MockLogger::new();
+        let cli: Cli = Cli::new(
+            Box::new(mocked_logger),
+            Box::new(MockSystem::new()),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery"]),
+        );
+        let api: DockerApi = DockerApi::new(&socket);
+        api.ensure_image(&image, &cli)
+            .expect("Failed to ensure image");
+        /* The image is available locally so it should never be pulled */
+        assert_eq!(
+            requests.lock().unwrap().clone(),
+            vec!["GET /images/test-registry/test-image:0.1/json"]
+        );
+    }
+
+    #[test]
+    fn test_docker_api_image_digest() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let socket: PathBuf = temp_dir.path().join("docker.sock");
+        let _requests = Helper::docker_api_stub(
+            &socket,
+            vec![
+                (
+                    "GET /images/test-registry/test-image:0.1/json",
+                    Helper::http_response(
+                        200,
+                        br#"{"Id": "sha256:1234", "RepoDigests": ["test-registry/test-image@sha256:abcd"]}"#.to_vec(),
+                        false,
+                    ),
+                ),
+                (
+                    "GET /images/test-registry/test-image:0.2/json",
+                    Helper::http_response(200, br#"{"Id": "sha256:5678", "RepoDigests": []}"#.to_vec(), false),
+                ),
+            ],
+        );
+        let api: DockerApi = DockerApi::new(&socket);
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        assert_eq!(
+            api.image_digest(&image).expect("Failed to get digest"),
+            Some(String::from("sha256:abcd"))
+        );
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.2").expect("Invalid docker image format");
+        assert_eq!(
+            api.image_digest(&image).expect("Failed to get digest"),
+            Some(String::from("sha256:5678"))
+        );
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.3").expect("Invalid docker image format");
+        assert_eq!(
+            api.image_digest(&image).expect("Failed to get digest"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_docker_api_ensure_image_pull() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let socket: PathBuf = temp_dir.path().join("docker.sock");
+        let requests = Helper::docker_api_stub(
+            &socket,
+            vec![
+                (
+                    "GET /images/test-registry/test-image:0.1/json",
+                    Helper::http_response(404, br#"{"message": "No such image"}"#.to_vec(), false),
+                ),
+                (
+                    "POST /images/create?fromImage=test-registry/test-image&tag=0.1",
+                    Helper::http_response(
+                        200,
+                        b"{\"status\": \"Pulling from test-image\"}\n{\"status\": \"Downloaded newer image\"}\n".to_vec(),
+                        true,
+                    ),
+                ),
+            ],
+        );
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(
+                "Pull image 'test-registry/test-image:0.1'".to_string(),
+            ))
+            .once()
+            .returning(|_x| ());
+        let cli: Cli = Cli::new(
+            Box::new(mocked_logger),
+            Box::new(MockSystem::new()),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery"]),
+        );
+        let api: DockerApi = DockerApi::new(&socket);
+        api.ensure_image(&image, &cli)
+            .expect("Failed to ensure image");
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_docker_api_pull_error() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let socket: PathBuf = temp_dir.path().join("docker.sock");
+        let _requests = Helper::docker_api_stub(
+            &socket,
+            vec![(
+                "POST /images/create",
+                Helper::http_response(200, b"{\"error\": \"manifest unknown\"}\n".to_vec(), true),
+            )],
+        );
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger.expect_info().returning(|_x| ());
+        let cli: Cli = Cli::new(
+            Box::new(mocked_logger),
+            Box::new(MockSystem::new()),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery"]),
+        );
+        let api: DockerApi = DockerApi::new(&socket);
+        let result: Result<(), BError> = api.pull_image(&image, &cli);
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because the pull failed");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    String::from(
+                        "Failed to pull image 'test-registry/test-image:0.1': manifest unknown"
+                    )
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_docker_api_run() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let socket: PathBuf = temp_dir.path().join("docker.sock");
+        let mut attach: Vec<u8> = frame(1, "hello ");
+        attach.extend(frame(2, "warning\n"));
+        attach.extend(frame(1, "world\n"));
+        let requests = Helper::docker_api_stub(
+            &socket,
+            vec![
+                (
+                    "POST /containers/create?name=bakery-test",
+                    Helper::http_response(
+                        201,
+                        br#"{"Id": "abc123", "Warnings": []}"#.to_vec(),
+                        false,
+                    ),
+                ),
+                (
+                    "POST /containers/abc123/attach",
+                    Helper::http_upgrade_response(attach),
+                ),
+                (
+                    "POST /containers/abc123/start",
+                    Helper::http_response(204, vec![], false),
+                ),
+                (
+                    "POST /containers/abc123/wait",
+                    Helper::http_response(200, br#"{"StatusCode": 3}"#.to_vec(), true),
+                ),
+                (
+                    "DELETE /containers/abc123?force=true",
+                    Helper::http_response(204, vec![], false),
+                ),
+            ],
+        );
+        let image: DockerImage =
+            DockerImage::new("test-registry/test-image:0.1").expect("Invalid docker image format");
+        let config: Value = DockerApi::container_config(
+            &image,
+            &[String::from("echo"), String::from("hello")],
+            &HashMap::new(),
+            temp_dir.path(),
+            &[],
+            false,
+        );
+        assert_eq!(config["Image"], json!("test-registry/test-image:0.1"));
+        let api: DockerApi = DockerApi::new(&socket);
+        let mut stdout: String = String::new();
+        let mut stderr: String = String::new();
+        let exit_code: i64 = api
+            .run("bakery-test", &config, &mut |stream, data| {
+                if stream == 1 {
+                    stdout.push_str(&String::from_utf8_lossy(data));
+                } else {
+                    stderr.push_str(&String::from_utf8_lossy(data));
+                }
+            })
+            .expect("Failed to run container");
+        assert_eq!(exit_code, 3);
+        assert_eq!(stdout, "hello world\n");
+        assert_eq!(stderr, "warning\n");
+        assert!(requests
+            .lock()
+            .unwrap()
+            .contains(&String::from("DELETE /containers/abc123?force=true")));
+    }
+
+    #[test]
+    fn test_docker_api_exec() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let socket: PathBuf = temp_dir.path().join("docker.sock");
+        let requests = Helper::docker_api_stub(
+            &socket,
+            vec![
+                (
+                    "POST /containers/bakery-workspace/exec",
+                    Helper::http_response(201, br#"{"Id": "exec123"}"#.to_vec(), false),
+                ),
+                (
+                    "POST /exec/exec123/start",
+                    Helper::http_upgrade_response(b"hello\r\n".to_vec()),
+                ),
+                (
+                    "GET /exec/exec123/json",
+                    Helper::http_response(200, br#"{"ExitCode": 0}"#.to_vec(), false),
+                ),
+            ],
+        );
+        let api: DockerApi = DockerApi::new(&socket);
+        let mut stdout: String = String::new();
+        let exit_code: i64 = api
+            .exec(
+                "bakery-workspace",
+                &json!({"Cmd": ["echo", "hello"], "Tty": true}),
+                &mut |_stream, data| {
+                    stdout.push_str(&String::from_utf8_lossy(data));
+                },
+            )
+            .expect("Failed to exec in container");
+        assert_eq!(exit_code, 0);
+        /* Using a tty the output is not multiplexed */
+        assert_eq!(stdout, "hello\r\n");
+        assert_eq!(
+            requests.lock().unwrap().clone(),
+            vec![
+                "POST /containers/bakery-workspace/exec",
+                "POST /exec/exec123/start",
+                "GET /exec/exec123/json"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_docker_api_logs() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let socket: PathBuf = temp_dir.path().join("docker.sock");
+        let mut logs: Vec<u8> = frame(1, "line1\n");
+        logs.extend(frame(2, "line2\n"));
+        let _requests = Helper::docker_api_stub(
+            &socket,
+            vec![(
+                "GET /containers/abc123/logs?stdout=true&stderr=true&follow=false",
+                Helper::http_response(200, logs, true),
+            )],
+        );
+        let api: DockerApi = DockerApi::new(&socket);
+        let mut output: Vec<(u8, String)> = vec![];
+        api.logs("abc123", false, false, &mut |stream, data| {
+            output.push((stream, String::from_utf8_lossy(data).to_string()));
+        })
+        .expect("Failed to read logs");
+        assert_eq!(
+            output,
+            vec![
+                (STREAM_STDOUT, String::from("line1\n")),
+                (STREAM_STDERR, String::from("line2\n"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_docker_api_error() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let socket: PathBuf = temp_dir.path().join("docker.sock");
+        let _requests = Helper::docker_api_stub(
+            &socket,
+            vec![(
+                "DELETE /containers/missing?force=true",
+                Helper::http_response(
+                    404,
+                    br#"{"message": "No such container: missing"}"#.to_vec(),
+                    false,
+                ),
+            )],
+        );
+        let api: DockerApi = DockerApi::new(&socket);
+        let result: Result<(), BError> = api.remove_container("missing");
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because the container is missing");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    String::from("Docker API 'DELETE /containers/missing?force=true' failed with status 404: No such container: missing")
+                );
+            }
+        }
+    }
+}
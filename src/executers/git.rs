@@ -0,0 +1,136 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use crate::error::BError;
+
+/*
+ * A git repository that is managed by bakery. The git commands are executed
+ * directly since the output is needed to find out the state of the repository.
+ */
+pub struct Git {
+    dir: PathBuf,
+}
+
+impl Git {
+    fn git(dir: Option<&Path>, args: &[&str]) -> Result<String, BError> {
+        let mut command: Command = Command::new("git");
+        if let Some(d) = dir {
+            command.arg("-C").arg(d);
+        }
+        let output: Output = command
+            .args(args)
+            .output()
+            .map_err(|e| BError::CliError(format!("Failed to execute 'git': {}", e)))?;
+        if !output.status.success() {
+            return Err(BError::CliError(format!(
+                "'git {}' failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn run(&self, args: &[&str]) -> Result<String, BError> {
+        Self::git(Some(&self.dir), args)
+    }
+
+    pub fn new(dir: &Path) -> Self {
+        Git {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /*
+     * Clone the repository into dir, the reference is a branch or a tag
+     * if not set the default branch of the remote is checked out
+     */
+    pub fn clone(url: &str, dir: &Path, reference: Option<&str>) -> Result<Self, BError> {
+        if let Some(parent) = dir.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let dir_str: String = dir.to_string_lossy().to_string();
+        let mut args: Vec<&str> = vec!["clone", "--quiet"];
+        if let Some(r) = reference {
+            args.append(&mut vec!["--branch", r]);
+        }
+        args.append(&mut vec![url, dir_str.as_str()]);
+        Self::git(None, &args)?;
+        Ok(Self::new(dir))
+    }
+
+    pub fn exists(&self) -> bool {
+        self.dir.join(".git").exists()
+    }
+
+    pub fn fetch(&self) -> Result<(), BError> {
+        self.run(&["fetch", "--quiet", "--tags", "origin"])?;
+        Ok(())
+    }
+
+    pub fn checkout_detached(&self, rev: &str) -> Result<(), BError> {
+        self.run(&["checkout", "--quiet", "--detach", rev])?;
+        Ok(())
+    }
+
+    /*
+     * Checkout the branch and fast-forward it to the remote branch, local
+     * commits on the branch are never thrown away
+     */
+    pub fn checkout_branch(&self, branch: &str) -> Result<(), BError> {
+        self.run(&["checkout", "--quiet", branch])?;
+        self.run(&[
+            "merge",
+            "--quiet",
+            "--ff-only",
+            &format!("origin/{}", branch),
+        ])?;
+        Ok(())
+    }
+
+    pub fn head(&self) -> Result<String, BError> {
+        self.run(&["rev-parse", "HEAD"])
+    }
+
+    pub fn rev_parse(&self, rev: &str) -> Result<String, BError> {
+        self.run(&["rev-parse", &format!("{}^{{commit}}", rev)])
+    }
+
+    pub fn has_commit(&self, rev: &str) -> bool {
+        self.rev_parse(rev).is_ok()
+    }
+
+    /*
+     * The current branch or None if HEAD is detached
+     */
+    pub fn branch(&self) -> Option<String> {
+        self.run(&["symbolic-ref", "--quiet", "--short", "HEAD"])
+            .ok()
+    }
+
+    /*
+     * The uncommitted changes to the tracked files below the dir
+     */
+    pub fn diff_head(&self) -> Result<String, BError> {
+        self.run(&["diff", "--binary", "HEAD", "--", "."])
+    }
+
+    /*
+     * The untracked files below the dir relative to the dir
+     */
+    pub fn untracked(&self) -> Result<Vec<String>, BError> {
+        Ok(self
+            .run(&["ls-files", "--others", "--exclude-standard", "--", "."])?
+            .lines()
+            .map(|l| l.to_string())
+            .collect())
+    }
+
+    pub fn is_dirty(&self) -> Result<bool, BError> {
+        Ok(!self.run(&["status", "--porcelain"])?.is_empty())
+    }
+
+    pub fn remote_url(&self) -> Result<String, BError> {
+        self.run(&["remote", "get-url", "origin"])
+    }
+}
@@ -1,15 +1,23 @@
 pub mod bitbake;
+pub mod container;
 pub mod customsubcmd;
 pub mod docker;
+pub mod dockerapi;
+pub mod git;
 pub mod nonbitbake;
 pub mod recipe;
+pub mod runtime;
 
 pub use bitbake::{BBBuildExecuter, BBCleanExecuter};
+pub use container::{ContainerStatus, WsContainer};
 pub use customsubcmd::CustomSubCmdExecuter;
 pub use docker::Docker;
 pub use docker::DockerImage;
+pub use dockerapi::DockerApi;
+pub use git::Git;
 pub use nonbitbake::{NonBBBuildExecuter, NonBBCleanExecuter};
 pub use recipe::Recipe;
+pub use runtime::ContainerRuntime;
 
 use crate::error::BError;
 
@@ -38,7 +38,8 @@ impl<'a> TaskExecuter for NonBBCleanExecuter<'a> {
 
         if !docker_str.is_empty() {
             let image: DockerImage = DockerImage::new(docker_str)?;
-            let docker: Docker = Docker::new(image, interactive);
+            let mut docker: Docker = Docker::new(image, interactive);
+            docker.set_container(self.task_data.container());
             docker.run_cmd(
                 &mut cmd_line,
                 args_env_variables,
@@ -92,7 +93,8 @@ impl<'a> TaskExecuter for NonBBBuildExecuter<'a> {
 
         if !self.task_data.docker_image().is_empty() && self.task_data.docker_image() != "NA" {
             let image: DockerImage = DockerImage::new(self.task_data.docker_image())?;
-            let docker: Docker = Docker::new(image, interactive);
+            let mut docker: Docker = Docker::new(image, interactive);
+            docker.set_container(self.task_data.container());
             docker.run_cmd(&mut cmd_line, env_variables, exec_dir, &self.cli)?;
         } else {
             self.cli.check_call(&cmd_line, env_variables, true)?;
@@ -111,6 +113,7 @@ impl<'a> NonBBBuildExecuter<'a> {
 mod tests {
     use std::collections::HashMap;
     use std::path::PathBuf;
+    use std::sync::Arc;
     use tempdir::TempDir;
 
     use crate::cli::*;
@@ -209,7 +212,9 @@ mod tests {
             .returning(|_x| ());
         let cli: Cli = Cli::new(
             Box::new(mocked_logger),
-            Box::new(BSystem::new()),
+            Box::new(BSystem::with_log_file(Arc::new(LogFile::new(
+                LogFormat::Text,
+            )))),
             clap::Command::new("bakery"),
             Some(vec!["bakery"]),
         );
@@ -0,0 +1,190 @@
+use std::path::PathBuf;
+use users::Groups;
+
+use crate::error::BError;
+use crate::executers::DockerApi;
+
+pub const PODMAN_SOCKET: &str = "/run/podman/podman.sock";
+
+/*
+ * The container runtime used to run the bakery workspace image. Podman is
+ * compatible with the docker CLI and the Docker Engine API but the user
+ * mapping, the group handling and the location of the socket differs.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContainerRuntime {
+    Docker,
+    Podman,
+    RootlessPodman,
+}
+
+impl ContainerRuntime {
+    pub fn new(runtime: &str) -> Result<Self, BError> {
+        match runtime {
+            "docker" => Ok(ContainerRuntime::Docker),
+            "podman" => Ok(ContainerRuntime::Podman),
+            "podman-rootless" => Ok(ContainerRuntime::RootlessPodman),
+            _ => Err(BError::ValueError(format!(
+                "Invalid container runtime '{}', expected one of 'docker', 'podman', 'podman-rootless'",
+                runtime
+            ))),
+        }
+    }
+
+    pub fn binary(&self) -> &str {
+        match self {
+            ContainerRuntime::Docker => "docker",
+            ContainerRuntime::Podman | ContainerRuntime::RootlessPodman => "podman",
+        }
+    }
+
+    /*
+     * Look for the runtime binary in the PATH and fallback to /usr/bin
+     * which is where the binary has been expected so far
+     */
+    pub fn locate(&self) -> Option<PathBuf> {
+        let mut dirs: Vec<PathBuf> = match std::env::var_os("PATH") {
+            Some(path) => std::env::split_paths(&path).collect(),
+            None => vec![],
+        };
+        dirs.push(PathBuf::from("/usr/bin"));
+        dirs.iter()
+            .map(|dir| dir.join(self.binary()))
+            .find(|path| path.is_file())
+    }
+
+    /*
+     * The socket of the Docker Engine API. Podman is reading the socket from
+     * CONTAINER_HOST and a rootless podman is running the socket as a user
+     * service under the runtime dir of the user.
+     */
+    pub fn socket(&self) -> PathBuf {
+        match self {
+            ContainerRuntime::Docker => DockerApi::default_socket(),
+            ContainerRuntime::Podman | ContainerRuntime::RootlessPodman => {
+                if let Ok(host) = std::env::var("CONTAINER_HOST") {
+                    if let Some(path) = host.strip_prefix("unix://") {
+                        return PathBuf::from(path);
+                    }
+                }
+                if *self == ContainerRuntime::Podman {
+                    return PathBuf::from(PODMAN_SOCKET);
+                }
+                match std::env::var("XDG_RUNTIME_DIR") {
+                    Ok(dir) => PathBuf::from(dir).join("podman/podman.sock"),
+                    Err(_e) => PathBuf::from(format!(
+                        "/run/user/{}/podman/podman.sock",
+                        users::get_current_uid()
+                    )),
+                }
+            }
+        }
+    }
+
+    /*
+     * A rootless podman is mapping the user into the container using
+     * keep-id otherwise the files created inside the container would be
+     * owned by a sub uid on the host
+     */
+    pub fn user(&self) -> Vec<String> {
+        match self {
+            ContainerRuntime::RootlessPodman => vec![String::from("--userns=keep-id")],
+            _ => vec![
+                String::from("-u"),
+                format!("{}:{}", users::get_current_uid(), users::get_current_gid()),
+            ],
+        }
+    }
+
+    /*
+     * The docker group is required to be able to use the docker socket
+     * inside the container. Not all distros are creating a docker group
+     * so it is only added if it exists. Podman is not using a group for
+     * the socket but a rootless podman can keep the groups of the user.
+     */
+    pub fn group(&self) -> Vec<String> {
+        match self {
+            ContainerRuntime::Docker => {
+                let cache: users::UsersCache = users::UsersCache::new();
+                match cache.get_group_by_name("docker") {
+                    Some(group) => vec![String::from("--group-add"), group.gid().to_string()],
+                    None => vec![],
+                }
+            }
+            ContainerRuntime::Podman => vec![],
+            ContainerRuntime::RootlessPodman => {
+                vec![String::from("--group-add"), String::from("keep-groups")]
+            }
+        }
+    }
+
+    /*
+     * Mount the socket of the runtime as the docker socket inside the
+     * container so that the docker CLI and the Docker Engine API can be
+     * used inside the container no matter which runtime is used
+     */
+    pub fn socket_volume(&self) -> Vec<String> {
+        let socket: PathBuf = self.socket();
+        if *self != ContainerRuntime::Docker && !socket.exists() {
+            return vec![];
+        }
+        vec![
+            String::from("-v"),
+            format!("{}:/var/run/docker.sock", socket.display()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::BError;
+    use crate::executers::ContainerRuntime;
+
+    #[test]
+    fn test_container_runtime() {
+        let runtime: ContainerRuntime =
+            ContainerRuntime::new("docker").expect("Invalid container runtime");
+        assert_eq!(runtime, ContainerRuntime::Docker);
+        assert_eq!(runtime.binary(), "docker");
+        assert_eq!(
+            runtime.user(),
+            vec![
+                String::from("-u"),
+                format!("{}:{}", users::get_current_uid(), users::get_current_gid())
+            ]
+        );
+        let runtime: ContainerRuntime =
+            ContainerRuntime::new("podman").expect("Invalid container runtime");
+        assert_eq!(runtime, ContainerRuntime::Podman);
+        assert_eq!(runtime.binary(), "podman");
+        assert!(runtime.group().is_empty());
+    }
+
+    #[test]
+    fn test_container_runtime_rootless_podman() {
+        let runtime: ContainerRuntime =
+            ContainerRuntime::new("podman-rootless").expect("Invalid container runtime");
+        assert_eq!(runtime.binary(), "podman");
+        assert_eq!(runtime.user(), vec![String::from("--userns=keep-id")]);
+        assert_eq!(
+            runtime.group(),
+            vec![String::from("--group-add"), String::from("keep-groups")]
+        );
+    }
+
+    #[test]
+    fn test_container_runtime_invalid() {
+        let result: Result<ContainerRuntime, BError> = ContainerRuntime::new("lxc");
+        match result {
+            Ok(_r) => {
+                panic!("We should have recived an error because the runtime is invalid");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    String::from("Invalid container runtime 'lxc', expected one of 'docker', 'podman', 'podman-rootless'")
+                );
+            }
+        }
+    }
+}
@@ -1,8 +1,12 @@
-use std::fs::File;
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use std::fs::{File, Metadata};
+use std::io::Write;
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 use zip::{write::FileOptions, ZipWriter};
 
 use crate::error::BError;
+use crate::fs::CpioWriter;
 
 #[derive(Debug)]
 pub struct Archiver {
@@ -10,6 +14,8 @@ pub struct Archiver {
     name: String,
     extension: String,
     compression: String,
+    level: Option<u32>,
+    source_date_epoch: Option<u64>,
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -46,24 +52,23 @@ impl Archiver {
                     archive_type = "zip".to_string();
                     break;
                 }
-                "tar" if i + 1 < suffixes.len() => {
-                    archive_type = "tar".to_string();
-                    match suffixes[i + 1] {
-                        "gz" => {
-                            compression = "gz".to_string();
-                            break;
-                        }
-                        "bz2" => {
-                            compression = "bz2".to_string();
-                            break;
-                        }
-                        _ => {
-                            return Err(BError::ArchiverError(format!(
-                                "Unsupported compression '{}'!",
-                                suffixes[i + 1]
-                            )));
+                "tar" | "cpio" => {
+                    archive_type = suffixes[i].to_string();
+                    // An archive without a compression suffix is not compressed
+                    if i + 1 < suffixes.len() {
+                        match suffixes[i + 1] {
+                            "gz" | "bz2" | "xz" | "zst" => {
+                                compression = suffixes[i + 1].to_string();
+                            }
+                            _ => {
+                                return Err(BError::ArchiverError(format!(
+                                    "Unsupported compression '{}'!",
+                                    suffixes[i + 1]
+                                )));
+                            }
                         }
                     }
+                    break;
                 }
                 _ => {}
             }
@@ -76,26 +81,87 @@ impl Archiver {
             )));
         }
 
-        if archive_type == "tar" && compression.is_empty() {
-            return Err(BError::ArchiverError(
-                "Archive must have a compression!".to_string(),
-            ));
-        }
-
         Ok(Archiver {
             path: path.clone(),
             name,
             extension: archive_type,
             compression,
+            level: None,
+            source_date_epoch: None,
         })
     }
 
-    pub fn path(&self) -> &PathBuf {
-        &self.path
+    /*
+     * A reproducible archive is created by sorting the entries, clamping the
+     * mtimes to the source date epoch, zeroing the ownership and normalizing
+     * the permissions so that the same input always gives the same archive.
+     */
+    pub fn set_reproducible(&mut self, source_date_epoch: u64) {
+        self.source_date_epoch = Some(source_date_epoch);
+    }
+
+    /*
+     * Only the execute bit is kept for reproducible archives
+     */
+    pub fn normalized_mode(mode: u32) -> u32 {
+        if mode & 0o100 == 0o100 {
+            return 0o755;
+        }
+        0o644
     }
 
-    pub fn name(&self) -> &str {
-        &self.name
+    pub fn clamped_mtime(metadata: &Metadata, source_date_epoch: u64) -> u64 {
+        std::cmp::min(metadata.mtime().max(0) as u64, source_date_epoch)
+    }
+
+    /*
+     * The zip format can only represent dates between 1980 and 2107
+     */
+    fn zip_time(mtime: u64) -> zip::DateTime {
+        match DateTime::<Utc>::from_timestamp(mtime as i64, 0) {
+            Some(t) => zip::DateTime::from_date_and_time(
+                t.year().clamp(1980, 2107) as u16,
+                t.month() as u8,
+                t.day() as u8,
+                t.hour() as u8,
+                t.minute() as u8,
+                t.second() as u8,
+            )
+            .unwrap_or_default(),
+            None => zip::DateTime::default(),
+        }
+    }
+
+    /*
+     * Set the compression level overriding the default level of the
+     * compression used by the archive.
+     */
+    pub fn set_compression_level(&mut self, level: u32) -> Result<(), BError> {
+        let (min, max): (u32, u32) = match (self.extension.as_str(), self.compression.as_str()) {
+            ("zip", _) | (_, "gz") | (_, "xz") => (0, 9),
+            (_, "bz2") => (1, 9),
+            (_, "zst") => (1, 22),
+            _ => {
+                return Err(BError::ArchiverError(format!(
+                    "Compression level is not supported for '{}'!",
+                    self.name
+                )));
+            }
+        };
+
+        if level < min || level > max {
+            return Err(BError::ArchiverError(format!(
+                "Invalid compression level '{}' for '{}', expected {}-{}!",
+                level, self.name, min, max
+            )));
+        }
+
+        self.level = Some(level);
+        Ok(())
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
     }
 
     pub fn extension(&self) -> &str {
@@ -106,6 +172,51 @@ impl Archiver {
         &self.compression
     }
 
+    fn encoder(&self, archive_file: File) -> Result<Box<dyn Write>, BError> {
+        let enc: Box<dyn Write>;
+        match self.compression() {
+            "gz" => {
+                enc = Box::new(flate2::write::GzEncoder::new(
+                    archive_file,
+                    self.level
+                        .map_or(flate2::Compression::default(), flate2::Compression::new),
+                ));
+            }
+            "bz2" => {
+                enc = Box::new(bzip2::write::BzEncoder::new(
+                    archive_file,
+                    self.level
+                        .map_or(bzip2::Compression::default(), bzip2::Compression::new),
+                ));
+            }
+            "xz" => {
+                enc = Box::new(xz2::write::XzEncoder::new(
+                    archive_file,
+                    self.level.unwrap_or(6),
+                ));
+            }
+            "zst" => {
+                enc = Box::new(
+                    zstd::stream::write::Encoder::new(
+                        archive_file,
+                        self.level.unwrap_or(0) as i32,
+                    )?
+                    .auto_finish(),
+                );
+            }
+            "" => {
+                enc = Box::new(archive_file);
+            }
+            _ => {
+                return Err(BError::ArchiverError(format!(
+                    "Unsupported compression '{}'!",
+                    self.compression
+                )));
+            }
+        }
+        Ok(enc)
+    }
+
     pub fn add_files(&self, files: &Vec<PathBuf>, work_dir: &Path) -> Result<(), BError> {
         let mut mode: Mode = Mode::Write;
 
@@ -123,37 +234,46 @@ impl Archiver {
         }
 
         let archive_file: File = File::create(&self.path)?;
+        let mut files: Vec<PathBuf> = files.clone();
+        if self.source_date_epoch.is_some() {
+            files.sort();
+        }
 
         if self.extension() == "tar" {
             if mode == Mode::Append {}
 
-            let enc: Box<dyn std::io::Write>;
-            let mut tar: tar::Builder<Box<dyn std::io::Write>>;
-            if self.compression() == "gz" {
-                enc = Box::new(flate2::write::GzEncoder::new(
-                    archive_file,
-                    flate2::Compression::default(),
-                ));
-            } else if self.compression() == "bz2" {
-                enc = Box::new(bzip2::write::BzEncoder::new(
-                    archive_file,
-                    bzip2::Compression::default(),
-                ));
-            } else {
-                return Err(BError::ArchiverError(format!(
-                    "Unsupported compression '{}'!",
-                    self.compression
-                )));
-            }
-
-            tar = tar::Builder::new(enc);
-            for path in files {
+            let enc: Box<dyn std::io::Write> = self.encoder(archive_file)?;
+            let mut tar: tar::Builder<Box<dyn std::io::Write>> = tar::Builder::new(enc);
+            for path in files.iter() {
                 let striped_path: PathBuf = path.strip_prefix(work_dir.as_os_str())?.to_path_buf();
                 let mut file: File = File::open(path)?;
-                tar.append_file(striped_path, &mut file)?;
+                match self.source_date_epoch {
+                    Some(epoch) => {
+                        let metadata: Metadata = file.metadata()?;
+                        let mut header: tar::Header = tar::Header::new_gnu();
+                        header.set_metadata_in_mode(&metadata, tar::HeaderMode::Deterministic);
+                        header.set_mtime(Self::clamped_mtime(&metadata, epoch));
+                        tar.append_data(&mut header, striped_path, &mut file)?;
+                    }
+                    None => {
+                        tar.append_file(striped_path, &mut file)?;
+                    }
+                }
             }
 
             tar.finish()?;
+        } else if self.extension() == "cpio" {
+            let enc: Box<dyn std::io::Write> = self.encoder(archive_file)?;
+            let mut cpio: CpioWriter<Box<dyn std::io::Write>> = CpioWriter::new(enc);
+            if let Some(epoch) = self.source_date_epoch {
+                cpio.set_reproducible(epoch);
+            }
+            for path in files.iter() {
+                let striped_path: PathBuf = path.strip_prefix(work_dir.as_os_str())?.to_path_buf();
+                cpio.append_file(&striped_path, path)?;
+            }
+
+            cpio.finish()?;
         } else if self.extension() == "zip" {
             if mode == Mode::Append {}
 
@@ -162,15 +282,25 @@ impl Archiver {
             let mut options: FileOptions =
                 zip::write::FileOptions::default().unix_permissions(0o755);
             options = options.large_file(true);
+            if let Some(level) = self.level {
+                options = options.compression_level(Some(level as i32));
+            }
 
-            for path in files {
+            for path in files.iter() {
                 //println!("{}", path.display());
                 //println!("{}", work_dir.display());
                 let striped_path: PathBuf = path.strip_prefix(work_dir.as_os_str())?.to_path_buf();
 
                 let mut file: File = File::open(path)?;
+                let mut file_options: FileOptions = options;
+                if let Some(epoch) = self.source_date_epoch {
+                    let metadata: Metadata = file.metadata()?;
+                    file_options = file_options
+                        .unix_permissions(Self::normalized_mode(metadata.mode()))
+                        .last_modified_time(Self::zip_time(Self::clamped_mtime(&metadata, epoch)));
+                }
 
-                zip.start_file(striped_path.to_string_lossy().to_owned(), options)?;
+                zip.start_file(striped_path.to_string_lossy().to_owned(), file_options)?;
 
                 std::io::copy(&mut file, &mut zip)?;
             }
@@ -184,7 +314,10 @@ impl Archiver {
 
 #[cfg(test)]
 mod tests {
+    use std::fs::File;
+    use std::os::unix::fs::PermissionsExt;
     use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
     use tempdir::TempDir;
 
     use crate::error::BError;
@@ -198,7 +331,6 @@ mod tests {
         let path: &Path = temp_dir.path();
         let archiver_path: PathBuf = path.join("test-archiver.zip");
         let archiver: Archiver = Archiver::new(&archiver_path).expect("Failed to setup archiver!");
-        assert_eq!(archiver.name(), "test-archiver.zip");
         assert_eq!(archiver.extension(), "zip");
         assert_eq!(archiver.compression(), "");
     }
@@ -210,7 +342,6 @@ mod tests {
         let path: &Path = temp_dir.path();
         let archiver_path: PathBuf = path.join("test-x.y.z-archiver.zip");
         let archiver: Archiver = Archiver::new(&archiver_path).expect("Failed to setup archiver!");
-        assert_eq!(archiver.name(), "test-x.y.z-archiver.zip");
         assert_eq!(archiver.extension(), "zip");
         assert_eq!(archiver.compression(), "");
     }
@@ -222,7 +353,6 @@ mod tests {
         let path: &Path = temp_dir.path();
         let archiver_path: PathBuf = path.join("test-archiver.tar.gz");
         let archiver: Archiver = Archiver::new(&archiver_path).expect("Failed to setup archiver!");
-        assert_eq!(archiver.name(), "test-archiver.tar.gz");
         assert_eq!(archiver.extension(), "tar");
         assert_eq!(archiver.compression(), "gz");
     }
@@ -234,7 +364,6 @@ mod tests {
         let path: &Path = temp_dir.path();
         let archiver_path: PathBuf = path.join("test-archiver.tar.bz2");
         let archiver: Archiver = Archiver::new(&archiver_path).expect("Failed to setup archiver!");
-        assert_eq!(archiver.name(), "test-archiver.tar.bz2");
         assert_eq!(archiver.extension(), "tar");
         assert_eq!(archiver.compression(), "bz2");
     }
@@ -246,23 +375,53 @@ mod tests {
         let path: &Path = temp_dir.path();
         let archiver_path: PathBuf = path.join("test-x.y.z-archiver.tar.bz2");
         let archiver: Archiver = Archiver::new(&archiver_path).expect("Failed to setup archiver!");
-        assert_eq!(archiver.name(), "test-x.y.z-archiver.tar.bz2");
         assert_eq!(archiver.extension(), "tar");
         assert_eq!(archiver.compression(), "bz2");
     }
 
-    /*
     #[test]
     fn test_archiver_tar_xz() {
-        let temp_dir: TempDir = TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
         let path: &Path = temp_dir.path();
         let archiver_path: PathBuf = path.join("test-archiver.tar.xz");
         let archiver: Archiver = Archiver::new(&archiver_path).expect("Failed to setup archiver!");
-        assert_eq!(archiver.name(), "test-archiver.tar.xz");
         assert_eq!(archiver.extension(), "tar");
         assert_eq!(archiver.compression(), "xz");
     }
-    */
+
+    #[test]
+    fn test_archiver_tar_zst() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let path: &Path = temp_dir.path();
+        let archiver_path: PathBuf = path.join("test-archiver.tar.zst");
+        let archiver: Archiver = Archiver::new(&archiver_path).expect("Failed to setup archiver!");
+        assert_eq!(archiver.extension(), "tar");
+        assert_eq!(archiver.compression(), "zst");
+    }
+
+    #[test]
+    fn test_archiver_tar() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let path: &Path = temp_dir.path();
+        let archiver_path: PathBuf = path.join("test-archiver.tar");
+        let archiver: Archiver = Archiver::new(&archiver_path).expect("Failed to setup archiver!");
+        assert_eq!(archiver.extension(), "tar");
+        assert_eq!(archiver.compression(), "");
+    }
+
+    #[test]
+    fn test_archiver_cpio_gz() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let path: &Path = temp_dir.path();
+        let archiver_path: PathBuf = path.join("test-archiver.cpio.gz");
+        let archiver: Archiver = Archiver::new(&archiver_path).expect("Failed to setup archiver!");
+        assert_eq!(archiver.extension(), "cpio");
+        assert_eq!(archiver.compression(), "gz");
+    }
 
     #[test]
     fn test_archiver_error_unsupported_archive() {
@@ -293,16 +452,31 @@ mod tests {
     }
 
     #[test]
-    fn test_archiver_error_no_compression() {
+    fn test_archiver_compression_level() {
         let temp_dir: TempDir =
             TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
         let path: &Path = temp_dir.path();
-        let archiver_path: PathBuf = path.join("test-archiver.tar");
-        let error: BError = Archiver::new(&archiver_path)
-            .expect_err("We are expecting an error but got an Archiver");
+        let mut archiver: Archiver =
+            Archiver::new(&path.join("test-archiver.tar.zst")).expect("Failed to setup archiver!");
+        archiver
+            .set_compression_level(19)
+            .expect("Failed to set compression level");
+        let error: BError = archiver
+            .set_compression_level(23)
+            .expect_err("We are expecting an error but got Ok");
         assert_eq!(
             error.to_string(),
-            "Unsupported archive 'test-archiver.tar'!".to_string()
+            "Invalid compression level '23' for 'test-archiver.tar.zst', expected 1-22!"
+                .to_string()
+        );
+        let mut archiver: Archiver =
+            Archiver::new(&path.join("test-archiver.tar")).expect("Failed to setup archiver!");
+        let error: BError = archiver
+            .set_compression_level(1)
+            .expect_err("We are expecting an error but got Ok");
+        assert_eq!(
+            error.to_string(),
+            "Compression level is not supported for 'test-archiver.tar'!".to_string()
         );
     }
 
@@ -398,4 +572,223 @@ mod tests {
         // we are iterating over the content and is collecting the file names
         Helper::verify_archived_files(&files, &archived_files, work_dir);
     }
+
+    #[test]
+    fn test_archiver_file_tar_xz() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: &Path = temp_dir.path();
+        let archiver_path: PathBuf = work_dir.join("test-archiver.tar.xz");
+        let files: Vec<PathBuf> = vec![
+            PathBuf::from(work_dir.join("dir1/file1.txt")),
+            PathBuf::from(work_dir.join("file2.txt")),
+            PathBuf::from(work_dir.join("dir2/file3.txt")),
+            PathBuf::from(work_dir.join("dir1/dir3/file4.txt")),
+        ];
+
+        Helper::create_test_files(&files);
+
+        let archiver: Archiver = Archiver::new(&archiver_path).expect("Failed to setup archiver!");
+        archiver
+            .add_files(&files, work_dir)
+            .expect("Failed too create archive test-archive.tar.xz");
+        assert!(archiver_path.exists());
+
+        let archived_files: Vec<PathBuf> =
+            Helper::list_files_in_archive(&archiver, &work_dir.join(PathBuf::from("unpack/")))
+                .expect("Failed to list files in archive");
+        Helper::verify_archived_files(&files, &archived_files, work_dir);
+    }
+
+    #[test]
+    fn test_archiver_file_tar_zst() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: &Path = temp_dir.path();
+        let archiver_path: PathBuf = work_dir.join("test-archiver.tar.zst");
+        let files: Vec<PathBuf> = vec![
+            PathBuf::from(work_dir.join("dir1/file1.txt")),
+            PathBuf::from(work_dir.join("file2.txt")),
+            PathBuf::from(work_dir.join("dir2/file3.txt")),
+            PathBuf::from(work_dir.join("dir1/dir3/file4.txt")),
+        ];
+
+        Helper::create_test_files(&files);
+
+        let mut archiver: Archiver =
+            Archiver::new(&archiver_path).expect("Failed to setup archiver!");
+        archiver
+            .set_compression_level(19)
+            .expect("Failed to set compression level");
+        archiver
+            .add_files(&files, work_dir)
+            .expect("Failed too create archive test-archive.tar.zst");
+        assert!(archiver_path.exists());
+
+        let archived_files: Vec<PathBuf> =
+            Helper::list_files_in_archive(&archiver, &work_dir.join(PathBuf::from("unpack/")))
+                .expect("Failed to list files in archive");
+        Helper::verify_archived_files(&files, &archived_files, work_dir);
+    }
+
+    #[test]
+    fn test_archiver_file_tar() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: &Path = temp_dir.path();
+        let archiver_path: PathBuf = work_dir.join("test-archiver.tar");
+        let files: Vec<PathBuf> = vec![
+            PathBuf::from(work_dir.join("dir1/file1.txt")),
+            PathBuf::from(work_dir.join("file2.txt")),
+            PathBuf::from(work_dir.join("dir2/file3.txt")),
+            PathBuf::from(work_dir.join("dir1/dir3/file4.txt")),
+        ];
+
+        Helper::create_test_files(&files);
+
+        let archiver: Archiver = Archiver::new(&archiver_path).expect("Failed to setup archiver!");
+        archiver
+            .add_files(&files, work_dir)
+            .expect("Failed too create archive test-archive.tar");
+        assert!(archiver_path.exists());
+
+        let archived_files: Vec<PathBuf> =
+            Helper::list_files_in_archive(&archiver, &work_dir.join(PathBuf::from("unpack/")))
+                .expect("Failed to list files in archive");
+        Helper::verify_archived_files(&files, &archived_files, work_dir);
+    }
+
+    #[test]
+    fn test_archiver_file_cpio_gz() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: &Path = temp_dir.path();
+        let archiver_path: PathBuf = work_dir.join("test-archiver.cpio.gz");
+        let files: Vec<PathBuf> = vec![
+            PathBuf::from(work_dir.join("dir1/file1.txt")),
+            PathBuf::from(work_dir.join("file2.txt")),
+            PathBuf::from(work_dir.join("dir2/file3.txt")),
+            PathBuf::from(work_dir.join("dir1/dir3/file4.txt")),
+        ];
+
+        Helper::create_test_files(&files);
+
+        let mut archiver: Archiver =
+            Archiver::new(&archiver_path).expect("Failed to setup archiver!");
+        archiver
+            .set_compression_level(9)
+            .expect("Failed to set compression level");
+        archiver
+            .add_files(&files, work_dir)
+            .expect("Failed too create archive test-archive.cpio.gz");
+        assert!(archiver_path.exists());
+
+        let archived_files: Vec<PathBuf> =
+            Helper::list_files_in_archive(&archiver, &work_dir.join(PathBuf::from("unpack/")))
+                .expect("Failed to list files in archive");
+        Helper::verify_archived_files(&files, &archived_files, work_dir);
+    }
+
+    #[test]
+    fn test_archiver_file_cpio() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: &Path = temp_dir.path();
+        let archiver_path: PathBuf = work_dir.join("test-archiver.cpio");
+        let files: Vec<PathBuf> = vec![
+            PathBuf::from(work_dir.join("dir1/file1.txt")),
+            PathBuf::from(work_dir.join("file2.txt")),
+            PathBuf::from(work_dir.join("dir2/file3.txt")),
+            PathBuf::from(work_dir.join("dir1/dir3/file4.txt")),
+        ];
+
+        Helper::create_test_files(&files);
+
+        let archiver: Archiver = Archiver::new(&archiver_path).expect("Failed to setup archiver!");
+        archiver
+            .add_files(&files, work_dir)
+            .expect("Failed too create archive test-archive.cpio");
+        assert!(archiver_path.exists());
+
+        let archived_files: Vec<PathBuf> =
+            Helper::list_files_in_archive(&archiver, &work_dir.join(PathBuf::from("unpack/")))
+                .expect("Failed to list files in archive");
+        Helper::verify_archived_files(&files, &archived_files, work_dir);
+    }
+
+    #[test]
+    fn test_archiver_reproducible() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: &Path = temp_dir.path();
+        let epoch: u64 = 1700000000;
+        let files: Vec<PathBuf> = vec![
+            work_dir.join("dir1/file1.txt"),
+            work_dir.join("file2.txt"),
+            work_dir.join("dir2/file3.sh"),
+        ];
+        Helper::create_test_files(&files);
+        std::fs::set_permissions(&files[2], std::fs::Permissions::from_mode(0o775))
+            .expect("Failed to set permissions");
+
+        for ext in ["tar.gz", "zip", "cpio.gz", "tar.zst"] {
+            let path1: PathBuf = work_dir.join(format!("archives/test-archiver1.{}", ext));
+            let path2: PathBuf = work_dir.join(format!("archives/test-archiver2.{}", ext));
+            let mut archiver: Archiver = Archiver::new(&path1).expect("Failed to setup archiver!");
+            archiver.set_reproducible(epoch);
+            archiver
+                .add_files(&files, work_dir)
+                .expect("Failed to create archive");
+
+            // Change the order, permissions and mtimes of the files
+            std::fs::set_permissions(&files[2], std::fs::Permissions::from_mode(0o700))
+                .expect("Failed to set permissions");
+            File::options()
+                .write(true)
+                .open(&files[0])
+                .expect("Failed to open file")
+                .set_modified(SystemTime::now() + Duration::from_secs(3600))
+                .expect("Failed to set mtime");
+            let reversed: Vec<PathBuf> = files.iter().rev().cloned().collect();
+            let mut archiver: Archiver = Archiver::new(&path2).expect("Failed to setup archiver!");
+            archiver.set_reproducible(epoch);
+            archiver
+                .add_files(&reversed, work_dir)
+                .expect("Failed to create archive");
+
+            assert_eq!(
+                std::fs::read(&path1).expect("Failed to read archive"),
+                std::fs::read(&path2).expect("Failed to read archive"),
+                "Archive '{}' is not reproducible",
+                ext
+            );
+        }
+
+        let file: File = File::open(work_dir.join("archives/test-archiver1.tar.gz"))
+            .expect("Failed to open archive");
+        let mut tar: tar::Archive<flate2::read::GzDecoder<File>> =
+            tar::Archive::new(flate2::read::GzDecoder::new(file));
+        let entries: Vec<(String, u64, u64, u32)> = tar
+            .entries()
+            .expect("Failed to read archive")
+            .map(|e| {
+                let entry = e.expect("Failed to read entry");
+                let header: &tar::Header = entry.header();
+                (
+                    entry.path().unwrap().to_string_lossy().to_string(),
+                    header.mtime().unwrap(),
+                    header.uid().unwrap(),
+                    header.mode().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (String::from("dir1/file1.txt"), epoch, 0, 0o644),
+                (String::from("dir2/file3.sh"), epoch, 0, 0o755),
+                (String::from("file2.txt"), epoch, 0, 0o644),
+            ]
+        );
+    }
 }
@@ -0,0 +1,337 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::error::BError;
+
+/* The max number of error messages listed when no failed task could be found */
+const MAX_ERRORS: usize = 10;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BBFailedTask {
+    /* The name of the recipe derived from the recipe file */
+    pub recipe: String,
+    /* The recipe file including any multiconfig or virtual prefix */
+    pub recipe_file: String,
+    pub task: String,
+    pub log: Option<PathBuf>,
+}
+
+/*
+ * Collects the failures from a bitbake build by parsing the output from bitbake.
+ * The output is read from the console log that bitbake is writing to
+ * tmp/log/cooker/<machine>/console-latest.log in the bitbake build dir. Each
+ * failed task is listed with the recipe and the path to the task log. If the
+ * path to the task log is not part of the output it is looked up in the temp
+ * dir of the recipe work dir under tmp/work.
+ */
+pub struct BBFailures {
+    tasks: Vec<BBFailedTask>,
+    errors: Vec<String>,
+}
+
+impl BBFailures {
+    fn recipe_name(recipe_file: &str) -> String {
+        /* Strip any prefix like mc:<multiconfig>: or virtual:native: */
+        let file: &str = recipe_file.rsplit(':').next().unwrap_or(recipe_file);
+        let stem: String = Path::new(file)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut name: String = stem.split('_').next().unwrap_or_default().to_string();
+        if recipe_file.contains("virtual:native:") {
+            name.push_str("-native");
+        }
+        name
+    }
+
+    fn is_task_log(path: &Path, recipe: &str, task: &str) -> bool {
+        let file_name: String = path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let log: String = format!("log.{}", task);
+        (file_name == log || file_name.starts_with(&format!("{}.", log)))
+            && path.iter().any(|c| c.to_string_lossy() == recipe)
+    }
+
+    /*
+     * Add a failed task unless it has already been added and return the index
+     * of the task
+     */
+    fn add_task(&mut self, failed: &str, logs: &[PathBuf]) -> Option<usize> {
+        let (recipe_file, task) = match failed.trim().rsplit_once(':') {
+            Some((recipe_file, task)) if task.starts_with("do_") => (recipe_file, task),
+            _ => return None,
+        };
+        if let Some(index) = self
+            .tasks
+            .iter()
+            .position(|t| t.recipe_file == recipe_file && t.task == task)
+        {
+            return Some(index);
+        }
+        let recipe: String = Self::recipe_name(recipe_file);
+        let log: Option<PathBuf> = logs
+            .iter()
+            .rev()
+            .find(|l| Self::is_task_log(l, &recipe, task))
+            .cloned();
+        self.tasks.push(BBFailedTask {
+            recipe,
+            recipe_file: recipe_file.to_string(),
+            task: task.to_string(),
+            log,
+        });
+        Some(self.tasks.len() - 1)
+    }
+
+    pub fn new() -> Self {
+        BBFailures {
+            tasks: vec![],
+            errors: vec![],
+        }
+    }
+
+    /*
+     * Parse the output from bitbake. A failed task is reported by bitbake as
+     *
+     * ERROR: Logfile of failure stored in: <path>/temp/log.do_compile.1234
+     * ERROR: Task (<recipe file>:do_compile) failed with exit code '1'
+     *
+     * and then once more in the summary at the end of the build
+     *
+     * Summary: 1 task failed:
+     *   <recipe file>:do_compile
+     *     log: <path>/temp/log.do_compile.1234
+     */
+    pub fn from_output(output: &str) -> Self {
+        let mut failures: BBFailures = BBFailures::new();
+        let mut logs: Vec<PathBuf> = vec![];
+        let mut in_summary: bool = false;
+        let mut current: Option<usize> = None;
+
+        for line in output.lines() {
+            let line: &str = line.trim();
+            if let Some(path) = line.strip_prefix("ERROR: Logfile of failure stored in:") {
+                logs.push(PathBuf::from(path.trim()));
+            } else if let Some(task) = line
+                .strip_prefix("ERROR: Task (")
+                .and_then(|l| l.split_once(") failed"))
+                .map(|(t, _)| t)
+            {
+                failures.add_task(task, &logs);
+            } else if line.starts_with("Summary:") {
+                current = None;
+                in_summary = line.ends_with("failed:");
+            } else if in_summary {
+                if let Some(path) = line.strip_prefix("log:") {
+                    let path: PathBuf = PathBuf::from(path.trim());
+                    if let Some(task) = current.and_then(|i| failures.tasks.get_mut(i)) {
+                        if task.log.is_none() {
+                            task.log = Some(path.clone());
+                        }
+                    }
+                    logs.push(path);
+                } else {
+                    current = failures.add_task(line, &logs);
+                }
+            } else if line.starts_with("ERROR:") && failures.errors.len() < MAX_ERRORS {
+                failures.errors.push(line.to_string());
+            }
+        }
+
+        failures
+    }
+
+    /*
+     * Read the failures from the console logs in the bitbake build dir. Only
+     * console logs that have been written since the build was started are used
+     * so that failures from a previous build are not reported. The file times
+     * are using a coarse clock so a second of slack is allowed. Bitbake is using
+     * tmp as TMPDIR but depending on the distro it could be e.g. tmp-glibc.
+     */
+    pub fn from_build_dir(build_dir: &Path, since: SystemTime) -> Result<Self, BError> {
+        let mut failures: BBFailures = BBFailures::new();
+        let pattern: String = format!(
+            "{}/tmp*/log/cooker/*/console-latest.log",
+            build_dir.display()
+        );
+        for entry in glob::glob(&pattern)?.flatten() {
+            let modified: SystemTime = match std::fs::metadata(&entry).and_then(|m| m.modified()) {
+                Ok(modified) => modified,
+                Err(_e) => continue,
+            };
+            if modified + Duration::from_secs(1) < since {
+                continue;
+            }
+            let content: String = std::fs::read_to_string(&entry)?;
+            failures.merge(BBFailures::from_output(&content));
+        }
+        failures.resolve_logs(build_dir)?;
+        Ok(failures)
+    }
+
+    fn merge(&mut self, other: BBFailures) {
+        for task in other.tasks {
+            if !self.tasks.contains(&task) {
+                self.tasks.push(task);
+            }
+        }
+        for error in other.errors {
+            if self.errors.len() < MAX_ERRORS {
+                self.errors.push(error);
+            }
+        }
+    }
+
+    /*
+     * Look up the task log for any failed task where the path was not part of
+     * the output. The log.<task> is a symlink to the log of the latest run.
+     */
+    fn resolve_logs(&mut self, build_dir: &Path) -> Result<(), BError> {
+        for task in self.tasks.iter_mut().filter(|t| t.log.is_none()) {
+            let pattern: String = format!(
+                "{}/tmp*/work/*/{}/*/temp/log.{}",
+                build_dir.display(),
+                glob::Pattern::escape(&task.recipe),
+                task.task
+            );
+            task.log = glob::glob(&pattern)?.flatten().next();
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty() && self.errors.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        let mut summary: String = String::new();
+        if !self.tasks.is_empty() {
+            summary.push_str(&format!(
+                "Bitbake failed, {} task(s) failed:",
+                self.tasks.len()
+            ));
+            for task in self.tasks.iter() {
+                summary.push_str(&format!(
+                    "\n  {} {} ({})",
+                    task.recipe, task.task, task.recipe_file
+                ));
+                match &task.log {
+                    Some(log) => summary.push_str(&format!("\n    log: {}", log.display())),
+                    None => summary.push_str("\n    log: not found"),
+                }
+            }
+        } else if !self.errors.is_empty() {
+            summary.push_str("Bitbake failed with errors:");
+            for error in self.errors.iter() {
+                summary.push_str(&format!("\n  {}", error));
+            }
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::SystemTime;
+    use tempdir::TempDir;
+
+    use crate::fs::bbfailures::BBFailedTask;
+    use crate::fs::BBFailures;
+
+    #[test]
+    fn test_bbfailures_output() {
+        let output: &str = r#"
+NOTE: Executing Tasks
+ERROR: foo-1.0-r0 do_compile: oe_runmake failed
+ERROR: Logfile of failure stored in: /build/tmp/work/cortexa53-poky-linux/foo/1.0-r0/temp/log.do_compile.1234
+ERROR: Task (/layers/meta-foo/recipes-foo/foo/foo_1.0.bb:do_compile) failed with exit code '1'
+ERROR: Task (virtual:native:/layers/meta-bar/recipes-bar/bar/bar_git.bb:do_fetch) failed with exit code '1'
+NOTE: Tasks Summary: Attempted 5000 tasks of which 4990 didn't need to be rerun and 2 failed.
+
+Summary: 2 tasks failed:
+  /layers/meta-foo/recipes-foo/foo/foo_1.0.bb:do_compile
+    log: /build/tmp/work/cortexa53-poky-linux/foo/1.0-r0/temp/log.do_compile.1234
+  virtual:native:/layers/meta-bar/recipes-bar/bar/bar_git.bb:do_fetch
+    log: /build/tmp/work/x86_64-linux/bar-native/git-r0/temp/log.do_fetch.5678
+Summary: There were 2 ERROR messages, returning a non-zero exit code.
+"#;
+        let failures: BBFailures = BBFailures::from_output(output);
+        assert_eq!(
+            &failures.tasks,
+            &vec![
+                BBFailedTask {
+                    recipe: String::from("foo"),
+                    recipe_file: String::from("/layers/meta-foo/recipes-foo/foo/foo_1.0.bb"),
+                    task: String::from("do_compile"),
+                    log: Some(PathBuf::from(
+                        "/build/tmp/work/cortexa53-poky-linux/foo/1.0-r0/temp/log.do_compile.1234"
+                    )),
+                },
+                BBFailedTask {
+                    recipe: String::from("bar-native"),
+                    recipe_file: String::from(
+                        "virtual:native:/layers/meta-bar/recipes-bar/bar/bar_git.bb"
+                    ),
+                    task: String::from("do_fetch"),
+                    log: Some(PathBuf::from(
+                        "/build/tmp/work/x86_64-linux/bar-native/git-r0/temp/log.do_fetch.5678"
+                    )),
+                },
+            ]
+        );
+        assert_eq!(
+            failures.summary(),
+            "Bitbake failed, 2 task(s) failed:\n  foo do_compile (/layers/meta-foo/recipes-foo/foo/foo_1.0.bb)\n    log: /build/tmp/work/cortexa53-poky-linux/foo/1.0-r0/temp/log.do_compile.1234\n  bar-native do_fetch (virtual:native:/layers/meta-bar/recipes-bar/bar/bar_git.bb)\n    log: /build/tmp/work/x86_64-linux/bar-native/git-r0/temp/log.do_fetch.5678"
+        );
+    }
+
+    #[test]
+    fn test_bbfailures_errors() {
+        let output: &str = r#"
+ERROR: Nothing PROVIDES 'foo-image'
+Summary: There was 1 ERROR message, returning a non-zero exit code.
+"#;
+        let failures: BBFailures = BBFailures::from_output(output);
+        assert!(failures.tasks.is_empty());
+        assert_eq!(
+            failures.summary(),
+            "Bitbake failed with errors:\n  ERROR: Nothing PROVIDES 'foo-image'"
+        );
+        assert!(BBFailures::from_output("NOTE: Tasks Summary").is_empty());
+    }
+
+    #[test]
+    fn test_bbfailures_build_dir() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let build_dir: PathBuf = temp_dir.path().join("build");
+        let since: SystemTime = SystemTime::now();
+        let cooker_dir: PathBuf = build_dir.join("tmp-glibc/log/cooker/qemuarm64");
+        let temp: PathBuf = build_dir.join("tmp-glibc/work/cortexa53-oe-linux/foo/1.0-r0/temp");
+        std::fs::create_dir_all(&cooker_dir).expect("Failed to create cooker dir");
+        std::fs::create_dir_all(&temp).expect("Failed to create temp dir");
+        std::fs::write(temp.join("log.do_install"), "install failed")
+            .expect("Failed to write task log");
+        std::fs::write(
+            cooker_dir.join("console-latest.log"),
+            "ERROR: Task (/layers/meta-foo/recipes-foo/foo/foo_1.0.bb:do_install) failed with exit code '1'\n",
+        )
+        .expect("Failed to write console log");
+        let failures: BBFailures =
+            BBFailures::from_build_dir(&build_dir, since).expect("Failed to read failures");
+        assert_eq!(failures.tasks.len(), 1);
+        assert_eq!(failures.tasks[0].recipe, "foo");
+        assert_eq!(failures.tasks[0].log, Some(temp.join("log.do_install")));
+
+        /* A console log from a previous build is not used */
+        let failures: BBFailures = BBFailures::from_build_dir(
+            &build_dir,
+            SystemTime::now() + std::time::Duration::from_secs(60),
+        )
+        .expect("Failed to read failures");
+        assert!(failures.is_empty());
+    }
+}
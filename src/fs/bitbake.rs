@@ -1,11 +1,11 @@
 use std::io::Read;
 use std::io::Write;
 use std::path::PathBuf;
-use std::result;
 
 use crate::cli::Cli;
 use crate::data::WsBitbakeData;
 use crate::error::BError;
+use crate::fs::LocalConf;
 
 static BBLAYERS_PATTERNS: [&str; 4] = ["BBLAYERS ?=", "BBLAYERS?=", "BBLAYERS=", "BBLAYERS ="];
 
@@ -16,7 +16,6 @@ pub struct BitbakeConf {
     local_conf_content: String,
     bblayers_conf_content: String,
     force: bool,
-    bb_variables: Vec<String>,
 }
 
 impl BitbakeConf {
@@ -102,31 +101,26 @@ impl BitbakeConf {
         Ok(true)
     }
 
+    fn conf_content(&self, content: &str) -> String {
+        let mut conf_str: String = String::from("# AUTO GENERATED\n");
+        conf_str.push_str(content);
+        conf_str
+    }
+
     fn create_bb_conf_file(
         &self,
         cli: &Cli,
         conf_path: &PathBuf,
         content: &str,
-        bb_variables: Option<&Vec<String>>,
         force: bool,
     ) -> Result<(), BError> {
-        let mut conf_str: String = String::from("# AUTO GENERATED\n");
-        conf_str.push_str(content);
+        let conf_str: String = self.conf_content(content);
         let file_name: String = conf_path
             .file_name()
             .unwrap_or_default()
             .to_string_lossy()
             .to_string();
 
-        match bb_variables {
-            Some(variables) => {
-                for line in variables {
-                    conf_str.push_str(format!("{}\n", line).as_str());
-                }
-            }
-            None => {}
-        }
-
         if force {
             cli.info(format!("Autogenerate {}", conf_path.display()));
             self.write_bb_build_conf(&conf_path, &conf_str)?;
@@ -154,37 +148,65 @@ impl BitbakeConf {
             cli,
             &self.bblayers_conf_path,
             &self.bblayers_conf_content,
-            None,
             self.force,
         )
     }
 
     pub fn create_local_conf(&self, cli: &Cli) -> Result<(), BError> {
+        /* Secrets should never end up in the local.conf */
+        if cli.contains_secret(&self.local_conf_content()) {
+            return Err(BError::ValueError(format!(
+                "The local.conf '{}' would contain a secret, secrets can only be exposed to tasks as env variables or files",
+                self.local_conf_path.display()
+            )));
+        }
         self.create_bb_conf_file(
             cli,
             &self.local_conf_path,
             &self.local_conf_content,
-            Some(&self.bb_variables),
             self.force,
         )
     }
 
+    /*
+     * The content of the local.conf including the bitbake variables
+     * as it will be generated by bakery
+     */
+    pub fn local_conf_content(&self) -> String {
+        self.conf_content(&self.local_conf_content)
+    }
+
+    pub fn bblayers_conf_content(&self) -> String {
+        self.conf_content(&self.bblayers_conf_content)
+    }
+
     pub fn create_bitbake_configs(&self, cli: &Cli) -> Result<(), BError> {
         std::fs::create_dir_all(&self.build_conf_dir)?;
         self.create_local_conf(cli)?;
         self.create_bblayers_conf(cli)
     }
 
-    pub fn new(bitbake: &WsBitbakeData, bb_variables: &Vec<String>, force: bool) -> Self {
-        Self::construct(
+    pub fn new(
+        bitbake: &WsBitbakeData,
+        bb_variables: &Vec<String>,
+        force: bool,
+    ) -> Result<Self, BError> {
+        /*
+         * The bitbake variables from the build command are added to the
+         * local.conf model so duplicates are dropped and conflicts with
+         * the build config are reported
+         */
+        let mut local_conf: LocalConf = bitbake.local_conf_vars();
+        local_conf.add_lines(bb_variables, "bakery build");
+        local_conf.check()?;
+        Ok(Self::construct(
             &bitbake.build_config_dir(),
             &bitbake.local_conf_path(),
             &bitbake.bblayers_conf_path(),
-            bitbake.local_conf(),
-            bitbake.bblayers_conf(),
+            local_conf.to_string(),
+            bitbake.bblayers_conf_discovered()?,
             force,
-            bb_variables.clone(),
-        )
+        ))
     }
 
     pub fn construct(
@@ -194,7 +216,6 @@ impl BitbakeConf {
         local_conf_content: String,
         bblayers_conf_content: String,
         force: bool,
-        bb_variables: Vec<String>,
     ) -> Self {
         BitbakeConf {
             build_conf_dir: build_conf_dir.clone(),
@@ -203,7 +224,6 @@ impl BitbakeConf {
             local_conf_content,
             bblayers_conf_content,
             force,
-            bb_variables,
         }
     }
 }
@@ -213,10 +233,13 @@ mod tests {
     use std::fs::File;
     use std::io::Read;
     use std::path::{Path, PathBuf};
+    use std::sync::Arc;
     use tempdir::TempDir;
 
-    use crate::cli::{BSystem, Cli, MockLogger};
+    use crate::cli::{BSystem, Cli, LogFile, LogFormat, MockLogger};
+    use crate::data::WsBitbakeData;
     use crate::fs::BitbakeConf;
+    use crate::workspace::WsSettingsHandler;
 
     #[test]
     fn test_bitbake_conf_create_local_conf() {
@@ -231,7 +254,6 @@ mod tests {
         local_conf_content.push_str("LOCAL_CONF_TEST_LINE ?= \"2\"\n");
         local_conf_content.push_str("LOCAL_CONF_TEST_LINE ?= \"3\"\n");
         let bblayers_conf_content: String = String::new();
-        let bb_variables: Vec<String> = vec![];
         let force: bool = false;
         let mut mocked_logger: MockLogger = MockLogger::new();
         mocked_logger
@@ -244,7 +266,9 @@ mod tests {
             .returning(|_x| ());
         let cli: Cli = Cli::new(
             Box::new(mocked_logger),
-            Box::new(BSystem::new()),
+            Box::new(BSystem::with_log_file(Arc::new(LogFile::new(
+                LogFormat::Text,
+            )))),
             clap::Command::new("bakery"),
             None,
         );
@@ -255,7 +279,6 @@ mod tests {
             local_conf_content.clone(),
             bblayers_conf_content,
             force,
-            bb_variables,
         );
         std::fs::create_dir_all(&bitbake_conf_path).expect("Failed to create bitbake conf dir");
         conf.create_local_conf(&cli)
@@ -269,6 +292,49 @@ mod tests {
         assert_eq!(validate_local_conf, contents);
     }
 
+    #[test]
+    fn test_bitbake_conf_local_conf_secret() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let path: &Path = temp_dir.path();
+        let bitbake_conf_path: PathBuf = path.join("conf");
+        let local_conf_path: PathBuf = bitbake_conf_path.join("local.conf");
+        let bblayers_conf_path: PathBuf = bitbake_conf_path.join("bblayers.conf");
+        let cli: Cli = Cli::new(
+            Box::new(MockLogger::new()),
+            Box::new(BSystem::with_log_file(Arc::new(LogFile::new(
+                LogFormat::Text,
+            )))),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery"]),
+        );
+        cli.add_secret("secret-token");
+        let conf: BitbakeConf = BitbakeConf::construct(
+            &bitbake_conf_path,
+            &local_conf_path,
+            &bblayers_conf_path,
+            String::from("UPLOAD_TOKEN = \"secret-token\"\n"),
+            String::new(),
+            false,
+        );
+        let result = conf.create_local_conf(&cli);
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because the local.conf contains a secret");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!(
+                        "The local.conf '{}' would contain a secret, secrets can only be exposed to tasks as env variables or files",
+                        local_conf_path.display()
+                    )
+                );
+            }
+        }
+        assert!(!local_conf_path.exists());
+    }
+
     #[test]
     fn test_bitbake_conf_create_bblayers_conf() {
         let temp_dir: TempDir =
@@ -282,7 +348,6 @@ mod tests {
         bblayers_conf_content.push_str("BBLAYERS_CONF_TEST_LINE ?= \"1\"\n");
         bblayers_conf_content.push_str("BBLAYERS_CONF_TEST_LINE ?= \"2\"\n");
         bblayers_conf_content.push_str("BBLAYERS_CONF_TEST_LINE ?= \"3\"\n");
-        let bb_variables: Vec<String> = vec![];
         let force: bool = false;
         let mut mocked_logger: MockLogger = MockLogger::new();
         mocked_logger
@@ -295,7 +360,9 @@ mod tests {
             .returning(|_x| ());
         let cli: Cli = Cli::new(
             Box::new(mocked_logger),
-            Box::new(BSystem::new()),
+            Box::new(BSystem::with_log_file(Arc::new(LogFile::new(
+                LogFormat::Text,
+            )))),
             clap::Command::new("bakery"),
             None,
         );
@@ -306,7 +373,6 @@ mod tests {
             local_conf_content,
             bblayers_conf_content.clone(),
             force,
-            bb_variables,
         );
         std::fs::create_dir_all(&bitbake_conf_path).expect("Failed to create bitbake conf dir");
         conf.create_bblayers_conf(&cli)
@@ -334,7 +400,6 @@ mod tests {
         bblayers_conf_content.push_str("BBLAYERS_CONF_TEST_LINE ?= \"1\"\n");
         bblayers_conf_content.push_str("BBLAYERS_CONF_TEST_LINE ?= \"2\"\n");
         bblayers_conf_content.push_str("BBLAYERS_CONF_TEST_LINE ?= \"3\"\n");
-        let bb_variables: Vec<String> = vec![];
         let force: bool = false;
         let mut mocked_logger: MockLogger = MockLogger::new();
         mocked_logger
@@ -354,7 +419,9 @@ mod tests {
             .returning(|_x| ());
         let cli: Cli = Cli::new(
             Box::new(mocked_logger),
-            Box::new(BSystem::new()),
+            Box::new(BSystem::with_log_file(Arc::new(LogFile::new(
+                LogFormat::Text,
+            )))),
             clap::Command::new("bakery"),
             None,
         );
@@ -365,7 +432,6 @@ mod tests {
             local_conf_content,
             bblayers_conf_content.clone(),
             force,
-            bb_variables,
         );
         std::fs::create_dir_all(&bitbake_conf_path).expect("Failed to create bitbake conf dir");
         conf.create_bblayers_conf(&cli)
@@ -395,7 +461,6 @@ mod tests {
         local_conf_content.push_str("LOCAL_CONF_TEST_LINE ?= \"2\"\n");
         local_conf_content.push_str("LOCAL_CONF_TEST_LINE ?= \"3\"\n");
         let bblayers_conf_content: String = String::new();
-        let bb_variables: Vec<String> = vec![];
         let force: bool = false;
         let mut mocked_logger: MockLogger = MockLogger::new();
         mocked_logger
@@ -415,7 +480,9 @@ mod tests {
             .returning(|_x| ());
         let cli: Cli = Cli::new(
             Box::new(mocked_logger),
-            Box::new(BSystem::new()),
+            Box::new(BSystem::with_log_file(Arc::new(LogFile::new(
+                LogFormat::Text,
+            )))),
             clap::Command::new("bakery"),
             None,
         );
@@ -426,7 +493,6 @@ mod tests {
             local_conf_content.clone(),
             bblayers_conf_content,
             force,
-            bb_variables,
         );
         std::fs::create_dir_all(&bitbake_conf_path).expect("Failed to create bitbake conf dir");
         conf.create_local_conf(&cli)
@@ -455,7 +521,6 @@ mod tests {
         bblayers_conf_content.push_str("BBLAYERS_CONF_TEST_LINE ?= \"1\"\n");
         bblayers_conf_content.push_str("BBLAYERS_CONF_TEST_LINE ?= \"2\"\n");
         bblayers_conf_content.push_str("BBLAYERS_CONF_TEST_LINE ?= \"3\"\n");
-        let bb_variables: Vec<String> = vec![];
         let force: bool = true;
         let mut mocked_logger: MockLogger = MockLogger::new();
         mocked_logger
@@ -476,7 +541,9 @@ mod tests {
             .returning(|_x| ());
         let cli: Cli = Cli::new(
             Box::new(mocked_logger),
-            Box::new(BSystem::new()),
+            Box::new(BSystem::with_log_file(Arc::new(LogFile::new(
+                LogFormat::Text,
+            )))),
             clap::Command::new("bakery"),
             None,
         );
@@ -487,7 +554,6 @@ mod tests {
             local_conf_content,
             bblayers_conf_content.clone(),
             force,
-            bb_variables,
         );
         std::fs::create_dir_all(&bitbake_conf_path).expect("Failed to create bitbake conf dir");
         conf.create_bblayers_conf(&cli)
@@ -517,7 +583,6 @@ mod tests {
         local_conf_content.push_str("LOCAL_CONF_TEST_LINE ?= \"2\"\n");
         local_conf_content.push_str("LOCAL_CONF_TEST_LINE ?= \"3\"\n");
         let bblayers_conf_content: String = String::new();
-        let bb_variables: Vec<String> = vec![];
         let force: bool = true;
         let mut mocked_logger: MockLogger = MockLogger::new();
         mocked_logger
@@ -538,7 +603,9 @@ mod tests {
             .returning(|_x| ());
         let cli: Cli = Cli::new(
             Box::new(mocked_logger),
-            Box::new(BSystem::new()),
+            Box::new(BSystem::with_log_file(Arc::new(LogFile::new(
+                LogFormat::Text,
+            )))),
             clap::Command::new("bakery"),
             None,
         );
@@ -549,7 +616,6 @@ mod tests {
             local_conf_content.clone(),
             bblayers_conf_content,
             force,
-            bb_variables,
         );
         std::fs::create_dir_all(&bitbake_conf_path).expect("Failed to create bitbake conf dir");
         conf.create_local_conf(&cli)
@@ -581,7 +647,6 @@ mod tests {
         bblayers_conf_content.push_str("BBLAYERS_CONF_TEST_LINE ?= \"1\"\n");
         bblayers_conf_content.push_str("BBLAYERS_CONF_TEST_LINE ?= \"2\"\n");
         bblayers_conf_content.push_str("BBLAYERS_CONF_TEST_LINE ?= \"3\"\n");
-        let bb_variables: Vec<String> = vec![];
         let force: bool = true;
         let mut mocked_logger: MockLogger = MockLogger::new();
         mocked_logger
@@ -602,7 +667,9 @@ mod tests {
             .returning(|_x| ());
         let cli: Cli = Cli::new(
             Box::new(mocked_logger),
-            Box::new(BSystem::new()),
+            Box::new(BSystem::with_log_file(Arc::new(LogFile::new(
+                LogFormat::Text,
+            )))),
             clap::Command::new("bakery"),
             None,
         );
@@ -613,7 +680,6 @@ mod tests {
             local_conf_content.clone(),
             bblayers_conf_content.clone(),
             force,
-            bb_variables,
         );
         conf.create_bitbake_configs(&cli)
             .expect("Failed to create conf files");
@@ -633,4 +699,69 @@ mod tests {
         validate_bblayers_conf.push_str(&bblayers_conf_content);
         assert_eq!(validate_bblayers_conf, contents);
     }
+
+    #[test]
+    fn test_bitbake_conf_local_conf_conflict() {
+        let json_settings: &str = r#"
+        {
+            "version": "6"
+        }"#;
+        let json_build_config: &str = r#"
+        {
+            "version": "6",
+            "name": "test-name",
+            "arch": "test-arch",
+            "bb": {
+                "machine": "test-machine",
+                "localconf": [
+                    "INHERIT += \"archiver\"",
+                    "BB_GENERATE_MIRROR_TARBALLS = \"0\""
+                ]
+            }
+        }"#;
+        let work_dir: PathBuf = PathBuf::from("/workspace");
+        let settings: WsSettingsHandler = WsSettingsHandler::from_str(&work_dir, json_settings)
+            .expect("Failed to parse settings");
+        let data: WsBitbakeData = WsBitbakeData::from_str(json_build_config, &settings)
+            .expect("Failed to parse bitbake data");
+        let conf: BitbakeConf =
+            BitbakeConf::new(&data, &vec![String::from("INHERIT += \"archiver\"")], false)
+                .expect("Failed to setup bitbake conf");
+        assert_eq!(
+            conf.local_conf_content(),
+            String::from(
+                "# AUTO GENERATED\n\
+                INHERIT += \"archiver\"\n\
+                BB_GENERATE_MIRROR_TARBALLS = \"0\"\n\
+                MACHINE ?= \"test-machine\"\n\
+                PRODUCT_NAME ?= \"test-name\"\n\
+                DISTRO ?= \"NA\"\n\
+                SSTATE_DIR ?= \"/workspace/.cache/test-arch/sstate-cache\"\n\
+                DL_DIR ?= \"/workspace/.cache/download\"\n"
+            )
+        );
+        let result = BitbakeConf::new(
+            &data,
+            &vec![
+                String::from("BB_GENERATE_MIRROR_TARBALLS = \"1\""),
+                String::from("MACHINE ?= \"qemux86\""),
+            ],
+            false,
+        );
+        match result {
+            Ok(_conf) => {
+                panic!("We should have recived an error because the variables are conflicting");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    String::from(
+                        "Conflicting bitbake variables in local.conf:\n\
+                        The variable 'BB_GENERATE_MIRROR_TARBALLS' is set to '0' by build config localconf and to '1' by bakery build\n\
+                        The variable 'MACHINE' is set to 'test-machine' by build config bb.machine and to 'qemux86' by bakery build"
+                    )
+                );
+            }
+        }
+    }
 }
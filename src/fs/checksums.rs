@@ -0,0 +1,421 @@
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::cli::Cli;
+use crate::error::BError;
+
+/*
+ * A checksums file using the same format as the sha256sum tool so
+ * it can be verified using 'sha256sum -c SHA256SUMS'
+ */
+pub struct Checksums {
+    path: PathBuf,
+}
+
+impl Checksums {
+    pub fn sha256(path: &Path) -> Result<String, BError> {
+        let mut file: File = File::open(path)?;
+        let mut hasher: Sha256 = Sha256::new();
+        let mut buffer: [u8; 8192] = [0; 8192];
+
+        loop {
+            let n: usize = file.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buffer[..n]);
+        }
+
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect())
+    }
+
+    pub fn new(path: &PathBuf) -> Self {
+        Checksums { path: path.clone() }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /*
+     * The files are listed relative to the directory of the checksums file
+     * if possible otherwise the full path is used.
+     */
+    fn entry_path(&self, file: &Path) -> String {
+        match self.path.parent() {
+            Some(dir) => match file.strip_prefix(dir) {
+                Ok(relative) => relative.to_string_lossy().to_string(),
+                Err(_e) => file.to_string_lossy().to_string(),
+            },
+            None => file.to_string_lossy().to_string(),
+        }
+    }
+
+    pub fn write(&self, files: &Vec<(PathBuf, String)>) -> Result<(), BError> {
+        let mut content: String = String::new();
+
+        for (file, sha256) in files.iter() {
+            content.push_str(&format!("{}  {}\n", sha256, self.entry_path(file)));
+        }
+
+        if let Some(parent_dir) = self.path.parent() {
+            std::fs::create_dir_all(parent_dir)?;
+        }
+        std::fs::write(&self.path, content)?;
+        Ok(())
+    }
+
+    /*
+     * Verify all the files listed in the checksums file. Returns the number
+     * of verified files or an error listing all the files that failed.
+     */
+    pub fn verify(&self) -> Result<usize, BError> {
+        let content: String = std::fs::read_to_string(&self.path).map_err(|e| {
+            BError::ChecksumError(format!(
+                "Failed to read checksums file '{}', {}",
+                self.path.display(),
+                e
+            ))
+        })?;
+        let dir: PathBuf = self
+            .path
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_default();
+        let mut failed: Vec<String> = vec![];
+        let mut verified: usize = 0;
+
+        for line in content.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (sha256, file) = match line.split_once(' ') {
+                Some((sha256, file)) => (sha256, file.trim_start_matches([' ', '*'])),
+                None => {
+                    return Err(BError::ChecksumError(format!(
+                        "Invalid line '{}' in checksums file '{}'",
+                        line,
+                        self.path.display()
+                    )));
+                }
+            };
+
+            match Self::sha256(&dir.join(file)) {
+                Ok(actual) if actual == sha256 => verified += 1,
+                _ => failed.push(file.to_string()),
+            }
+        }
+
+        if !failed.is_empty() {
+            return Err(BError::ChecksumError(format!(
+                "Checksum verification of '{}' failed for '{}'",
+                self.path.display(),
+                failed.join("', '")
+            )));
+        }
+
+        Ok(verified)
+    }
+
+    /*
+     * The detached signature is written next to the checksums file
+     */
+    pub fn signature_path(&self) -> PathBuf {
+        let mut path: std::ffi::OsString = self.path.clone().into_os_string();
+        path.push(".sig");
+        PathBuf::from(path)
+    }
+
+    /*
+     * Create a detached signature of the checksums file using gpg or openssl.
+     * For gpg the key is the id of the key in the keyring and for openssl it is
+     * the path to the private key.
+     */
+    pub fn sign(&self, cli: &Cli, tool: &str, key: &str) -> Result<PathBuf, BError> {
+        let signature: String = self.signature_path().to_string_lossy().to_string();
+        let path: String = self.path.to_string_lossy().to_string();
+        let cmd_line: Vec<&str> = match tool {
+            "gpg" => vec![
+                "gpg",
+                "--batch",
+                "--yes",
+                "--local-user",
+                key,
+                "--output",
+                &signature,
+                "--detach-sign",
+                &path,
+            ],
+            "openssl" => vec![
+                "openssl", "dgst", "-sha256", "-sign", key, "-out", &signature, &path,
+            ],
+            _ => {
+                return Err(BError::ChecksumError(format!(
+                    "Unsupported signing tool '{}'",
+                    tool
+                )));
+            }
+        };
+        cli.check_call(
+            &cmd_line.iter().map(|s| s.to_string()).collect(),
+            &HashMap::new(),
+            false,
+        )
+        .map_err(|e| {
+            BError::ChecksumError(format!(
+                "Failed to sign checksums file '{}', {}",
+                self.path.display(),
+                e
+            ))
+        })?;
+        Ok(self.signature_path())
+    }
+
+    /*
+     * Verify the detached signature of the checksums file. For gpg the key is
+     * not used since the signature is verified using the keyring and for openssl
+     * it is the path to the public key.
+     */
+    pub fn verify_signature(&self, cli: &Cli, tool: &str, key: &str) -> Result<(), BError> {
+        if !self.signature_path().is_file() {
+            return Err(BError::ChecksumError(format!(
+                "Missing signature '{}' for checksums file '{}'",
+                self.signature_path().display(),
+                self.path.display()
+            )));
+        }
+        let signature: String = self.signature_path().to_string_lossy().to_string();
+        let path: String = self.path.to_string_lossy().to_string();
+        let cmd_line: Vec<&str> = match tool {
+            "gpg" => vec!["gpg", "--batch", "--verify", &signature, &path],
+            "openssl" => vec![
+                "openssl",
+                "dgst",
+                "-sha256",
+                "-verify",
+                key,
+                "-signature",
+                &signature,
+                &path,
+            ],
+            _ => {
+                return Err(BError::ChecksumError(format!(
+                    "Unsupported signing tool '{}'",
+                    tool
+                )));
+            }
+        };
+        cli.check_call(
+            &cmd_line.iter().map(|s| s.to_string()).collect(),
+            &HashMap::new(),
+            false,
+        )
+        .map_err(|e| {
+            BError::ChecksumError(format!(
+                "Signature verification of '{}' failed, {}",
+                self.path.display(),
+                e
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use tempdir::TempDir;
+
+    use crate::cli::{BLogger, CallParams, Cli, MockSystem};
+    use crate::error::BError;
+    use crate::fs::Checksums;
+
+    #[test]
+    fn test_checksums_sha256() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let path: &Path = temp_dir.path();
+        let file: PathBuf = path.join("file.txt");
+        std::fs::write(&file, "hello\n").expect("Failed to write file");
+        assert_eq!(
+            Checksums::sha256(&file).expect("Failed to calculate sha256"),
+            "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03"
+        );
+    }
+
+    #[test]
+    fn test_checksums_write_verify() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let path: &Path = temp_dir.path();
+        let file1: PathBuf = path.join("artifacts/file1.txt");
+        let file2: PathBuf = path.join("artifacts/dir/file2.txt");
+        std::fs::create_dir_all(file2.parent().unwrap()).expect("Failed to create dir");
+        std::fs::write(&file1, "file1").expect("Failed to write file");
+        std::fs::write(&file2, "file2").expect("Failed to write file");
+        let checksums: Checksums = Checksums::new(&path.join("artifacts/SHA256SUMS"));
+        checksums
+            .write(&vec![
+                (file1.clone(), Checksums::sha256(&file1).unwrap()),
+                (file2.clone(), Checksums::sha256(&file2).unwrap()),
+            ])
+            .expect("Failed to write checksums file");
+        let content: String = std::fs::read_to_string(checksums.path()).unwrap();
+        assert_eq!(
+            content,
+            format!(
+                "{}  file1.txt\n{}  dir/file2.txt\n",
+                Checksums::sha256(&file1).unwrap(),
+                Checksums::sha256(&file2).unwrap()
+            )
+        );
+        assert_eq!(checksums.verify().expect("Failed to verify checksums"), 2);
+
+        std::fs::write(&file2, "modified").expect("Failed to write file");
+        let result: Result<usize, BError> = checksums.verify();
+        match result {
+            Ok(_n) => {
+                panic!("We should have recived an error because file2 has been modified!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!(
+                        "Checksum verification of '{}' failed for 'dir/file2.txt'",
+                        checksums.path().display()
+                    )
+                );
+            }
+        }
+    }
+
+    fn helper_call(cmd_line: &[&str]) -> CallParams {
+        CallParams {
+            cmd_line: cmd_line.iter().map(|s| s.to_string()).collect(),
+            env: HashMap::new(),
+            shell: false,
+        }
+    }
+
+    #[test]
+    fn test_checksums_sign_verify() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let path: &Path = temp_dir.path();
+        let checksums: Checksums = Checksums::new(&path.join("SHA256SUMS"));
+        let sums: String = checksums.path().to_string_lossy().to_string();
+        let sig: String = path.join("SHA256SUMS.sig").to_string_lossy().to_string();
+        assert_eq!(checksums.signature_path(), path.join("SHA256SUMS.sig"));
+        let mut mocked_system: MockSystem = MockSystem::new();
+        mocked_system
+            .expect_check_call()
+            .with(mockall::predicate::eq(helper_call(&[
+                "openssl",
+                "dgst",
+                "-sha256",
+                "-sign",
+                "/keys/private.pem",
+                "-out",
+                &sig,
+                &sums,
+            ])))
+            .once()
+            .returning(|_x| Ok(()));
+        mocked_system
+            .expect_check_call()
+            .with(mockall::predicate::eq(helper_call(&[
+                "gpg",
+                "--batch",
+                "--yes",
+                "--local-user",
+                "release@example.com",
+                "--output",
+                &sig,
+                "--detach-sign",
+                &sums,
+            ])))
+            .once()
+            .returning(|_x| Ok(()));
+        mocked_system
+            .expect_check_call()
+            .with(mockall::predicate::eq(helper_call(&[
+                "openssl",
+                "dgst",
+                "-sha256",
+                "-verify",
+                "/keys/public.pem",
+                "-signature",
+                &sig,
+                &sums,
+            ])))
+            .once()
+            .returning(|_x| Ok(()));
+        mocked_system
+            .expect_check_call()
+            .with(mockall::predicate::eq(helper_call(&[
+                "gpg", "--batch", "--verify", &sig, &sums,
+            ])))
+            .once()
+            .returning(|_x| Err(BError::CliError(String::from("BAD signature"))));
+        let cli: Cli = Cli::new(
+            Box::new(BLogger::new()),
+            Box::new(mocked_system),
+            clap::Command::new("bakery"),
+            None,
+        );
+        assert_eq!(
+            checksums
+                .sign(&cli, "openssl", "/keys/private.pem")
+                .expect("Failed to sign checksums"),
+            path.join("SHA256SUMS.sig")
+        );
+        checksums
+            .sign(&cli, "gpg", "release@example.com")
+            .expect("Failed to sign checksums");
+        let result: Result<(), BError> = checksums.verify_signature(&cli, "gpg", "");
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because the signature is missing!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!("Missing signature '{}' for checksums file '{}'", sig, sums)
+                );
+            }
+        }
+        std::fs::write(checksums.signature_path(), "signature").expect("Failed to write file");
+        checksums
+            .verify_signature(&cli, "openssl", "/keys/public.pem")
+            .expect("Failed to verify signature");
+        let result: Result<(), BError> = checksums.verify_signature(&cli, "gpg", "");
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because the signature is invalid!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!("Signature verification of '{}' failed, BAD signature", sums)
+                );
+            }
+        }
+        let result: Result<PathBuf, BError> = checksums.sign(&cli, "md5", "");
+        match result {
+            Ok(_p) => {
+                panic!("We should have recived an error because the tool is not supported!");
+            }
+            Err(e) => {
+                assert_eq!(e.to_string(), "Unsupported signing tool 'md5'");
+            }
+        }
+    }
+}
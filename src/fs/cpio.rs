@@ -0,0 +1,192 @@
+use std::collections::HashSet;
+use std::fs::Metadata;
+use std::io::Write;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use crate::error::BError;
+use crate::fs::Archiver;
+
+const NEWC_MAGIC: &str = "070701";
+const NEWC_TRAILER: &str = "TRAILER!!!";
+const S_IFDIR: u32 = 0o040000;
+
+/*
+ * Writer for cpio archives using the "new" portable format (newc) which is
+ * the format expected by the linux kernel for an initramfs. The parent
+ * directories are added to the archive before the first file in them since
+ * the kernel will not create any missing directories when unpacking.
+ */
+pub struct CpioWriter<W: Write> {
+    writer: W,
+    ino: u32,
+    dirs: HashSet<PathBuf>,
+    source_date_epoch: Option<u64>,
+}
+
+impl<W: Write> CpioWriter<W> {
+    pub fn new(writer: W) -> Self {
+        CpioWriter {
+            writer,
+            ino: 0,
+            dirs: HashSet::new(),
+            source_date_epoch: None,
+        }
+    }
+
+    /*
+     * Zero the ownership, normalize the permissions and clamp the mtimes
+     * to the source date epoch for all entries added to the archive.
+     */
+    pub fn set_reproducible(&mut self, source_date_epoch: u64) {
+        self.source_date_epoch = Some(source_date_epoch);
+    }
+
+    fn attributes(&self, mode: u32, metadata: &Metadata) -> (u32, u32, u32, u32) {
+        match self.source_date_epoch {
+            Some(epoch) => (
+                (mode & !0o7777) | Archiver::normalized_mode(mode),
+                0,
+                0,
+                Archiver::clamped_mtime(metadata, epoch) as u32,
+            ),
+            None => (
+                mode,
+                metadata.uid(),
+                metadata.gid(),
+                metadata.mtime() as u32,
+            ),
+        }
+    }
+
+    fn write_entry(
+        &mut self,
+        name: &str,
+        mode: u32,
+        uid: u32,
+        gid: u32,
+        mtime: u32,
+        data: &[u8],
+    ) -> Result<(), BError> {
+        let nlink: u32 = if mode & S_IFDIR == S_IFDIR { 2 } else { 1 };
+        let namesize: usize = name.len() + 1;
+        self.ino += 1;
+
+        let header: String = format!(
+            "{}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}",
+            NEWC_MAGIC,
+            self.ino,
+            mode,
+            uid,
+            gid,
+            nlink,
+            mtime,
+            data.len(),
+            0,
+            0,
+            0,
+            0,
+            namesize,
+            0
+        );
+        self.writer.write_all(header.as_bytes())?;
+        self.writer.write_all(name.as_bytes())?;
+        self.writer.write_all(&[0u8])?;
+        // The header and name are padded to a multiple of four bytes
+        self.pad(header.len() + namesize)?;
+        self.writer.write_all(data)?;
+        // The file data is padded to a multiple of four bytes
+        self.pad(data.len())?;
+        Ok(())
+    }
+
+    fn pad(&mut self, len: usize) -> Result<(), BError> {
+        let padding: usize = (4 - len % 4) % 4;
+        self.writer.write_all(&vec![0u8; padding])?;
+        Ok(())
+    }
+
+    fn append_dirs(&mut self, name: &Path, metadata: &Metadata) -> Result<(), BError> {
+        let mut dirs: Vec<PathBuf> = vec![];
+        let mut parent: Option<&Path> = name.parent();
+
+        while let Some(dir) = parent {
+            if dir.as_os_str().is_empty() || self.dirs.contains(dir) {
+                break;
+            }
+            dirs.push(dir.to_path_buf());
+            parent = dir.parent();
+        }
+
+        for dir in dirs.iter().rev() {
+            let (mode, uid, gid, mtime) = self.attributes(S_IFDIR | 0o755, metadata);
+            self.write_entry(&dir.to_string_lossy(), mode, uid, gid, mtime, &[])?;
+            self.dirs.insert(dir.clone());
+        }
+
+        Ok(())
+    }
+
+    /*
+     * Add the file at path to the archive using name as the path inside
+     * of the archive.
+     */
+    pub fn append_file(&mut self, name: &Path, path: &Path) -> Result<(), BError> {
+        let metadata: Metadata = std::fs::metadata(path)?;
+        let data: Vec<u8> = std::fs::read(path)?;
+
+        self.append_dirs(name, &metadata)?;
+        let (mode, uid, gid, mtime) = self.attributes(metadata.mode(), &metadata);
+        self.write_entry(&name.to_string_lossy(), mode, uid, gid, mtime, &data)
+    }
+
+    /*
+     * Write the trailer marking the end of the archive and return the
+     * underlying writer.
+     */
+    pub fn finish(mut self) -> Result<W, BError> {
+        self.write_entry(NEWC_TRAILER, 0, 0, 0, 0, &[])?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use tempdir::TempDir;
+
+    use crate::fs::CpioWriter;
+    use crate::helper::Helper;
+
+    #[test]
+    fn test_cpio_writer() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: &Path = temp_dir.path();
+        let files: Vec<PathBuf> = vec![
+            work_dir.join("file1.txt"),
+            work_dir.join("dir1/dir2/file2.txt"),
+            work_dir.join("dir1/file3.txt"),
+        ];
+        Helper::create_test_files(&files);
+        let mut cpio: CpioWriter<Vec<u8>> = CpioWriter::new(vec![]);
+        for f in files.iter() {
+            cpio.append_file(f.strip_prefix(work_dir).unwrap(), f)
+                .expect("Failed to add file to cpio archive");
+        }
+        let data: Vec<u8> = cpio.finish().expect("Failed to finish cpio archive");
+        assert_eq!(data.len() % 4, 0);
+        assert_eq!(&data[0..6], b"070701");
+        assert_eq!(
+            Helper::list_files_in_cpio(&data, true).expect("Failed to list cpio archive"),
+            vec![
+                PathBuf::from("file1.txt"),
+                PathBuf::from("dir1"),
+                PathBuf::from("dir1/dir2"),
+                PathBuf::from("dir1/dir2/file2.txt"),
+                PathBuf::from("dir1/file3.txt"),
+            ]
+        );
+    }
+}
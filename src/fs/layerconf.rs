@@ -0,0 +1,444 @@
+use indexmap::IndexMap;
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::error::BError;
+
+pub const LAYER_CONF: &str = "conf/layer.conf";
+const MAX_LAYER_DEPTH: usize = 4;
+
+/*
+ * The parts of a conf/layer.conf that are needed to put the BBLAYERS
+ * together. Only plain assignments are evaluated, variables referencing
+ * other variables are expanded if the variable is set in the same file.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayerConf {
+    path: PathBuf,
+    collection: String,
+    depends: Vec<String>,
+    series: Vec<String>,
+    corenames: Vec<String>,
+    priority: i32,
+}
+
+impl LayerConf {
+    fn parse_vars(content: &str) -> Result<IndexMap<String, String>, BError> {
+        let regex: Regex = Regex::new(
+            r#"^(?:export\s+)?([A-Za-z0-9_\-\.\$\{\}]+)((?::append|:prepend|_append|_prepend)?)\s*(\?\?=|\?=|:=|\+=|=\+|\.=|=\.|=)\s*"(.*)"\s*$"#,
+        )
+        .map_err(|e| BError::ParseError(format!("Invalid layer.conf pattern: {}", e)))?;
+        let mut vars: IndexMap<String, String> = IndexMap::new();
+        let joined: String = content.replace("\\\n", " ");
+
+        for line in joined.lines() {
+            let line: &str = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let captures = match regex.captures(line) {
+                Some(c) => c,
+                None => continue,
+            };
+            let name: String = Self::expand(&vars, &captures[1]);
+            let value: String = Self::expand(&vars, &captures[4]);
+            let current: Option<&String> = vars.get(&name);
+            let operator: &str = match &captures[2] {
+                ":append" | "_append" => ".=",
+                ":prepend" | "_prepend" => "=.",
+                _ => &captures[3],
+            };
+            let new_value: String = match (operator, current) {
+                ("?=", Some(c)) | ("??=", Some(c)) => c.clone(),
+                ("+=", Some(c)) => format!("{} {}", c, value),
+                ("=+", Some(c)) => format!("{} {}", value, c),
+                (".=", Some(c)) => format!("{}{}", c, value),
+                ("=.", Some(c)) => format!("{}{}", value, c),
+                _ => value,
+            };
+            vars.insert(name, new_value);
+        }
+
+        Ok(vars)
+    }
+
+    fn expand(vars: &IndexMap<String, String>, value: &str) -> String {
+        let mut expanded: String = value.to_string();
+        for (name, var) in vars.iter() {
+            expanded = expanded.replace(&format!("${{{}}}", name), var);
+        }
+        expanded
+    }
+
+    fn words(vars: &IndexMap<String, String>, name: &str) -> Vec<String> {
+        vars.get(name)
+            .map(|v| {
+                v.split_whitespace()
+                    .filter(|w| !w.contains("${"))
+                    .map(|w| w.to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /*
+     * The version constraints in LAYERDEPENDS like "core (>= 12)" are
+     * dropped, only the name of the collection is used
+     */
+    fn parse_depends(vars: &IndexMap<String, String>, name: &str) -> Vec<String> {
+        let regex: Regex = Regex::new(r"\([^)]*\)").unwrap();
+        let value: String = vars.get(name).cloned().unwrap_or_default();
+        regex
+            .replace_all(&value, " ")
+            .split_whitespace()
+            .filter(|w| !w.contains("${"))
+            .map(|w| w.to_string())
+            .collect()
+    }
+
+    /*
+     * A layer.conf can define more than one collection so one LayerConf
+     * is returned for every collection in BBFILE_COLLECTIONS
+     */
+    pub fn from_str(path: &Path, content: &str) -> Result<Vec<Self>, BError> {
+        let vars: IndexMap<String, String> = Self::parse_vars(content)?;
+        let corenames: Vec<String> = Self::words(&vars, "LAYERSERIES_CORENAMES");
+        let mut layers: Vec<LayerConf> = vec![];
+
+        for collection in Self::words(&vars, "BBFILE_COLLECTIONS") {
+            let priority: String = vars
+                .get(&format!("BBFILE_PRIORITY_{}", collection))
+                .cloned()
+                .unwrap_or(String::from("0"));
+            layers.push(LayerConf {
+                path: path.to_path_buf(),
+                depends: Self::parse_depends(&vars, &format!("LAYERDEPENDS_{}", collection)),
+                series: Self::words(&vars, &format!("LAYERSERIES_COMPAT_{}", collection)),
+                corenames: corenames.clone(),
+                priority: priority.trim().parse::<i32>().map_err(|_e| {
+                    BError::ParseError(format!(
+                        "Invalid BBFILE_PRIORITY_{} '{}' in '{}'",
+                        collection,
+                        priority,
+                        path.join(LAYER_CONF).display()
+                    ))
+                })?,
+                collection,
+            });
+        }
+
+        Ok(layers)
+    }
+
+    pub fn read(path: &Path) -> Result<Vec<Self>, BError> {
+        Self::from_str(path, &std::fs::read_to_string(path.join(LAYER_CONF))?)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn collection(&self) -> &str {
+        &self.collection
+    }
+
+    pub fn depends(&self) -> &Vec<String> {
+        &self.depends
+    }
+
+    pub fn series(&self) -> &Vec<String> {
+        &self.series
+    }
+
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+}
+
+/*
+ * All the layers found in the layers dir by looking for conf/layer.conf.
+ * The layers requested by collection name are resolved together with the
+ * layers they depend on into the list of layers to add to BBLAYERS.
+ */
+pub struct LayerCollections {
+    layers_dir: PathBuf,
+    layers: IndexMap<String, LayerConf>,
+}
+
+impl LayerCollections {
+    fn find_layers(dir: &Path, depth: usize, layers: &mut Vec<PathBuf>) -> Result<(), BError> {
+        if !dir.is_dir() || depth > MAX_LAYER_DEPTH {
+            return Ok(());
+        }
+        if dir.join(LAYER_CONF).is_file() {
+            layers.push(dir.to_path_buf());
+            return Ok(());
+        }
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .filter(|p| {
+                !p.file_name()
+                    .map(|n| n.to_string_lossy().starts_with('.'))
+                    .unwrap_or(true)
+            })
+            .collect();
+        entries.sort();
+        for entry in entries {
+            Self::find_layers(&entry, depth + 1, layers)?;
+        }
+        Ok(())
+    }
+
+    pub fn new(layers_dir: &Path) -> Self {
+        LayerCollections {
+            layers_dir: layers_dir.to_path_buf(),
+            layers: IndexMap::new(),
+        }
+    }
+
+    pub fn scan(layers_dir: &Path) -> Result<Self, BError> {
+        let mut collections: LayerCollections = Self::new(layers_dir);
+        let mut paths: Vec<PathBuf> = vec![];
+        Self::find_layers(layers_dir, 0, &mut paths)?;
+        for path in paths {
+            for layer in LayerConf::read(&path)? {
+                collections.add(layer)?;
+            }
+        }
+        Ok(collections)
+    }
+
+    pub fn add(&mut self, layer: LayerConf) -> Result<(), BError> {
+        if let Some(existing) = self.layers.get(layer.collection()) {
+            return Err(BError::ValueError(format!(
+                "The layer '{}' is defined both in '{}' and '{}'",
+                layer.collection(),
+                existing.path().display(),
+                layer.path().display()
+            )));
+        }
+        self.layers.insert(layer.collection().to_string(), layer);
+        Ok(())
+    }
+
+    /*
+     * The series defaults to LAYERSERIES_CORENAMES of the core layer
+     */
+    fn series(&self, series: &str) -> Vec<String> {
+        if !series.is_empty() {
+            return series.split_whitespace().map(|s| s.to_string()).collect();
+        }
+        self.layers
+            .values()
+            .find(|l| !l.corenames.is_empty())
+            .map(|l| l.corenames.clone())
+            .unwrap_or_default()
+    }
+
+    fn visit(
+        &self,
+        collection: &str,
+        chain: &mut Vec<String>,
+        closure: &mut IndexMap<String, LayerConf>,
+        errors: &mut Vec<String>,
+    ) {
+        if closure.contains_key(collection) {
+            return;
+        }
+        if let Some(index) = chain.iter().position(|c| c == collection) {
+            errors.push(format!(
+                "Circular layer dependency {} -> {}",
+                chain[index..].join(" -> "),
+                collection
+            ));
+            return;
+        }
+        let layer: &LayerConf = match self.layers.get(collection) {
+            Some(l) => l,
+            None => {
+                match chain.last() {
+                    Some(parent) => errors.push(format!(
+                        "The layer '{}' depends on '{}' that is not found in '{}'",
+                        parent,
+                        collection,
+                        self.layers_dir.display()
+                    )),
+                    None => errors.push(format!(
+                        "The layer '{}' is not found in '{}'",
+                        collection,
+                        self.layers_dir.display()
+                    )),
+                }
+                return;
+            }
+        };
+        chain.push(collection.to_string());
+        for dep in layer.depends().iter() {
+            self.visit(dep, chain, closure, errors);
+        }
+        chain.pop();
+        closure.insert(collection.to_string(), layer.clone());
+    }
+
+    /*
+     * Resolve the requested layers and all the layers they depend on. The
+     * layers are sorted so that a layer always comes after the layers it
+     * depends on, layers that are independent of each other are sorted by
+     * priority lowest first. All the missing dependencies and the layers
+     * that are not compatible with the series are reported in one go.
+     */
+    pub fn resolve(&self, collections: &[String], series: &str) -> Result<Vec<PathBuf>, BError> {
+        let mut closure: IndexMap<String, LayerConf> = IndexMap::new();
+        let mut errors: Vec<String> = vec![];
+
+        for collection in collections.iter() {
+            self.visit(collection, &mut vec![], &mut closure, &mut errors);
+        }
+
+        let series: Vec<String> = self.series(series);
+        if !series.is_empty() {
+            for layer in closure.values() {
+                if !layer.series().is_empty() && !layer.series().iter().any(|s| series.contains(s))
+                {
+                    errors.push(format!(
+                        "The layer '{}' in '{}' is compatible with '{}' but the series is '{}'",
+                        layer.collection(),
+                        layer.path().display(),
+                        layer.series().join(" "),
+                        series.join(" ")
+                    ));
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(BError::ValueError(format!(
+                "Failed to resolve layers:\n{}",
+                errors.join("\n")
+            )));
+        }
+
+        let mut resolved: Vec<PathBuf> = vec![];
+        let mut done: HashSet<String> = HashSet::new();
+        while done.len() < closure.len() {
+            let next: &LayerConf = closure
+                .values()
+                .filter(|l| !done.contains(l.collection()))
+                .filter(|l| l.depends().iter().all(|d| done.contains(d)))
+                .min_by_key(|l| l.priority())
+                .expect("A layer dependency cycle should have been reported");
+            done.insert(next.collection().to_string());
+            if !resolved.contains(&next.path().to_path_buf()) {
+                resolved.push(next.path().to_path_buf());
+            }
+        }
+
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use tempdir::TempDir;
+
+    use crate::fs::layerconf::LayerConf;
+    use crate::fs::LayerCollections;
+
+    fn helper_layer(layers_dir: &Path, path: &str, content: &str) -> PathBuf {
+        let layer: PathBuf = layers_dir.join(path);
+        std::fs::create_dir_all(layer.join("conf")).expect("Failed to create layer dir");
+        std::fs::write(layer.join("conf/layer.conf"), content).expect("Failed to write layer.conf");
+        layer
+    }
+
+    #[test]
+    fn test_layer_conf() {
+        let content: &str = r#"
+# We have a conf and classes directory, add to BBPATH
+BBPATH .= ":${LAYERDIR}"
+BBFILE_COLLECTIONS += "openembedded-layer"
+BBFILE_PATTERN_openembedded-layer := "^${LAYERDIR}/"
+BBFILE_PRIORITY_openembedded-layer = "5"
+LAYERVERSION_openembedded-layer = "1"
+LAYERDEPENDS_openembedded-layer = "core (>= 12) \
+    "
+LAYERDEPENDS_openembedded-layer += "networking-layer"
+LAYERSERIES_COMPAT_openembedded-layer = "nanbield scarthgap"
+"#;
+        let layers: Vec<LayerConf> =
+            LayerConf::from_str(Path::new("/layers/meta-openembedded/meta-oe"), content)
+                .expect("Failed to parse layer.conf");
+        assert_eq!(layers.len(), 1);
+        let layer: &LayerConf = &layers[0];
+        assert_eq!(layer.collection(), "openembedded-layer");
+        assert_eq!(layer.priority(), 5);
+        assert_eq!(
+            layer.depends(),
+            &vec![String::from("core"), String::from("networking-layer")]
+        );
+        assert_eq!(
+            layer.series(),
+            &vec![String::from("nanbield"), String::from("scarthgap")]
+        );
+    }
+
+    #[test]
+    fn test_layer_collections_resolve() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let layers_dir: &Path = temp_dir.path();
+        let meta: PathBuf = helper_layer(
+            layers_dir,
+            "poky/meta",
+            "BBFILE_COLLECTIONS += \"core\"\nBBFILE_PRIORITY_core = \"5\"\nLAYERSERIES_CORENAMES = \"scarthgap\"\nLAYERSERIES_COMPAT_core = \"scarthgap\"\n",
+        );
+        let poky: PathBuf = helper_layer(
+            layers_dir,
+            "poky/meta-poky",
+            "BBFILE_COLLECTIONS += \"yocto\"\nBBFILE_PRIORITY_yocto = \"5\"\nLAYERDEPENDS_yocto = \"core\"\nLAYERSERIES_COMPAT_yocto = \"scarthgap\"\n",
+        );
+        let oe: PathBuf = helper_layer(
+            layers_dir,
+            "meta-openembedded/meta-oe",
+            "BBFILE_COLLECTIONS += \"openembedded-layer\"\nBBFILE_PRIORITY_openembedded-layer = \"5\"\nLAYERDEPENDS_openembedded-layer = \"core\"\nLAYERSERIES_COMPAT_openembedded-layer = \"scarthgap\"\n",
+        );
+        let bsp: PathBuf = helper_layer(
+            layers_dir,
+            "meta-bsp",
+            "BBFILE_COLLECTIONS += \"bsp\"\nBBFILE_PRIORITY_bsp = \"9\"\nLAYERDEPENDS_bsp = \"core openembedded-layer\"\nLAYERSERIES_COMPAT_bsp = \"nanbield scarthgap\"\n",
+        );
+        helper_layer(
+            layers_dir,
+            "meta-old",
+            "BBFILE_COLLECTIONS += \"old\"\nLAYERDEPENDS_old = \"core missing\"\nLAYERSERIES_COMPAT_old = \"kirkstone\"\n",
+        );
+        let collections: LayerCollections =
+            LayerCollections::scan(layers_dir).expect("Failed to scan layers dir");
+        assert_eq!(collections.layers.len(), 5);
+        let resolved: Vec<PathBuf> = collections
+            .resolve(&[String::from("bsp"), String::from("yocto")], "")
+            .expect("Failed to resolve layers");
+        assert_eq!(resolved, vec![meta, oe, poky, bsp]);
+        let result = collections.resolve(&[String::from("old"), String::from("foo")], "");
+        match result {
+            Ok(_layers) => {
+                panic!("We should have recived an error because the layers can't be resolved");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!(
+                        "Failed to resolve layers:\nThe layer 'old' depends on 'missing' that is not found in '{}'\nThe layer 'foo' is not found in '{}'\nThe layer 'old' in '{}' is compatible with 'kirkstone' but the series is 'scarthgap'",
+                        layers_dir.display(),
+                        layers_dir.display(),
+                        layers_dir.join("meta-old").display()
+                    )
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use regex::Regex;
+
+use crate::error::BError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BBOperator {
+    Assign,
+    Immediate,
+    Default,
+    WeakDefault,
+    Append,
+    Prepend,
+    DotAppend,
+    DotPrepend,
+}
+
+impl BBOperator {
+    pub fn from_str(operator: &str) -> Option<Self> {
+        match operator {
+            "=" => Some(BBOperator::Assign),
+            ":=" => Some(BBOperator::Immediate),
+            "?=" => Some(BBOperator::Default),
+            "??=" => Some(BBOperator::WeakDefault),
+            "+=" => Some(BBOperator::Append),
+            "=+" => Some(BBOperator::Prepend),
+            ".=" => Some(BBOperator::DotAppend),
+            "=." => Some(BBOperator::DotPrepend),
+            _ => None,
+        }
+    }
+}
+
+/*
+ * A bitbake variable assignment like 'ARCHIVER_MODE[src] = "original"' or
+ * 'IMAGE_INSTALL:append:rpi = " vim"'. The overrides are everything after
+ * the name separated by ':', including the append, prepend and remove
+ * operations.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct BBVariable {
+    name: String,
+    flag: Option<String>,
+    overrides: Vec<String>,
+    operator: BBOperator,
+    value: String,
+}
+
+impl BBVariable {
+    fn regex() -> Regex {
+        Regex::new(
+            r#"(?s)^(?:export\s+)?([A-Za-z0-9_\-\.\+\$\{\}/~]+)(?:\[([A-Za-z0-9_\-\.\$\{\}]+)\])?((?::[A-Za-z0-9_\-\.\$\{\}]+)*)\s*(\?\?=|\?=|:=|\+=|=\+|\.=|=\.|=)\s*"(.*)"\s*$"#,
+        )
+        .unwrap()
+    }
+
+    /*
+     * Parse a single logical line, lines that are not a variable assignment
+     * like include, require, inherit or comments returns None
+     */
+    pub fn parse(line: &str) -> Option<Self> {
+        let joined: String = line.replace("\\\n", " ");
+        let captures = Self::regex().captures(joined.trim())?;
+        Some(BBVariable {
+            name: captures[1].to_string(),
+            flag: captures.get(2).map(|f| f.as_str().to_string()),
+            overrides: captures[3]
+                .split(':')
+                .filter(|o| !o.is_empty())
+                .map(|o| o.to_string())
+                .collect(),
+            operator: BBOperator::from_str(&captures[4])?,
+            value: captures[5].to_string(),
+        })
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /*
+     * The name including the flag and the overrides
+     */
+    pub fn key(&self) -> String {
+        let mut key: String = self.name.clone();
+        if let Some(flag) = &self.flag {
+            key.push_str(&format!("[{}]", flag));
+        }
+        for o in self.overrides.iter() {
+            key.push_str(&format!(":{}", o));
+        }
+        key
+    }
+
+    /*
+     * An appending assignment is adding to the value so more than one is
+     * never a conflict
+     */
+    pub fn is_appending(&self) -> bool {
+        match self.operator {
+            BBOperator::Append
+            | BBOperator::Prepend
+            | BBOperator::DotAppend
+            | BBOperator::DotPrepend => true,
+            _ => self
+                .overrides
+                .last()
+                .map(|o| o == "append" || o == "prepend" || o == "remove")
+                .unwrap_or(false),
+        }
+    }
+
+    /*
+     * Two assignments with the same strength is a conflict since one of them
+     * silently wins, a weak default can always be overridden
+     */
+    fn strength(&self) -> u8 {
+        match self.operator {
+            BBOperator::WeakDefault => 0,
+            BBOperator::Default => 1,
+            _ => 2,
+        }
+    }
+
+    pub fn conflicts(&self, other: &BBVariable) -> bool {
+        self.key() == other.key()
+            && !self.is_appending()
+            && !other.is_appending()
+            && self.strength() != 0
+            && self.strength() == other.strength()
+            && self.value != other.value
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct LocalConfLine {
+    text: String,
+    variable: Option<BBVariable>,
+    sources: Vec<String>,
+    unchecked: bool,
+}
+
+/*
+ * The local.conf put together from the build config, the workspace settings
+ * and the build flags. Every line is recording where it is coming from so
+ * that a duplicated assignment is only added once and two sources assigning
+ * different values to the same variable can be reported instead of one of
+ * them silently winning.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocalConf {
+    lines: Vec<LocalConfLine>,
+    conflicts: Vec<String>,
+}
+
+impl LocalConf {
+    pub fn new() -> Self {
+        LocalConf {
+            lines: vec![],
+            conflicts: vec![],
+        }
+    }
+
+    pub fn add_line(&mut self, line: &str, source: &str) {
+        self.add_unchecked(line, source, false);
+    }
+
+    /*
+     * An unchecked line is never reported as a conflict, used for values
+     * that are only a placeholder
+     */
+    pub fn add_unchecked(&mut self, line: &str, source: &str, unchecked: bool) {
+        let variable: Option<BBVariable> = BBVariable::parse(line);
+
+        if let Some(var) = &variable {
+            for l in self.lines.iter_mut() {
+                let existing: &BBVariable = match &l.variable {
+                    Some(v) => v,
+                    None => continue,
+                };
+                if existing == var {
+                    if !l.sources.iter().any(|s| s == source) {
+                        l.sources.push(source.to_string());
+                    }
+                    return;
+                }
+                if !unchecked && !l.unchecked && existing.conflicts(var) {
+                    self.conflicts.push(format!(
+                        "The variable '{}' is set to '{}' by {} and to '{}' by {}",
+                        var.key(),
+                        existing.value(),
+                        l.sources.join(", "),
+                        var.value(),
+                        source
+                    ));
+                }
+            }
+        }
+
+        self.lines.push(LocalConfLine {
+            text: line.to_string(),
+            variable,
+            sources: vec![source.to_string()],
+            unchecked,
+        });
+    }
+
+    pub fn add_lines(&mut self, lines: &[String], source: &str) {
+        for line in lines.iter() {
+            self.add_line(line, source);
+        }
+    }
+
+    /*
+     * Add the content of a conf file, a line ending with a backslash is
+     * continued on the next line
+     */
+    pub fn add_conf(&mut self, content: &str, source: &str) {
+        let mut logical: Vec<&str> = vec![];
+        for line in content.lines() {
+            logical.push(line);
+            if !line.trim_end().ends_with('\\') {
+                self.add_line(&logical.join("\n"), source);
+                logical.clear();
+            }
+        }
+        if !logical.is_empty() {
+            self.add_line(&logical.join("\n"), source);
+        }
+    }
+
+    pub fn extend(&mut self, other: &LocalConf) {
+        for line in other.lines.iter() {
+            for source in line.sources.iter() {
+                self.add_unchecked(&line.text, source, line.unchecked);
+            }
+        }
+    }
+
+    pub fn check(&self) -> Result<(), BError> {
+        if !self.conflicts.is_empty() {
+            return Err(BError::ValueError(format!(
+                "Conflicting bitbake variables in local.conf:\n{}",
+                self.conflicts.join("\n")
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.iter().map(|l| l.text.clone()).collect()
+    }
+
+    pub fn to_string(&self) -> String {
+        let mut conf_str: String = String::new();
+        for line in self.lines.iter() {
+            conf_str.push_str(&format!("{}\n", line.text));
+        }
+        conf_str
+    }
+
+    /*
+     * Same as to_string but with a comment every time the source of
+     * the lines is changing
+     */
+    pub fn to_string_with_provenance(&self) -> String {
+        let mut conf_str: String = String::new();
+        let mut sources: &[String] = &[];
+        for line in self.lines.iter() {
+            if line.sources.as_slice() != sources {
+                conf_str.push_str(&format!("# {}\n", line.sources.join(", ")));
+                sources = &line.sources;
+            }
+            conf_str.push_str(&format!("{}\n", line.text));
+        }
+        conf_str
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fs::localconf::{BBOperator, BBVariable};
+    use crate::fs::LocalConf;
+
+    #[test]
+    fn test_bb_variable_parse() {
+        let var: BBVariable = BBVariable::parse("IMAGE_INSTALL:append:rpi = \" vim\"")
+            .expect("Failed to parse variable");
+        assert_eq!(var.name, "IMAGE_INSTALL");
+        assert_eq!(var.key(), "IMAGE_INSTALL:append:rpi");
+        assert_eq!(var.operator, BBOperator::Assign);
+        assert_eq!(var.value(), " vim");
+        assert!(!var.is_appending());
+        let var: BBVariable =
+            BBVariable::parse("IMAGE_INSTALL:remove = \"vim\"").expect("Failed to parse variable");
+        assert!(var.is_appending());
+        let var: BBVariable = BBVariable::parse("ARCHIVER_MODE[src] ??= \"original\"")
+            .expect("Failed to parse variable");
+        assert_eq!(var.key(), "ARCHIVER_MODE[src]");
+        assert_eq!(var.operator, BBOperator::WeakDefault);
+        assert_eq!(var.value(), "original");
+        let var: BBVariable = BBVariable::parse(
+            "SSTATE_MIRRORS ?= \"\\\nfile://.* https://sstate.example.com/PATH \\\n\"",
+        )
+        .expect("Failed to parse variable");
+        assert_eq!(var.key(), "SSTATE_MIRRORS");
+        assert_eq!(var.operator, BBOperator::Default);
+        assert!(BBVariable::parse("require conf/distro/poky.conf").is_none());
+        assert!(BBVariable::parse("# MACHINE = \"qemux86\"").is_none());
+    }
+
+    #[test]
+    fn test_local_conf() {
+        let mut local_conf: LocalConf = LocalConf::new();
+        local_conf.add_conf(
+            "MACHINE ?= \"raspberrypi3\"\nINHERIT += \"archiver\"\nSSTATE_MIRRORS ?= \"\\\nfile://.* https://sstate.example.com/PATH \\\n\"\n",
+            "build config",
+        );
+        local_conf.add_lines(
+            &[
+                String::from("MACHINE ??= \"qemux86\""),
+                String::from("INHERIT += \"archiver\""),
+                String::from("INHERIT += \"buildhistory\""),
+            ],
+            "--archiver",
+        );
+        local_conf.add_line("DISTRO = \"poky\"", "bakery");
+        assert!(local_conf.conflicts.is_empty());
+        assert_eq!(
+            local_conf.to_string(),
+            String::from(
+                "MACHINE ?= \"raspberrypi3\"\n\
+                INHERIT += \"archiver\"\n\
+                SSTATE_MIRRORS ?= \"\\\n\
+                file://.* https://sstate.example.com/PATH \\\n\
+                \"\n\
+                MACHINE ??= \"qemux86\"\n\
+                INHERIT += \"buildhistory\"\n\
+                DISTRO = \"poky\"\n"
+            )
+        );
+        assert_eq!(
+            local_conf.to_string_with_provenance(),
+            String::from(
+                "# build config\n\
+                MACHINE ?= \"raspberrypi3\"\n\
+                # build config, --archiver\n\
+                INHERIT += \"archiver\"\n\
+                # build config\n\
+                SSTATE_MIRRORS ?= \"\\\n\
+                file://.* https://sstate.example.com/PATH \\\n\
+                \"\n\
+                # --archiver\n\
+                MACHINE ??= \"qemux86\"\n\
+                INHERIT += \"buildhistory\"\n\
+                # bakery\n\
+                DISTRO = \"poky\"\n"
+            )
+        );
+        let mut flags: LocalConf = LocalConf::new();
+        flags.add_line("MACHINE ?= \"qemux86\"", "--machine");
+        flags.add_line("DISTRO := \"strix\"", "--distro");
+        local_conf.extend(&flags);
+        let result = local_conf.check();
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because the variables are conflicting");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    String::from(
+                        "Conflicting bitbake variables in local.conf:\n\
+                        The variable 'MACHINE' is set to 'raspberrypi3' by build config and to 'qemux86' by --machine\n\
+                        The variable 'DISTRO' is set to 'poky' by bakery and to 'strix' by --distro"
+                    )
+                );
+            }
+        }
+    }
+}
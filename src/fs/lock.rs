@@ -0,0 +1,223 @@
+use indexmap::IndexMap;
+use serde_json::{json, Map, Value};
+use std::path::Path;
+
+use crate::configs::Config;
+use crate::error::BError;
+
+pub const LOCK_FILE: &str = "bakery.lock";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LockedLayer {
+    pub path: String,
+    pub url: String,
+    pub commit: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LockedImage {
+    pub image: String,
+    pub digest: String,
+}
+
+/*
+ * The lock file is recording the exact commit of every layer so that the
+ * layers can be checked out at the same commits again. The path of a layer
+ * is relative to the layers dir. A lock file written by a build is also
+ * recording the docker image used by the build.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct BakeryLock {
+    config: String,
+    docker: Option<LockedImage>,
+    layers: IndexMap<String, LockedLayer>,
+}
+
+impl Config for BakeryLock {}
+
+impl BakeryLock {
+    pub fn new(config: &str) -> Self {
+        BakeryLock {
+            config: config.to_string(),
+            docker: None,
+            layers: IndexMap::new(),
+        }
+    }
+
+    pub fn from_str(json_string: &str) -> Result<Self, BError> {
+        let data: Value = Self::parse(json_string)?;
+        let config: String = Self::get_str_value("config", &data, Some(String::from("NA")))?;
+        let mut lock: BakeryLock = BakeryLock::new(&config);
+        if let Some(docker) = data.get("docker") {
+            lock.set_docker(
+                &Self::get_str_value("image", docker, None)?,
+                &Self::get_str_value("digest", docker, Some(String::new()))?,
+            );
+        }
+        if let Some(layers) = data.get("layers").and_then(|l| l.as_object()) {
+            for (name, layer) in layers.iter() {
+                lock.add_layer(
+                    name,
+                    &Self::get_str_value("path", layer, Some(name.clone()))?,
+                    &Self::get_str_value("url", layer, Some(String::new()))?,
+                    &Self::get_str_value("commit", layer, None)?,
+                );
+            }
+        }
+        Ok(lock)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, BError> {
+        if !path.exists() {
+            return Err(BError::ValueError(format!(
+                "No lock file '{}', create it using 'bakery layers lock'",
+                path.display()
+            )));
+        }
+        Self::from_str(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), BError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, format!("{}\n", self.to_string()))?;
+        Ok(())
+    }
+
+    pub fn add_layer(&mut self, name: &str, path: &str, url: &str, commit: &str) {
+        self.layers.insert(
+            name.to_string(),
+            LockedLayer {
+                path: path.to_string(),
+                url: url.to_string(),
+                commit: commit.to_string(),
+            },
+        );
+    }
+
+    pub fn set_docker(&mut self, image: &str, digest: &str) {
+        self.docker = Some(LockedImage {
+            image: image.to_string(),
+            digest: digest.to_string(),
+        });
+    }
+
+    /*
+     * A lock file is only valid for the build config it was written for since
+     * the layers are defined by the build config
+     */
+    pub fn verify_config(&self, config: &str) -> Result<(), BError> {
+        if self.config != config {
+            return Err(BError::ValueError(format!(
+                "The lock file is for build config '{}' not '{}'",
+                self.config, config
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn docker(&self) -> Option<&LockedImage> {
+        self.docker.as_ref()
+    }
+
+    pub fn layers(&self) -> &IndexMap<String, LockedLayer> {
+        &self.layers
+    }
+
+    pub fn commit(&self, name: &str) -> Option<&str> {
+        self.layers.get(name).map(|l| l.commit.as_str())
+    }
+
+    pub fn to_string(&self) -> String {
+        let mut layers: Map<String, Value> = Map::new();
+        for (name, layer) in self.layers.iter() {
+            layers.insert(
+                name.clone(),
+                json!({
+                    "path": layer.path,
+                    "url": layer.url,
+                    "commit": layer.commit,
+                }),
+            );
+        }
+        let mut data: Value = json!({
+            "config": self.config,
+            "layers": layers,
+        });
+        if let Some(docker) = &self.docker {
+            data["docker"] = json!({
+                "image": docker.image,
+                "digest": docker.digest,
+            });
+        }
+        serde_json::to_string_pretty(&data).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use crate::fs::BakeryLock;
+
+    #[test]
+    fn test_bakery_lock() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let mut lock: BakeryLock = BakeryLock::new("default");
+        lock.add_layer(
+            "poky",
+            "poky",
+            "https://git.yoctoproject.org/poky",
+            "4b07a5316ed4b858863dfdb7cab63859d46891b0",
+        );
+        lock.add_layer(
+            "meta-foo",
+            "vendor/meta-foo",
+            "",
+            "bd4ab7a4e7e7a1b4ee3e0f1dc3bbbc8a7b6bd1a4",
+        );
+        lock.set_docker(
+            "test-registry/test-image:0.1",
+            "sha256:7ab0b2b5e9a0e1d3b4e2a4f8ac7f0d2e6cbb1c5d2d06e3e6d1a8e6a5ba1d3f10",
+        );
+        let path = temp_dir.path().join("bakery.lock");
+        lock.write(&path).expect("Failed to write lock file");
+        let read: BakeryLock = BakeryLock::read(&path).expect("Failed to read lock file");
+        assert_eq!(read, lock);
+        assert_eq!(read.config, "default");
+        assert_eq!(
+            read.commit("poky"),
+            Some("4b07a5316ed4b858863dfdb7cab63859d46891b0")
+        );
+        assert_eq!(read.layers()["meta-foo"].path, "vendor/meta-foo");
+        assert_eq!(
+            read.docker().map(|d| d.image.as_str()),
+            Some("test-registry/test-image:0.1")
+        );
+        assert_eq!(read.commit("meta-bar"), None);
+    }
+
+    #[test]
+    fn test_bakery_lock_missing() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let path = temp_dir.path().join("bakery.lock");
+        let result = BakeryLock::read(&path);
+        match result {
+            Ok(_lock) => {
+                panic!("We should have recived an error because there is no lock file");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!(
+                        "No lock file '{}', create it using 'bakery layers lock'",
+                        path.display()
+                    )
+                );
+            }
+        }
+    }
+}
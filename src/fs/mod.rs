@@ -1,9 +1,25 @@
 pub mod archiver;
+pub mod bbfailures;
 pub mod bitbake;
+pub mod checksums;
 pub mod config;
+pub mod cpio;
+pub mod layerconf;
+pub mod localconf;
+pub mod lock;
 pub mod manifest;
+pub mod repomanifest;
+pub mod stamp;
 
 pub use archiver::Archiver;
+pub use bbfailures::BBFailures;
 pub use bitbake::BitbakeConf;
+pub use checksums::Checksums;
 pub use config::ConfigFileReader;
+pub use cpio::CpioWriter;
+pub use layerconf::LayerCollections;
+pub use localconf::LocalConf;
+pub use lock::{BakeryLock, LockedImage, LOCK_FILE};
 pub use manifest::Manifest;
+pub use repomanifest::{RepoFile, RepoManifest, RepoProject};
+pub use stamp::Stamp;
@@ -0,0 +1,583 @@
+use indexmap::IndexMap;
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::BError;
+
+/* Includes are followed this deep to catch manifests including each other */
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RepoRemote {
+    pub name: String,
+    pub fetch: String,
+    pub revision: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RepoFile {
+    pub src: String,
+    pub dest: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RepoProject {
+    pub name: String,
+    pub path: String,
+    pub remote: String,
+    pub revision: String,
+    pub upstream: String,
+    pub linkfiles: Vec<RepoFile>,
+    pub copyfiles: Vec<RepoFile>,
+}
+
+impl RepoProject {
+    pub fn new(name: &str, path: &str, remote: &str, revision: &str) -> Self {
+        RepoProject {
+            name: name.to_string(),
+            path: path.to_string(),
+            remote: remote.to_string(),
+            revision: revision.to_string(),
+            upstream: String::new(),
+            linkfiles: vec![],
+            copyfiles: vec![],
+        }
+    }
+
+    /*
+     * The path of the project checkout, if not set the name is used
+     */
+    pub fn path(&self) -> &str {
+        if self.path.is_empty() {
+            return &self.name;
+        }
+        &self.path
+    }
+}
+
+/*
+ * A manifest used by the Android repo tool. Only the parts needed to check
+ * out the projects are supported which is the remotes, the default remote
+ * and revision, the projects with linkfile and copyfile and includes of
+ * other manifests.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct RepoManifest {
+    remotes: IndexMap<String, RepoRemote>,
+    default_remote: String,
+    default_revision: String,
+    projects: Vec<RepoProject>,
+}
+
+impl RepoManifest {
+    fn error(message: String) -> BError {
+        BError::ParseError(format!("Failed to parse repo manifest: {}", message))
+    }
+
+    fn attributes(element: &BytesStart) -> Result<HashMap<String, String>, BError> {
+        let mut attributes: HashMap<String, String> = HashMap::new();
+        for attribute in element.attributes() {
+            let attribute = attribute.map_err(|e| Self::error(e.to_string()))?;
+            let key: String = String::from_utf8_lossy(attribute.key.as_ref()).to_string();
+            let value: String = attribute
+                .unescape_value()
+                .map_err(|e| Self::error(e.to_string()))?
+                .to_string();
+            attributes.insert(key, value);
+        }
+        Ok(attributes)
+    }
+
+    fn required(
+        attributes: &HashMap<String, String>,
+        element: &str,
+        key: &str,
+    ) -> Result<String, BError> {
+        match attributes.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => Err(Self::error(format!("'{}' has no '{}'", element, key))),
+        }
+    }
+
+    fn optional(attributes: &HashMap<String, String>, key: &str) -> String {
+        attributes.get(key).cloned().unwrap_or_default()
+    }
+
+    /*
+     * The paths in the manifest are relative to the layers dir or the project
+     * so an absolute path or a path containing '..' could be used to write
+     * outside of the layers dir
+     */
+    fn relative(element: &str, key: &str, path: &str) -> Result<(), BError> {
+        let p: &Path = Path::new(path);
+        if p.is_absolute() || p.components().any(|c| c == Component::ParentDir) {
+            return Err(Self::error(format!(
+                "'{}' has an invalid '{}' '{}', only relative paths without '..' are supported",
+                element, key, path
+            )));
+        }
+        Ok(())
+    }
+
+    fn parse(&mut self, xml: &str, include_dir: Option<&Path>, depth: usize) -> Result<(), BError> {
+        let mut reader: Reader<&[u8]> = Reader::from_str(xml);
+        reader.trim_text(true);
+        let mut project: Option<usize> = None;
+        loop {
+            let event: Event = reader
+                .read_event()
+                .map_err(|e| Self::error(e.to_string()))?;
+            let (element, empty) = match event {
+                Event::Start(e) => (e, false),
+                Event::Empty(e) => (e, true),
+                Event::End(e) => {
+                    if e.name().as_ref() == b"project" {
+                        project = None;
+                    }
+                    continue;
+                }
+                Event::Eof => break,
+                _ => continue,
+            };
+            let attributes: HashMap<String, String> = Self::attributes(&element)?;
+            match element.name().as_ref() {
+                b"remote" => {
+                    let name: String = Self::required(&attributes, "remote", "name")?;
+                    self.add_remote(
+                        &name,
+                        &Self::required(&attributes, "remote", "fetch")?,
+                        &Self::optional(&attributes, "revision"),
+                    );
+                }
+                b"default" => {
+                    self.set_default(
+                        &Self::optional(&attributes, "remote"),
+                        &Self::optional(&attributes, "revision"),
+                    );
+                }
+                b"project" => {
+                    let mut p: RepoProject = RepoProject::new(
+                        &Self::required(&attributes, "project", "name")?,
+                        &Self::optional(&attributes, "path"),
+                        &Self::optional(&attributes, "remote"),
+                        &Self::optional(&attributes, "revision"),
+                    );
+                    p.upstream = Self::optional(&attributes, "upstream");
+                    Self::relative("project", "path", p.path())?;
+                    self.projects.push(p);
+                    if !empty {
+                        project = Some(self.projects.len() - 1);
+                    }
+                }
+                b"linkfile" | b"copyfile" => {
+                    let name: &str = if element.name().as_ref() == b"linkfile" {
+                        "linkfile"
+                    } else {
+                        "copyfile"
+                    };
+                    let file: RepoFile = RepoFile {
+                        src: Self::required(&attributes, name, "src")?,
+                        dest: Self::required(&attributes, name, "dest")?,
+                    };
+                    Self::relative(name, "src", &file.src)?;
+                    Self::relative(name, "dest", &file.dest)?;
+                    match project {
+                        Some(index) if name == "linkfile" => {
+                            self.projects[index].linkfiles.push(file)
+                        }
+                        Some(index) => self.projects[index].copyfiles.push(file),
+                        None => {
+                            return Err(Self::error(format!(
+                                "'{}' is only supported inside a 'project'",
+                                name
+                            )));
+                        }
+                    }
+                }
+                b"include" => {
+                    let name: String = Self::required(&attributes, "include", "name")?;
+                    if depth >= MAX_INCLUDE_DEPTH {
+                        return Err(Self::error(format!(
+                            "too many nested includes when including '{}'",
+                            name
+                        )));
+                    }
+                    let path: PathBuf = match include_dir {
+                        Some(dir) => dir.join(&name),
+                        None => PathBuf::from(&name),
+                    };
+                    if !path.exists() {
+                        return Err(Self::error(format!(
+                            "the included manifest '{}' does not exist",
+                            path.display()
+                        )));
+                    }
+                    self.parse(&std::fs::read_to_string(&path)?, include_dir, depth + 1)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn new() -> Self {
+        RepoManifest {
+            remotes: IndexMap::new(),
+            default_remote: String::new(),
+            default_revision: String::new(),
+            projects: vec![],
+        }
+    }
+
+    /*
+     * Parse the manifest, included manifests are read from the include dir
+     */
+    pub fn from_str(xml: &str, include_dir: Option<&Path>) -> Result<Self, BError> {
+        let mut manifest: RepoManifest = RepoManifest::new();
+        manifest.parse(xml, include_dir, 0)?;
+        Ok(manifest)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, BError> {
+        if !path.exists() {
+            return Err(BError::ValueError(format!(
+                "No repo manifest '{}'",
+                path.display()
+            )));
+        }
+        Self::from_str(&std::fs::read_to_string(path)?, path.parent())
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), BError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    pub fn add_remote(&mut self, name: &str, fetch: &str, revision: &str) {
+        self.remotes.insert(
+            name.to_string(),
+            RepoRemote {
+                name: name.to_string(),
+                fetch: fetch.to_string(),
+                revision: revision.to_string(),
+            },
+        );
+    }
+
+    pub fn add_project(&mut self, project: RepoProject) {
+        self.projects.push(project);
+    }
+
+    pub fn set_default(&mut self, remote: &str, revision: &str) {
+        self.default_remote = remote.to_string();
+        self.default_revision = revision.to_string();
+    }
+
+    pub fn remotes(&self) -> &IndexMap<String, RepoRemote> {
+        &self.remotes
+    }
+
+    pub fn projects(&self) -> &Vec<RepoProject> {
+        &self.projects
+    }
+
+    pub fn remote(&self, project: &RepoProject) -> Result<&RepoRemote, BError> {
+        let name: &str = if project.remote.is_empty() {
+            &self.default_remote
+        } else {
+            &project.remote
+        };
+        match self.remotes.get(name) {
+            Some(remote) => Ok(remote),
+            None => Err(BError::ValueError(format!(
+                "The project '{}' is using remote '{}' that is not defined in the repo manifest",
+                project.name, name
+            ))),
+        }
+    }
+
+    /*
+     * The revision of the project falls back on the revision of the remote
+     * and then the default revision. An empty revision means the default
+     * branch of the remote.
+     */
+    pub fn revision(&self, project: &RepoProject) -> Result<String, BError> {
+        if !project.revision.is_empty() {
+            return Ok(project.revision.clone());
+        }
+        let remote: &RepoRemote = self.remote(project)?;
+        if !remote.revision.is_empty() {
+            return Ok(remote.revision.clone());
+        }
+        Ok(self.default_revision.clone())
+    }
+
+    /*
+     * The url of the project is the fetch url of the remote followed by the
+     * name of the project. A relative fetch url is relative to the url of
+     * the repository containing the manifest, in the same way as the repo
+     * tool is resolving it.
+     */
+    pub fn url(&self, project: &RepoProject, manifest_url: Option<&str>) -> Result<String, BError> {
+        let remote: &RepoRemote = self.remote(project)?;
+        let mut fetch: String = remote.fetch.trim_end_matches('/').to_string();
+        if fetch.is_empty() || fetch.starts_with('.') {
+            let base: &str = match manifest_url {
+                Some(url) => url.trim_end_matches('/'),
+                None => {
+                    return Err(BError::ValueError(format!(
+                        "The remote '{}' is using the relative fetch url '{}' but the url of the manifest is unknown",
+                        remote.name, remote.fetch
+                    )));
+                }
+            };
+            let mut url: String = base
+                .rsplit_once('/')
+                .map(|(dir, _file)| dir.to_string())
+                .unwrap_or_default();
+            for component in fetch.split('/') {
+                match component {
+                    "." | "" => {}
+                    ".." => {
+                        url = url
+                            .rsplit_once('/')
+                            .map(|(dir, _file)| dir.to_string())
+                            .unwrap_or_default()
+                    }
+                    c => url = format!("{}/{}", url, c),
+                }
+            }
+            fetch = url;
+        }
+        Ok(format!("{}/{}", fetch, project.name))
+    }
+
+    pub fn to_string(&self) -> String {
+        let mut xml: String =
+            String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<manifest>\n");
+        for remote in self.remotes.values() {
+            xml.push_str(&format!(
+                "  <remote name=\"{}\" fetch=\"{}\"",
+                escape(&remote.name),
+                escape(&remote.fetch)
+            ));
+            if !remote.revision.is_empty() {
+                xml.push_str(&format!(" revision=\"{}\"", escape(&remote.revision)));
+            }
+            xml.push_str("/>\n");
+        }
+        if !self.default_remote.is_empty() || !self.default_revision.is_empty() {
+            xml.push_str("  <default");
+            if !self.default_remote.is_empty() {
+                xml.push_str(&format!(" remote=\"{}\"", escape(&self.default_remote)));
+            }
+            if !self.default_revision.is_empty() {
+                xml.push_str(&format!(" revision=\"{}\"", escape(&self.default_revision)));
+            }
+            xml.push_str("/>\n");
+        }
+        for project in self.projects.iter() {
+            xml.push_str(&format!("  <project name=\"{}\"", escape(&project.name)));
+            for (key, value) in [
+                ("path", &project.path),
+                ("remote", &project.remote),
+                ("revision", &project.revision),
+                ("upstream", &project.upstream),
+            ] {
+                if !value.is_empty() {
+                    xml.push_str(&format!(" {}=\"{}\"", key, escape(value)));
+                }
+            }
+            if project.linkfiles.is_empty() && project.copyfiles.is_empty() {
+                xml.push_str("/>\n");
+                continue;
+            }
+            xml.push_str(">\n");
+            for (element, files) in [
+                ("linkfile", &project.linkfiles),
+                ("copyfile", &project.copyfiles),
+            ] {
+                for file in files.iter() {
+                    xml.push_str(&format!(
+                        "    <{} src=\"{}\" dest=\"{}\"/>\n",
+                        element,
+                        escape(&file.src),
+                        escape(&file.dest)
+                    ));
+                }
+            }
+            xml.push_str("  </project>\n");
+        }
+        xml.push_str("</manifest>\n");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use crate::fs::{RepoManifest, RepoProject};
+
+    #[test]
+    fn test_repo_manifest() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        std::fs::write(
+            temp_dir.path().join("bsp.xml"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+  <remote name="vendor" fetch="https://git.vendor.com/bsp/" revision="refs/tags/v1.0"/>
+  <project name="meta-vendor" path="layers/meta-vendor" remote="vendor"/>
+</manifest>
+"#,
+        )
+        .expect("Failed to write manifest");
+        std::fs::write(
+            temp_dir.path().join("default.xml"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+  <!-- The yocto layers -->
+  <remote name="yocto" fetch="https://git.yoctoproject.org"/>
+  <remote name="origin" fetch=".."/>
+  <default remote="yocto" revision="scarthgap" sync-j="4"/>
+  <project name="poky" path="sources/poky">
+    <linkfile src="oe-init-build-env" dest="setup-env"/>
+    <copyfile src="README.poky.md" dest="README.md"/>
+  </project>
+  <project name="meta-foo" remote="origin" revision="4b07a5316ed4b858863dfdb7cab63859d46891b0" upstream="main"/>
+  <include name="bsp.xml"/>
+</manifest>
+"#,
+        )
+        .expect("Failed to write manifest");
+        let manifest: RepoManifest = RepoManifest::read(&temp_dir.path().join("default.xml"))
+            .expect("Failed to read manifest");
+        assert_eq!(manifest.remotes().len(), 3);
+        let projects: &Vec<RepoProject> = manifest.projects();
+        assert_eq!(projects.len(), 3);
+        assert_eq!(projects[0].path(), "sources/poky");
+        assert_eq!(projects[0].linkfiles[0].src, "oe-init-build-env");
+        assert_eq!(projects[0].linkfiles[0].dest, "setup-env");
+        assert_eq!(projects[0].copyfiles[0].dest, "README.md");
+        assert_eq!(
+            manifest.url(&projects[0], None).unwrap(),
+            "https://git.yoctoproject.org/poky"
+        );
+        assert_eq!(manifest.revision(&projects[0]).unwrap(), "scarthgap");
+        assert_eq!(projects[1].path(), "meta-foo");
+        assert_eq!(projects[1].upstream, "main");
+        assert_eq!(
+            manifest
+                .url(
+                    &projects[1],
+                    Some("https://git.example.com/org/manifests.git")
+                )
+                .unwrap(),
+            "https://git.example.com/meta-foo"
+        );
+        assert_eq!(
+            manifest.revision(&projects[1]).unwrap(),
+            "4b07a5316ed4b858863dfdb7cab63859d46891b0"
+        );
+        assert_eq!(
+            manifest.url(&projects[2], None).unwrap(),
+            "https://git.vendor.com/bsp/meta-vendor"
+        );
+        assert_eq!(manifest.revision(&projects[2]).unwrap(), "refs/tags/v1.0");
+
+        /* Writing the manifest and reading it again should give the same manifest */
+        let path = temp_dir.path().join("export/manifest.xml");
+        manifest.write(&path).expect("Failed to write manifest");
+        assert_eq!(
+            RepoManifest::read(&path).expect("Failed to read manifest"),
+            manifest
+        );
+    }
+
+    #[test]
+    fn test_repo_manifest_error() {
+        let result = RepoManifest::from_str(r#"<manifest><project path="poky"/></manifest>"#, None);
+        match result {
+            Ok(_manifest) => {
+                panic!("We should have recived an error because the project has no name");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Failed to parse repo manifest: 'project' has no 'name'"
+                );
+            }
+        }
+        let manifest: RepoManifest = RepoManifest::from_str(
+            r#"<manifest><remote name="origin" fetch="."/><project name="poky" remote="yocto"/><project name="meta-foo" remote="origin"/></manifest>"#,
+            None,
+        )
+        .expect("Failed to parse manifest");
+        match manifest.url(&manifest.projects()[0], None) {
+            Ok(_url) => {
+                panic!("We should have recived an error because the remote is not defined");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "The project 'poky' is using remote 'yocto' that is not defined in the repo manifest"
+                );
+            }
+        }
+        match manifest.url(&manifest.projects()[1], None) {
+            Ok(_url) => {
+                panic!("We should have recived an error because the manifest url is unknown");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "The remote 'origin' is using the relative fetch url '.' but the url of the manifest is unknown"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_repo_manifest_invalid_path() {
+        for (xml, error) in [
+            (
+                r#"<manifest><project name="poky" path="/etc/poky"/></manifest>"#,
+                "'project' has an invalid 'path' '/etc/poky'",
+            ),
+            (
+                r#"<manifest><project name="../poky"/></manifest>"#,
+                "'project' has an invalid 'path' '../poky'",
+            ),
+            (
+                r#"<manifest><project name="poky"><copyfile src="README" dest="../README"/></project></manifest>"#,
+                "'copyfile' has an invalid 'dest' '../README'",
+            ),
+            (
+                r#"<manifest><project name="poky"><linkfile src="/etc/passwd" dest="passwd"/></project></manifest>"#,
+                "'linkfile' has an invalid 'src' '/etc/passwd'",
+            ),
+        ] {
+            match RepoManifest::from_str(xml, None) {
+                Ok(_manifest) => {
+                    panic!("We should have recived an error because the path is not relative");
+                }
+                Err(e) => {
+                    assert_eq!(
+                        e.to_string(),
+                        format!(
+                            "Failed to parse repo manifest: {}, only relative paths without '..' are supported",
+                            error
+                        )
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,397 @@
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::error::BError;
+use crate::executers::Git;
+
+/*
+ * A stamp is recording a fingerprint of all the inputs to a task after the task
+ * has been successfully executed. If the fingerprint of the inputs is identical
+ * the next time the task is about to be executed nothing has changed and the task
+ * can be skipped.
+ */
+pub struct Stamp {
+    path: PathBuf,
+    fingerprint: String,
+}
+
+impl Stamp {
+    fn read_ref(git_dir: &Path, reference: &str) -> Option<String> {
+        let ref_path: PathBuf = git_dir.join(reference);
+        if ref_path.is_file() {
+            return std::fs::read_to_string(ref_path)
+                .ok()
+                .map(|s| s.trim().to_string());
+        }
+
+        // The ref could have been packed by git gc
+        let packed: String = std::fs::read_to_string(git_dir.join("packed-refs")).ok()?;
+        for line in packed.lines() {
+            if let Some((sha, name)) = line.split_once(' ') {
+                if name.trim() == reference {
+                    return Some(sha.to_string());
+                }
+            }
+        }
+
+        None
+    }
+
+    /*
+     * Reading out the HEAD of the git repository that the path is part of without
+     * calling git. If the path is not part of a git repository None is returned.
+     */
+    pub fn git_head(path: &Path) -> Option<String> {
+        let mut dir: Option<&Path> = Some(path);
+
+        while let Some(d) = dir {
+            let git: PathBuf = d.join(".git");
+            if git.exists() {
+                let mut git_dir: PathBuf = git.clone();
+                // When using worktrees or submodules .git is a file pointing to the git dir
+                if git.is_file() {
+                    let content: String = std::fs::read_to_string(&git).ok()?;
+                    let gitdir: &str = content.trim().strip_prefix("gitdir:")?.trim();
+                    git_dir = d.join(gitdir);
+                }
+
+                let head: String = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+                let head: &str = head.trim();
+                return match head.strip_prefix("ref:") {
+                    Some(reference) => Self::read_ref(&git_dir, reference.trim()),
+                    None => Some(head.to_string()),
+                };
+            }
+            dir = d.parent();
+        }
+
+        None
+    }
+
+    fn hex(hasher: Sha256) -> String {
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    fn hash_dir(hasher: &mut Sha256, root: &Path, dir: &Path, skip: &[&str]) {
+        let mut entries: Vec<PathBuf> = match std::fs::read_dir(dir) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+            Err(_e) => return,
+        };
+        entries.sort();
+        for entry in entries {
+            let name: String = entry
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            if name.starts_with('.') || (dir == root && skip.contains(&name.as_str())) {
+                continue;
+            }
+            if entry.is_dir() {
+                Self::hash_dir(hasher, root, &entry, skip);
+            } else if let Ok(content) = std::fs::read(&entry) {
+                hasher.update(
+                    entry
+                        .strip_prefix(root)
+                        .unwrap_or(&entry)
+                        .to_string_lossy()
+                        .as_bytes(),
+                );
+                hasher.update([0u8]);
+                hasher.update(&content);
+            }
+        }
+    }
+
+    /*
+     * Hash the content of all the files in the dir except hidden files and the
+     * entries in skip at the top of the dir
+     */
+    pub fn dir_hash(dir: &Path, skip: &[&str]) -> String {
+        let mut hasher: Sha256 = Sha256::new();
+        Self::hash_dir(&mut hasher, dir, dir, skip);
+        Self::hex(hasher)
+    }
+
+    /*
+     * The state of a layer is the git HEAD and a hash of any uncommitted changes
+     * so that editing a recipe without committing it is detected. A layer that is
+     * not part of a git repository is hashed file by file.
+     */
+    pub fn layer_state(path: &Path) -> String {
+        let head: String = match Self::git_head(path) {
+            Some(head) => head,
+            None => return Self::dir_hash(path, &[]),
+        };
+        let git: Git = Git::new(path);
+        let diff: String = git.diff_head().unwrap_or_default();
+        let untracked: Vec<String> = git.untracked().unwrap_or_default();
+        if diff.is_empty() && untracked.is_empty() {
+            return head;
+        }
+        let mut hasher: Sha256 = Sha256::new();
+        hasher.update(diff.as_bytes());
+        for file in untracked.iter() {
+            hasher.update([0u8]);
+            hasher.update(file.as_bytes());
+            hasher.update([0u8]);
+            hasher.update(std::fs::read(path.join(file)).unwrap_or_default());
+        }
+        format!("{}-dirty-{}", head, Self::hex(hasher))
+    }
+
+    /*
+     * The devtool workspace layer is not a git repository but the sources it
+     * is pointing to are so each source is handled as a layer
+     */
+    pub fn workspace_state(path: &Path) -> Vec<String> {
+        let mut states: Vec<String> = vec![format!(
+            "{}={}",
+            path.display(),
+            Self::dir_hash(path, &["sources"])
+        )];
+        let mut sources: Vec<PathBuf> = std::fs::read_dir(path.join("sources"))
+            .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+            .unwrap_or_default();
+        sources.sort();
+        for source in sources.iter().filter(|s| s.is_dir()) {
+            states.push(format!(
+                "{}={}",
+                source.display(),
+                Self::layer_state(source)
+            ));
+        }
+        states
+    }
+
+    /*
+     * Hash every file referenced by a command line like the scripts called by a
+     * non-bitbake task, relative paths are relative to the dir
+     */
+    pub fn file_hashes(cmd: &str, dir: &Path) -> Vec<String> {
+        let mut hashes: Vec<String> = vec![];
+        for token in cmd.split_whitespace() {
+            let token: &str = token.trim_matches(|c| c == '"' || c == '\'' || c == ';');
+            if token.is_empty() {
+                continue;
+            }
+            let path: PathBuf = dir.join(token);
+            if let Ok(content) = std::fs::read(&path) {
+                let mut hasher: Sha256 = Sha256::new();
+                hasher.update(&content);
+                hashes.push(format!("{}={}", token, Self::hex(hasher)));
+            }
+        }
+        hashes
+    }
+
+    /*
+     * Find all the layers referenced by a bblayers.conf and return a list of
+     * the layer paths together with the state of each layer.
+     */
+    pub fn layer_heads(bblayers_conf: &str) -> Vec<String> {
+        let mut heads: Vec<String> = vec![];
+
+        for token in bblayers_conf.split_whitespace() {
+            let token: &str = token.trim_matches(|c| c == '"' || c == '\\');
+            if !token.starts_with('/') {
+                continue;
+            }
+
+            let path: &Path = Path::new(token);
+            if path.is_dir() {
+                heads.push(format!("{}={}", token, Self::layer_state(path)));
+            }
+        }
+
+        heads
+    }
+
+    pub fn new(path: &Path, inputs: &[String]) -> Self {
+        let mut hasher: Sha256 = Sha256::new();
+        for input in inputs.iter() {
+            hasher.update(input.as_bytes());
+            hasher.update([0u8]);
+        }
+        let fingerprint: String = Self::hex(hasher);
+
+        Stamp {
+            path: path.to_path_buf(),
+            fingerprint,
+        }
+    }
+
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /*
+     * Returns the fingerprint recorded the last time the task was successfully executed
+     */
+    pub fn read(&self) -> Option<String> {
+        std::fs::read_to_string(&self.path)
+            .ok()
+            .map(|s| s.trim().to_string())
+    }
+
+    pub fn is_unchanged(&self) -> bool {
+        match self.read() {
+            Some(fingerprint) => fingerprint == self.fingerprint,
+            None => false,
+        }
+    }
+
+    pub fn write(&self) -> Result<(), BError> {
+        if let Some(parent_dir) = self.path.parent() {
+            std::fs::create_dir_all(parent_dir)?;
+        }
+        std::fs::write(&self.path, format!("{}\n", self.fingerprint))?;
+        Ok(())
+    }
+
+    pub fn remove(&self) -> Result<(), BError> {
+        if self.path.exists() {
+            std::fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use tempdir::TempDir;
+
+    use crate::fs::Stamp;
+
+    #[test]
+    fn test_stamp() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let path: &Path = temp_dir.path();
+        let stamp_path: PathBuf = path.join("stamps/default/task.stamp");
+        let inputs: Vec<String> = vec![String::from("input1"), String::from("input2")];
+        let stamp: Stamp = Stamp::new(&stamp_path, &inputs);
+        assert_eq!(stamp.fingerprint().len(), 64);
+        assert!(!stamp.is_unchanged());
+        stamp.write().expect("Failed to write stamp");
+        assert!(stamp_path.exists());
+        assert!(Stamp::new(&stamp_path, &inputs).is_unchanged());
+        let changed: Vec<String> = vec![String::from("input1"), String::from("input3")];
+        assert!(!Stamp::new(&stamp_path, &changed).is_unchanged());
+        // Make sure the inputs are separated when calculating the fingerprint
+        let joined: Vec<String> = vec![String::from("input1input2")];
+        assert!(!Stamp::new(&stamp_path, &joined).is_unchanged());
+        stamp.remove().expect("Failed to remove stamp");
+        assert!(!stamp_path.exists());
+    }
+
+    #[test]
+    fn test_stamp_git_head() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let path: &Path = temp_dir.path();
+        let sha: &str = "0123456789abcdef0123456789abcdef01234567";
+        let layer_dir: PathBuf = path.join("layers/meta-test");
+        std::fs::create_dir_all(layer_dir.join(".git/refs/heads")).unwrap();
+        std::fs::create_dir_all(layer_dir.join("meta-sub")).unwrap();
+        std::fs::write(layer_dir.join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+        std::fs::write(layer_dir.join(".git/refs/heads/main"), format!("{}\n", sha)).unwrap();
+        assert_eq!(Stamp::git_head(&layer_dir), Some(sha.to_string()));
+        assert_eq!(
+            Stamp::git_head(&layer_dir.join("meta-sub")),
+            Some(sha.to_string())
+        );
+        std::fs::remove_file(layer_dir.join(".git/refs/heads/main")).unwrap();
+        std::fs::write(
+            layer_dir.join(".git/packed-refs"),
+            format!("# pack-refs with: peeled\n{} refs/heads/main\n", sha),
+        )
+        .unwrap();
+        assert_eq!(Stamp::git_head(&layer_dir), Some(sha.to_string()));
+        let bblayers_conf: String = format!(
+            "BBLAYERS ?= \" \\\n{}/meta-sub \\\n{}/layers/missing \\\n\"\n",
+            layer_dir.display(),
+            path.display()
+        );
+        assert_eq!(
+            Stamp::layer_heads(&bblayers_conf),
+            vec![format!("{}/meta-sub={}", layer_dir.display(), sha)]
+        );
+    }
+
+    fn helper_git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args([
+                "-c",
+                "user.name=bakery",
+                "-c",
+                "user.email=bakery@localhost",
+            ])
+            .args(args)
+            .output()
+            .expect("Failed to execute git");
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    #[test]
+    fn test_stamp_layer_state() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let layer_dir: PathBuf = temp_dir.path().join("layers/meta-test");
+        std::fs::create_dir_all(layer_dir.join("recipes-test")).unwrap();
+        std::fs::write(
+            layer_dir.join("recipes-test/test.bb"),
+            "LICENSE = \"MIT\"\n",
+        )
+        .unwrap();
+        helper_git(&layer_dir, &["init", "--quiet"]);
+        helper_git(&layer_dir, &["add", "."]);
+        helper_git(&layer_dir, &["commit", "--quiet", "-m", "init"]);
+        let head: String = helper_git(&layer_dir, &["rev-parse", "HEAD"]);
+        assert_eq!(Stamp::layer_state(&layer_dir), head);
+        // An uncommitted change to a recipe should change the state
+        std::fs::write(
+            layer_dir.join("recipes-test/test.bb"),
+            "LICENSE = \"GPL\"\n",
+        )
+        .unwrap();
+        let modified: String = Stamp::layer_state(&layer_dir);
+        assert!(modified.starts_with(&format!("{}-dirty-", head)));
+        // An untracked file should change the state as well
+        std::fs::write(layer_dir.join("recipes-test/new.bb"), "").unwrap();
+        let untracked: String = Stamp::layer_state(&layer_dir);
+        assert_ne!(untracked, modified);
+        std::fs::write(layer_dir.join("recipes-test/new.bb"), "PV = \"1.0\"\n").unwrap();
+        assert_ne!(Stamp::layer_state(&layer_dir), untracked);
+        // A layer that is not in a git repository is hashed
+        let plain_dir: PathBuf = temp_dir.path().join("layers/meta-plain");
+        std::fs::create_dir_all(&plain_dir).unwrap();
+        std::fs::write(plain_dir.join("test.bb"), "1").unwrap();
+        let plain: String = Stamp::layer_state(&plain_dir);
+        assert_eq!(plain, Stamp::layer_state(&plain_dir));
+        std::fs::write(plain_dir.join("test.bb"), "2").unwrap();
+        assert_ne!(plain, Stamp::layer_state(&plain_dir));
+    }
+
+    #[test]
+    fn test_stamp_file_hashes() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let path: &Path = temp_dir.path();
+        std::fs::write(path.join("build.sh"), "echo build").unwrap();
+        let hashes: Vec<String> = Stamp::file_hashes("./build.sh --release missing.sh", path);
+        assert_eq!(hashes.len(), 1);
+        assert!(hashes[0].starts_with("./build.sh="));
+        std::fs::write(path.join("build.sh"), "echo rebuild").unwrap();
+        assert_ne!(Stamp::file_hashes("./build.sh --release", path), hashes);
+    }
+}
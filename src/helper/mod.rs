@@ -11,8 +11,10 @@ use rand::prelude::*;
 use serde_json::Value;
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use users::Groups;
 
 pub struct Helper;
@@ -112,13 +114,19 @@ impl Helper {
             )));
         }
 
-        if archive.extension() == "tar" {
+        if archive.extension() == "tar" || archive.extension() == "cpio" {
             let file: File = File::open(archive.path())?;
-            let mut tar: tar::Archive<Box<dyn std::io::Read>>;
+            let mut dec: Box<dyn std::io::Read>;
             if archive.compression() == "gz" {
-                tar = tar::Archive::new(Box::new(flate2::read::GzDecoder::new(file)));
+                dec = Box::new(flate2::read::GzDecoder::new(file));
             } else if archive.compression() == "bz2" {
-                tar = tar::Archive::new(Box::new(bzip2::read::BzDecoder::new(file)));
+                dec = Box::new(bzip2::read::BzDecoder::new(file));
+            } else if archive.compression() == "xz" {
+                dec = Box::new(xz2::read::XzDecoder::new(file));
+            } else if archive.compression() == "zst" {
+                dec = Box::new(zstd::stream::read::Decoder::new(file)?);
+            } else if archive.compression().is_empty() {
+                dec = Box::new(file);
             } else {
                 return Err(BError::ArchiverError(format!(
                     "Unsupported compression '{}'!",
@@ -126,6 +134,13 @@ impl Helper {
                 )));
             }
 
+            if archive.extension() == "cpio" {
+                let mut data: Vec<u8> = vec![];
+                dec.read_to_end(&mut data)?;
+                return Helper::list_files_in_cpio(&data, false);
+            }
+
+            let mut tar: tar::Archive<Box<dyn std::io::Read>> = tar::Archive::new(dec);
             tar.unpack(unpack_dir.to_str().unwrap()).unwrap();
 
             Helper::list_files_in_dir(&unpack_dir, &mut archived_files, &unpack_dir)
@@ -145,6 +160,46 @@ impl Helper {
         Ok(archived_files)
     }
 
+    /*
+     * List the names of the entries in a newc cpio archive. The directory
+     * entries are only included if include_dirs is set.
+     */
+    pub fn list_files_in_cpio(data: &[u8], include_dirs: bool) -> Result<Vec<PathBuf>, BError> {
+        let mut names: Vec<PathBuf> = vec![];
+        let mut offset: usize = 0;
+
+        loop {
+            let header: &[u8] = &data[offset..offset + 110];
+            if &header[0..6] != b"070701" {
+                return Err(BError::ArchiverError(
+                    "Invalid cpio archive, only newc is supported!".to_string(),
+                ));
+            }
+
+            let field = |i: usize| -> usize {
+                let hex: &str = std::str::from_utf8(&header[6 + i * 8..14 + i * 8]).unwrap();
+                usize::from_str_radix(hex, 16).expect("Invalid cpio header")
+            };
+            let mode: usize = field(1);
+            let filesize: usize = field(6);
+            let namesize: usize = field(11);
+            let name_start: usize = offset + 110;
+            let name: &str = std::str::from_utf8(&data[name_start..name_start + namesize - 1])?;
+
+            if name == "TRAILER!!!" {
+                break;
+            }
+
+            if include_dirs || mode & 0o040000 == 0 {
+                names.push(PathBuf::from(name));
+            }
+            let data_start: usize = (name_start + namesize + 3) & !3;
+            offset = (data_start + filesize + 3) & !3;
+        }
+
+        Ok(names)
+    }
+
     pub fn verify_archived_files(
         expected_files: &Vec<PathBuf>,
         archived_files: &Vec<PathBuf>,
@@ -338,10 +393,12 @@ impl Helper {
             cmd_line.push("-i".to_string());
         }
         let cache: users::UsersCache = users::UsersCache::new();
-        cmd_line.append(&mut vec![
-            String::from("--group-add"),
-            cache.get_group_by_name("docker").unwrap().gid().to_string(),
-        ]);
+        if let Some(group) = cache.get_group_by_name("docker") {
+            cmd_line.append(&mut vec![
+                String::from("--group-add"),
+                group.gid().to_string(),
+            ]);
+        }
         if !volumes.is_empty() {
             volumes.iter().for_each(|v| {
                 cmd_line.append(&mut vec![String::from("-v"), v.to_string()]);
@@ -423,10 +480,12 @@ impl Helper {
             cmd_line.push("-i".to_string());
         }
         let cache: users::UsersCache = users::UsersCache::new();
-        cmd_line.append(&mut vec![
-            String::from("--group-add"),
-            cache.get_group_by_name("docker").unwrap().gid().to_string(),
-        ]);
+        if let Some(group) = cache.get_group_by_name("docker") {
+            cmd_line.append(&mut vec![
+                String::from("--group-add"),
+                group.gid().to_string(),
+            ]);
+        }
         cmd_line.append(&mut vec![
             String::from("--env-file"),
             env_file.to_string_lossy().to_string(),
@@ -436,4 +495,91 @@ impl Helper {
         //println!("cmd_line {:?}", cmd_line);
         cmd_line
     }
+
+    /*
+     * Stub of the docker engine listening on a unix socket. Each request is
+     * answered with the first response where the request line starts with
+     * the route. All requests are recorded so the test can verify them.
+     */
+    pub fn docker_api_stub(socket: &Path, routes: Vec<(&str, Vec<u8>)>) -> Arc<Mutex<Vec<String>>> {
+        let listener: UnixListener =
+            UnixListener::bind(socket).expect("Failed to bind docker stub socket");
+        let requests: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+        let recorded: Arc<Mutex<Vec<String>>> = requests.clone();
+        let routes: Arc<Vec<(String, Vec<u8>)>> = Arc::new(
+            routes
+                .into_iter()
+                .map(|(r, response)| (r.to_string(), response))
+                .collect(),
+        );
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream: UnixStream = match stream {
+                    Ok(s) => s,
+                    Err(_e) => break,
+                };
+                let recorded: Arc<Mutex<Vec<String>>> = recorded.clone();
+                let routes: Arc<Vec<(String, Vec<u8>)>> = routes.clone();
+                std::thread::spawn(move || {
+                    let mut reader: BufReader<UnixStream> =
+                        BufReader::new(stream.try_clone().expect("Failed to clone stream"));
+                    let mut line: String = String::new();
+                    reader.read_line(&mut line).unwrap_or(0);
+                    let request: String = line.trim().trim_end_matches(" HTTP/1.1").to_string();
+                    let mut length: usize = 0;
+                    loop {
+                        line.clear();
+                        if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim().is_empty() {
+                            break;
+                        }
+                        if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                            length = value.trim().parse::<usize>().unwrap_or(0);
+                        }
+                    }
+                    let mut body: Vec<u8> = vec![0; length];
+                    reader.read_exact(&mut body).unwrap_or(());
+                    recorded.lock().unwrap().push(request.clone());
+                    let response: Vec<u8> =
+                        match routes.iter().find(|(r, _)| request.starts_with(r)) {
+                            Some((_r, response)) => response.clone(),
+                            None => Helper::http_response(
+                                404,
+                                br#"{"message": "not found"}"#.to_vec(),
+                                false,
+                            ),
+                        };
+                    stream.write_all(&response).unwrap_or(());
+                });
+            }
+        });
+        requests
+    }
+
+    pub fn http_response(status: u16, body: Vec<u8>, chunked: bool) -> Vec<u8> {
+        let mut response: Vec<u8> = format!("HTTP/1.1 {} Stub\r\n", status).into_bytes();
+        if chunked {
+            response.extend_from_slice(b"Transfer-Encoding: chunked\r\n\r\n");
+            /* Split the body into two chunks to make sure chunks are joined */
+            let (first, second) = body.split_at(body.len() / 2);
+            for chunk in [first, second] {
+                if !chunk.is_empty() {
+                    response.extend(format!("{:x}\r\n", chunk.len()).into_bytes());
+                    response.extend_from_slice(chunk);
+                    response.extend_from_slice(b"\r\n");
+                }
+            }
+            response.extend_from_slice(b"0\r\n\r\n");
+        } else {
+            response.extend(format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes());
+            response.extend(body);
+        }
+        response
+    }
+
+    pub fn http_upgrade_response(stream: Vec<u8>) -> Vec<u8> {
+        let mut response: Vec<u8> =
+            b"HTTP/1.1 101 UPGRADED\r\nConnection: Upgrade\r\nUpgrade: tcp\r\n\r\n".to_vec();
+        response.extend(stream);
+        response
+    }
 }
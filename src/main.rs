@@ -7,6 +7,7 @@ mod error;
 mod executers;
 mod fs;
 mod helper;
+mod report;
 mod workspace;
 
 use crate::cli::bakery::Bakery;
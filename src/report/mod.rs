@@ -0,0 +1,283 @@
+use chrono::{DateTime, Local};
+use indexmap::IndexMap;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::cli::Cli;
+use crate::collector::Collected;
+use crate::error::BError;
+use crate::fs::Manifest;
+
+pub const BUILD_REPORT_FILE: &str = "build-report.json";
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum TaskStatus {
+    Success,
+    Failed,
+    Skipped,
+}
+
+impl TaskStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TaskStatus::Success => "success",
+            TaskStatus::Failed => "failed",
+            TaskStatus::Skipped => "skipped",
+        }
+    }
+}
+
+pub struct TaskReport {
+    name: String,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+    status: TaskStatus,
+    skip_reason: String,
+    error: String,
+    commands: Vec<Vec<String>>,
+    collected: Vec<Collected>,
+}
+
+impl TaskReport {
+    pub fn new(name: &str) -> Self {
+        let now: DateTime<Local> = Local::now();
+        TaskReport {
+            name: name.to_string(),
+            start: now,
+            end: now,
+            status: TaskStatus::Success,
+            skip_reason: String::new(),
+            error: String::new(),
+            commands: vec![],
+            collected: vec![],
+        }
+    }
+
+    pub fn skip(&mut self, reason: &str) {
+        self.status = TaskStatus::Skipped;
+        self.skip_reason = reason.to_string();
+    }
+
+    pub fn add_collected(&mut self, collected: &mut Vec<Collected>) {
+        self.collected.append(collected);
+    }
+
+    /*
+     * Set the end time, the status and all the command lines that have been
+     * executed by the current thread while executing the task
+     */
+    pub fn finish(&mut self, cli: &Cli, result: &Result<(), BError>) {
+        self.end = Local::now();
+        self.commands.append(&mut cli.take_calls());
+        if let Err(err) = result {
+            self.status = TaskStatus::Failed;
+            self.error = err.to_string();
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        let collected: Vec<Value> = self
+            .collected
+            .iter()
+            .map(|c| {
+                json!({
+                    "src": c.src.to_string_lossy(),
+                    "dest": c.dest.to_string_lossy(),
+                    "size": c.size,
+                    "sha256": c.sha256,
+                })
+            })
+            .collect();
+        let mut value: Value = json!({
+            "name": self.name,
+            "status": self.status.as_str(),
+            "start": self.start.to_rfc3339(),
+            "end": self.end.to_rfc3339(),
+            "commands": self.commands,
+            "collected": collected,
+        });
+        if !self.skip_reason.is_empty() {
+            value["skip_reason"] = json!(self.skip_reason);
+        }
+        if !self.error.is_empty() {
+            value["error"] = json!(self.error);
+        }
+        value
+    }
+}
+
+/*
+ * The build report is shared between all the tasks that are executed during a
+ * build which could be executed in parallel so the task reports are protected
+ * by a mutex.
+ */
+pub struct BuildReport {
+    config: String,
+    start: DateTime<Local>,
+    context: Mutex<IndexMap<String, String>>,
+    tasks: Mutex<Vec<TaskReport>>,
+}
+
+impl BuildReport {
+    pub fn new(config: &str) -> Self {
+        BuildReport {
+            config: config.to_string(),
+            start: Local::now(),
+            context: Mutex::new(IndexMap::new()),
+            tasks: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn set_context(&self, context: &IndexMap<String, String>) {
+        if let Ok(mut ctx) = self.context.lock() {
+            *ctx = context
+                .iter()
+                .map(|(key, value)| (key.to_ascii_uppercase(), value.clone()))
+                .collect();
+        }
+    }
+
+    pub fn add_task(&self, task: TaskReport) {
+        if let Ok(mut tasks) = self.tasks.lock() {
+            tasks.push(task);
+        }
+    }
+
+    pub fn to_string(&self, result: &Result<(), BError>) -> String {
+        let context: IndexMap<String, String> = match self.context.lock() {
+            Ok(ctx) => ctx.clone(),
+            Err(_e) => IndexMap::new(),
+        };
+        let tasks: Vec<Value> = match self.tasks.lock() {
+            Ok(tasks) => tasks.iter().map(|t| t.to_value()).collect(),
+            Err(_e) => vec![],
+        };
+        let mut ctx: serde_json::Map<String, Value> = serde_json::Map::new();
+        for (key, value) in context.iter() {
+            ctx.insert(key.clone(), json!(value));
+        }
+        let mut value: Value = json!({
+            "config": self.config,
+            "status": if result.is_ok() { "success" } else { "failed" },
+            "start": self.start.to_rfc3339(),
+            "end": Local::now().to_rfc3339(),
+            "context": Value::Object(ctx),
+            "tasks": tasks,
+        });
+        if let Err(err) = result {
+            value["error"] = json!(err.to_string());
+        }
+        serde_json::to_string_pretty(&value).unwrap_or_default()
+    }
+
+    pub fn write(
+        &self,
+        artifacts_dir: &PathBuf,
+        result: &Result<(), BError>,
+    ) -> Result<PathBuf, BError> {
+        let path: PathBuf = artifacts_dir.join(BUILD_REPORT_FILE);
+        let report: Manifest = Manifest::new(&path)?;
+        report.write(&self.to_string(result))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::{indexmap, IndexMap};
+    use serde_json::Value;
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    use crate::cli::{BLogger, CallParams, Cli, MockSystem};
+    use crate::collector::Collected;
+    use crate::error::BError;
+    use crate::report::{BuildReport, TaskReport};
+
+    #[test]
+    fn test_build_report() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let artifacts_dir: PathBuf = PathBuf::from(temp_dir.path()).join("artifacts");
+        let mut mocked_system: MockSystem = MockSystem::new();
+        mocked_system
+            .expect_check_call()
+            .with(mockall::predicate::eq(CallParams {
+                cmd_line: vec!["cd", "/workspace", "&&", "build.sh"]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+                env: std::collections::HashMap::new(),
+                shell: true,
+            }))
+            .once()
+            .returning(|_x| Ok(()));
+        let cli: Cli = Cli::new(
+            Box::new(BLogger::new()),
+            Box::new(mocked_system),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery"]),
+        );
+        let context: IndexMap<String, String> = indexmap! {
+            "bkry_machine".to_string() => "test-machine".to_string(),
+        };
+        let report: BuildReport = BuildReport::new("default");
+        report.set_context(&context);
+
+        let mut task1: TaskReport = TaskReport::new("task1");
+        let cmd_line: Vec<String> = vec!["cd", "/workspace", "&&", "build.sh"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        cli.check_call(&cmd_line, &std::collections::HashMap::new(), true)
+            .expect("Failed to call command");
+        task1.add_collected(&mut vec![Collected {
+            src: PathBuf::from("/workspace/file.txt"),
+            dest: artifacts_dir.join("file.txt"),
+            size: 4,
+            sha256: String::from("sha256"),
+        }]);
+        task1.finish(&cli, &Ok(()));
+        report.add_task(task1);
+
+        let mut task2: TaskReport = TaskReport::new("task2");
+        task2.skip("disabled");
+        task2.finish(&cli, &Ok(()));
+        report.add_task(task2);
+
+        let mut task3: TaskReport = TaskReport::new("task3");
+        let result: Result<(), BError> = Err(BError::CliError("failed".to_string()));
+        task3.finish(&cli, &result);
+        report.add_task(task3);
+
+        let path: PathBuf = report
+            .write(&artifacts_dir, &result)
+            .expect("Failed to write build report");
+        assert_eq!(path, artifacts_dir.join("build-report.json"));
+        let content: String = std::fs::read_to_string(&path).expect("Failed to read report");
+        let value: Value = serde_json::from_str(&content).expect("Failed to parse report");
+        assert_eq!(value["config"], "default");
+        assert_eq!(value["status"], "failed");
+        assert_eq!(value["error"], "failed");
+        assert_eq!(value["context"]["BKRY_MACHINE"], "test-machine");
+        assert_eq!(value["tasks"][0]["name"], "task1");
+        assert_eq!(value["tasks"][0]["status"], "success");
+        assert_eq!(
+            value["tasks"][0]["commands"],
+            serde_json::json!([["cd", "/workspace", "&&", "build.sh"]])
+        );
+        assert_eq!(
+            value["tasks"][0]["collected"][0]["src"],
+            "/workspace/file.txt"
+        );
+        assert_eq!(value["tasks"][0]["collected"][0]["size"], 4);
+        assert_eq!(value["tasks"][0]["collected"][0]["sha256"], "sha256");
+        assert!(value["tasks"][0].get("skip_reason").is_none());
+        assert_eq!(value["tasks"][1]["status"], "skipped");
+        assert_eq!(value["tasks"][1]["skip_reason"], "disabled");
+        assert!(value["tasks"][1]["commands"].as_array().unwrap().is_empty());
+        assert_eq!(value["tasks"][2]["status"], "failed");
+        assert_eq!(value["tasks"][2]["error"], "failed");
+    }
+}
@@ -36,8 +36,9 @@ impl WsArtifactsHandler {
 
     pub fn expand_ctx(&mut self, ctx: &Context) -> Result<(), BError> {
         self.data.expand_ctx(ctx)?;
-        for c in self.children.iter_mut() {
-            c.expand_ctx(ctx)?;
+        for (i, c) in self.children.iter_mut().enumerate() {
+            c.expand_ctx(ctx)
+                .map_err(|err| Context::key_error(err, &format!("artifacts[{}]", i)))?;
         }
         Ok(())
     }
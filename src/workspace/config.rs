@@ -2,15 +2,16 @@ use indexmap::IndexMap;
 use serde_json::Value;
 
 use crate::configs::Context;
-use crate::data::{WsBuildData, WsContextData};
+use crate::data::WsBuildData;
 use crate::error::BError;
 use crate::fs::ConfigFileReader;
-use crate::workspace::{WsCustomSubCmdHandler, WsSettingsHandler, WsTaskHandler};
+use crate::workspace::{TaskGraph, WsCustomSubCmdHandler, WsSettingsHandler, WsTaskHandler};
 
 pub struct WsBuildConfigHandler {
     data: WsBuildData,
     tasks: IndexMap<String, WsTaskHandler>,
     subcmds: IndexMap<String, WsCustomSubCmdHandler>,
+    file: String,
 }
 
 impl WsBuildConfigHandler {
@@ -34,9 +35,21 @@ impl WsBuildConfigHandler {
             data: build_data,
             tasks,
             subcmds,
+            file: String::new(),
         })
     }
 
+    /*
+     * Set the build config file that this build config is read from. The tasks
+     * and sub-commands are keeping the file when merged into the main build
+     * config so a context error can point to the file where the key is located
+     */
+    pub fn set_file(&mut self, file: &str) {
+        self.file = file.to_string();
+        self.tasks.values_mut().for_each(|task| task.set_file(file));
+        self.subcmds.values_mut().for_each(|cmd| cmd.set_file(file));
+    }
+
     pub fn build_data(&self) -> &WsBuildData {
         &self.data
     }
@@ -45,14 +58,58 @@ impl WsBuildConfigHandler {
         self.data.update_ctx(context);
     }
 
+    /*
+     * Add the location in the build config to any context error so it is
+     * possible to tell where the context variable that failed is used
+     */
+    fn ctx_error(&self, err: BError, location: &str, file: &str) -> BError {
+        let err: BError = if location.is_empty() {
+            err
+        } else {
+            Context::key_error(err, location)
+        };
+        match err {
+            BError::CtxKeyError(msg) if file.is_empty() => {
+                BError::CtxKeyError(format!("{} of build config '{}'", msg, self.data.name()))
+            }
+            BError::CtxKeyError(msg) => BError::CtxKeyError(format!(
+                "{} of build config '{}' read from '{}'",
+                msg,
+                self.data.name(),
+                file
+            )),
+            _ => err,
+        }
+    }
+
+    pub fn set_strict_ctx(&mut self, strict: bool) {
+        self.data.set_strict_ctx(strict);
+    }
+
     pub fn expand_ctx(&mut self) -> Result<(), BError> {
-        self.data.expand_ctx()?;
-        for (_name, task) in self.tasks.iter_mut() {
-            task.expand_ctx(self.data.context().ctx())?;
+        if let Err(err) = self.data.expand_ctx() {
+            return Err(self.ctx_error(err, "", &self.file));
         }
-        for (_name, cmd) in self.subcmds.iter_mut() {
-            cmd.expand_ctx(self.data.context().ctx())?;
+        for (name, task) in self.tasks.iter_mut() {
+            if let Err(err) = task.expand_ctx(self.data.context().ctx()) {
+                let location: String = format!("tasks.{}", name);
+                let file: String = task.file().to_string();
+                return Err(self.ctx_error(err, &location, &file));
+            }
         }
+        for (name, cmd) in self.subcmds.iter_mut() {
+            if let Err(err) = cmd.expand_ctx(self.data.context().ctx()) {
+                let location: String = name.clone();
+                let file: String = cmd.file().to_string();
+                return Err(self.ctx_error(err, &location, &file));
+            }
+        }
+        /*
+         * The task dependencies can contain context variables so verify that there
+         * are no circular dependencies and that all dependencies can be resolved
+         * once the tasks have been expanded
+         */
+        self.task_graph()?;
         Ok(())
     }
 
@@ -130,6 +187,10 @@ impl WsBuildConfigHandler {
         &self.tasks
     }
 
+    pub fn task_graph(&self) -> Result<TaskGraph, BError> {
+        TaskGraph::new(&self.tasks, true)
+    }
+
     pub fn subcmds(&self) -> &IndexMap<String, WsCustomSubCmdHandler> {
         &self.subcmds
     }
@@ -235,12 +296,22 @@ mod tests {
             ws_config.build_data().bitbake().bblayers_conf_path(),
             PathBuf::from("/workspace/builds/test-name/conf/bblayers.conf")
         );
-        assert!(ws_config.build_data().bitbake().bblayers_conf().is_empty());
+        assert!(ws_config
+            .build_data()
+            .bitbake()
+            .bblayers_conf_discovered()
+            .unwrap()
+            .is_empty());
         assert_eq!(
             ws_config.build_data().bitbake().local_conf_path(),
             PathBuf::from("/workspace/builds/test-name/conf/local.conf")
         );
-        assert!(!ws_config.build_data().bitbake().local_conf().is_empty());
+        assert!(!ws_config
+            .build_data()
+            .bitbake()
+            .local_conf_vars()
+            .to_string()
+            .is_empty());
         let mut conf_str: String = String::new();
         conf_str.push_str(&format!(
             "MACHINE ?= \"{}\"\n",
@@ -267,7 +338,14 @@ mod tests {
             "DL_DIR ?= \"{}\"\n",
             ws_config.build_data().bitbake().dl_dir().to_str().unwrap()
         ));
-        assert_eq!(ws_config.build_data().bitbake().local_conf(), conf_str);
+        assert_eq!(
+            ws_config
+                .build_data()
+                .bitbake()
+                .local_conf_vars()
+                .to_string(),
+            conf_str
+        );
         assert_eq!(
             ws_config.build_data().bitbake().docker_image(),
             "NA".to_string()
@@ -330,6 +408,103 @@ mod tests {
         assert!(ws_config.tasks().is_empty());
     }
 
+    #[test]
+    fn test_ws_config_tasks_circular_depends() {
+        let json_settings = r#"
+        {
+            "version": "6"
+        }"#;
+        let json_build_config = r#"
+        {
+            "version": "6",
+            "name": "test-name",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "tasks": {
+                "task1": {
+                    "index": "1",
+                    "name": "task1",
+                    "type": "non-bitbake",
+                    "depends": [ "task2" ]
+                },
+                "task2": {
+                    "index": "2",
+                    "name": "task2",
+                    "type": "non-bitbake",
+                    "depends": [ "task1" ]
+                }
+            }
+        }"#;
+        let work_dir: PathBuf = PathBuf::from("/workspace");
+        let mut ws_settings: WsSettingsHandler =
+            WsSettingsHandler::from_str(&work_dir, json_settings).unwrap();
+        let mut config: WsBuildConfigHandler =
+            WsBuildConfigHandler::from_str(json_build_config, &mut ws_settings)
+                .expect("Failed to parse build config");
+        let result: Result<(), BError> = config.expand_ctx();
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because of the circular dependency!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Invalid 'task' node in build config. Circular task dependency 'task1 -> task2 -> task1'"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_ws_config_tasks_ctx_circular_depends() {
+        let json_settings = r#"
+        {
+            "version": "6"
+        }"#;
+        let json_build_config = r#"
+        {
+            "version": "6",
+            "name": "test-name",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "context": [
+                "DEP_TASK=task1"
+            ],
+            "tasks": {
+                "task1": {
+                    "index": "1",
+                    "name": "task1",
+                    "type": "non-bitbake",
+                    "depends": [ "task2" ]
+                },
+                "task2": {
+                    "index": "2",
+                    "name": "task2",
+                    "type": "non-bitbake",
+                    "depends": [ "$#[DEP_TASK]" ]
+                }
+            }
+        }"#;
+        let work_dir: PathBuf = PathBuf::from("/workspace");
+        let mut ws_settings: WsSettingsHandler =
+            WsSettingsHandler::from_str(&work_dir, json_settings).unwrap();
+        let mut config: WsBuildConfigHandler =
+            WsBuildConfigHandler::from_str(json_build_config, &mut ws_settings)
+                .expect("Failed to parse build config");
+        let result: Result<(), BError> = config.expand_ctx();
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because of the circular dependency!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Invalid 'task' node in build config. Circular task dependency 'task1 -> task2 -> task1'"
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_ws_task_config_condition() {
         let json_settings = r#"
@@ -550,6 +725,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ws_config_strict_ctx() {
+        let json_settings = r#"
+        {
+            "version": "6",
+            "context": {
+                "strict": "true"
+            }
+        }"#;
+        let json_build_config = r#"
+        {
+            "version": "6",
+            "name": "test-name",
+            "description": "Test Description",
+            "arch": "test-arch",
+            "bb": {
+                "localconf": [
+                    "DEVICE=\"$#[BKRY_DEVICE:-qemu]\""
+                ]
+            },
+            "tasks": {
+                "sign-image": {
+                    "index": "0",
+                    "name": "sign-image",
+                    "type": "non-bitbake",
+                    "build": "sign.sh",
+                    "artifacts": [
+                        {
+                            "source": "image-$#[BKRY_DEVCE].bin"
+                        }
+                    ]
+                }
+            }
+        }"#;
+        let work_dir: PathBuf = PathBuf::from("/workspace");
+        let ws_settings: WsSettingsHandler =
+            WsSettingsHandler::from_str(&work_dir, json_settings).unwrap();
+        let mut ws_config: WsBuildConfigHandler =
+            WsBuildConfigHandler::from_str(json_build_config, &ws_settings)
+                .expect("Failed to parse build config");
+        ws_config.set_strict_ctx(ws_settings.strict_ctx());
+        let result: Result<(), BError> = ws_config.expand_ctx();
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because BKRY_DEVCE is not defined!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Context variable 'BKRY_DEVCE' is not defined when expanding 'image-$#[BKRY_DEVCE].bin' in 'tasks.sign-image.artifacts[0].source' of build config 'test-name'".to_string()
+                );
+            }
+        }
+        assert!(ws_config
+            .build_data()
+            .bitbake()
+            .local_conf_vars()
+            .to_string()
+            .starts_with("DEVICE=\"qemu\"\n"));
+    }
+
     #[test]
     fn test_ws_config_tasks() {
         let json_settings = r#"
@@ -652,7 +888,7 @@ mod tests {
             Err(err) => {
                 assert_eq!(
                     "The build config version '5' is not compatible with current bakery version. \
-                    Update config to match the format of version '6'",
+                    Update config to match the format of version '6' using 'bakery migrate'",
                     err.to_string()
                 );
             }
@@ -10,6 +10,7 @@ use std::collections::HashMap;
 
 pub struct WsCustomSubCmdHandler {
     data: WsCustomSubCmdData,
+    file: String,
 }
 
 impl WsCustomSubCmdHandler {
@@ -21,7 +22,19 @@ impl WsCustomSubCmdHandler {
     pub fn new(name: &str, data: &Value) -> Result<Self, BError> {
         let taskcmd_data: WsCustomSubCmdData = WsCustomSubCmdData::from_value(name, data)?;
 
-        Ok(WsCustomSubCmdHandler { data: taskcmd_data })
+        Ok(WsCustomSubCmdHandler {
+            data: taskcmd_data,
+            file: String::new(),
+        })
+    }
+
+    /* The build config file that the sub-command is defined in, empty if not read from a file */
+    pub fn set_file(&mut self, file: &str) {
+        self.file = file.to_string();
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
     }
 
     pub fn expand_ctx(&mut self, ctx: &Context) -> Result<(), BError> {
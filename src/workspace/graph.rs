@@ -0,0 +1,349 @@
+use indexmap::{IndexMap, IndexSet};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+use crate::data::TType;
+use crate::error::BError;
+use crate::workspace::WsTaskHandler;
+
+pub struct TaskGraph {
+    // The tasks in the order they should be built. A task will always come
+    // after all the tasks that it depends on
+    order: Vec<String>,
+    depends: IndexMap<String, Vec<String>>,
+    // Bitbake tasks sharing the same bitbake build dir cannot be executed at the
+    // same time so for each bitbake task we keep track of the build dir
+    locks: IndexMap<String, Option<PathBuf>>,
+}
+
+impl TaskGraph {
+    fn visit(
+        name: &str,
+        depends: &IndexMap<String, Vec<String>>,
+        stack: &mut Vec<String>,
+        visited: &mut IndexSet<String>,
+        order: &mut Vec<String>,
+    ) -> Result<(), BError> {
+        if visited.contains(name) {
+            return Ok(());
+        }
+
+        if let Some(pos) = stack.iter().position(|t| t == name) {
+            let mut cycle: Vec<String> = stack[pos..].to_vec();
+            cycle.push(name.to_string());
+            return Err(BError::ParseTasksError(format!(
+                "Circular task dependency '{}'",
+                cycle.join(" -> ")
+            )));
+        }
+
+        stack.push(name.to_string());
+        if let Some(deps) = depends.get(name) {
+            for dep in deps.iter() {
+                Self::visit(dep, depends, stack, visited, order)?;
+            }
+        }
+        stack.pop();
+
+        visited.insert(name.to_string());
+        order.push(name.to_string());
+        Ok(())
+    }
+
+    /*
+     * Sort the tasks so that the dependencies of a task are always built before
+     * the task itself. The tasks are visited in the same order as they are defined
+     * in the build config which means that if there are no dependencies the order
+     * will be the same as the index order.
+     */
+    fn sort(depends: &IndexMap<String, Vec<String>>) -> Result<Vec<String>, BError> {
+        let mut order: Vec<String> = vec![];
+        let mut visited: IndexSet<String> = IndexSet::new();
+        let mut stack: Vec<String> = vec![];
+
+        for name in depends.keys() {
+            Self::visit(name, depends, &mut stack, &mut visited, &mut order)?;
+        }
+
+        Ok(order)
+    }
+
+    /*
+     * When strict is false any dependency to a task that is not part of the tasks
+     * is ignored. This is needed when parsing a build config that is included by
+     * another build config since the tasks it depends on might be defined in the
+     * main build config.
+     */
+    pub fn new(tasks: &IndexMap<String, WsTaskHandler>, strict: bool) -> Result<Self, BError> {
+        let mut depends: IndexMap<String, Vec<String>> = IndexMap::new();
+        let mut locks: IndexMap<String, Option<PathBuf>> = IndexMap::new();
+
+        for (name, task) in tasks.iter() {
+            let mut deps: Vec<String> = vec![];
+            for dep in task.data().depends().iter() {
+                if tasks.contains_key(dep) {
+                    if !deps.contains(dep) {
+                        deps.push(dep.clone());
+                    }
+                } else if strict {
+                    return Err(BError::ParseTasksError(format!(
+                        "Task '{}' depends on unknown task '{}'",
+                        name, dep
+                    )));
+                }
+            }
+            depends.insert(name.clone(), deps);
+
+            let lock: Option<PathBuf> = match task.data().ttype() {
+                TType::Bitbake => Some(task.data().build_dir().clone()),
+                TType::NonBitbake => None,
+            };
+            locks.insert(name.clone(), lock);
+        }
+
+        let order: Vec<String> = Self::sort(&depends)?;
+
+        Ok(TaskGraph {
+            order,
+            depends,
+            locks,
+        })
+    }
+
+    /*
+     * Execute the build closure for every task in the graph. A task is only started
+     * once all the tasks it depends on have been built successfully. Up to jobs
+     * tasks are executed at the same time but tasks that are sharing the same
+     * bitbake build dir are never executed at the same time. If a task fails no
+     * new tasks are started and the first error is returned once all running tasks
+     * have finished.
+     */
+    pub fn run<F>(&self, jobs: usize, build: F) -> Result<(), BError>
+    where
+        F: Fn(&str) -> Result<(), BError> + Sync,
+    {
+        if jobs <= 1 {
+            for name in self.order.iter() {
+                build(name)?;
+            }
+            return Ok(());
+        }
+
+        let mut pending: Vec<&String> = self.order.iter().collect();
+        let mut done: HashSet<String> = HashSet::new();
+        let mut busy: Vec<PathBuf> = vec![];
+        let mut running: usize = 0;
+        let mut error: Option<BError> = None;
+        let build: &F = &build;
+
+        std::thread::scope(|s| {
+            let (tx, rx) = mpsc::channel::<(String, Result<(), BError>)>();
+
+            loop {
+                if error.is_none() {
+                    let mut i: usize = 0;
+                    while running < jobs && i < pending.len() {
+                        let name: &String = pending[i];
+                        let ready: bool = self
+                            .depends
+                            .get(name)
+                            .is_none_or(|deps| deps.iter().all(|d| done.contains(d)));
+                        let lock: &Option<PathBuf> = self.locks.get(name).unwrap_or(&None);
+                        let free: bool = lock.as_ref().is_none_or(|dir| !busy.contains(dir));
+
+                        if ready && free {
+                            pending.remove(i);
+                            if let Some(dir) = lock {
+                                busy.push(dir.clone());
+                            }
+                            running += 1;
+                            let tx: mpsc::Sender<(String, Result<(), BError>)> = tx.clone();
+                            s.spawn(move || {
+                                let result: Result<(), BError> = build(name);
+                                let _ = tx.send((name.clone(), result));
+                            });
+                        } else {
+                            i += 1;
+                        }
+                    }
+                }
+
+                if running == 0 {
+                    break;
+                }
+
+                // We are keeping a sender so recv will only fail if all the threads
+                // have been terminated which should not happen
+                let (name, result) = match rx.recv() {
+                    Ok(msg) => msg,
+                    Err(_e) => break,
+                };
+                running -= 1;
+
+                if let Some(Some(dir)) = self.locks.get(&name) {
+                    busy.retain(|b| b != dir);
+                }
+
+                match result {
+                    Ok(()) => {
+                        done.insert(name);
+                    }
+                    Err(err) => {
+                        if error.is_none() {
+                            error = Some(err);
+                        }
+                    }
+                }
+            }
+        });
+
+        match error {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    use crate::data::WsBuildData;
+    use crate::error::BError;
+    use crate::helper::Helper;
+    use crate::workspace::{TaskGraph, WsTaskHandler};
+
+    fn helper_setup_tasks(json_tasks: &[&str]) -> IndexMap<String, WsTaskHandler> {
+        let work_dir: PathBuf = PathBuf::from("/workspace");
+        let build_data: WsBuildData = Helper::setup_build_data(&work_dir, None, None);
+        let mut tasks: IndexMap<String, WsTaskHandler> = IndexMap::new();
+        for json_task in json_tasks.iter() {
+            let task: WsTaskHandler =
+                WsTaskHandler::from_str(json_task, &build_data).expect("Failed to parse task");
+            tasks.insert(task.data().name().to_string(), task);
+        }
+        tasks
+    }
+
+    fn helper_order(graph: &TaskGraph) -> Vec<String> {
+        let order: Mutex<Vec<String>> = Mutex::new(vec![]);
+        graph
+            .run(1, |name| {
+                order.lock().unwrap().push(name.to_string());
+                Ok(())
+            })
+            .expect("Failed to run tasks");
+        order.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_task_graph_index_order() {
+        let tasks: IndexMap<String, WsTaskHandler> = helper_setup_tasks(&[
+            r#"{ "index": "0", "name": "task1", "type": "non-bitbake" }"#,
+            r#"{ "index": "1", "name": "task2", "type": "non-bitbake" }"#,
+            r#"{ "index": "2", "name": "task3", "type": "non-bitbake" }"#,
+        ]);
+        let graph: TaskGraph = TaskGraph::new(&tasks, true).expect("Failed to setup graph");
+        assert_eq!(helper_order(&graph), vec!["task1", "task2", "task3"]);
+    }
+
+    #[test]
+    fn test_task_graph_depends_order() {
+        let tasks: IndexMap<String, WsTaskHandler> = helper_setup_tasks(&[
+            r#"{ "index": "0", "name": "sign", "type": "non-bitbake", "depends": [ "image" ] }"#,
+            r#"{ "index": "1", "name": "docs", "type": "non-bitbake" }"#,
+            r#"{ "index": "2", "name": "image", "recipes": [ "test-image" ] }"#,
+        ]);
+        let graph: TaskGraph = TaskGraph::new(&tasks, true).expect("Failed to setup graph");
+        assert_eq!(helper_order(&graph), vec!["image", "sign", "docs"]);
+    }
+
+    #[test]
+    fn test_task_graph_cycle() {
+        let tasks: IndexMap<String, WsTaskHandler> = helper_setup_tasks(&[
+            r#"{ "index": "0", "name": "task1", "type": "non-bitbake", "depends": [ "task3" ] }"#,
+            r#"{ "index": "1", "name": "task2", "type": "non-bitbake", "depends": [ "task1" ] }"#,
+            r#"{ "index": "2", "name": "task3", "type": "non-bitbake", "depends": [ "task2" ] }"#,
+        ]);
+        let result: Result<TaskGraph, BError> = TaskGraph::new(&tasks, false);
+        match result {
+            Ok(_graph) => {
+                panic!("We should have recived an error because of the circular dependency!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Invalid 'task' node in build config. Circular task dependency 'task1 -> task3 -> task2 -> task1'"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_task_graph_unknown_depends() {
+        let tasks: IndexMap<String, WsTaskHandler> = helper_setup_tasks(&[
+            r#"{ "index": "0", "name": "task1", "type": "non-bitbake", "depends": [ "invalid" ] }"#,
+        ]);
+        assert!(TaskGraph::new(&tasks, false).is_ok());
+        let result: Result<TaskGraph, BError> = TaskGraph::new(&tasks, true);
+        match result {
+            Ok(_graph) => {
+                panic!("We should have recived an error because of the unknown dependency!");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Invalid 'task' node in build config. Task 'task1' depends on unknown task 'invalid'"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_task_graph_run_jobs() {
+        let tasks: IndexMap<String, WsTaskHandler> = helper_setup_tasks(&[
+            r#"{ "index": "0", "name": "image", "recipes": [ "test-image" ] }"#,
+            r#"{ "index": "1", "name": "sdk", "recipes": [ "test-image:sdk" ] }"#,
+            r#"{ "index": "2", "name": "sign", "type": "non-bitbake", "depends": [ "image" ] }"#,
+            r#"{ "index": "3", "name": "docs", "type": "non-bitbake" }"#,
+        ]);
+        let graph: TaskGraph = TaskGraph::new(&tasks, true).expect("Failed to setup graph");
+        let events: Mutex<Vec<String>> = Mutex::new(vec![]);
+        graph
+            .run(4, |name| {
+                events.lock().unwrap().push(format!("start {}", name));
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                events.lock().unwrap().push(format!("end {}", name));
+                Ok(())
+            })
+            .expect("Failed to run tasks");
+        let events: Vec<String> = events.into_inner().unwrap();
+        let pos = |e: &str| events.iter().position(|x| x == e).unwrap();
+        assert_eq!(events.len(), 8);
+        // Bitbake tasks sharing the same build dir are serialized
+        assert!(pos("end image") < pos("start sdk"));
+        // The sign task depends on the image task
+        assert!(pos("end image") < pos("start sign"));
+        // The docs task is independent and is started right away
+        assert!(pos("start docs") < pos("end image"));
+    }
+
+    #[test]
+    fn test_task_graph_run_error() {
+        let tasks: IndexMap<String, WsTaskHandler> = helper_setup_tasks(&[
+            r#"{ "index": "0", "name": "task1", "type": "non-bitbake" }"#,
+            r#"{ "index": "1", "name": "task2", "type": "non-bitbake", "depends": [ "task1" ] }"#,
+        ]);
+        let graph: TaskGraph = TaskGraph::new(&tasks, true).expect("Failed to setup graph");
+        let built: Mutex<Vec<String>> = Mutex::new(vec![]);
+        let result: Result<(), BError> = graph.run(2, |name| {
+            built.lock().unwrap().push(name.to_string());
+            Err(BError::CliError(format!("{} failed", name)))
+        });
+        assert_eq!(result.unwrap_err().to_string(), "task1 failed");
+        assert_eq!(built.into_inner().unwrap(), vec!["task1"]);
+    }
+}
@@ -0,0 +1,935 @@
+use indexmap::IndexMap;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::cli::Cli;
+use crate::configs::Context;
+use crate::data::WsLayerData;
+use crate::error::BError;
+use crate::executers::Git;
+use crate::fs::{BakeryLock, RepoFile, RepoManifest, RepoProject};
+
+/* How deep to search for git repositories in the layers dir */
+const MAX_REPO_DEPTH: usize = 3;
+
+/*
+ * Manages the meta layers defined in the layers section of the build config.
+ * Every layer is a git repository that is fetched into the layers dir and
+ * checked out on the branch, tag or commit defined for the layer. If a lock
+ * file is used the layers are checked out on the commits in the lock file.
+ */
+pub struct WsLayersHandler {
+    layers: IndexMap<String, WsLayerData>,
+}
+
+impl WsLayersHandler {
+    pub fn from_value(data: &Value, layers_dir: &Path) -> Result<Self, BError> {
+        let mut layers: IndexMap<String, WsLayerData> = IndexMap::new();
+        if let Some(value) = data.get("layers") {
+            match value.as_object() {
+                Some(layers_map) => {
+                    for (name, layer_data) in layers_map.iter() {
+                        layers.insert(
+                            name.clone(),
+                            WsLayerData::new(name, layer_data, layers_dir)?,
+                        );
+                    }
+                }
+                None => {
+                    return Err(BError::ParseError(format!(
+                        "Expected layers to be an object but found '{}'",
+                        value
+                    )));
+                }
+            }
+        }
+        Ok(Self::new(layers))
+    }
+
+    pub fn new(layers: IndexMap<String, WsLayerData>) -> Self {
+        WsLayersHandler { layers }
+    }
+
+    pub fn expand_ctx(&mut self, ctx: &Context) -> Result<(), BError> {
+        for (name, layer) in self.layers.iter_mut() {
+            layer
+                .expand_ctx(ctx)
+                .map_err(|err| Context::key_error(err, name))?;
+        }
+        Ok(())
+    }
+
+    pub fn layers(&self) -> &IndexMap<String, WsLayerData> {
+        &self.layers
+    }
+
+    pub fn get(&self, name: &str) -> Result<&WsLayerData, BError> {
+        match self.layers.get(name) {
+            Some(layer) => Ok(layer),
+            None => Err(BError::ValueError(format!(
+                "Layer '{}' is not defined in the build config",
+                name
+            ))),
+        }
+    }
+
+    /*
+     * Resolve a reference to a layer by name to the path of the layer. A layer
+     * repository can contain multiple layers so the reference can include a
+     * sub dir e.g. poky/meta
+     */
+    pub fn layer_path(&self, reference: &str) -> Result<PathBuf, BError> {
+        match reference.split_once('/') {
+            Some((name, sub_dir)) => Ok(self.get(name)?.dir().join(sub_dir)),
+            None => Ok(self.get(reference)?.dir()),
+        }
+    }
+
+    fn pinned(
+        &self,
+        layer: &WsLayerData,
+        lock: Option<&BakeryLock>,
+    ) -> Result<Option<String>, BError> {
+        if let Some(l) = lock {
+            return match l.commit(layer.name()) {
+                Some(commit) => Ok(Some(commit.to_string())),
+                None => Err(BError::ValueError(format!(
+                    "Layer '{}' is not in the lock file",
+                    layer.name()
+                ))),
+            };
+        }
+        if !layer.commit().is_empty() {
+            return Ok(Some(layer.commit().to_string()));
+        }
+        Ok(None)
+    }
+
+    fn checkout(
+        &self,
+        layer: &WsLayerData,
+        git: &Git,
+        lock: Option<&BakeryLock>,
+    ) -> Result<(), BError> {
+        if let Some(commit) = self.pinned(layer, lock)? {
+            return git.checkout_detached(&commit);
+        }
+        if !layer.tag().is_empty() {
+            return git.checkout_detached(&format!("refs/tags/{}", layer.tag()));
+        }
+        if !layer.branch().is_empty() {
+            return git.checkout_branch(layer.branch());
+        }
+        /* No reference, stay on the current branch and fast-forward it */
+        match git.branch() {
+            Some(branch) => git.checkout_branch(&branch),
+            None => Ok(()),
+        }
+    }
+
+    fn clone(
+        &self,
+        cli: &Cli,
+        layer: &WsLayerData,
+        lock: Option<&BakeryLock>,
+    ) -> Result<(), BError> {
+        cli.info(format!(
+            "Fetching layer '{}' from '{}' to '{}'",
+            layer.name(),
+            layer.url(),
+            layer.dir().display()
+        ));
+        let git: Git = Git::clone(layer.url(), &layer.dir(), layer.reference())?;
+        self.checkout(layer, &git, lock)
+    }
+
+    /*
+     * Fetch all the layers that are missing in the layers dir, layers that
+     * have already been fetched are left untouched
+     */
+    pub fn fetch(&self, cli: &Cli, lock: Option<&BakeryLock>) -> Result<(), BError> {
+        for layer in self.layers.values() {
+            let git: Git = Git::new(&layer.dir());
+            if git.exists() {
+                cli.info(format!(
+                    "Layer '{}' already fetched to '{}'",
+                    layer.name(),
+                    layer.dir().display()
+                ));
+                continue;
+            }
+            self.clone(cli, layer, lock)?;
+        }
+        Ok(())
+    }
+
+    /*
+     * Update all the layers to the latest commit of the branch or to the tag
+     * or commit defined for the layer. A layer with local changes is never
+     * updated.
+     */
+    pub fn update(&self, cli: &Cli, lock: Option<&BakeryLock>) -> Result<(), BError> {
+        for layer in self.layers.values() {
+            let git: Git = Git::new(&layer.dir());
+            if !git.exists() {
+                self.clone(cli, layer, lock)?;
+                continue;
+            }
+            if git.is_dirty()? {
+                return Err(BError::CliError(format!(
+                    "Layer '{}' has local changes in '{}', commit or stash them before updating",
+                    layer.name(),
+                    layer.dir().display()
+                )));
+            }
+            cli.info(format!(
+                "Updating layer '{}' in '{}'",
+                layer.name(),
+                layer.dir().display()
+            ));
+            git.fetch()?;
+            self.checkout(layer, &git, lock)?;
+        }
+        Ok(())
+    }
+
+    /*
+     * Print the commit that each layer is at and if it differs from what
+     * is defined for the layer
+     */
+    pub fn status(&self, cli: &Cli) -> Result<(), BError> {
+        for layer in self.layers.values() {
+            let git: Git = Git::new(&layer.dir());
+            if !git.exists() {
+                cli.stdout(format!(
+                    "{}: not fetched to '{}'",
+                    layer.name(),
+                    layer.dir().display()
+                ));
+                continue;
+            }
+            let head: String = git.head()?;
+            let mut status: String = format!("{}: {}", layer.name(), &head[..12.min(head.len())]);
+            if !layer.commit().is_empty() {
+                status.push_str(&format!(" commit {}", layer.commit()));
+                if !head.starts_with(layer.commit()) {
+                    status.push_str(" (not at commit)");
+                }
+            } else if !layer.tag().is_empty() {
+                status.push_str(&format!(" tag {}", layer.tag()));
+                if git.rev_parse(&format!("refs/tags/{}", layer.tag())).ok() != Some(head.clone()) {
+                    status.push_str(" (not at tag)");
+                }
+            } else if !layer.branch().is_empty() {
+                status.push_str(&format!(" branch {}", layer.branch()));
+                if git.branch().as_deref() != Some(layer.branch()) {
+                    status.push_str(" (not on branch)");
+                }
+            }
+            if git.is_dirty()? {
+                status.push_str(" (modified)");
+            }
+            cli.stdout(status);
+        }
+        Ok(())
+    }
+
+    /*
+     * Record the current commit of every layer
+     */
+    pub fn lock(&self, config: &str) -> Result<BakeryLock, BError> {
+        let mut lock: BakeryLock = BakeryLock::new(config);
+        for layer in self.layers.values() {
+            let git: Git = Git::new(&layer.dir());
+            if !git.exists() {
+                return Err(BError::CliError(format!(
+                    "Layer '{}' has not been fetched, fetch it using 'bakery layers fetch'",
+                    layer.name()
+                )));
+            }
+            lock.add_layer(layer.name(), layer.path(), layer.url(), &git.head()?);
+        }
+        Ok(lock)
+    }
+
+    /*
+     * Find all the git repositories in the layers dir, the search is not
+     * descending into a repository or into hidden dirs
+     */
+    fn find_repos(dir: &Path, depth: usize, repos: &mut Vec<PathBuf>) -> Result<(), BError> {
+        if !dir.is_dir() || depth > MAX_REPO_DEPTH {
+            return Ok(());
+        }
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .filter(|p| {
+                !p.file_name()
+                    .map(|n| n.to_string_lossy().starts_with('.'))
+                    .unwrap_or(true)
+            })
+            .collect();
+        entries.sort();
+        for entry in entries {
+            if Git::new(&entry).exists() {
+                repos.push(entry);
+            } else {
+                Self::find_repos(&entry, depth + 1, repos)?;
+            }
+        }
+        Ok(())
+    }
+
+    /*
+     * Record the current commit of every git repository in the layers dir
+     * and not only the layers defined in the build config since the layers
+     * can also be handled by git submodules or the repo tool. A repository
+     * of a layer defined in the build config is using the name of the layer
+     * all other repositories are using the path relative to the layers dir.
+     */
+    pub fn lock_layers_dir(&self, config: &str, layers_dir: &Path) -> Result<BakeryLock, BError> {
+        let mut lock: BakeryLock = BakeryLock::new(config);
+        let mut repos: Vec<PathBuf> = vec![];
+        Self::find_repos(layers_dir, 1, &mut repos)?;
+        for repo in repos {
+            let git: Git = Git::new(&repo);
+            let path: String = repo
+                .strip_prefix(layers_dir)
+                .unwrap_or(&repo)
+                .to_string_lossy()
+                .to_string();
+            let name: String = match self.layers.values().find(|l| l.dir() == repo) {
+                Some(layer) => layer.name().to_string(),
+                None => path.clone(),
+            };
+            lock.add_layer(
+                &name,
+                &path,
+                &git.remote_url().unwrap_or_default(),
+                &git.head()?,
+            );
+        }
+        Ok(lock)
+    }
+
+    /*
+     * Checkout every repository in the lock file on the locked commit. A
+     * missing repository is cloned and if the commit is not available the
+     * remote is fetched first. A repository with local changes is never
+     * touched.
+     */
+    pub fn restore(&self, cli: &Cli, lock: &BakeryLock, layers_dir: &Path) -> Result<(), BError> {
+        for (name, layer) in lock.layers().iter() {
+            let dir: PathBuf = layers_dir.join(&layer.path);
+            let mut git: Git = Git::new(&dir);
+            if !git.exists() {
+                if layer.url.is_empty() {
+                    return Err(BError::ValueError(format!(
+                        "Layer '{}' is missing in '{}' and the lock file has no url for it",
+                        name,
+                        dir.display()
+                    )));
+                }
+                cli.info(format!(
+                    "Fetching layer '{}' from '{}' to '{}'",
+                    name,
+                    layer.url,
+                    dir.display()
+                ));
+                git = Git::clone(&layer.url, &dir, None)?;
+            } else if git.is_dirty()? {
+                return Err(BError::CliError(format!(
+                    "Layer '{}' has local changes in '{}', commit or stash them before updating",
+                    name,
+                    dir.display()
+                )));
+            }
+            if git.head()? == layer.commit {
+                continue;
+            }
+            cli.info(format!(
+                "Checkout layer '{}' in '{}' at '{}'",
+                name,
+                dir.display(),
+                layer.commit
+            ));
+            if !git.has_commit(&layer.commit) {
+                git.fetch()?;
+            }
+            git.checkout_detached(&layer.commit)?;
+        }
+        Ok(())
+    }
+
+    /*
+     * A revision in a repo manifest is a commit, a tag as refs/tags/<tag>
+     * or a branch with or without refs/heads/
+     */
+    fn repo_reference(revision: &str) -> Option<String> {
+        if revision.is_empty()
+            || (revision.len() == 40 && revision.chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            return None;
+        }
+        Some(
+            revision
+                .trim_start_matches("refs/heads/")
+                .trim_start_matches("refs/tags/")
+                .to_string(),
+        )
+    }
+
+    fn repo_files(dir: &Path, project: &RepoProject, layers_dir: &Path) -> Result<(), BError> {
+        let files =
+            |f: &RepoFile| -> (PathBuf, PathBuf) { (dir.join(&f.src), layers_dir.join(&f.dest)) };
+        for (src, dest) in project.copyfiles.iter().map(files) {
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(&src, &dest)?;
+        }
+        for (src, dest) in project.linkfiles.iter().map(files) {
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            if dest.symlink_metadata().is_ok() {
+                std::fs::remove_file(&dest)?;
+            }
+            std::os::unix::fs::symlink(&src, &dest)?;
+        }
+        Ok(())
+    }
+
+    /*
+     * Checkout the projects of a repo manifest in the layers dir. Projects
+     * that have already been fetched are left untouched but the linkfile and
+     * copyfile of all projects are always updated.
+     */
+    pub fn import_repo_manifest(
+        &self,
+        cli: &Cli,
+        manifest: &RepoManifest,
+        manifest_url: Option<&str>,
+        layers_dir: &Path,
+    ) -> Result<(), BError> {
+        for project in manifest.projects().iter() {
+            let dir: PathBuf = layers_dir.join(project.path());
+            let git: Git = Git::new(&dir);
+            if git.exists() {
+                cli.info(format!(
+                    "Project '{}' already fetched to '{}'",
+                    project.name,
+                    dir.display()
+                ));
+            } else {
+                let url: String = manifest.url(project, manifest_url)?;
+                let revision: String = manifest.revision(project)?;
+                cli.info(format!(
+                    "Fetching project '{}' from '{}' to '{}'",
+                    project.name,
+                    url,
+                    dir.display()
+                ));
+                let reference: Option<String> = Self::repo_reference(&revision);
+                let git: Git = Git::clone(&url, &dir, reference.as_deref())?;
+                if reference.is_none() && !revision.is_empty() {
+                    git.checkout_detached(&revision)?;
+                }
+            }
+            Self::repo_files(&dir, project, layers_dir)?;
+        }
+        Ok(())
+    }
+
+    /*
+     * Create a repo manifest of all the git repositories in the layers dir
+     * where each project is pinned to the current commit. The remotes are
+     * created from the urls of the repositories.
+     */
+    pub fn export_repo_manifest(&self, layers_dir: &Path) -> Result<RepoManifest, BError> {
+        let mut manifest: RepoManifest = RepoManifest::new();
+        let mut repos: Vec<PathBuf> = vec![];
+        Self::find_repos(layers_dir, 1, &mut repos)?;
+        for repo in repos {
+            let git: Git = Git::new(&repo);
+            let path: String = repo
+                .strip_prefix(layers_dir)
+                .unwrap_or(&repo)
+                .to_string_lossy()
+                .to_string();
+            let url: String = git.remote_url().unwrap_or_default();
+            let (fetch, name) = match url.rsplit_once('/') {
+                Some((fetch, name)) if !fetch.is_empty() && !name.is_empty() => (fetch, name),
+                _ => {
+                    return Err(BError::ValueError(format!(
+                        "The repository '{}' has no remote url that can be used in a repo manifest",
+                        repo.display()
+                    )));
+                }
+            };
+            let remote: String = Self::repo_remote(&mut manifest, fetch);
+            let mut project: RepoProject = RepoProject::new(name, &path, &remote, &git.head()?);
+            project.upstream = git.branch().unwrap_or_default();
+            manifest.add_project(project);
+        }
+        Ok(manifest)
+    }
+
+    /*
+     * Get the name of the remote with the fetch url or add a new remote named
+     * after the host of the url
+     */
+    fn repo_remote(manifest: &mut RepoManifest, fetch: &str) -> String {
+        if let Some(remote) = manifest.remotes().values().find(|r| r.fetch == fetch) {
+            return remote.name.clone();
+        }
+        let host: &str = fetch.split_once("://").map(|(_s, h)| h).unwrap_or(fetch);
+        let host: &str = host.rsplit_once('@').map(|(_u, h)| h).unwrap_or(host);
+        let mut name: String = host
+            .split(['/', ':'])
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        if name.is_empty() {
+            name = String::from("origin");
+        }
+        let mut unique: String = name.clone();
+        let mut index: usize = 1;
+        while manifest.remotes().contains_key(&unique) {
+            index += 1;
+            unique = format!("{}-{}", name, index);
+        }
+        manifest.add_remote(&unique, fetch, "");
+        unique
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use tempdir::TempDir;
+
+    use crate::cli::*;
+    use crate::executers::Git;
+    use crate::fs::{BakeryLock, ConfigFileReader, RepoManifest, RepoProject};
+    use crate::workspace::WsLayersHandler;
+
+    fn helper_git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args([
+                "-c",
+                "user.name=bakery",
+                "-c",
+                "user.email=bakery@localhost",
+            ])
+            .args(args)
+            .output()
+            .expect("Failed to execute git");
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    fn helper_commit(repo: &Path, file: &str) -> String {
+        std::fs::create_dir_all(repo.join("conf")).expect("Failed to create conf dir");
+        std::fs::write(repo.join(file), file).expect("Failed to write file");
+        helper_git(repo, &["add", "."]);
+        helper_git(repo, &["commit", "--quiet", "-m", file]);
+        helper_git(repo, &["rev-parse", "HEAD"])
+    }
+
+    /*
+     * Setup a local repository with a commit and push it to a bare
+     * repository that is used as the remote of the layer
+     */
+    fn helper_bare_repo(dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+        let repo: PathBuf = dir.join("src").join(name);
+        let bare: PathBuf = dir.join("remotes").join(format!("{}.git", name));
+        std::fs::create_dir_all(&repo).expect("Failed to create repo dir");
+        helper_git(&repo, &["init", "--quiet", "-b", "main"]);
+        helper_commit(&repo, "conf/layer.conf");
+        helper_git(
+            dir,
+            &[
+                "clone",
+                "--quiet",
+                "--bare",
+                &repo.to_string_lossy(),
+                &bare.to_string_lossy(),
+            ],
+        );
+        helper_git(&repo, &["remote", "add", "origin", &bare.to_string_lossy()]);
+        (repo, bare)
+    }
+
+    fn helper_layers(bare: &Path, layers_dir: &Path, extra: &str) -> WsLayersHandler {
+        let json_build_config: String = format!(
+            r#"
+        {{
+            "layers": {{
+                "meta-test": {{
+                    "url": "{}"{}
+                }}
+            }}
+        }}"#,
+            bare.display(),
+            extra
+        );
+        WsLayersHandler::from_value(
+            &ConfigFileReader::parse(&json_build_config).expect("Failed to parse config"),
+            layers_dir,
+        )
+        .expect("Failed to parse layers")
+    }
+
+    fn helper_cli(logger: MockLogger) -> Cli {
+        Cli::new(
+            Box::new(logger),
+            Box::new(MockSystem::new()),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery"]),
+        )
+    }
+
+    #[test]
+    fn test_ws_layers_fetch_update() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let layers_dir: PathBuf = temp_dir.path().join("layers");
+        let (repo, bare) = helper_bare_repo(temp_dir.path(), "meta-test");
+        let layers: WsLayersHandler = helper_layers(&bare, &layers_dir, r#", "branch": "main""#);
+        let layer_dir: PathBuf = layers_dir.join("meta-test");
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Fetching layer 'meta-test' from '{}' to '{}'",
+                bare.display(),
+                layer_dir.display()
+            )))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Layer 'meta-test' already fetched to '{}'",
+                layer_dir.display()
+            )))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Updating layer 'meta-test' in '{}'",
+                layer_dir.display()
+            )))
+            .once()
+            .returning(|_x| ());
+        let cli: Cli = helper_cli(mocked_logger);
+        layers.fetch(&cli, None).expect("Failed to fetch layers");
+        assert!(layer_dir.join("conf/layer.conf").exists());
+        layers.fetch(&cli, None).expect("Failed to fetch layers");
+
+        /* A new commit on the remote branch is checked out by update */
+        let commit: String = helper_commit(&repo, "conf/update.conf");
+        helper_git(&repo, &["push", "--quiet", "origin", "main"]);
+        layers.update(&cli, None).expect("Failed to update layers");
+        assert_eq!(Git::new(&layer_dir).head().unwrap(), commit);
+        assert_eq!(Git::new(&layer_dir).branch(), Some(String::from("main")));
+        assert!(layer_dir.join("conf/update.conf").exists());
+    }
+
+    #[test]
+    fn test_ws_layers_lock() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let layers_dir: PathBuf = temp_dir.path().join("layers");
+        let (repo, bare) = helper_bare_repo(temp_dir.path(), "meta-test");
+        let first: String = helper_git(&repo, &["rev-parse", "HEAD"]);
+        helper_commit(&repo, "conf/second.conf");
+        helper_git(&repo, &["push", "--quiet", "origin", "main"]);
+        let layers: WsLayersHandler = helper_layers(&bare, &layers_dir, "");
+        let layer_dir: PathBuf = layers_dir.join("meta-test");
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger.expect_info().returning(|_x| ());
+        let cli: Cli = helper_cli(mocked_logger);
+
+        /* The layer is checked out on the commit in the lock file */
+        let mut lock: BakeryLock = BakeryLock::new("default");
+        lock.add_layer("meta-test", "meta-test", &bare.to_string_lossy(), &first);
+        layers
+            .fetch(&cli, Some(&lock))
+            .expect("Failed to fetch layers");
+        assert_eq!(Git::new(&layer_dir).head().unwrap(), first);
+        assert!(!layer_dir.join("conf/second.conf").exists());
+        assert_eq!(layers.lock("default").unwrap(), lock);
+    }
+
+    #[test]
+    fn test_ws_layers_status() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let layers_dir: PathBuf = temp_dir.path().join("layers");
+        let (repo, bare) = helper_bare_repo(temp_dir.path(), "meta-test");
+        let commit: String = helper_git(&repo, &["rev-parse", "HEAD"]);
+        let layers: WsLayersHandler = helper_layers(&bare, &layers_dir, r#", "branch": "main""#);
+        let layer_dir: PathBuf = layers_dir.join("meta-test");
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger.expect_info().returning(|_x| ());
+        mocked_logger
+            .expect_stdout()
+            .with(mockall::predicate::eq(format!(
+                "meta-test: not fetched to '{}'",
+                layer_dir.display()
+            )))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_stdout()
+            .with(mockall::predicate::eq(format!(
+                "meta-test: {} branch main (modified)",
+                &commit[..12]
+            )))
+            .once()
+            .returning(|_x| ());
+        let cli: Cli = helper_cli(mocked_logger);
+        layers.status(&cli).expect("Failed to get status");
+        let result = layers.lock("default");
+        match result {
+            Ok(_lock) => {
+                panic!("We should have recived an error because the layer is not fetched");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Layer 'meta-test' has not been fetched, fetch it using 'bakery layers fetch'"
+                );
+            }
+        }
+        layers.fetch(&cli, None).expect("Failed to fetch layers");
+        std::fs::write(layer_dir.join("conf/layer.conf"), "modified").expect("Failed to write");
+        layers.status(&cli).expect("Failed to get status");
+        let result = layers.update(&cli, None);
+        match result {
+            Ok(()) => {
+                panic!("We should have recived an error because the layer is modified");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    format!(
+                        "Layer 'meta-test' has local changes in '{}', commit or stash them before updating",
+                        layer_dir.display()
+                    )
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_ws_layers_layer_path() {
+        let layers: WsLayersHandler = helper_layers(
+            Path::new("https://git.yoctoproject.org/poky"),
+            Path::new("/workspace/layers"),
+            r#", "path": "poky""#,
+        );
+        assert_eq!(
+            layers.layer_path("meta-test/meta").unwrap(),
+            PathBuf::from("/workspace/layers/poky/meta")
+        );
+        match layers.layer_path("meta-foo") {
+            Ok(_path) => {
+                panic!("We should have recived an error because the layer is not defined");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Layer 'meta-foo' is not defined in the build config"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_ws_layers_lock_layers_dir_restore() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let layers_dir: PathBuf = temp_dir.path().join("layers");
+        let (repo, bare) = helper_bare_repo(temp_dir.path(), "meta-test");
+        let (extra_repo, extra_bare) = helper_bare_repo(temp_dir.path(), "meta-extra");
+        let layers: WsLayersHandler = helper_layers(&bare, &layers_dir, "");
+        let layer_dir: PathBuf = layers_dir.join("meta-test");
+        let extra_dir: PathBuf = layers_dir.join("vendor/meta-extra");
+        let first: String = helper_git(&repo, &["rev-parse", "HEAD"]);
+        let extra_first: String = helper_git(&extra_repo, &["rev-parse", "HEAD"]);
+        Git::clone(&bare.to_string_lossy(), &layer_dir, None).expect("Failed to clone layer");
+        Git::clone(&extra_bare.to_string_lossy(), &extra_dir, None).expect("Failed to clone layer");
+
+        /* The layer not defined in the build config is using the path as name */
+        let lock: BakeryLock = layers
+            .lock_layers_dir("default", &layers_dir)
+            .expect("Failed to lock layers dir");
+        assert_eq!(
+            lock.layers().keys().collect::<Vec<&String>>(),
+            vec!["meta-test", "vendor/meta-extra"]
+        );
+        assert_eq!(lock.layers()["vendor/meta-extra"].path, "vendor/meta-extra");
+        assert_eq!(
+            lock.layers()["vendor/meta-extra"].url,
+            extra_bare.to_string_lossy()
+        );
+        assert_eq!(lock.commit("meta-test"), Some(first.as_str()));
+        assert_eq!(lock.commit("vendor/meta-extra"), Some(extra_first.as_str()));
+
+        /* Move the layer to a newer commit and remove the other layer */
+        helper_commit(&repo, "conf/second.conf");
+        helper_git(&repo, &["push", "--quiet", "origin", "main"]);
+        helper_commit(&extra_repo, "conf/second.conf");
+        helper_git(&extra_repo, &["push", "--quiet", "origin", "main"]);
+        helper_git(&layer_dir, &["pull", "--quiet", "--ff-only"]);
+        std::fs::remove_dir_all(&extra_dir).expect("Failed to remove layer");
+
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Checkout layer 'meta-test' in '{}' at '{}'",
+                layer_dir.display(),
+                first
+            )))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Fetching layer 'vendor/meta-extra' from '{}' to '{}'",
+                extra_bare.display(),
+                extra_dir.display()
+            )))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Checkout layer 'vendor/meta-extra' in '{}' at '{}'",
+                extra_dir.display(),
+                extra_first
+            )))
+            .once()
+            .returning(|_x| ());
+        layers
+            .restore(&helper_cli(mocked_logger), &lock, &layers_dir)
+            .expect("Failed to restore layers");
+        assert_eq!(Git::new(&layer_dir).head().unwrap(), first);
+        assert_eq!(Git::new(&extra_dir).head().unwrap(), extra_first);
+
+        /* Nothing to do when the layers are already at the locked commits */
+        layers
+            .restore(&helper_cli(MockLogger::new()), &lock, &layers_dir)
+            .expect("Failed to restore layers");
+    }
+
+    #[test]
+    fn test_ws_layers_repo_manifest() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let layers_dir: PathBuf = temp_dir.path().join("layers");
+        let (repo, _bare) = helper_bare_repo(temp_dir.path(), "meta-test");
+        let (extra_repo, _extra_bare) = helper_bare_repo(temp_dir.path(), "meta-extra");
+        let extra_first: String = helper_git(&extra_repo, &["rev-parse", "HEAD"]);
+        helper_commit(&extra_repo, "conf/second.conf");
+        helper_git(&extra_repo, &["push", "--quiet", "origin", "main"]);
+        let head: String = helper_git(&repo, &["rev-parse", "HEAD"]);
+        let remotes: PathBuf = temp_dir.path().join("remotes");
+        let manifest: RepoManifest = RepoManifest::from_str(
+            &format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+  <remote name="local" fetch="{}"/>
+  <default remote="local" revision="refs/heads/main"/>
+  <project name="meta-test.git" path="meta-test">
+    <copyfile src="conf/layer.conf" dest="conf/layer.conf"/>
+    <linkfile src="conf" dest="links/meta-test-conf"/>
+  </project>
+  <project name="meta-extra.git" path="vendor/meta-extra" revision="{}"/>
+</manifest>
+"#,
+                remotes.display(),
+                extra_first
+            ),
+            None,
+        )
+        .expect("Failed to parse manifest");
+        let layers: WsLayersHandler = WsLayersHandler::new(IndexMap::new());
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Fetching project 'meta-test.git' from '{}/meta-test.git' to '{}'",
+                remotes.display(),
+                layers_dir.join("meta-test").display()
+            )))
+            .once()
+            .returning(|_x| ());
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(format!(
+                "Fetching project 'meta-extra.git' from '{}/meta-extra.git' to '{}'",
+                remotes.display(),
+                layers_dir.join("vendor/meta-extra").display()
+            )))
+            .once()
+            .returning(|_x| ());
+        layers
+            .import_repo_manifest(&helper_cli(mocked_logger), &manifest, None, &layers_dir)
+            .expect("Failed to import repo manifest");
+        assert_eq!(
+            Git::new(&layers_dir.join("meta-test")).head().unwrap(),
+            head
+        );
+        assert_eq!(
+            Git::new(&layers_dir.join("meta-test")).branch(),
+            Some(String::from("main"))
+        );
+        assert_eq!(
+            Git::new(&layers_dir.join("vendor/meta-extra"))
+                .head()
+                .unwrap(),
+            extra_first
+        );
+        assert!(layers_dir.join("conf/layer.conf").is_file());
+        assert_eq!(
+            std::fs::read_link(layers_dir.join("links/meta-test-conf")).unwrap(),
+            layers_dir.join("meta-test/conf")
+        );
+
+        /* Export the layers dir, every project is pinned to the current commit */
+        let exported: RepoManifest = layers
+            .export_repo_manifest(&layers_dir)
+            .expect("Failed to export repo manifest");
+        assert_eq!(exported.remotes().len(), 1);
+        assert_eq!(
+            exported.remotes()["origin"].fetch,
+            remotes.to_string_lossy()
+        );
+        let mut meta_test: RepoProject =
+            RepoProject::new("meta-test.git", "meta-test", "origin", &head);
+        meta_test.upstream = String::from("main");
+        assert_eq!(
+            exported.projects(),
+            &vec![
+                meta_test,
+                RepoProject::new(
+                    "meta-extra.git",
+                    "vendor/meta-extra",
+                    "origin",
+                    &extra_first
+                ),
+            ]
+        );
+    }
+}
@@ -1,6 +1,9 @@
 pub mod artifact;
 pub mod config;
 pub mod customsubcmd;
+pub mod graph;
+pub mod layers;
+pub mod secrets;
 pub mod settings;
 pub mod tasks;
 pub mod workspace;
@@ -8,6 +11,9 @@ pub mod workspace;
 pub use artifact::WsArtifactsHandler;
 pub use config::WsBuildConfigHandler;
 pub use customsubcmd::WsCustomSubCmdHandler;
+pub use graph::TaskGraph;
+pub use layers::WsLayersHandler;
+pub use secrets::WsSecretsHandler;
 pub use settings::WsSettingsHandler;
-pub use tasks::WsTaskHandler;
+pub use tasks::{TaskOptions, WsTaskHandler};
 pub use workspace::Workspace;
@@ -0,0 +1,250 @@
+use indexmap::IndexMap;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tempdir::TempDir;
+
+use crate::cli::Cli;
+use crate::configs::Context;
+use crate::data::secret::{SExpose, SSource};
+use crate::data::WsSecretData;
+use crate::error::BError;
+
+/*
+ * Keeps track of the secrets defined in the build config and resolves the
+ * secrets requested by a task or a custom sub-command into env variables.
+ * Every resolved value is registered in the cli so that it is redacted from
+ * all output. Secrets exposed as files are written to a temporary dir under
+ * the bakery dir which is removed when the handler is dropped.
+ */
+pub struct WsSecretsHandler {
+    secrets: IndexMap<String, WsSecretData>,
+    bakery_dir: PathBuf,
+    files_dir: Mutex<Option<TempDir>>,
+}
+
+impl WsSecretsHandler {
+    pub fn from_value(data: &Value, bakery_dir: &Path) -> Result<Self, BError> {
+        let mut secrets: IndexMap<String, WsSecretData> = IndexMap::new();
+        if let Some(value) = data.get("secrets") {
+            match value.as_object() {
+                Some(secrets_map) => {
+                    for (name, secret_data) in secrets_map.iter() {
+                        secrets.insert(name.clone(), WsSecretData::new(name, secret_data)?);
+                    }
+                }
+                None => {
+                    return Err(BError::ParseError(format!(
+                        "Expected secrets to be an object but found '{}'",
+                        value
+                    )));
+                }
+            }
+        }
+        Ok(Self::new(secrets, bakery_dir))
+    }
+
+    pub fn new(secrets: IndexMap<String, WsSecretData>, bakery_dir: &Path) -> Self {
+        WsSecretsHandler {
+            secrets,
+            bakery_dir: bakery_dir.to_path_buf(),
+            files_dir: Mutex::new(None),
+        }
+    }
+
+    pub fn expand_ctx(&mut self, ctx: &Context) -> Result<(), BError> {
+        for (name, secret) in self.secrets.iter_mut() {
+            secret
+                .expand_ctx(ctx)
+                .map_err(|err| Context::key_error(err, name))?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Result<&WsSecretData, BError> {
+        match self.secrets.get(name) {
+            Some(secret) => Ok(secret),
+            None => Err(BError::ValueError(format!(
+                "Secret '{}' is not defined in the build config",
+                name
+            ))),
+        }
+    }
+
+    /*
+     * The env variables that secrets with source env are read from. When bakery
+     * is bootstrapped into docker these needs to be passed on to the container.
+     */
+    pub fn env_sources(&self) -> Vec<String> {
+        self.secrets
+            .values()
+            .filter(|s| s.source() == &SSource::Env)
+            .map(|s| s.from().to_string())
+            .collect()
+    }
+
+    fn write_file(&self, name: &str, value: &str) -> Result<PathBuf, BError> {
+        let mut files_dir = self
+            .files_dir
+            .lock()
+            .map_err(|e| BError::ValueError(e.to_string()))?;
+        if files_dir.is_none() {
+            std::fs::create_dir_all(&self.bakery_dir)?;
+            *files_dir = Some(TempDir::new_in(&self.bakery_dir, "secrets")?);
+        }
+        let path: PathBuf = files_dir.as_ref().unwrap().path().join(name);
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&path)?;
+        file.write_all(value.as_bytes())?;
+        Ok(path)
+    }
+
+    /*
+     * Resolve the secrets and return them as env variables. A secret exposed
+     * as a file will have the path to the file as value.
+     */
+    pub fn env(&self, cli: &Cli, names: &[String]) -> Result<HashMap<String, String>, BError> {
+        let mut env: HashMap<String, String> = HashMap::new();
+        if names.is_empty() {
+            return Ok(env);
+        }
+        let cli_env: HashMap<String, String> = cli.env();
+        for name in names.iter() {
+            let secret: &WsSecretData = self.get(name)?;
+            let value: String = secret.value(&cli_env, &self.bakery_dir)?;
+            cli.add_secret(&value);
+            match secret.expose() {
+                SExpose::Env => {
+                    env.insert(name.clone(), value);
+                }
+                SExpose::File => {
+                    let path: PathBuf = self.write_file(name, &value)?;
+                    env.insert(name.clone(), path.to_string_lossy().to_string());
+                }
+            }
+        }
+        Ok(env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    use crate::cli::*;
+    use crate::fs::ConfigFileReader;
+    use crate::workspace::WsSecretsHandler;
+
+    #[test]
+    fn test_ws_secrets_handler_env() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let bakery_dir: PathBuf = temp_dir.path().join(".bakery");
+        std::fs::create_dir_all(&bakery_dir).expect("Failed to create bakery dir");
+        std::fs::write(
+            bakery_dir.join("secrets.json"),
+            r#"{"SIGN_KEY": "local-value"}"#,
+        )
+        .expect("Failed to write secrets file");
+        let json_build_config: &str = r#"
+        {
+            "secrets": {
+                "UPLOAD_TOKEN": {
+                    "source": "env",
+                    "from": "CI_UPLOAD_TOKEN"
+                },
+                "SIGN_KEY": {
+                    "expose": "file"
+                }
+            }
+        }"#;
+        let handler: WsSecretsHandler = WsSecretsHandler::from_value(
+            &ConfigFileReader::parse(json_build_config).expect("Failed to parse config"),
+            &bakery_dir,
+        )
+        .expect("Failed to parse secrets");
+        assert_eq!(handler.env_sources(), vec![String::from("CI_UPLOAD_TOKEN")]);
+        let mut mocked_system: MockSystem = MockSystem::new();
+        mocked_system.expect_env().returning(|| {
+            HashMap::from([(String::from("CI_UPLOAD_TOKEN"), String::from("env-value"))])
+        });
+        let mut mocked_logger: MockLogger = MockLogger::new();
+        mocked_logger
+            .expect_info()
+            .with(mockall::predicate::eq(String::from(
+                "Uploading using **** and ****",
+            )))
+            .once()
+            .returning(|_x| ());
+        let cli: Cli = Cli::new(
+            Box::new(mocked_logger),
+            Box::new(mocked_system),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery"]),
+        );
+        let env: HashMap<String, String> = handler
+            .env(
+                &cli,
+                &[String::from("UPLOAD_TOKEN"), String::from("SIGN_KEY")],
+            )
+            .expect("Failed to resolve secrets");
+        assert_eq!(env.get("UPLOAD_TOKEN").unwrap(), "env-value");
+        let key_path: PathBuf = PathBuf::from(env.get("SIGN_KEY").unwrap());
+        assert!(key_path.starts_with(&bakery_dir));
+        assert_eq!(
+            std::fs::read_to_string(&key_path).expect("Failed to read secret file"),
+            "local-value"
+        );
+        assert_eq!(
+            std::fs::metadata(&key_path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        assert!(cli.is_secret("env-value"));
+        cli.info(String::from("Uploading using env-value and local-value"));
+        drop(handler);
+        assert!(!key_path.exists());
+    }
+
+    #[test]
+    fn test_ws_secrets_handler_undefined() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let handler: WsSecretsHandler = WsSecretsHandler::from_value(
+            &ConfigFileReader::parse("{}").expect("Failed to parse config"),
+            temp_dir.path(),
+        )
+        .expect("Failed to parse secrets");
+        let mut mocked_system: MockSystem = MockSystem::new();
+        mocked_system.expect_env().returning(HashMap::new);
+        let cli: Cli = Cli::new(
+            Box::new(MockLogger::new()),
+            Box::new(mocked_system),
+            clap::Command::new("bakery"),
+            Some(vec!["bakery"]),
+        );
+        assert!(handler.env(&cli, &[]).unwrap().is_empty());
+        let result = handler.env(&cli, &[String::from("UPLOAD_TOKEN")]);
+        match result {
+            Ok(_env) => {
+                panic!("We should have recived an error because the secret is not defined");
+            }
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Secret 'UPLOAD_TOKEN' is not defined in the build config"
+                );
+            }
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use crate::configs::WsSettings;
 use crate::error::BError;
-use crate::{configs::WsSettings, executers::DockerImage};
+use crate::executers::{ContainerRuntime, DockerImage};
+use crate::fs::LOCK_FILE;
 
 use std::path::{Path, PathBuf};
 
@@ -88,6 +90,14 @@ impl WsSettingsHandler {
         self.append_dir(&self.ws_settings.layers_dir)
     }
 
+    /*
+     * The default lock file is written next to the collected artifacts by a
+     * build and by 'bakery layers lock'
+     */
+    pub fn lock_file(&self) -> PathBuf {
+        self.artifacts_dir().join(LOCK_FILE)
+    }
+
     pub fn configs_dir(&self) -> PathBuf {
         self.append_dir(&self.ws_settings.configs_dir)
     }
@@ -121,6 +131,10 @@ impl WsSettingsHandler {
         &self.ws_settings.docker_args
     }
 
+    pub fn docker_runtime(&self) -> ContainerRuntime {
+        ContainerRuntime::new(&self.ws_settings.docker_runtime).unwrap_or(ContainerRuntime::Docker)
+    }
+
     pub fn docker_disabled(&self) -> bool {
         match self.ws_settings.docker_disabled.as_str() {
             "true" => {
@@ -135,16 +149,133 @@ impl WsSettingsHandler {
         }
     }
 
+    pub fn strict_ctx(&self) -> bool {
+        match self.ws_settings.strict_ctx.as_str() {
+            "true" => {
+                return true;
+            }
+            "false" => {
+                return false;
+            }
+            _ => {
+                return false;
+            }
+        }
+    }
+
     pub fn supported_builds(&self) -> &Vec<String> {
         &self.ws_settings.supported
     }
+
+    fn shared_dir(&self, dir: &String) -> Option<PathBuf> {
+        if dir.is_empty() {
+            return None;
+        }
+        Some(self.append_dir(dir))
+    }
+
+    /*
+     * A read-only sstate cache shared between workspaces e.g. on an NFS mount
+     */
+    pub fn shared_sstate_dir(&self) -> Option<PathBuf> {
+        self.shared_dir(&self.ws_settings.shared_sstate_dir)
+    }
+
+    /*
+     * A read-only download dir shared between workspaces e.g. on an NFS mount
+     */
+    pub fn shared_dl_dir(&self) -> Option<PathBuf> {
+        self.shared_dir(&self.ws_settings.shared_dl_dir)
+    }
+
+    /*
+     * The sstate mirrors including the shared sstate dir which is used
+     * before any other mirror
+     */
+    pub fn sstate_mirrors(&self) -> Vec<String> {
+        let mut mirrors: Vec<String> = vec![];
+        if let Some(dir) = self.shared_sstate_dir() {
+            mirrors.push(format!("file://.* file://{}/PATH", dir.display()));
+        }
+        mirrors.append(&mut self.ws_settings.sstate_mirrors.clone());
+        mirrors
+    }
+
+    /*
+     * The premirrors including the shared download dir which is used
+     * before any other mirror
+     */
+    pub fn premirrors(&self) -> Vec<String> {
+        let mut mirrors: Vec<String> = vec![];
+        if let Some(dir) = self.shared_dl_dir() {
+            for scheme in ["git", "gitsm", "ftp", "http", "https"] {
+                mirrors.push(format!("{}://.*/.* file://{}/", scheme, dir.display()));
+            }
+        }
+        mirrors.append(&mut self.ws_settings.premirrors.clone());
+        mirrors
+    }
+
+    pub fn hashserve(&self) -> &str {
+        &self.ws_settings.hashserve
+    }
+
+    /*
+     * The shared cache dirs are mounted read-only into the docker container
+     * using the same path as on the host. A dir that is not available on the
+     * host is not mounted.
+     */
+    pub fn cache_volumes(&self) -> Vec<String> {
+        let mut volumes: Vec<String> = vec![];
+        for dir in [self.shared_sstate_dir(), self.shared_dl_dir()]
+            .into_iter()
+            .flatten()
+        {
+            if dir.is_dir() {
+                volumes.push(format!("{}:{}:ro", dir.display(), dir.display()));
+            }
+        }
+        volumes
+    }
+
+    /*
+     * The tool used for signing the checksums files, either gpg or openssl.
+     * Empty if the checksums files should not be signed.
+     */
+    pub fn signing_tool(&self) -> &str {
+        &self.ws_settings.signing_tool
+    }
+
+    /*
+     * For gpg the key is the id of the key in the keyring used for signing. For
+     * openssl the key is the path to the private key in PEM format.
+     */
+    pub fn signing_key(&self) -> String {
+        self.signing_path(&self.ws_settings.signing_key)
+    }
+
+    /*
+     * The path to the public key in PEM format used by openssl when verifying
+     * the signature. Not used by gpg which is verifying using the keyring.
+     */
+    pub fn signing_pubkey(&self) -> String {
+        self.signing_path(&self.ws_settings.signing_pubkey)
+    }
+
+    fn signing_path(&self, key: &String) -> String {
+        if self.signing_tool() == "openssl" && !key.is_empty() {
+            return self.append_dir(key).to_string_lossy().to_string();
+        }
+        key.clone()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
+    use tempdir::TempDir;
 
-    use crate::executers::DockerImage;
+    use crate::executers::{ContainerRuntime, DockerImage};
     use crate::helper::Helper;
     use crate::workspace::WsSettingsHandler;
 
@@ -164,6 +295,10 @@ mod tests {
             PathBuf::from("/workspace/artifacts")
         );
         assert_eq!(settings.layers_dir(), PathBuf::from("/workspace/layers"));
+        assert_eq!(
+            settings.lock_file(),
+            PathBuf::from("/workspace/artifacts/bakery.lock")
+        );
         assert_eq!(settings.scripts_dir(), PathBuf::from("/workspace/scripts"));
         assert_eq!(settings.docker_dir(), PathBuf::from("/workspace/docker"));
         assert_eq!(settings.configs_dir(), PathBuf::from("/workspace/configs"));
@@ -284,6 +419,24 @@ mod tests {
         assert_eq!(format!("{}", docker_image), "test-registry/test-image:0.1");
     }
 
+    #[test]
+    fn test_settings_docker_runtime() {
+        let json_test_str = r#"
+        {
+            "version": "6",
+            "docker": {
+                "runtime": "podman-rootless"
+            }
+        }"#;
+        let work_dir: PathBuf = PathBuf::from("/workspace");
+        let settings: WsSettingsHandler =
+            WsSettingsHandler::new(work_dir.clone(), Helper::setup_ws_settings(json_test_str));
+        assert_eq!(settings.docker_runtime(), ContainerRuntime::RootlessPodman);
+        let settings: WsSettingsHandler =
+            WsSettingsHandler::new(work_dir, Helper::setup_ws_settings(r#"{"version": "6"}"#));
+        assert_eq!(settings.docker_runtime(), ContainerRuntime::Docker);
+    }
+
     #[test]
     fn test_settings_default_docker_args() {
         let json_test_str = r#"
@@ -358,4 +511,126 @@ mod tests {
             &vec!["build1".to_string(), "build2".to_string()]
         );
     }
+
+    #[test]
+    fn test_settings_strict_ctx() {
+        let json_test_str = r#"
+        {
+            "version": "6"
+        }"#;
+        let work_dir: PathBuf = PathBuf::from("/workspace");
+        let settings: WsSettingsHandler =
+            WsSettingsHandler::new(work_dir.clone(), Helper::setup_ws_settings(json_test_str));
+        assert!(!settings.strict_ctx());
+        let json_test_str = r#"
+        {
+            "version": "6",
+            "context": {
+                "strict": "true"
+            }
+        }"#;
+        let settings: WsSettingsHandler =
+            WsSettingsHandler::new(work_dir, Helper::setup_ws_settings(json_test_str));
+        assert!(settings.strict_ctx());
+    }
+
+    #[test]
+    fn test_settings_cache() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let shared_dir: PathBuf = temp_dir.path().join("sstate-cache");
+        std::fs::create_dir_all(&shared_dir).expect("Failed to create shared dir");
+        let json_test_str: String = format!(
+            r#"
+        {{
+            "version": "6",
+            "cache": {{
+                "sstatemirrors": [
+                    "file://.* https://sstate.example.com/PATH;downloadfilename=PATH"
+                ],
+                "hashserve": "hashserv.example.com:8686",
+                "sharedsstatedir": "{}",
+                "shareddldir": "/nfs/downloads"
+            }}
+        }}"#,
+            shared_dir.display()
+        );
+        let work_dir: PathBuf = PathBuf::from("/workspace");
+        let settings: WsSettingsHandler =
+            WsSettingsHandler::new(work_dir, Helper::setup_ws_settings(&json_test_str));
+        assert_eq!(
+            settings.sstate_mirrors(),
+            vec![
+                format!("file://.* file://{}/PATH", shared_dir.display()),
+                String::from("file://.* https://sstate.example.com/PATH;downloadfilename=PATH"),
+            ]
+        );
+        assert_eq!(
+            settings.premirrors(),
+            vec![
+                String::from("git://.*/.* file:///nfs/downloads/"),
+                String::from("gitsm://.*/.* file:///nfs/downloads/"),
+                String::from("ftp://.*/.* file:///nfs/downloads/"),
+                String::from("http://.*/.* file:///nfs/downloads/"),
+                String::from("https://.*/.* file:///nfs/downloads/"),
+            ]
+        );
+        assert_eq!(settings.hashserve(), "hashserv.example.com:8686");
+        /* Only the shared dirs that are available are mounted */
+        assert_eq!(
+            settings.cache_volumes(),
+            vec![format!(
+                "{}:{}:ro",
+                shared_dir.display(),
+                shared_dir.display()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_settings_default_cache() {
+        let work_dir: PathBuf = PathBuf::from("/workspace");
+        let settings: WsSettingsHandler =
+            WsSettingsHandler::new(work_dir, Helper::setup_ws_settings(r#"{"version": "6"}"#));
+        assert!(settings.shared_sstate_dir().is_none());
+        assert!(settings.sstate_mirrors().is_empty());
+        assert!(settings.premirrors().is_empty());
+        assert!(settings.hashserve().is_empty());
+        assert!(settings.cache_volumes().is_empty());
+    }
+
+    #[test]
+    fn test_settings_signing() {
+        let work_dir: PathBuf = PathBuf::from("/workspace");
+        let json_test_str = r#"
+        {
+            "version": "6",
+            "signing": {
+                "tool": "openssl",
+                "key": "keys/private.pem",
+                "pubkey": "/etc/keys/public.pem"
+            }
+        }"#;
+        let settings: WsSettingsHandler =
+            WsSettingsHandler::new(work_dir.clone(), Helper::setup_ws_settings(json_test_str));
+        assert_eq!(settings.signing_tool(), "openssl");
+        assert_eq!(settings.signing_key(), "/workspace/keys/private.pem");
+        assert_eq!(settings.signing_pubkey(), "/etc/keys/public.pem");
+        let json_test_str = r#"
+        {
+            "version": "6",
+            "signing": {
+                "tool": "gpg",
+                "key": "release@example.com"
+            }
+        }"#;
+        let settings: WsSettingsHandler =
+            WsSettingsHandler::new(work_dir.clone(), Helper::setup_ws_settings(json_test_str));
+        assert_eq!(settings.signing_tool(), "gpg");
+        assert_eq!(settings.signing_key(), "release@example.com");
+        assert!(settings.signing_pubkey().is_empty());
+        let settings: WsSettingsHandler =
+            WsSettingsHandler::new(work_dir, Helper::setup_ws_settings(r#"{"version": "6"}"#));
+        assert!(settings.signing_tool().is_empty());
+    }
 }
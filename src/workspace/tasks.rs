@@ -6,15 +6,32 @@ use crate::error::BError;
 use crate::executers::{
     BBBuildExecuter, BBCleanExecuter, NonBBBuildExecuter, NonBBCleanExecuter, TaskExecuter,
 };
-use crate::fs::ConfigFileReader;
+use crate::fs::{BitbakeConf, Checksums, ConfigFileReader, Stamp};
+use crate::report::{BuildReport, TaskReport};
 use crate::workspace::WsArtifactsHandler;
 
 use serde_json::Value;
 use std::collections::HashMap;
+use std::path::PathBuf;
+
+/*
+ * Options controlling how a task is built. The force option will build a
+ * task even if it is disabled or if the condition is not met and the
+ * incremental option will skip a task if the inputs are unchanged since
+ * the last successful build.
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TaskOptions {
+    pub dry_run: bool,
+    pub interactive: bool,
+    pub force: bool,
+    pub incremental: bool,
+}
 
 pub struct WsTaskHandler {
     data: WsTaskData,
     artifacts: Vec<WsArtifactsHandler>,
+    file: String,
 }
 
 impl WsTaskHandler {
@@ -31,31 +48,65 @@ impl WsTaskHandler {
         Ok(WsTaskHandler {
             data: task_data,
             artifacts,
+            file: String::new(),
         })
     }
 
+    /* The build config file that the task is defined in, empty if not read from a file */
+    pub fn set_file(&mut self, file: &str) {
+        self.file = file.to_string();
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
     pub fn build<'a>(
         &self,
         cli: &'a Cli,
         build_data: &WsBuildData,
         bb_variables: &Vec<String>,
         env_variables: &HashMap<String, String>,
-        dry_run: bool,
-        interactive: bool,
-        force: bool,
+        options: &TaskOptions,
+        report: &BuildReport,
+    ) -> Result<(), BError> {
+        let mut task_report: TaskReport = TaskReport::new(self.data.name());
+        let result: Result<(), BError> = self.execute(
+            cli,
+            build_data,
+            bb_variables,
+            env_variables,
+            options,
+            &mut task_report,
+        );
+        task_report.finish(cli, &result);
+        report.add_task(task_report);
+        result
+    }
+
+    fn execute<'a>(
+        &self,
+        cli: &'a Cli,
+        build_data: &WsBuildData,
+        bb_variables: &Vec<String>,
+        env_variables: &HashMap<String, String>,
+        options: &TaskOptions,
+        task_report: &mut TaskReport,
     ) -> Result<(), BError> {
         let executer: Box<dyn TaskExecuter>;
 
-        if !force && self.data.disabled() {
+        if !options.force && self.data.disabled() {
             cli.info(format!("Task '{}' disabled, skipping", self.data.name()));
+            task_report.skip("disabled");
             return Ok(());
         }
 
-        if !force && !self.data.condition() {
+        if !options.force && !self.data.condition() {
             cli.info(format!(
                 "Task condition for '{}' is not met, skipping",
                 self.data.name()
             ));
+            task_report.skip("condition not met");
             return Ok(());
         }
 
@@ -73,15 +124,147 @@ impl WsTaskHandler {
             }
         }
 
-        executer.exec(env_variables, dry_run, interactive)?;
+        /*
+         * The fingerprint of the task inputs is calculated before executing the task
+         * and is only stored once the task has been built successfully
+         */
+        let stamp: Stamp = self.stamp(build_data, bb_variables, env_variables)?;
+        if options.incremental && !options.dry_run && stamp.is_unchanged() {
+            cli.info(format!(
+                "Task '{}' is unchanged since last build, skipping",
+                self.data.name()
+            ));
+            task_report.skip("unchanged");
+            task_report.add_collected(&mut self.collect(cli, build_data)?);
+            return Ok(());
+        }
+
+        /*
+         * The secrets are resolved after the fingerprint has been calculated so
+         * the values are never part of the stamp
+         */
+        let mut env: HashMap<String, String> = env_variables.clone();
+        env.extend(build_data.secrets().env(cli, self.data.secrets())?);
 
-        if !dry_run {
-            self.collect(cli, build_data)?;
+        /*
+         * The output of the task is captured to a task log file, the last lines
+         * are added to the error if the task fails. The output is always captured
+         * even for an interactive build since only the stdin of the task is
+         * attached to the terminal.
+         */
+        cli.start_task_log(self.data.name(), &self.log_path(build_data))?;
+        /*
+         * The old stamp is removed so a task that fails is never skipped the next
+         * time even if the inputs are reverted to the last successful build
+         */
+        if !options.dry_run {
+            stamp.remove()?;
+        }
+        let result: Result<(), BError> = executer.exec(&env, options.dry_run, options.interactive);
+        let tail: Vec<String> = cli.finish_task_log();
+        if let Err(err) = result {
+            return Err(Self::task_error(self.data.name(), err, &tail));
+        }
+
+        if !options.dry_run {
+            task_report.add_collected(&mut self.collect(cli, build_data)?);
+            stamp.write()?;
+            cli.debug(format!(
+                "Task '{}' stamp {}",
+                self.data.name(),
+                stamp.fingerprint()
+            ));
         }
 
         Ok(())
     }
 
+    pub fn log_path(&self, build_data: &WsBuildData) -> PathBuf {
+        build_data
+            .settings()
+            .builds_dir()
+            .join("logs")
+            .join(format!("{}.log", self.data.name()))
+    }
+
+    /*
+     * Add the last lines of output from a failed task to the error so that
+     * the reason for the failure is part of the error and not only the
+     * exit status
+     */
+    pub fn task_error(name: &str, err: BError, tail: &[String]) -> BError {
+        if tail.is_empty() {
+            return err;
+        }
+        BError::TaskError(format!(
+            "{}\nLast {} lines of output from task '{}':\n{}",
+            err,
+            tail.len(),
+            name,
+            tail.join("\n")
+        ))
+    }
+
+    pub fn stamp_path(&self, build_data: &WsBuildData) -> PathBuf {
+        Self::stamps_dir(build_data).join(format!("{}.stamp", self.data.name()))
+    }
+
+    fn stamps_dir(build_data: &WsBuildData) -> PathBuf {
+        build_data
+            .settings()
+            .builds_dir()
+            .join("stamps")
+            .join(build_data.name())
+    }
+
+    /*
+     * Setup the stamp for the task based on the expanded task definition, the generated
+     * bitbake configs and the state of the layers for a bitbake task or the content of
+     * the scripts called by a non-bitbake task. The stamps of the tasks it depends on are
+     * included so if a dependency is rebuilt with new inputs this task is rebuilt as well.
+     */
+    pub fn stamp(
+        &self,
+        build_data: &WsBuildData,
+        bb_variables: &Vec<String>,
+        env_variables: &HashMap<String, String>,
+    ) -> Result<Stamp, BError> {
+        let mut inputs: Vec<String> = vec![self.data.to_string()];
+
+        if self.data.ttype() == &TType::Bitbake {
+            let conf: BitbakeConf = BitbakeConf::new(build_data.bitbake(), bb_variables, false)?;
+            let bblayers_conf: String = conf.bblayers_conf_content();
+            inputs.push(conf.local_conf_content());
+            inputs.append(&mut Stamp::layer_heads(&bblayers_conf));
+            inputs.push(bblayers_conf);
+            /* The workspace layer created by devtool is not part of the generated bblayers.conf */
+            let workspace_layer: PathBuf = build_data.bitbake().build_dir().join("workspace");
+            if workspace_layer.is_dir() {
+                inputs.append(&mut Stamp::workspace_state(&workspace_layer));
+            }
+        } else {
+            inputs.append(&mut Stamp::file_hashes(
+                self.data.build_cmd(),
+                self.data.build_dir(),
+            ));
+        }
+
+        let mut env: Vec<String> = env_variables
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        env.sort();
+        inputs.append(&mut env);
+
+        for dep in self.data.depends().iter() {
+            let dep_stamp: PathBuf = Self::stamps_dir(build_data).join(format!("{}.stamp", dep));
+            let fingerprint: String = std::fs::read_to_string(dep_stamp).unwrap_or_default();
+            inputs.push(format!("{}={}", dep, fingerprint.trim()));
+        }
+
+        Ok(Stamp::new(&self.stamp_path(build_data), &inputs))
+    }
+
     pub fn clean<'a>(
         &self,
         cli: &'a Cli,
@@ -115,7 +298,12 @@ impl WsTaskHandler {
             }
         }
 
-        executer.exec(env_variables, false, false)?;
+        let mut env: HashMap<String, String> = env_variables.clone();
+        env.extend(build_data.secrets().env(cli, self.data.secrets())?);
+        executer.exec(&env, false, false)?;
+
+        // Make sure the task is built the next time
+        Stamp::new(&self.stamp_path(build_data), &[]).remove()?;
 
         Ok(())
     }
@@ -137,6 +325,37 @@ impl WsTaskHandler {
                 collected.append(&mut c);
             }
 
+            /*
+             * Some collectors like the checksums collector depends on
+             * everything else that has been collected by the task
+             */
+            let mut post_collected: Vec<Collected> = vec![];
+            for artifact in self.artifacts.iter() {
+                let collector: Box<dyn Collector> = CollectorFactory::create(artifact, Some(cli))?;
+                let mut c: Vec<Collected> =
+                    collector.post_collect(&build_data.settings().artifacts_dir(), &collected)?;
+                post_collected.append(&mut c);
+            }
+
+            /*
+             * The files created when post collecting are the checksums files which
+             * are signed if a signing tool has been set in the workspace settings
+             */
+            let tool: &str = build_data.settings().signing_tool();
+            if !tool.is_empty() {
+                let mut signatures: Vec<Collected> = vec![];
+                for c in post_collected.iter() {
+                    let signature: PathBuf = Checksums::new(&c.dest).sign(
+                        cli,
+                        tool,
+                        &build_data.settings().signing_key(),
+                    )?;
+                    signatures.push(Collected::new(&PathBuf::from(""), &signature)?);
+                }
+                post_collected.append(&mut signatures);
+            }
+            collected.append(&mut post_collected);
+
             cli.info(format!(
                 "All artifacts for task '{}' have been collected to '{}'",
                 self.data.name(),
@@ -158,8 +377,9 @@ impl WsTaskHandler {
 
     pub fn expand_ctx(&mut self, ctx: &Context) -> Result<(), BError> {
         self.data.expand_ctx(ctx)?;
-        for a in self.artifacts.iter_mut() {
-            a.expand_ctx(ctx)?;
+        for (i, a) in self.artifacts.iter_mut().enumerate() {
+            a.expand_ctx(ctx)
+                .map_err(|err| Context::key_error(err, &format!("artifacts[{}]", i)))?;
         }
         Ok(())
     }
@@ -175,6 +395,7 @@ impl WsTaskHandler {
 
 #[cfg(test)]
 mod tests {
+    use serde_json::Value;
     use std::collections::HashMap;
     use std::fs::File;
     use std::io::Read;
@@ -183,8 +404,10 @@ mod tests {
 
     use crate::cli::{BLogger, CallParams, Cli, MockLogger, MockSystem};
     use crate::data::{AType, TType, WsBuildData};
+    use crate::error::BError;
     use crate::helper::Helper;
-    use crate::workspace::{WsArtifactsHandler, WsTaskHandler};
+    use crate::report::BuildReport;
+    use crate::workspace::{TaskOptions, WsArtifactsHandler, WsTaskHandler};
 
     fn helper_verify_bitbake_conf(
         local_conf_path: &PathBuf,
@@ -560,13 +783,332 @@ mod tests {
             &build_data,
             &vec![],
             &HashMap::new(),
-            false,
-            false,
-            false,
+            &TaskOptions {
+                dry_run: false,
+                interactive: false,
+                force: false,
+                incremental: true,
+            },
+            &BuildReport::new("NA"),
         )
         .expect("Failed to run task!");
     }
 
+    #[test]
+    fn test_ws_task_run_incremental() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let path: &Path = temp_dir.path();
+        let work_dir: PathBuf = PathBuf::from(path);
+        let json_task_str: &str = r#"
+        {
+            "index": "2",
+            "name": "task-name",
+            "type": "non-bitbake",
+            "builddir": "test-dir",
+            "build": "build.sh"
+        }"#;
+        let build_data: WsBuildData = Helper::setup_build_data(&work_dir, None, None);
+        let task: WsTaskHandler = WsTaskHandler::from_str(json_task_str, &build_data)
+            .expect("Failed to parse Task config");
+        let mut mocked_system: MockSystem = MockSystem::new();
+        mocked_system
+            .expect_check_call()
+            .with(mockall::predicate::eq(CallParams {
+                cmd_line: vec![
+                    "cd",
+                    &work_dir.join("test-dir").to_string_lossy().to_string(),
+                    "&&",
+                    "build.sh",
+                ]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+                env: HashMap::new(),
+                shell: true,
+            }))
+            .times(2)
+            .returning(|_x| Ok(()));
+        mocked_system
+            .expect_check_call()
+            .with(mockall::predicate::eq(CallParams {
+                cmd_line: vec![
+                    "cd",
+                    &work_dir.join("test-dir").to_string_lossy().to_string(),
+                    "&&",
+                    "build.sh",
+                ]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+                env: HashMap::from([(String::from("TEST_VAR"), String::from("1"))]),
+                shell: true,
+            }))
+            .once()
+            .returning(|_x| Ok(()));
+        mocked_system.expect_env().returning(|| HashMap::new());
+        let cli: Cli = Cli::new(
+            Box::new(BLogger::new()),
+            Box::new(mocked_system),
+            clap::Command::new("bakery"),
+            None,
+        );
+        let report: BuildReport = BuildReport::new("NA");
+        let stamp_path: PathBuf = work_dir.join("builds/stamps/NA/task-name.stamp");
+        assert_eq!(task.stamp_path(&build_data), stamp_path);
+        assert!(!stamp_path.exists());
+        // The first build should execute the task and create the stamp
+        task.build(
+            &cli,
+            &build_data,
+            &vec![],
+            &HashMap::new(),
+            &TaskOptions {
+                dry_run: false,
+                interactive: false,
+                force: false,
+                incremental: true,
+            },
+            &report,
+        )
+        .expect("Failed to run task!");
+        assert!(stamp_path.exists());
+        // Nothing has changed so the second build should be skipped
+        task.build(
+            &cli,
+            &build_data,
+            &vec![],
+            &HashMap::new(),
+            &TaskOptions {
+                dry_run: false,
+                interactive: false,
+                force: false,
+                incremental: true,
+            },
+            &report,
+        )
+        .expect("Failed to run task!");
+        // Not using the stamps should always execute the task
+        task.build(
+            &cli,
+            &build_data,
+            &vec![],
+            &HashMap::new(),
+            &TaskOptions {
+                dry_run: false,
+                interactive: false,
+                force: false,
+                incremental: false,
+            },
+            &report,
+        )
+        .expect("Failed to run task!");
+        // Changing the env variables should result in executing the task again
+        let env: HashMap<String, String> =
+            HashMap::from([(String::from("TEST_VAR"), String::from("1"))]);
+        assert!(!task
+            .stamp(&build_data, &vec![], &env)
+            .expect("Failed to setup stamp")
+            .is_unchanged());
+        assert!(task
+            .stamp(&build_data, &vec![], &HashMap::new())
+            .expect("Failed to setup stamp")
+            .is_unchanged());
+        let fingerprint: String = std::fs::read_to_string(&stamp_path).unwrap();
+        assert_eq!(
+            fingerprint.trim(),
+            task.stamp(&build_data, &vec![], &HashMap::new())
+                .expect("Failed to setup stamp")
+                .fingerprint()
+        );
+        task.build(
+            &cli,
+            &build_data,
+            &vec![],
+            &env,
+            &TaskOptions {
+                dry_run: false,
+                interactive: false,
+                force: false,
+                incremental: true,
+            },
+            &report,
+        )
+        .expect("Failed to run task!");
+        let value: Value =
+            serde_json::from_str(&report.to_string(&Ok(()))).expect("Failed to parse report");
+        assert_eq!(value["tasks"][0]["status"], "success");
+        assert_eq!(value["tasks"][0]["commands"][0][3], "build.sh");
+        assert_eq!(value["tasks"][1]["status"], "skipped");
+        assert_eq!(value["tasks"][1]["skip_reason"], "unchanged");
+        assert!(value["tasks"][1]["commands"].as_array().unwrap().is_empty());
+        assert_eq!(value["tasks"][2]["status"], "success");
+        assert_eq!(value["tasks"][3]["status"], "success");
+        // Editing the script called by the task should result in executing the task again
+        assert!(task
+            .stamp(&build_data, &vec![], &env)
+            .expect("Failed to setup stamp")
+            .is_unchanged());
+        std::fs::create_dir_all(work_dir.join("test-dir")).unwrap();
+        std::fs::write(work_dir.join("test-dir/build.sh"), "echo build").unwrap();
+        assert!(!task
+            .stamp(&build_data, &vec![], &env)
+            .expect("Failed to setup stamp")
+            .is_unchanged());
+    }
+
+    #[test]
+    fn test_ws_task_run_incremental_failed() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let path: &Path = temp_dir.path();
+        let work_dir: PathBuf = PathBuf::from(path);
+        let json_task_str: &str = r#"
+        {
+            "index": "2",
+            "name": "task-name",
+            "type": "non-bitbake",
+            "builddir": "test-dir",
+            "build": "build.sh"
+        }"#;
+        let build_data: WsBuildData = Helper::setup_build_data(&work_dir, None, None);
+        let task: WsTaskHandler = WsTaskHandler::from_str(json_task_str, &build_data)
+            .expect("Failed to parse Task config");
+        let mut mocked_system: MockSystem = MockSystem::new();
+        mocked_system
+            .expect_check_call()
+            .with(mockall::predicate::eq(CallParams {
+                cmd_line: vec![
+                    "cd",
+                    &work_dir.join("test-dir").to_string_lossy().to_string(),
+                    "&&",
+                    "build.sh",
+                ]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+                env: HashMap::new(),
+                shell: true,
+            }))
+            .once()
+            .returning(|_x| Ok(()));
+        mocked_system
+            .expect_check_call()
+            .with(mockall::predicate::eq(CallParams {
+                cmd_line: vec![
+                    "cd",
+                    &work_dir.join("test-dir").to_string_lossy().to_string(),
+                    "&&",
+                    "build.sh",
+                ]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+                env: HashMap::from([(String::from("TEST_VAR"), String::from("1"))]),
+                shell: true,
+            }))
+            .once()
+            .returning(|_x| Err(BError::CliError(String::from("build failed"))));
+        mocked_system.expect_env().returning(|| HashMap::new());
+        let cli: Cli = Cli::new(
+            Box::new(BLogger::new()),
+            Box::new(mocked_system),
+            clap::Command::new("bakery"),
+            None,
+        );
+        let report: BuildReport = BuildReport::new("NA");
+        let stamp_path: PathBuf = task.stamp_path(&build_data);
+        task.build(
+            &cli,
+            &build_data,
+            &vec![],
+            &HashMap::new(),
+            &TaskOptions {
+                dry_run: false,
+                interactive: false,
+                force: false,
+                incremental: true,
+            },
+            &report,
+        )
+        .expect("Failed to run task!");
+        assert!(stamp_path.exists());
+        // A failed build should remove the stamp of the last successful build so
+        // the task is not skipped when the inputs are reverted
+        let env: HashMap<String, String> =
+            HashMap::from([(String::from("TEST_VAR"), String::from("1"))]);
+        assert!(task
+            .build(
+                &cli,
+                &build_data,
+                &vec![],
+                &env,
+                &TaskOptions {
+                    dry_run: false,
+                    interactive: false,
+                    force: false,
+                    incremental: true,
+                },
+                &report,
+            )
+            .is_err());
+        assert!(!stamp_path.exists());
+        assert!(!task
+            .stamp(&build_data, &vec![], &HashMap::new())
+            .expect("Failed to setup stamp")
+            .is_unchanged());
+    }
+
+    #[test]
+    fn test_ws_task_run_interactive_log() {
+        let temp_dir: TempDir =
+            TempDir::new("bakery-test-dir").expect("Failed to create temp directory");
+        let work_dir: PathBuf = PathBuf::from(temp_dir.path());
+        let json_task_str: &str = r#"
+        {
+            "index": "2",
+            "name": "task-name",
+            "type": "non-bitbake",
+            "builddir": "test-dir",
+            "build": "build.sh"
+        }"#;
+        let build_data: WsBuildData = Helper::setup_build_data(&work_dir, None, None);
+        let task: WsTaskHandler = WsTaskHandler::from_str(json_task_str, &build_data)
+            .expect("Failed to parse Task config");
+        let mut mocked_system: MockSystem = MockSystem::new();
+        mocked_system
+            .expect_check_call()
+            .once()
+            .returning(|_x| Ok(()));
+        mocked_system.expect_env().returning(|| HashMap::new());
+        let cli: Cli = Cli::new(
+            Box::new(BLogger::new()),
+            Box::new(mocked_system),
+            clap::Command::new("bakery"),
+            None,
+        );
+        cli.open_log_file(&work_dir.join("builds/logs/bakery-NA.log"))
+            .expect("Failed to open log file");
+        let log_path: PathBuf = task.log_path(&build_data);
+        assert!(!log_path.exists());
+        /* The build is interactive by default which should not stop the task from being logged */
+        task.build(
+            &cli,
+            &build_data,
+            &vec![],
+            &HashMap::new(),
+            &TaskOptions {
+                dry_run: false,
+                interactive: true,
+                force: false,
+                incremental: false,
+            },
+            &BuildReport::new("NA"),
+        )
+        .expect("Failed to run task!");
+        assert!(log_path.exists());
+    }
+
     /*
     Disable it for now we need some more work to get this working
     #[test]
@@ -696,9 +1238,13 @@ mod tests {
             &build_data,
             &vec![],
             &HashMap::new(),
-            false,
-            false,
-            false,
+            &TaskOptions {
+                dry_run: false,
+                interactive: false,
+                force: false,
+                incremental: true,
+            },
+            &BuildReport::new("NA"),
         )
         .expect("Failed to run task!");
     }
@@ -846,9 +1392,14 @@ mod tests {
             &build_data,
             &vec![],
             &HashMap::new(),
-            true, // Running dry-run should skip the execution and instead only create the bitbake confs
-            false,
-            false,
+            // Running dry-run should skip the execution and instead only create the bitbake confs
+            &TaskOptions {
+                dry_run: true,
+                interactive: false,
+                force: false,
+                incremental: true,
+            },
+            &BuildReport::new("NA"),
         )
         .expect("Failed to run task!");
         let mut local_conf_content: String = String::from("");
@@ -909,4 +1460,22 @@ mod tests {
             &bblayers_conf_content,
         );
     }
+
+    #[test]
+    fn test_ws_task_error() {
+        let err: BError = BError::CliError(String::from("exit status: 1"));
+        assert_eq!(
+            WsTaskHandler::task_error("task-name", err, &[]).to_string(),
+            "exit status: 1"
+        );
+        let err: BError = BError::CliError(String::from("exit status: 1"));
+        let tail: Vec<String> = vec![
+            String::from("make: *** [all] Error 1"),
+            String::from("build failed"),
+        ];
+        assert_eq!(
+            WsTaskHandler::task_error("task-name", err, &tail).to_string(),
+            "exit status: 1\nLast 2 lines of output from task 'task-name':\nmake: *** [all] Error 1\nbuild failed"
+        );
+    }
 }
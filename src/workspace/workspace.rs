@@ -98,11 +98,19 @@ impl Workspace {
                                 if let Some(file_name_str) = file_name.to_str() {
                                     let build_config_json: String =
                                         ConfigFileReader::new(&path).read_json()?;
+                                    /*
+                                     * A build config using an older format is not
+                                     * supported until it has been migrated
+                                     */
                                     let config: WsBuildConfigHandler =
-                                        WsBuildConfigHandler::from_str(
+                                        match WsBuildConfigHandler::from_str(
                                             &build_config_json,
                                             settings,
-                                        )?;
+                                        ) {
+                                            Ok(config) => config,
+                                            Err(BError::InvalidBuildConfigError(_v)) => continue,
+                                            Err(err) => return Err(err),
+                                        };
                                     if config.build_data().valid() {
                                         list_of_files.push(file_name_str.to_string());
                                     }
@@ -149,7 +157,8 @@ impl Workspace {
     ) -> Result<Self, BError> {
         let work_dir: PathBuf = Self::setup_work_directory(&workdir);
         let mut settings: WsSettingsHandler = Self::setup_settings(work_dir, settings);
-        let config: WsBuildConfigHandler = Self::setup_config(&mut settings, config);
+        let mut config: WsBuildConfigHandler = Self::setup_config(&mut settings, config);
+        config.set_strict_ctx(settings.strict_ctx());
         let configs: IndexMap<PathBuf, String> =
             Self::setup_list_of_available_configs(&settings, &config)?;
 
@@ -187,6 +196,10 @@ impl Workspace {
         Ok(())
     }
 
+    pub fn set_strict_ctx(&mut self, strict: bool) {
+        self.config.set_strict_ctx(strict);
+    }
+
     pub fn expand_ctx(&mut self) -> Result<(), BError> {
         self.config.expand_ctx()?;
         Ok(())